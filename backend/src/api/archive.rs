@@ -0,0 +1,108 @@
+use axum::{
+    extract::{Query, State},
+    http::header::{CONTENT_DISPOSITION, CONTENT_TYPE},
+    response::{IntoResponse, Response},
+};
+use chrono::{DateTime, Utc};
+use tokio::fs;
+use tracing::instrument;
+
+use crate::{
+    api::{
+        ApiError, ErrorCode,
+        search::{RawSearchParams, search_query},
+        stream::{chunked_body, resolve_media_path},
+    },
+    indexer::MediaFile,
+    media::archive::{ArchiveEntry, MAX_ZIP_BYTES, write_zip},
+    routes::AppState,
+    services::search::SearchService,
+};
+
+/// Most files a single archive may contain.
+const MAX_ARCHIVE_FILES: usize = 1000;
+/// Most bytes of media a single archive may contain; kept under the ZIP32 limit so
+/// headers and the central directory still fit.
+const MAX_ARCHIVE_BYTES: u64 = MAX_ZIP_BYTES / 2;
+
+/// Stream every media matching the search filters as one ZIP, each file stored under
+/// its relative path. Pagination parameters are ignored; files that vanished since the
+/// last scan are skipped.
+#[instrument(skip(state, params), fields(galarie.archive.files, galarie.archive.bytes))]
+pub async fn media_archive(
+    State(state): State<AppState>,
+    Query(params): Query<RawSearchParams>,
+) -> Result<Response, ApiError> {
+    let query = search_query(&params, &state.config)?;
+    let matches: Vec<MediaFile> = {
+        let snapshot = state.snapshot.read().await;
+        SearchService::matching(&snapshot, &query)
+            .into_iter()
+            .cloned()
+            .collect()
+    };
+    if matches.len() > MAX_ARCHIVE_FILES {
+        return Err(ApiError::bad_request(format!(
+            "archive would contain {} files; narrow the filters to at most {MAX_ARCHIVE_FILES}",
+            matches.len()
+        )));
+    }
+
+    let mut entries = Vec::with_capacity(matches.len());
+    let mut total_bytes: u64 = 0;
+    for media in matches {
+        let path = match resolve_media_path(&state.config.indexer, &media.relative_path).await {
+            Ok(path) => path,
+            Err(err) if err.code() == ErrorCode::Gone => continue,
+            Err(err) => return Err(err),
+        };
+        let metadata = match fs::metadata(&path).await {
+            Ok(metadata) if metadata.is_file() => metadata,
+            _ => continue,
+        };
+        total_bytes += metadata.len();
+        if total_bytes > MAX_ARCHIVE_BYTES {
+            return Err(ApiError::bad_request(format!(
+                "archive would exceed {MAX_ARCHIVE_BYTES} bytes; narrow the filters"
+            )));
+        }
+        entries.push(ArchiveEntry {
+            name: media.relative_path.replace('\\', "/"),
+            path,
+            size: metadata.len(),
+            modified: metadata
+                .modified()
+                .map(DateTime::<Utc>::from)
+                .unwrap_or(media.indexed_at),
+        });
+    }
+
+    let span = tracing::Span::current();
+    span.record("galarie.archive.files", entries.len() as u64);
+    span.record("galarie.archive.bytes", total_bytes);
+
+    let chunk_bytes = state.config.stream.chunk_bytes;
+    let (writer, reader) = tokio::io::duplex(chunk_bytes);
+    tokio::spawn(async move {
+        // A failure mid-body can only truncate the archive; a broken pipe just means
+        // the client went away.
+        match write_zip(writer, &entries).await {
+            Err(err) if err.kind() != std::io::ErrorKind::BrokenPipe => {
+                tracing::warn!(error = %err, "media archive aborted");
+            }
+            _ => {}
+        }
+    });
+
+    Ok((
+        [
+            (CONTENT_TYPE, "application/zip"),
+            (
+                CONTENT_DISPOSITION,
+                "attachment; filename=\"galarie-media.zip\"",
+            ),
+        ],
+        chunked_body(reader, chunk_bytes),
+    )
+        .into_response())
+}
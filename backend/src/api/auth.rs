@@ -0,0 +1,89 @@
+use std::{fmt, sync::Arc};
+
+use axum::{
+    body::Body,
+    extract::State,
+    http::{HeaderValue, Request, header::AUTHORIZATION, header::WWW_AUTHENTICATE},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use sha1::{Digest, Sha1};
+
+use crate::api::ApiError;
+
+/// Shared secret clients present as `Authorization: Bearer <token>`.
+///
+/// `Debug` is redacted so the token never reaches the startup config log.
+#[derive(Clone, PartialEq, Eq)]
+pub struct ApiToken(String);
+
+impl ApiToken {
+    pub fn new(token: impl Into<String>) -> Self {
+        Self(token.into())
+    }
+
+    /// Compare in constant time. Both sides are hashed first so neither the
+    /// position of the first mismatch nor the token length leaks through timing.
+    pub fn matches(&self, candidate: &str) -> bool {
+        let expected = Sha1::digest(self.0.as_bytes());
+        let actual = Sha1::digest(candidate.as_bytes());
+        expected
+            .iter()
+            .zip(actual.iter())
+            .fold(0u8, |diff, (a, b)| diff | (a ^ b))
+            == 0
+    }
+}
+
+impl fmt::Debug for ApiToken {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("ApiToken(<redacted>)")
+    }
+}
+
+/// Middleware requiring the configured bearer token: `401` when it is missing,
+/// empty or malformed, `403` when it does not match.
+pub async fn require_api_token(
+    State(token): State<Arc<ApiToken>>,
+    req: Request<Body>,
+    next: Next,
+) -> Response {
+    let presented = req
+        .headers()
+        .get(AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| {
+            let (scheme, credentials) = value.split_once(' ')?;
+            scheme
+                .eq_ignore_ascii_case("bearer")
+                .then(|| credentials.trim())
+        })
+        .filter(|credentials| !credentials.is_empty());
+
+    match presented {
+        None => {
+            let mut response = ApiError::unauthorized("missing bearer token").into_response();
+            response
+                .headers_mut()
+                .insert(WWW_AUTHENTICATE, HeaderValue::from_static("Bearer"));
+            response
+        }
+        Some(candidate) if token.matches(candidate) => next.run(req).await,
+        Some(_) => ApiError::forbidden("invalid bearer token").into_response(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn compares_tokens_and_redacts_debug() {
+        let token = ApiToken::new("s3cret");
+        assert!(token.matches("s3cret"));
+        assert!(!token.matches("s3cre"));
+        assert!(!token.matches("s3cret "));
+        assert!(!token.matches(""));
+        assert_eq!(format!("{token:?}"), "ApiToken(<redacted>)");
+    }
+}
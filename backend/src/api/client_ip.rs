@@ -0,0 +1,170 @@
+use std::{
+    net::{IpAddr, SocketAddr},
+    sync::Arc,
+};
+
+use axum::{
+    body::Body,
+    extract::{ConnectInfo, State},
+    http::{HeaderMap, Request, header::FORWARDED},
+    middleware::Next,
+    response::Response,
+};
+use ipnet::IpNet;
+
+const X_FORWARDED_FOR: &str = "x-forwarded-for";
+
+/// Networks whose `Forwarded` / `X-Forwarded-For` headers are believed.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TrustedProxies(Vec<IpNet>);
+
+impl TrustedProxies {
+    pub fn new(networks: Vec<IpNet>) -> Self {
+        Self(networks)
+    }
+
+    pub fn contains(&self, ip: IpAddr) -> bool {
+        self.0.iter().any(|network| network.contains(&ip))
+    }
+
+    /// Address the request originated from. Forwarded hops are read right to left and
+    /// only while the hop that reported them is trusted, so a client cannot spoof its
+    /// address by sending the headers itself or by prepending entries to them.
+    pub fn client_ip(&self, peer: IpAddr, headers: &HeaderMap) -> IpAddr {
+        let mut client = peer;
+        if !self.contains(client) {
+            return client;
+        }
+        for hop in forwarded_chain(headers).into_iter().rev() {
+            // An unparseable or obfuscated hop ends the chain at the last known address.
+            let Some(hop) = hop else { break };
+            client = hop;
+            if !self.contains(client) {
+                break;
+            }
+        }
+        client
+    }
+}
+
+/// Client address resolved by [`resolve_client_ip`], for logging and rate limiting.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ClientIp(pub IpAddr);
+
+/// Middleware recording the [`ClientIp`] of requests that carry connection info.
+pub async fn resolve_client_ip(
+    State(proxies): State<Arc<TrustedProxies>>,
+    mut req: Request<Body>,
+    next: Next,
+) -> Response {
+    if let Some(ConnectInfo(peer)) = req.extensions().get::<ConnectInfo<SocketAddr>>().copied() {
+        let client = proxies.client_ip(peer.ip(), req.headers());
+        req.extensions_mut().insert(ClientIp(client));
+    }
+    next.run(req).await
+}
+
+/// Hops in the order proxies appended them. RFC 7239 `Forwarded` wins over
+/// `X-Forwarded-For` when both are present.
+fn forwarded_chain(headers: &HeaderMap) -> Vec<Option<IpAddr>> {
+    if headers.contains_key(FORWARDED) {
+        header_elements(headers, FORWARDED.as_str())
+            .map(|element| {
+                element
+                    .split(';')
+                    .filter_map(|pair| pair.split_once('='))
+                    .find(|(key, _)| key.trim().eq_ignore_ascii_case("for"))
+                    .and_then(|(_, node)| parse_node(node))
+            })
+            .collect()
+    } else {
+        header_elements(headers, X_FORWARDED_FOR)
+            .map(parse_node)
+            .collect()
+    }
+}
+
+fn header_elements<'a>(headers: &'a HeaderMap, name: &str) -> impl Iterator<Item = &'a str> {
+    headers
+        .get_all(name)
+        .iter()
+        .flat_map(|value| value.to_str().unwrap_or_default().split(','))
+        .map(str::trim)
+        .filter(|element| !element.is_empty())
+}
+
+/// Parse a node such as `192.0.2.1`, `192.0.2.1:8080`, `"[2001:db8::1]:443"` or `2001:db8::1`.
+fn parse_node(node: &str) -> Option<IpAddr> {
+    let node = node.trim().trim_matches('"');
+    if let Some(rest) = node.strip_prefix('[') {
+        let (ip, _) = rest.split_once(']')?;
+        return ip.parse().ok();
+    }
+    node.parse()
+        .ok()
+        .or_else(|| node.parse::<SocketAddr>().ok().map(|address| address.ip()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::http::HeaderValue;
+
+    fn proxies() -> TrustedProxies {
+        TrustedProxies::new(vec![
+            "10.0.0.0/8".parse().unwrap(),
+            "2001:db8::/32".parse().unwrap(),
+        ])
+    }
+
+    fn headers(name: &'static str, value: &str) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert(name, HeaderValue::from_str(value).unwrap());
+        headers
+    }
+
+    #[test]
+    fn ignores_forwarded_headers_from_untrusted_peers() {
+        let spoofed = headers(X_FORWARDED_FOR, "203.0.113.9");
+        let peer: IpAddr = "198.51.100.7".parse().unwrap();
+        assert_eq!(proxies().client_ip(peer, &spoofed), peer);
+        assert_eq!(TrustedProxies::default().client_ip(peer, &spoofed), peer);
+    }
+
+    #[test]
+    fn reads_the_nearest_untrusted_hop_behind_trusted_proxies() {
+        let peer: IpAddr = "10.0.0.2".parse().unwrap();
+        // The client prepended 203.0.113.9 itself; the proxies appended the rest.
+        let chain = headers(X_FORWARDED_FOR, "203.0.113.9, 198.51.100.7, 10.0.0.1");
+        assert_eq!(
+            proxies().client_ip(peer, &chain),
+            "198.51.100.7".parse::<IpAddr>().unwrap()
+        );
+
+        let only_proxies = headers(X_FORWARDED_FOR, "10.0.0.3:8080");
+        assert_eq!(
+            proxies().client_ip(peer, &only_proxies),
+            "10.0.0.3".parse::<IpAddr>().unwrap()
+        );
+
+        let garbage = headers(X_FORWARDED_FOR, "not-an-ip");
+        assert_eq!(proxies().client_ip(peer, &garbage), peer);
+    }
+
+    #[test]
+    fn prefers_the_forwarded_header() {
+        let peer: IpAddr = "2001:db8::10".parse().unwrap();
+        let mut both = headers(
+            FORWARDED.as_str(),
+            r#"for=192.0.2.60;proto=https, For="[2001:db8:cafe::17]:4711""#,
+        );
+        both.insert(X_FORWARDED_FOR, HeaderValue::from_static("203.0.113.9"));
+        assert_eq!(
+            proxies().client_ip(peer, &both),
+            "192.0.2.60".parse::<IpAddr>().unwrap()
+        );
+
+        let obfuscated = headers(FORWARDED.as_str(), "for=_hidden");
+        assert_eq!(proxies().client_ip(peer, &obfuscated), peer);
+    }
+}
@@ -0,0 +1,229 @@
+use std::collections::HashMap;
+
+use axum::{
+    Json,
+    extract::{Path, Query, State},
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    api::{ApiError, ApiResult, search::MediaSearchResponse, validate_media_id},
+    indexer::MediaFile,
+    routes::AppState,
+    services::search::SearchService,
+};
+
+const DEFAULT_RELATED_LIMIT: usize = 12;
+
+/// Return a single media record by its stable id.
+#[utoipa::path(
+    get,
+    path = "/api/v1/media/{id}",
+    tag = "media",
+    params(("id" = String, Path, description = "Media identifier")),
+    responses(
+        (status = 200, description = "Media record", body = MediaFile),
+        (status = 400, description = "Invalid media id", body = crate::api::ErrorResponse),
+        (status = 404, description = "Unknown media id", body = crate::api::ErrorResponse),
+    )
+)]
+pub async fn media_detail(
+    Path(media_id): Path<String>,
+    State(state): State<AppState>,
+) -> ApiResult<MediaFile> {
+    validate_media_id(&media_id)?;
+    let snapshot = state.snapshot.read().await;
+    snapshot
+        .media
+        .iter()
+        .find(|media| media.id == media_id)
+        .cloned()
+        .map(Json)
+        .ok_or_else(|| ApiError::not_found("media not found"))
+}
+
+#[derive(Debug, Deserialize, Default)]
+pub struct RelatedParams {
+    pub limit: Option<usize>,
+}
+
+/// Other media sharing tags with `media_id`, best matches first, as a single search page.
+pub async fn related_media(
+    Path(media_id): Path<String>,
+    State(state): State<AppState>,
+    Query(params): Query<RelatedParams>,
+) -> ApiResult<MediaSearchResponse> {
+    validate_media_id(&media_id)?;
+    let snapshot = state.snapshot.read().await;
+    SearchService::related(
+        &snapshot,
+        &media_id,
+        state
+            .config
+            .page_limits
+            .page_size(params.limit.unwrap_or(DEFAULT_RELATED_LIMIT)),
+    )
+    .map(|result| Json(result.into()))
+    .ok_or_else(|| ApiError::not_found("media not found"))
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct InvalidMediaResponse {
+    pub items: Vec<MediaFile>,
+    pub total: usize,
+}
+
+/// List media whose filenames contained tokens that could not be parsed into tags.
+pub async fn invalid_media(State(state): State<AppState>) -> ApiResult<InvalidMediaResponse> {
+    let snapshot = state.snapshot.read().await;
+    let items: Vec<MediaFile> = snapshot
+        .media
+        .iter()
+        .filter(|media| !media.invalid_tokens.is_empty())
+        .cloned()
+        .collect();
+    Ok(Json(InvalidMediaResponse {
+        total: items.len(),
+        items,
+    }))
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DuplicatesResponse {
+    pub groups: Vec<DuplicateGroup>,
+    pub total: usize,
+}
+
+/// Media sharing one content fingerprint, ordered by relative path.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DuplicateGroup {
+    pub hash: String,
+    pub filesize: u64,
+    pub items: Vec<DuplicateMember>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DuplicateMember {
+    pub id: String,
+    pub relative_path: String,
+}
+
+/// Group media by content hash and list every group with more than one member, the
+/// largest files first since they free the most space.
+pub async fn duplicate_media(State(state): State<AppState>) -> ApiResult<DuplicatesResponse> {
+    let snapshot = state.snapshot.read().await;
+    let mut by_hash: HashMap<&str, Vec<&MediaFile>> = HashMap::new();
+    for media in &snapshot.media {
+        if let Some(hash) = media.hash.as_deref() {
+            by_hash.entry(hash).or_default().push(media);
+        }
+    }
+
+    let mut groups: Vec<DuplicateGroup> = by_hash
+        .into_iter()
+        .filter(|(_, members)| members.len() > 1)
+        .map(|(hash, mut members)| {
+            members.sort_by(|a, b| a.relative_path.cmp(&b.relative_path));
+            DuplicateGroup {
+                hash: hash.to_string(),
+                filesize: members[0].filesize,
+                items: members
+                    .into_iter()
+                    .map(|media| DuplicateMember {
+                        id: media.id.clone(),
+                        relative_path: media.relative_path.clone(),
+                    })
+                    .collect(),
+            }
+        })
+        .collect();
+    groups.sort_by(|a, b| {
+        b.filesize
+            .cmp(&a.filesize)
+            .then_with(|| a.hash.cmp(&b.hash))
+    });
+
+    Ok(Json(DuplicatesResponse {
+        total: groups.len(),
+        groups,
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::test_support::{app_state_with_media, get, kv_tag, sample_media};
+    use axum::http::StatusCode;
+    use tempfile::tempdir;
+
+    fn rated_media(id: &str) -> MediaFile {
+        sample_media(id, vec![kv_tag("rating", "5")])
+    }
+
+    #[tokio::test]
+    async fn returns_media_record_for_known_id() {
+        let (state, _tmp) = app_state_with_media(vec![rated_media("first"), rated_media("second")]);
+        let (status, payload) = get(state, "/api/v1/media/second").await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(payload["id"], "second");
+        assert_eq!(payload["relativePath"], "second.png");
+        assert_eq!(payload["mediaType"], "image");
+        assert_eq!(payload["attributes"]["rating"], "5");
+        assert_eq!(payload["tags"][0]["normalized"], "rating=5");
+    }
+
+    #[tokio::test]
+    async fn groups_media_with_identical_content() {
+        let root = tempdir().unwrap();
+        std::fs::write(root.path().join("beach.png"), b"same bytes").unwrap();
+        std::fs::write(root.path().join("beach copy.png"), b"same bytes").unwrap();
+        std::fs::write(root.path().join("forest.png"), b"other bytes").unwrap();
+        let media = crate::indexer::Indexer::scan_once(root.path()).unwrap();
+        let (state, _tmp) = app_state_with_media(media);
+
+        let (status, payload) = get(state, "/api/v1/media/duplicates").await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(payload["total"], 1);
+        let group = &payload["groups"][0];
+        assert_eq!(group["filesize"], 10);
+        let paths: Vec<&str> = group["items"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|item| item["relativePath"].as_str().unwrap())
+            .collect();
+        assert_eq!(paths, ["beach copy.png", "beach.png"]);
+        assert!(group["items"][0]["id"].is_string());
+    }
+
+    #[tokio::test]
+    async fn related_lists_other_media_sharing_tags() {
+        let (state, _tmp) = app_state_with_media(vec![
+            rated_media("first"),
+            rated_media("second"),
+            sample_media("third", Vec::new()),
+        ]);
+        let (status, payload) = get(state.clone(), "/api/v1/media/first/related?limit=5").await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(payload["items"].as_array().unwrap().len(), 1);
+        assert_eq!(payload["items"][0]["id"], "second");
+        assert_eq!(payload["pageSize"], 5);
+
+        let (status, payload) = get(state, "/api/v1/media/missing/related").await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+        assert_eq!(payload["error"]["code"], "RESOURCE_NOT_FOUND");
+    }
+
+    #[tokio::test]
+    async fn returns_not_found_envelope_for_unknown_id() {
+        let (state, _tmp) = app_state_with_media(vec![rated_media("first")]);
+        let (status, payload) = get(state, "/api/v1/media/missing").await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+        assert_eq!(payload["error"]["code"], "RESOURCE_NOT_FOUND");
+        assert_eq!(payload["error"]["message"], "media not found");
+    }
+}
@@ -1,15 +1,29 @@
 use axum::{
-    Json,
+    BoxError, Json,
     body::Body,
-    http::{Request, StatusCode},
+    http::{
+        HeaderMap, Request, StatusCode,
+        header::{IF_MODIFIED_SINCE, IF_NONE_MATCH},
+    },
     middleware::Next,
     response::{IntoResponse, Response},
 };
+use chrono::{DateTime, Utc};
 use serde::Serialize;
 use thiserror::Error;
-
+use utoipa::ToSchema;
+
+pub mod archive;
+pub mod auth;
+pub mod client_ip;
+pub mod detail;
+pub mod openapi;
+pub mod rate_limit;
 pub mod search;
 pub mod stream;
+pub mod tags;
+#[cfg(test)]
+mod test_support;
 pub mod thumbnails;
 
 /// Result alias for JSON payloads that map API errors automatically.
@@ -18,15 +32,19 @@ pub type ApiResult<T> = Result<Json<T>, ApiError>;
 /// Result alias for JSON payloads that also customize the HTTP status code.
 pub type ApiResponse<T> = Result<(StatusCode, Json<T>), ApiError>;
 
-/// Machine-readable error codes mirrored in the OpenAPI schema.
-#[derive(Debug, Clone, Copy, Serialize, PartialEq, Eq)]
+/// Machine-readable error codes; the generated OpenAPI document (`/api/v1/openapi.json`)
+/// derives its `ErrorCode` enum from this one.
+#[derive(Debug, Clone, Copy, Serialize, PartialEq, Eq, ToSchema)]
 #[serde(rename_all = "SCREAMING_SNAKE_CASE")]
 pub enum ErrorCode {
     ValidationFailed,
     Unauthorized,
     Forbidden,
     MethodNotAllowed,
+    UnsupportedMediaType,
     ResourceNotFound,
+    /// The id is still indexed but its file has disappeared from disk.
+    Gone,
     Conflict,
     TooManyRequests,
     InternalServerError,
@@ -40,7 +58,9 @@ impl ErrorCode {
             ErrorCode::Unauthorized => StatusCode::UNAUTHORIZED,
             ErrorCode::Forbidden => StatusCode::FORBIDDEN,
             ErrorCode::MethodNotAllowed => StatusCode::METHOD_NOT_ALLOWED,
+            ErrorCode::UnsupportedMediaType => StatusCode::UNSUPPORTED_MEDIA_TYPE,
             ErrorCode::ResourceNotFound => StatusCode::NOT_FOUND,
+            ErrorCode::Gone => StatusCode::GONE,
             ErrorCode::Conflict => StatusCode::CONFLICT,
             ErrorCode::TooManyRequests => StatusCode::TOO_MANY_REQUESTS,
             ErrorCode::InternalServerError => StatusCode::INTERNAL_SERVER_ERROR,
@@ -50,13 +70,13 @@ impl ErrorCode {
 }
 
 /// Error envelope returned to HTTP clients.
-#[derive(Debug, Clone, Serialize)]
+#[derive(Debug, Clone, Serialize, ToSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct ErrorResponse {
     pub error: ErrorBody,
 }
 
-#[derive(Debug, Clone, Serialize)]
+#[derive(Debug, Clone, Serialize, ToSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct ErrorBody {
     pub code: ErrorCode,
@@ -122,6 +142,11 @@ impl ApiError {
         Self::new(ErrorCode::ResourceNotFound, message)
     }
 
+    /// Build a gone error (HTTP 410) for indexed media whose file was removed.
+    pub fn gone(message: impl Into<String>) -> Self {
+        Self::new(ErrorCode::Gone, message)
+    }
+
     /// Build a method-not-allowed error (HTTP 405).
     pub fn method_not_allowed(message: impl Into<String>) -> Self {
         Self::new(ErrorCode::MethodNotAllowed, message)
@@ -210,9 +235,7 @@ impl IntoResponse for ApiError {
             error: ErrorBody { code, message },
         };
         let mut response = (status, Json(payload)).into_response();
-        response
-            .extensions_mut()
-            .insert(ErrorEnvelopeApplied::default());
+        response.extensions_mut().insert(ErrorEnvelopeApplied);
         response
     }
 }
@@ -226,27 +249,113 @@ impl From<anyhow::Error> for ApiError {
 #[derive(Clone, Copy, Debug, Default)]
 struct ErrorEnvelopeApplied;
 
-/// Middleware that rewrites Axum default errors into the shared envelope.
+/// Longest extractor rejection text carried over into the envelope message.
+const MAX_REJECTION_MESSAGE_BYTES: usize = 1024;
+
+/// Middleware that rewrites Axum default errors, including extractor rejections, into
+/// the shared envelope.
 pub async fn ensure_error_envelope(req: Request<Body>, next: Next) -> Response {
     let response = next.run(req).await;
     let status = response.status();
 
-    if (status == StatusCode::METHOD_NOT_ALLOWED || status == StatusCode::NOT_FOUND)
-        && response
-            .extensions()
-            .get::<ErrorEnvelopeApplied>()
-            .is_none()
+    if !matches!(
+        status,
+        StatusCode::BAD_REQUEST
+            | StatusCode::METHOD_NOT_ALLOWED
+            | StatusCode::NOT_FOUND
+            | StatusCode::PAYLOAD_TOO_LARGE
+            | StatusCode::UNSUPPORTED_MEDIA_TYPE
+            | StatusCode::UNPROCESSABLE_ENTITY
+    ) || response
+        .extensions()
+        .get::<ErrorEnvelopeApplied>()
+        .is_some()
     {
-        return match status {
-            StatusCode::METHOD_NOT_ALLOWED => {
-                ApiError::method_not_allowed("method not allowed").into_response()
-            }
-            StatusCode::NOT_FOUND => ApiError::not_found("route not found").into_response(),
-            _ => unreachable!(),
-        };
+        return response;
+    }
+
+    match status {
+        StatusCode::METHOD_NOT_ALLOWED => {
+            ApiError::method_not_allowed("method not allowed").into_response()
+        }
+        StatusCode::NOT_FOUND => ApiError::not_found("route not found").into_response(),
+        StatusCode::PAYLOAD_TOO_LARGE => ApiError::with_status(
+            StatusCode::PAYLOAD_TOO_LARGE,
+            ErrorCode::ValidationFailed,
+            "request body too large",
+        )
+        .into_response(),
+        StatusCode::UNSUPPORTED_MEDIA_TYPE => ApiError::new(
+            ErrorCode::UnsupportedMediaType,
+            rejection_message(response, "unsupported content type").await,
+        )
+        .into_response(),
+        // Query, path and body rejections: malformed (400) or unusable (422) input.
+        _ => ApiError::with_status(
+            status,
+            ErrorCode::ValidationFailed,
+            rejection_message(response, "invalid request").await,
+        )
+        .into_response(),
+    }
+}
+
+/// The plain-text reason axum puts in rejection bodies, or `fallback` when it is empty.
+async fn rejection_message(response: Response, fallback: &str) -> String {
+    let body = axum::body::to_bytes(response.into_body(), MAX_REJECTION_MESSAGE_BYTES)
+        .await
+        .unwrap_or_default();
+    match String::from_utf8_lossy(&body).trim() {
+        "" => fallback.to_string(),
+        message => message.to_string(),
+    }
+}
+
+/// Reject path ids that the indexer could not have produced before they reach a
+/// snapshot lookup or get joined onto a cache path.
+pub(crate) fn validate_media_id(id: &str) -> Result<(), ApiError> {
+    if crate::indexer::is_valid_media_id(id) {
+        Ok(())
+    } else {
+        Err(ApiError::bad_request("invalid media id"))
     }
+}
+
+/// Whether the request's `If-None-Match` list names `etag` (weak comparison, `*` matches all).
+pub(crate) fn if_none_match(headers: &HeaderMap, etag: &str) -> bool {
+    let strip_weak = |tag: &str| tag.trim().trim_start_matches("W/").to_string();
+    let etag = strip_weak(etag);
+    headers
+        .get_all(IF_NONE_MATCH)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .any(|candidate| candidate.trim() == "*" || strip_weak(candidate) == etag)
+}
 
-    response
+/// Format `time` as an HTTP date (`Last-Modified` and friends).
+pub(crate) fn http_date(time: DateTime<Utc>) -> String {
+    time.format("%a, %d %b %Y %H:%M:%S GMT").to_string()
+}
+
+/// Whether `If-Modified-Since` is at or after `modified` (compared at second precision,
+/// the resolution of HTTP dates). Unparseable dates are ignored.
+pub(crate) fn not_modified_since(headers: &HeaderMap, modified: DateTime<Utc>) -> bool {
+    headers
+        .get(IF_MODIFIED_SINCE)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| DateTime::parse_from_rfc2822(value.trim()).ok())
+        .is_some_and(|since| modified.timestamp() <= since.timestamp())
+}
+
+/// Map errors from the request timeout layer onto the envelope instead of dropping the
+/// connection.
+pub async fn handle_layer_error(err: BoxError) -> ApiError {
+    if err.is::<tower::timeout::error::Elapsed>() {
+        ApiError::service_unavailable("request timed out")
+    } else {
+        ApiError::internal_with_source(anyhow::anyhow!(err))
+    }
 }
 
 /// Fallback handler ensuring unknown routes return the API envelope.
@@ -294,6 +403,39 @@ mod tests {
         assert_eq!(json["error"]["message"], "internal server error");
     }
 
+    #[test]
+    fn compares_if_modified_since_at_second_precision() {
+        let modified = DateTime::parse_from_rfc3339("2024-05-01T12:00:00.750Z")
+            .unwrap()
+            .with_timezone(&Utc);
+        assert_eq!(http_date(modified), "Wed, 01 May 2024 12:00:00 GMT");
+
+        let mut headers = HeaderMap::new();
+        assert!(!not_modified_since(&headers, modified));
+        headers.insert(IF_MODIFIED_SINCE, http_date(modified).parse().unwrap());
+        assert!(not_modified_since(&headers, modified));
+        headers.insert(
+            IF_MODIFIED_SINCE,
+            "Wed, 01 May 2024 11:59:59 GMT".parse().unwrap(),
+        );
+        assert!(!not_modified_since(&headers, modified));
+        headers.insert(IF_MODIFIED_SINCE, "yesterday".parse().unwrap());
+        assert!(!not_modified_since(&headers, modified));
+    }
+
+    #[test]
+    fn if_none_match_compares_weakly_across_lists() {
+        let mut headers = HeaderMap::new();
+        assert!(!if_none_match(&headers, "\"a\""));
+
+        headers.insert(IF_NONE_MATCH, "\"b\", W/\"a\"".parse().unwrap());
+        assert!(if_none_match(&headers, "\"a\""));
+        assert!(!if_none_match(&headers, "\"c\""));
+
+        headers.insert(IF_NONE_MATCH, "*".parse().unwrap());
+        assert!(if_none_match(&headers, "\"c\""));
+    }
+
     #[test]
     fn helper_builders_emit_expected_statuses() {
         assert_eq!(
@@ -0,0 +1,35 @@
+use axum::Json;
+use utoipa::OpenApi;
+
+use crate::{
+    api::{ErrorBody, ErrorCode, ErrorResponse, detail, search},
+    indexer::{Dimensions, GeoPoint, MediaFile, MediaType},
+    tags::{Tag, TagKind},
+};
+
+/// OpenAPI document generated from the handler annotations and API types, so schemas
+/// such as the error codes cannot drift from the code.
+#[derive(OpenApi)]
+#[openapi(
+    info(title = "Galarie API"),
+    paths(search::media_search, detail::media_detail),
+    components(schemas(
+        MediaFile,
+        Dimensions,
+        GeoPoint,
+        MediaType,
+        Tag,
+        TagKind,
+        search::MediaSearchResponse,
+        search::SearchItems,
+        ErrorResponse,
+        ErrorBody,
+        ErrorCode,
+    ))
+)]
+pub struct ApiDoc;
+
+/// Serve the generated OpenAPI document.
+pub async fn openapi_json() -> Json<utoipa::openapi::OpenApi> {
+    Json(ApiDoc::openapi())
+}
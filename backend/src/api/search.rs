@@ -1,196 +1,477 @@
-use std::collections::HashMap;
+use std::{collections::HashMap, time::Instant};
 
 use axum::{
     Json,
+    body::Body,
     extract::{Query, State},
+    http::{
+        HeaderMap, StatusCode,
+        header::{CACHE_CONTROL, ETAG, IF_NONE_MATCH, LAST_MODIFIED},
+    },
+    response::{IntoResponse, Response},
 };
 use serde::Deserialize;
+use sha1::{Digest, Sha1};
 
 use crate::{
-    api::{ApiError, ApiResult},
+    api::{ApiError, http_date, if_none_match, not_modified_since},
+    config::AppConfig,
+    o11y::metrics,
     routes::AppState,
-    services::search::{SearchQuery, SearchResult, SearchService},
+    services::search::{
+        BoundingBox, SearchQuery, SearchResult, SearchService, SortField, SortOrder, SortSpec,
+        TagClause, TagMatch,
+    },
+    tags::normalize_tag_text,
 };
 
+/// Upper bound on `ids=` values accepted in one search request.
+const MAX_REQUESTED_IDS: usize = 500;
+
 #[derive(Debug, Deserialize, Default)]
 #[serde(rename_all = "camelCase")]
 pub struct RawSearchParams {
     pub tags: Option<String>,
+    /// Comma-separated media ids returned in that order; bypasses every filter.
+    pub ids: Option<String>,
+    /// Comma-separated tags of which at least one must be present (ANDed with `tags`).
+    pub any_tags: Option<String>,
     pub page: Option<usize>,
     #[serde(rename = "pageSize")]
     pub page_size: Option<usize>,
+    pub cursor: Option<String>,
+    pub sort: Option<String>,
+    pub order: Option<String>,
+    /// Required with `sort=random`; the same seed reproduces the same order.
+    pub seed: Option<String>,
+    /// Simple-tag comparison: `exact` (default), `substring`, or `fuzzy`.
+    #[serde(rename = "match")]
+    pub tag_match: Option<String>,
+    /// `true` compares attribute values exactly instead of case-insensitively.
+    pub case_sensitive: Option<String>,
+    /// `id` trims each item to its id for lightweight polling.
+    pub fields: Option<String>,
+    /// `minLon,minLat,maxLon,maxLat`: only media located inside the box.
+    pub bbox: Option<String>,
     #[serde(flatten)]
     pub rest: HashMap<String, String>,
 }
 
-#[derive(Debug, serde::Serialize)]
+#[derive(Debug, serde::Serialize, utoipa::ToSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct MediaSearchResponse {
-    pub items: Vec<crate::indexer::MediaFile>,
+    pub items: SearchItems,
     pub total: usize,
+    pub grand_total: usize,
     pub page: usize,
     pub page_size: usize,
+    pub total_pages: usize,
+    pub has_next: bool,
+    pub has_prev: bool,
+    pub next_cursor: Option<String>,
+}
+
+/// Items of a search page: full records, or only their ids when `fields=id`.
+#[derive(Debug, serde::Serialize, utoipa::ToSchema)]
+#[serde(untagged)]
+pub enum SearchItems {
+    Full(Vec<crate::indexer::MediaFile>),
+    Ids(Vec<String>),
+}
+
+impl SearchItems {
+    fn into_ids(self) -> Self {
+        match self {
+            Self::Full(items) => Self::Ids(items.into_iter().map(|media| media.id).collect()),
+            ids => ids,
+        }
+    }
 }
 
+/// Search the current snapshot. Responses carry a weak ETag derived from the snapshot
+/// version and the normalized query, plus the snapshot time as `Last-Modified`, so
+/// clients can revalidate a page with `If-None-Match` / `If-Modified-Since` and get a
+/// `304` without the search being re-run.
+#[utoipa::path(
+    get,
+    path = "/api/v1/media",
+    tag = "media",
+    params(
+        ("ids" = Option<String>, Query, description = "Comma-separated media ids (at most 500) returned in request order; unknown ids are skipped and other filters ignored"),
+        ("tags" = Option<String>, Query, description = "Comma-separated tag terms (AND); `a|b` alternatives, `-a` negation"),
+        ("anyTags" = Option<String>, Query, description = "Comma-separated tags of which at least one must be present"),
+        ("attributes[{key}]" = Option<String>, Query, description = "Comma-separated values for an attribute key, optionally with a >=, <=, > or < prefix"),
+        ("caseSensitive" = Option<bool>, Query, description = "Compare attribute values exactly"),
+        ("page" = Option<usize>, Query, description = "1-based page number; exclusive with cursor"),
+        ("pageSize" = Option<usize>, Query, description = "Items per page, clamped to the configured maximum"),
+        ("cursor" = Option<String>, Query, description = "Resume after this media id"),
+        ("sort" = Option<String>, Query, description = "id, name, filesize, indexedAt, modifiedAt, duration or random"),
+        ("order" = Option<String>, Query, description = "asc or desc"),
+        ("seed" = Option<String>, Query, description = "Required with sort=random"),
+        ("match" = Option<String>, Query, description = "exact, substring or fuzzy"),
+        ("fields" = Option<String>, Query, description = "`id` returns only media ids"),
+        ("bbox" = Option<String>, Query, description = "minLon,minLat,maxLon,maxLat; only media whose EXIF location falls inside"),
+    ),
+    responses(
+        (status = 200, description = "Paginated media list", body = MediaSearchResponse),
+        (status = 304, description = "Not modified (ETag / Last-Modified revalidation)"),
+        (status = 400, description = "Invalid query", body = crate::api::ErrorResponse),
+    )
+)]
 pub async fn media_search(
     State(state): State<AppState>,
     Query(params): Query<RawSearchParams>,
-) -> ApiResult<MediaSearchResponse> {
-    let tags = parse_tags(params.tags.as_deref()).map_err(|msg| ApiError::bad_request(msg))?;
+    headers: HeaderMap,
+) -> Result<Response, ApiError> {
+    let query = search_query(&params, &state.config)?;
+    let ids_only = parse_fields(params.fields.as_deref()).map_err(ApiError::bad_request)?;
+    let requested_ids = parse_ids(params.ids.as_deref()).map_err(ApiError::bad_request)?;
+    let snapshot = state.snapshot.read().await;
+    let cache_key = match &requested_ids {
+        Some(ids) => format!("ids={ids:?}"),
+        None => query.cache_key(),
+    };
+    let etag = search_etag(&snapshot.generated_at.to_rfc3339(), &cache_key, ids_only);
+    let validators = [
+        (CACHE_CONTROL, "no-cache".to_string()),
+        (ETAG, etag.clone()),
+        (LAST_MODIFIED, http_date(snapshot.generated_at)),
+    ];
+    // If-None-Match takes precedence; If-Modified-Since only applies without it.
+    let not_modified = if headers.contains_key(IF_NONE_MATCH) {
+        if_none_match(&headers, &etag)
+    } else {
+        not_modified_since(&headers, snapshot.generated_at)
+    };
+    if not_modified {
+        return Ok((StatusCode::NOT_MODIFIED, validators, Body::empty()).into_response());
+    }
+
+    let started = Instant::now();
+    let result = match &requested_ids {
+        Some(ids) => SearchService::by_ids(&snapshot, ids),
+        None => SearchService::search(&snapshot, &query),
+    };
+    metrics::record_search(started);
+
+    let mut response = MediaSearchResponse::from(result);
+    if ids_only {
+        response.items = response.items.into_ids();
+    }
+    Ok((validators, Json(response)).into_response())
+}
 
-    let attributes = parse_attributes(&params.rest);
-    let query = SearchQuery::new(
-        tags,
+/// Build the filter, sort and pagination of a search from its query parameters.
+pub(crate) fn search_query(
+    params: &RawSearchParams,
+    config: &AppConfig,
+) -> Result<SearchQuery, ApiError> {
+    let page_limits = config.page_limits;
+    if params.page.is_some() && params.cursor.is_some() {
+        return Err(ApiError::bad_request(
+            "page and cursor query parameters are mutually exclusive",
+        ));
+    }
+    let tag_clauses = parse_tags(params.tags.as_deref()).map_err(ApiError::bad_request)?;
+    let any_tags = parse_any_tags(params.any_tags.as_deref()).map_err(ApiError::bad_request)?;
+    let sort = parse_sort(
+        params.sort.as_deref(),
+        params.order.as_deref(),
+        params.seed.as_deref(),
+    )
+    .map_err(ApiError::bad_request)?;
+    let tag_match = parse_tag_match(params.tag_match.as_deref()).map_err(ApiError::bad_request)?;
+    let case_sensitive =
+        parse_case_sensitive(params.case_sensitive.as_deref()).map_err(ApiError::bad_request)?;
+    let attributes = parse_attributes(&params.rest).map_err(ApiError::bad_request)?;
+    let bbox = parse_bbox(params.bbox.as_deref()).map_err(ApiError::bad_request)?;
+
+    Ok(SearchQuery::new(
+        Vec::new(),
         attributes,
         params.page.unwrap_or(1),
-        params.page_size.unwrap_or(60),
-    );
-    let snapshot = state.snapshot.read().await;
-    let result = SearchService::search(&snapshot, &query);
+        params.page_size.unwrap_or(page_limits.default_size),
+    )
+    .with_page_limits(page_limits)
+    .with_tag_clauses(tag_clauses)
+    .with_any_tags(any_tags)
+    .with_tag_aliases(&config.indexer.tag_aliases)
+    .with_tag_match(tag_match)
+    .with_case_sensitive_attributes(case_sensitive)
+    .with_cursor(params.cursor.clone())
+    .with_bbox(bbox)
+    .with_sort(sort))
+}
 
-    Ok(Json(MediaSearchResponse::from(result)))
+/// Weak validator for a search page: the same snapshot and equivalent query always
+/// serialize to the same JSON.
+fn search_etag(snapshot_version: &str, cache_key: &str, ids_only: bool) -> String {
+    let mut hasher = Sha1::new();
+    hasher.update(snapshot_version.as_bytes());
+    hasher.update(cache_key.as_bytes());
+    hasher.update([u8::from(ids_only)]);
+    format!("W/\"{:x}\"", hasher.finalize())
 }
 
 impl From<SearchResult> for MediaSearchResponse {
     fn from(value: SearchResult) -> Self {
         Self {
-            items: value.items,
+            items: SearchItems::Full(value.items),
             total: value.total,
+            grand_total: value.grand_total,
             page: value.page,
             page_size: value.page_size,
+            total_pages: value.total_pages,
+            has_next: value.has_next,
+            has_prev: value.has_prev,
+            next_cursor: value.next_cursor,
         }
     }
 }
 
-fn parse_tags(raw: Option<&str>) -> Result<Vec<String>, &'static str> {
+/// Parse the `tags` grammar: comma-separated terms are ANDed, `|` separates OR
+/// alternatives within a term, and a leading `-` negates the term.
+pub(crate) fn parse_tags(raw: Option<&str>) -> Result<Vec<TagClause>, &'static str> {
     let Some(raw) = raw else {
         return Ok(Vec::new());
     };
 
-    let tags: Vec<String> = raw
+    let mut clauses = Vec::new();
+    for term in raw
         .split(',')
-        .map(|token| token.trim().to_lowercase())
-        .filter(|token| !token.is_empty())
-        .collect();
+        .map(str::trim)
+        .filter(|term| !term.is_empty())
+    {
+        let (negated, body) = match term.strip_prefix('-') {
+            Some(rest) => (true, rest),
+            None => (false, term),
+        };
+        let clause = TagClause::new(body.split('|'), negated)
+            .ok_or("tags query parameter contains an empty term")?;
+        clauses.push(clause);
+    }
 
-    if tags.is_empty() {
+    if clauses.is_empty() {
         Err("tags query parameter must contain at least one value")
+    } else {
+        Ok(clauses)
+    }
+}
+
+/// Parse `anyTags`: plain comma-separated names, at least one of which must match.
+pub(crate) fn parse_any_tags(raw: Option<&str>) -> Result<Vec<&str>, &'static str> {
+    let Some(raw) = raw else {
+        return Ok(Vec::new());
+    };
+    let tags: Vec<&str> = raw
+        .split(',')
+        .map(str::trim)
+        .filter(|tag| !tag.is_empty())
+        .collect();
+    if tags.is_empty() {
+        Err("anyTags query parameter must contain at least one value")
     } else {
         Ok(tags)
     }
 }
 
-fn parse_attributes(rest: &HashMap<String, String>) -> HashMap<String, Vec<String>> {
-    let mut attributes = HashMap::new();
+/// `ids=a,b,c`: `None` when omitted; at most [`MAX_REQUESTED_IDS`] non-empty ids.
+fn parse_ids(raw: Option<&str>) -> Result<Option<Vec<&str>>, String> {
+    let Some(raw) = raw else {
+        return Ok(None);
+    };
+    let ids: Vec<&str> = raw
+        .split(',')
+        .map(str::trim)
+        .filter(|id| !id.is_empty())
+        .collect();
+    if ids.is_empty() {
+        return Err("ids query parameter must contain at least one value".to_string());
+    }
+    if ids.len() > MAX_REQUESTED_IDS {
+        return Err(format!(
+            "ids accepts at most {MAX_REQUESTED_IDS} values (got {})",
+            ids.len()
+        ));
+    }
+    Ok(Some(ids))
+}
+
+/// `bbox=minLon,minLat,maxLon,maxLat` in decimal degrees; `minLon > maxLon` wraps
+/// across the antimeridian.
+fn parse_bbox(raw: Option<&str>) -> Result<Option<BoundingBox>, String> {
+    let Some(raw) = raw.map(str::trim).filter(|raw| !raw.is_empty()) else {
+        return Ok(None);
+    };
+    let invalid = || format!("bbox must be minLon,minLat,maxLon,maxLat in degrees (got '{raw}')");
+    let coordinates = raw
+        .split(',')
+        .map(|value| value.trim().parse::<f64>())
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|_| invalid())?;
+    let [min_lon, min_lat, max_lon, max_lat] = coordinates[..] else {
+        return Err(invalid());
+    };
+    BoundingBox::new(min_lon, min_lat, max_lon, max_lat)
+        .map(Some)
+        .ok_or_else(invalid)
+}
+
+/// `caseSensitive=true|false`; omitted means case-insensitive.
+pub(crate) fn parse_case_sensitive(value: Option<&str>) -> Result<bool, String> {
+    match value.map(str::trim) {
+        None | Some("") => Ok(false),
+        Some(value) if value.eq_ignore_ascii_case("true") => Ok(true),
+        Some(value) if value.eq_ignore_ascii_case("false") => Ok(false),
+        Some(value) => Err(format!(
+            "caseSensitive must be true or false (got '{value}')"
+        )),
+    }
+}
+
+/// `fields=id` selects the id-only projection; omitting it returns full records.
+fn parse_fields(fields: Option<&str>) -> Result<bool, String> {
+    match fields.map(str::trim) {
+        None | Some("") => Ok(false),
+        Some(value) if value.eq_ignore_ascii_case("id") => Ok(true),
+        Some(value) => Err(format!("fields must be 'id' (got '{value}')")),
+    }
+}
+
+fn parse_tag_match(value: Option<&str>) -> Result<TagMatch, String> {
+    match value
+        .map(|value| value.trim().to_ascii_lowercase())
+        .as_deref()
+    {
+        None | Some("") | Some("exact") => Ok(TagMatch::Exact),
+        Some("substring") => Ok(TagMatch::Substring),
+        Some("fuzzy") => Ok(TagMatch::Fuzzy),
+        Some(_) => Err(format!(
+            "match must be one of exact, substring, fuzzy (got '{}')",
+            value.unwrap_or_default()
+        )),
+    }
+}
+
+fn parse_sort(
+    sort: Option<&str>,
+    order: Option<&str>,
+    seed: Option<&str>,
+) -> Result<SortSpec, String> {
+    let field = match sort
+        .map(|value| value.trim().to_ascii_lowercase())
+        .as_deref()
+    {
+        None | Some("") | Some("id") => SortField::Id,
+        Some("name") => SortField::Name,
+        Some("filesize") => SortField::Filesize,
+        Some("indexedat") => SortField::IndexedAt,
+        Some("modifiedat") => SortField::ModifiedAt,
+        Some("duration") => SortField::Duration,
+        Some("random") => {
+            let seed = seed
+                .and_then(|seed| seed.trim().parse::<u64>().ok())
+                .ok_or("sort=random requires a non-negative integer seed")?;
+            SortField::Random { seed }
+        }
+        Some(_) => {
+            return Err(format!(
+                "sort must be one of id, name, filesize, indexedAt, modifiedAt, duration, random (got '{}')",
+                sort.unwrap_or_default()
+            ));
+        }
+    };
+    let order = match order
+        .map(|value| value.trim().to_ascii_lowercase())
+        .as_deref()
+    {
+        None | Some("") | Some("asc") => SortOrder::Asc,
+        Some("desc") => SortOrder::Desc,
+        Some(_) => return Err("order must be asc or desc".to_string()),
+    };
+    Ok(SortSpec::new(field, order))
+}
+
+/// Collect `attributes[key]=v1,v2` filters. Values may carry a comparison prefix
+/// (`>=4`); the `attributes[key][gte]=4` form is rewritten into that prefix form.
+/// Keys starting with `attributes[` that are malformed or carry no value are rejected
+/// with a message naming the key; other unknown query keys are ignored.
+pub(crate) fn parse_attributes(
+    rest: &HashMap<String, String>,
+) -> Result<HashMap<String, Vec<String>>, String> {
+    let mut attributes: HashMap<String, Vec<String>> = HashMap::new();
     for (key, value) in rest {
-        if let Some(name) = key
-            .strip_prefix("attributes[")
-            .and_then(|s| s.strip_suffix(']'))
-        {
-            let values = value
-                .split(',')
-                .map(|token| token.trim().to_lowercase())
-                .filter(|token| !token.is_empty())
-                .collect::<Vec<_>>();
-            if !values.is_empty() {
-                attributes.insert(name.to_lowercase(), values);
-            }
+        let Some(inner) = key.strip_prefix("attributes[") else {
+            continue;
+        };
+        let (name, operator) = inner
+            .strip_suffix(']')
+            .ok_or_else(|| format!("attribute filter '{key}' is missing a closing ']'"))
+            .and_then(|inner| split_attribute_key(key, inner))?;
+
+        // Values keep their case; the search query lowercases them unless
+        // `caseSensitive=true`.
+        let values = value
+            .split(',')
+            .map(str::trim)
+            .filter(|token| !token.is_empty())
+            .map(|token| format!("{operator}{token}"))
+            .collect::<Vec<_>>();
+        if values.is_empty() {
+            return Err(format!("attribute filter '{key}' has an empty value"));
         }
+        attributes
+            .entry(normalize_tag_text(name))
+            .or_default()
+            .extend(values);
     }
-    attributes
+    Ok(attributes)
+}
+
+/// Split `rating` or `rating][gte` into the attribute name and its comparison prefix.
+fn split_attribute_key<'a>(key: &str, inner: &'a str) -> Result<(&'a str, &'static str), String> {
+    let (name, prefix) = match inner.split_once("][") {
+        None => (inner, ""),
+        Some((name, operator)) => {
+            let prefix = match operator {
+                "eq" => "",
+                "gt" => ">",
+                "gte" => ">=",
+                "lt" => "<",
+                "lte" => "<=",
+                _ => {
+                    return Err(format!(
+                        "attribute filter '{key}' has unknown operator '{operator}' \
+                         (expected eq, gt, gte, lt or lte)"
+                    ));
+                }
+            };
+            (name, prefix)
+        }
+    };
+    if name.trim().is_empty() || name.contains(['[', ']']) {
+        return Err(format!(
+            "attribute filter '{key}' has an empty or malformed name"
+        ));
+    }
+    Ok((name, prefix))
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
     use crate::{
-        cache::CacheSnapshot,
-        config::{AppConfig, LogConfig, OtelConfig},
-        indexer::{MediaFile, MediaType},
-        tags::{Tag, TagKind},
+        api::test_support::{app_state_with_media, kv_tag, sample_media, simple_tag},
+        services::search::PageLimits,
     };
     use axum::{
         body::Body,
         http::{Method, Request},
     };
-    use chrono::Utc;
     use http_body_util::BodyExt;
-    use std::{net::SocketAddr, sync::Arc};
-    use tempfile::tempdir;
-    use tokio::sync::RwLock;
+    use std::sync::Arc;
     use tower::ServiceExt;
 
-    fn app_state_with_media(media: Vec<MediaFile>) -> AppState {
-        let tmp = tempdir().unwrap();
-        let config = Arc::new(AppConfig {
-            media_root: tmp.path().to_path_buf(),
-            cache_dir: tmp.path().to_path_buf(),
-            listen_addr: SocketAddr::from(([127, 0, 0, 1], 0)),
-            environment: "test".into(),
-            otel: OtelConfig {
-                endpoint: None,
-                service_name: "test".into(),
-                disable_traces: true,
-                disable_logs: true,
-            },
-            log: LogConfig {
-                level: "info".into(),
-            },
-            cors_allowed_origins: Vec::new(),
-            frontend_dist_dir: None,
-        });
-        let cache_store = Arc::new(crate::cache::CacheStore::new(tmp.path()));
-        let snapshot = CacheSnapshot::new(media);
-        AppState::new(config, cache_store, Arc::new(RwLock::new(snapshot)))
-    }
-
-    fn sample_media(id: &str, tags: Vec<Tag>) -> MediaFile {
-        let mut attributes = HashMap::new();
-        for tag in &tags {
-            if matches!(tag.kind, TagKind::KeyValue) {
-                if let Some(value) = &tag.value {
-                    attributes
-                        .entry(tag.name.clone())
-                        .or_insert_with(|| value.clone());
-                }
-            }
-        }
-
-        MediaFile {
-            id: id.to_string(),
-            relative_path: format!("{id}.png"),
-            media_type: MediaType::Image,
-            tags,
-            attributes,
-            filesize: 0,
-            dimensions: None,
-            duration_ms: None,
-            thumbnail_path: Some(format!("/media/{id}/thumbnail")),
-            hash: None,
-            indexed_at: Utc::now(),
-        }
-    }
-
-    fn simple_tag(name: &str) -> Tag {
-        Tag {
-            raw_token: name.into(),
-            kind: TagKind::Simple,
-            name: name.to_lowercase(),
-            value: None,
-            normalized: name.to_lowercase(),
-        }
-    }
-
-    fn kv_tag(key: &str, value: &str) -> Tag {
-        Tag {
-            raw_token: format!("{key}-{value}"),
-            kind: TagKind::KeyValue,
-            name: key.to_lowercase(),
-            value: Some(value.to_lowercase()),
-            normalized: format!("{}={}", key.to_lowercase(), value.to_lowercase()),
-        }
-    }
-
     #[tokio::test]
     async fn allows_browsing_without_filters() {
         let media = vec![
@@ -200,7 +481,7 @@ mod tests {
             ),
             sample_media("macro_B", vec![simple_tag("macro"), kv_tag("rating", "4")]),
         ];
-        let state = app_state_with_media(media);
+        let (state, _tmp) = app_state_with_media(media);
         let router = crate::routes::router(state);
         let request = Request::builder()
             .method(Method::GET)
@@ -213,6 +494,271 @@ mod tests {
         let payload: serde_json::Value = serde_json::from_slice(&body).unwrap();
         assert_eq!(payload["total"], 2);
         assert_eq!(payload["items"].as_array().unwrap().len(), 2);
+        assert_eq!(payload["totalPages"], 1);
+        assert_eq!(payload["hasNext"], false);
+        assert_eq!(payload["hasPrev"], false);
+    }
+
+    #[tokio::test]
+    async fn returns_only_ids_when_fields_is_id() {
+        let media = vec![
+            sample_media(
+                "sunset_A",
+                vec![simple_tag("sunset"), kv_tag("rating", "5")],
+            ),
+            sample_media("sunset_B", vec![simple_tag("sunset")]),
+            sample_media("macro_C", vec![simple_tag("macro")]),
+        ];
+        let (state, _tmp) = app_state_with_media(media);
+        let router = crate::routes::router(state);
+        let request = Request::builder()
+            .method(Method::GET)
+            .uri("/api/v1/media?tags=sunset&fields=id&pageSize=1")
+            .body(Body::empty())
+            .unwrap();
+        let response = router.clone().oneshot(request).await.unwrap();
+        assert_eq!(response.status(), axum::http::StatusCode::OK);
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        let payload: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(payload["items"], serde_json::json!(["sunset_A"]));
+        assert_eq!(payload["total"], 2);
+        assert_eq!(payload["pageSize"], 1);
+        assert!(payload["nextCursor"].is_string());
+
+        let request = Request::builder()
+            .method(Method::GET)
+            .uri("/api/v1/media?fields=tags")
+            .body(Body::empty())
+            .unwrap();
+        let response = router.oneshot(request).await.unwrap();
+        assert_eq!(response.status(), axum::http::StatusCode::BAD_REQUEST);
+    }
+
+    #[tokio::test]
+    async fn fetches_requested_ids_in_order() {
+        let media = vec![
+            sample_media("sunset_A", vec![simple_tag("sunset")]),
+            sample_media("macro_B", vec![simple_tag("macro")]),
+            sample_media("video_C", vec![simple_tag("video")]),
+        ];
+        let (state, _tmp) = app_state_with_media(media);
+        let router = crate::routes::router(state);
+        // Tag filters and pagination are bypassed when ids are given.
+        let request = Request::builder()
+            .method(Method::GET)
+            .uri("/api/v1/media?ids=video_C,unknown,sunset_A&tags=macro&pageSize=1")
+            .body(Body::empty())
+            .unwrap();
+        let response = router.clone().oneshot(request).await.unwrap();
+        assert_eq!(response.status(), axum::http::StatusCode::OK);
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        let payload: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        let ids: Vec<&str> = payload["items"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|item| item["id"].as_str().unwrap())
+            .collect();
+        assert_eq!(ids, ["video_C", "sunset_A"]);
+        assert_eq!(payload["total"], 2);
+
+        let too_many = (0..=MAX_REQUESTED_IDS)
+            .map(|index| format!("id{index}"))
+            .collect::<Vec<_>>()
+            .join(",");
+        let request = Request::builder()
+            .method(Method::GET)
+            .uri(format!("/api/v1/media?ids={too_many}"))
+            .body(Body::empty())
+            .unwrap();
+        let response = router.oneshot(request).await.unwrap();
+        assert_eq!(response.status(), axum::http::StatusCode::BAD_REQUEST);
+    }
+
+    #[tokio::test]
+    async fn filters_by_bounding_box() {
+        let mut located = sample_media("sunset_A", vec![simple_tag("sunset")]);
+        located.location = Some(crate::indexer::GeoPoint {
+            latitude: 26.21,
+            longitude: 127.68,
+        });
+        let media = vec![
+            located,
+            sample_media("sunset_B", vec![simple_tag("sunset")]),
+        ];
+        let (state, _tmp) = app_state_with_media(media);
+        let router = crate::routes::router(state);
+
+        for (uri, expected) in [
+            ("/api/v1/media?bbox=127,26,128.5,27", Some(vec!["sunset_A"])),
+            ("/api/v1/media?bbox=0,0,1,1", Some(vec![])),
+            ("/api/v1/media?bbox=127,26,128.5", None),
+            ("/api/v1/media?bbox=0,10,1,5", None),
+        ] {
+            let request = Request::builder()
+                .method(Method::GET)
+                .uri(uri)
+                .body(Body::empty())
+                .unwrap();
+            let response = router.clone().oneshot(request).await.unwrap();
+            let Some(expected) = expected else {
+                assert_eq!(
+                    response.status(),
+                    axum::http::StatusCode::BAD_REQUEST,
+                    "{uri}"
+                );
+                continue;
+            };
+            assert_eq!(response.status(), axum::http::StatusCode::OK, "{uri}");
+            let body = response.into_body().collect().await.unwrap().to_bytes();
+            let payload: serde_json::Value = serde_json::from_slice(&body).unwrap();
+            let ids: Vec<&str> = payload["items"]
+                .as_array()
+                .unwrap()
+                .iter()
+                .map(|item| item["id"].as_str().unwrap())
+                .collect();
+            assert_eq!(ids, expected, "{uri}");
+        }
+    }
+
+    #[tokio::test]
+    async fn applies_configured_page_limits() {
+        let media = (0..6)
+            .map(|index| sample_media(&format!("item_{index}"), vec![simple_tag("x")]))
+            .collect();
+        let (mut state, _tmp) = app_state_with_media(media);
+        let mut config = (*state.config).clone();
+        config.page_limits = PageLimits {
+            default_size: 2,
+            max_size: 4,
+        };
+        state.config = Arc::new(config);
+        let router = crate::routes::router(state);
+
+        for (uri, expected) in [("/api/v1/media", 2), ("/api/v1/media?pageSize=50", 4)] {
+            let request = Request::builder()
+                .method(Method::GET)
+                .uri(uri)
+                .body(Body::empty())
+                .unwrap();
+            let response = router.clone().oneshot(request).await.unwrap();
+            let body = response.into_body().collect().await.unwrap().to_bytes();
+            let payload: serde_json::Value = serde_json::from_slice(&body).unwrap();
+            assert_eq!(payload["pageSize"], expected, "{uri}");
+            assert_eq!(
+                payload["items"].as_array().unwrap().len(),
+                expected,
+                "{uri}"
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn revalidates_search_pages_with_etag() {
+        let media = vec![
+            sample_media("sunset_A", vec![simple_tag("sunset")]),
+            sample_media("macro_B", vec![simple_tag("macro")]),
+        ];
+        let (state, _tmp) = app_state_with_media(media);
+        let snapshot = state.snapshot.clone();
+        let router = crate::routes::router(state);
+        let send = |uri: &str, etag: Option<&str>| {
+            let mut request = Request::builder().method(Method::GET).uri(uri);
+            if let Some(etag) = etag {
+                request = request.header("if-none-match", etag);
+            }
+            router.clone().oneshot(request.body(Body::empty()).unwrap())
+        };
+
+        let response = send("/api/v1/media?tags=sunset", None).await.unwrap();
+        assert_eq!(response.status(), axum::http::StatusCode::OK);
+        let etag = response.headers()["etag"].to_str().unwrap().to_string();
+        assert!(etag.starts_with("W/\""), "{etag}");
+        assert!(response.headers().contains_key("last-modified"));
+
+        let response = send("/api/v1/media?tags=sunset", Some(&etag))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), axum::http::StatusCode::NOT_MODIFIED);
+        assert_eq!(response.headers()["etag"], etag.as_str());
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        assert!(body.is_empty());
+
+        let response = send("/api/v1/media?tags=macro", Some(&etag)).await.unwrap();
+        assert_eq!(response.status(), axum::http::StatusCode::OK);
+        assert_ne!(response.headers()["etag"], etag.as_str());
+
+        // A new snapshot invalidates the page even for the same query.
+        snapshot.write().await.generated_at += chrono::Duration::seconds(1);
+        let response = send("/api/v1/media?tags=sunset", Some(&etag))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), axum::http::StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn any_tags_narrow_required_tags() {
+        let media = vec![
+            sample_media("a_b", vec![simple_tag("a"), simple_tag("b")]),
+            sample_media("a_c", vec![simple_tag("a"), simple_tag("c")]),
+            sample_media("a_only", vec![simple_tag("a")]),
+            sample_media("b_c", vec![simple_tag("b"), simple_tag("c")]),
+        ];
+        let (state, _tmp) = app_state_with_media(media);
+        let router = crate::routes::router(state);
+        let request = Request::builder()
+            .method(Method::GET)
+            .uri("/api/v1/media?tags=a&anyTags=b,c&fields=id")
+            .body(Body::empty())
+            .unwrap();
+        let response = router.clone().oneshot(request).await.unwrap();
+        assert_eq!(response.status(), axum::http::StatusCode::OK);
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        let payload: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(payload["items"], serde_json::json!(["a_b", "a_c"]));
+
+        let request = Request::builder()
+            .method(Method::GET)
+            .uri("/api/v1/media?anyTags=,")
+            .body(Body::empty())
+            .unwrap();
+        let response = router.oneshot(request).await.unwrap();
+        assert_eq!(response.status(), axum::http::StatusCode::BAD_REQUEST);
+    }
+
+    #[tokio::test]
+    async fn applies_requested_tag_match_mode() {
+        let media = vec![
+            sample_media("sunset_A", vec![simple_tag("sunset")]),
+            sample_media("macro_B", vec![simple_tag("macro")]),
+        ];
+        let (state, _tmp) = app_state_with_media(media);
+        let router = crate::routes::router(state);
+        for (uri, expected) in [
+            ("/api/v1/media?tags=sunet", 0),
+            ("/api/v1/media?tags=sunet&match=fuzzy", 1),
+            ("/api/v1/media?tags=acr&match=substring", 1),
+        ] {
+            let request = Request::builder()
+                .method(Method::GET)
+                .uri(uri)
+                .body(Body::empty())
+                .unwrap();
+            let response = router.clone().oneshot(request).await.unwrap();
+            assert_eq!(response.status(), axum::http::StatusCode::OK, "{uri}");
+            let body = response.into_body().collect().await.unwrap().to_bytes();
+            let payload: serde_json::Value = serde_json::from_slice(&body).unwrap();
+            assert_eq!(payload["total"], expected, "{uri}");
+        }
+
+        let request = Request::builder()
+            .method(Method::GET)
+            .uri("/api/v1/media?tags=sunset&match=regex")
+            .body(Body::empty())
+            .unwrap();
+        let response = router.oneshot(request).await.unwrap();
+        assert_eq!(response.status(), axum::http::StatusCode::BAD_REQUEST);
     }
 
     #[tokio::test]
@@ -224,7 +770,7 @@ mod tests {
             ),
             sample_media("macro_B", vec![simple_tag("macro"), kv_tag("rating", "4")]),
         ];
-        let state = app_state_with_media(media);
+        let (state, _tmp) = app_state_with_media(media);
         let router = crate::routes::router(state);
         let request = Request::builder()
             .method(Method::GET)
@@ -239,13 +785,363 @@ mod tests {
         assert_eq!(payload["items"][0]["id"], "sunset_A");
     }
 
+    #[tokio::test]
+    async fn rejects_malformed_attribute_filters() {
+        let media = vec![sample_media("rating_5", vec![kv_tag("rating", "5")])];
+        let (state, _tmp) = app_state_with_media(media);
+        let router = crate::routes::router(state);
+
+        for (uri, offending) in [
+            ("/api/v1/media?attributes%5Brating=5", "attributes[rating"),
+            ("/api/v1/media?attributes%5B%5D=x", "attributes[]"),
+            (
+                "/api/v1/media?attributes%5Brating%5D=",
+                "attributes[rating]",
+            ),
+            (
+                "/api/v1/media?attributes%5Brating%5D%5Bnear%5D=5",
+                "attributes[rating][near]",
+            ),
+        ] {
+            let request = Request::builder()
+                .method(Method::GET)
+                .uri(uri)
+                .body(Body::empty())
+                .unwrap();
+            let response = router.clone().oneshot(request).await.unwrap();
+            assert_eq!(
+                response.status(),
+                axum::http::StatusCode::BAD_REQUEST,
+                "{uri}"
+            );
+            let body = response.into_body().collect().await.unwrap().to_bytes();
+            let payload: serde_json::Value = serde_json::from_slice(&body).unwrap();
+            let message = payload["error"]["message"].as_str().unwrap();
+            assert!(
+                message.contains(&format!("'{offending}'")),
+                "{uri}: {message}"
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn matches_attribute_case_only_when_requested() {
+        let media = vec![
+            sample_media("upper", vec![kv_tag("camera", "A7iii")]),
+            sample_media("lower", vec![kv_tag("camera", "a7III")]),
+        ];
+        let (state, _tmp) = app_state_with_media(media);
+        let router = crate::routes::router(state);
+
+        for (uri, expected) in [
+            (
+                "/api/v1/media?attributes%5Bcamera%5D=A7iii&fields=id",
+                vec!["lower", "upper"],
+            ),
+            (
+                "/api/v1/media?attributes%5Bcamera%5D=A7iii&caseSensitive=true&fields=id",
+                vec!["upper"],
+            ),
+        ] {
+            let request = Request::builder()
+                .method(Method::GET)
+                .uri(uri)
+                .body(Body::empty())
+                .unwrap();
+            let response = router.clone().oneshot(request).await.unwrap();
+            assert_eq!(response.status(), axum::http::StatusCode::OK, "{uri}");
+            let body = response.into_body().collect().await.unwrap().to_bytes();
+            let payload: serde_json::Value = serde_json::from_slice(&body).unwrap();
+            assert_eq!(payload["items"], serde_json::json!(expected), "{uri}");
+        }
+    }
+
+    #[tokio::test]
+    async fn filters_attributes_by_numeric_range() {
+        let media = vec![
+            sample_media("rating_3", vec![kv_tag("rating", "3")]),
+            sample_media("rating_4", vec![kv_tag("rating", "4")]),
+            sample_media("rating_5", vec![kv_tag("rating", "5")]),
+        ];
+        let (state, _tmp) = app_state_with_media(media);
+        let router = crate::routes::router(state);
+
+        for (uri, expected) in [
+            (
+                "/api/v1/media?attributes[rating]=%3E%3D4",
+                vec!["rating_4", "rating_5"],
+            ),
+            ("/api/v1/media?attributes[rating][lte]=3", vec!["rating_3"]),
+            ("/api/v1/media?attributes[rating][gt]=4", vec!["rating_5"]),
+        ] {
+            let request = Request::builder()
+                .method(Method::GET)
+                .uri(uri)
+                .body(Body::empty())
+                .unwrap();
+            let response = router.clone().oneshot(request).await.unwrap();
+            assert_eq!(response.status(), axum::http::StatusCode::OK);
+            let body = response.into_body().collect().await.unwrap().to_bytes();
+            let payload: serde_json::Value = serde_json::from_slice(&body).unwrap();
+            let ids: Vec<&str> = payload["items"]
+                .as_array()
+                .unwrap()
+                .iter()
+                .map(|item| item["id"].as_str().unwrap())
+                .collect();
+            assert_eq!(ids, expected, "{uri}");
+        }
+    }
+
+    #[tokio::test]
+    async fn follows_next_cursor_until_exhausted() {
+        let media = vec![
+            sample_media("b", vec![simple_tag("sunset")]),
+            sample_media("a", vec![simple_tag("sunset")]),
+            sample_media("c", vec![simple_tag("sunset")]),
+        ];
+        let (state, _tmp) = app_state_with_media(media);
+        let router = crate::routes::router(state);
+
+        let mut uri = "/api/v1/media?pageSize=2".to_string();
+        let mut seen = Vec::new();
+        loop {
+            let request = Request::builder()
+                .method(Method::GET)
+                .uri(&uri)
+                .body(Body::empty())
+                .unwrap();
+            let response = router.clone().oneshot(request).await.unwrap();
+            assert_eq!(response.status(), axum::http::StatusCode::OK);
+            let body = response.into_body().collect().await.unwrap().to_bytes();
+            let payload: serde_json::Value = serde_json::from_slice(&body).unwrap();
+            for item in payload["items"].as_array().unwrap() {
+                seen.push(item["id"].as_str().unwrap().to_string());
+            }
+            match payload["nextCursor"].as_str() {
+                Some(cursor) => uri = format!("/api/v1/media?pageSize=2&cursor={cursor}"),
+                None => {
+                    assert!(payload["nextCursor"].is_null());
+                    break;
+                }
+            }
+        }
+        assert_eq!(seen, vec!["a", "b", "c"]);
+    }
+
+    #[tokio::test]
+    async fn rejects_page_combined_with_cursor() {
+        let (state, _tmp) = app_state_with_media(vec![sample_media("a", vec![simple_tag("x")])]);
+        let router = crate::routes::router(state);
+        let request = Request::builder()
+            .method(Method::GET)
+            .uri("/api/v1/media?page=1&cursor=a")
+            .body(Body::empty())
+            .unwrap();
+        let response = router.oneshot(request).await.unwrap();
+        assert_eq!(response.status(), axum::http::StatusCode::BAD_REQUEST);
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        let payload: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(payload["error"]["code"], "VALIDATION_FAILED");
+    }
+
+    #[tokio::test]
+    async fn sorts_by_requested_field_and_order() {
+        let mut small = sample_media("small", vec![simple_tag("x")]);
+        small.filesize = 10;
+        let mut large = sample_media("large", vec![simple_tag("x")]);
+        large.filesize = 1_000;
+        let (state, _tmp) = app_state_with_media(vec![small, large]);
+        let router = crate::routes::router(state);
+        let request = Request::builder()
+            .method(Method::GET)
+            .uri("/api/v1/media?sort=filesize&order=desc")
+            .body(Body::empty())
+            .unwrap();
+        let response = router.oneshot(request).await.unwrap();
+        assert_eq!(response.status(), axum::http::StatusCode::OK);
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        let payload: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(payload["items"][0]["id"], "large");
+        assert_eq!(payload["items"][1]["id"], "small");
+    }
+
+    #[tokio::test]
+    async fn sorts_by_file_modification_time() {
+        let dir = tempfile::tempdir().unwrap();
+        let base =
+            std::time::SystemTime::UNIX_EPOCH + std::time::Duration::from_secs(1_700_000_000);
+        for (name, age_days) in [("alpha.png", 3), ("bravo.png", 1), ("charlie.png", 2)] {
+            let path = dir.path().join(name);
+            std::fs::write(&path, b"bytes").unwrap();
+            let file = std::fs::File::options().write(true).open(&path).unwrap();
+            file.set_modified(base - std::time::Duration::from_secs(age_days * 86_400))
+                .unwrap();
+        }
+        let mut media = crate::indexer::Indexer::scan_once(dir.path()).unwrap();
+        // Media without an mtime sort last either way.
+        media.push(sample_media("undated", vec![]));
+        let (state, _tmp) = app_state_with_media(media);
+        let router = crate::routes::router(state);
+
+        let request = Request::builder()
+            .method(Method::GET)
+            .uri("/api/v1/media?sort=modifiedAt&order=desc")
+            .body(Body::empty())
+            .unwrap();
+        let response = router.oneshot(request).await.unwrap();
+        assert_eq!(response.status(), axum::http::StatusCode::OK);
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        let payload: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        let paths: Vec<&str> = payload["items"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|item| item["relativePath"].as_str().unwrap())
+            .collect();
+        assert_eq!(
+            paths,
+            ["bravo.png", "charlie.png", "alpha.png", "undated.png"]
+        );
+        assert_eq!(payload["items"][0]["modifiedAt"], "2023-11-13T22:13:20Z");
+    }
+
+    #[tokio::test]
+    async fn expands_tag_aliases_from_the_alias_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let alias_file = dir.path().join("aliases.txt");
+        std::fs::write(&alias_file, "# places\nnyc=new-york\n").unwrap();
+        let media = vec![
+            sample_media("skyline_A", vec![simple_tag("new-york")]),
+            sample_media("subway_B", vec![simple_tag("nyc")]),
+            sample_media("harbor_C", vec![simple_tag("yokohama")]),
+        ];
+        let (mut state, _tmp) = app_state_with_media(media);
+        let mut config = (*state.config).clone();
+        config.indexer = config
+            .indexer
+            .with_tag_aliases(crate::tags::TagAliases::load(&alias_file).unwrap());
+        state.config = Arc::new(config);
+        let router = crate::routes::router(state);
+
+        for uri in ["/api/v1/media?tags=nyc", "/api/v1/media?tags=New-York"] {
+            let request = Request::builder()
+                .method(Method::GET)
+                .uri(uri)
+                .body(Body::empty())
+                .unwrap();
+            let response = router.clone().oneshot(request).await.unwrap();
+            assert_eq!(response.status(), axum::http::StatusCode::OK);
+            let body = response.into_body().collect().await.unwrap().to_bytes();
+            let payload: serde_json::Value = serde_json::from_slice(&body).unwrap();
+            let mut ids: Vec<&str> = payload["items"]
+                .as_array()
+                .unwrap()
+                .iter()
+                .map(|item| item["id"].as_str().unwrap())
+                .collect();
+            ids.sort_unstable();
+            assert_eq!(ids, ["skyline_A", "subway_B"], "{uri}");
+        }
+    }
+
+    #[tokio::test]
+    async fn random_sort_requires_seed_and_is_reproducible() {
+        let media = (0..12)
+            .map(|index| sample_media(&format!("item_{index:02}"), vec![simple_tag("x")]))
+            .collect();
+        let (state, _tmp) = app_state_with_media(media);
+        let router = crate::routes::router(state);
+        let ids = |uri: &'static str| {
+            let router = router.clone();
+            async move {
+                let request = Request::builder()
+                    .method(Method::GET)
+                    .uri(uri)
+                    .body(Body::empty())
+                    .unwrap();
+                let response = router.oneshot(request).await.unwrap();
+                let status = response.status();
+                let body = response.into_body().collect().await.unwrap().to_bytes();
+                let payload: serde_json::Value = serde_json::from_slice(&body).unwrap();
+                (status, payload["items"].clone())
+            }
+        };
+
+        let (status, first) = ids("/api/v1/media?sort=random&seed=42&fields=id").await;
+        assert_eq!(status, axum::http::StatusCode::OK);
+        let (_, again) = ids("/api/v1/media?sort=random&seed=42&fields=id").await;
+        let (_, other) = ids("/api/v1/media?sort=random&seed=43&fields=id").await;
+        assert_eq!(first, again);
+        assert_ne!(first, other);
+
+        let (status, _) = ids("/api/v1/media?sort=random").await;
+        assert_eq!(status, axum::http::StatusCode::BAD_REQUEST);
+    }
+
+    #[tokio::test]
+    async fn rejects_unknown_sort_field() {
+        let (state, _tmp) = app_state_with_media(Vec::new());
+        let router = crate::routes::router(state);
+        for uri in [
+            "/api/v1/media?sort=color",
+            "/api/v1/media?sort=name&order=sideways",
+        ] {
+            let request = Request::builder()
+                .method(Method::GET)
+                .uri(uri)
+                .body(Body::empty())
+                .unwrap();
+            let response = router.clone().oneshot(request).await.unwrap();
+            assert_eq!(response.status(), axum::http::StatusCode::BAD_REQUEST);
+        }
+    }
+
+    #[test]
+    fn parses_tag_grammar_into_clauses() {
+        let clauses = parse_tags(Some("Sunset|sunrise, -draft,coast")).unwrap();
+        assert_eq!(clauses.len(), 3);
+        assert_eq!(clauses[0].alternatives(), ["sunset", "sunrise"]);
+        assert!(!clauses[0].negated());
+        assert_eq!(clauses[1].alternatives(), ["draft"]);
+        assert!(clauses[1].negated());
+        assert_eq!(clauses[2].alternatives(), ["coast"]);
+
+        assert!(parse_tags(Some("-")).is_err());
+        assert!(parse_tags(Some("a,|")).is_err());
+        assert!(parse_tags(Some(" , ")).is_err());
+    }
+
+    #[tokio::test]
+    async fn applies_or_and_not_operators() {
+        let media = vec![
+            sample_media("sunrise_A", vec![simple_tag("sunrise")]),
+            sample_media("sunset_B", vec![simple_tag("sunset"), simple_tag("draft")]),
+            sample_media("sunset_C", vec![simple_tag("sunset")]),
+        ];
+        let (state, _tmp) = app_state_with_media(media);
+        let router = crate::routes::router(state);
+        let request = Request::builder()
+            .method(Method::GET)
+            .uri("/api/v1/media?tags=sunset%7Csunrise,-draft")
+            .body(Body::empty())
+            .unwrap();
+        let response = router.oneshot(request).await.unwrap();
+        assert_eq!(response.status(), axum::http::StatusCode::OK);
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        let payload: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(payload["total"], 2);
+        assert_eq!(payload["items"][0]["id"], "sunrise_A");
+        assert_eq!(payload["items"][1]["id"], "sunset_C");
+    }
+
     #[tokio::test]
     async fn matches_kv_tag_names_with_tags_query() {
         let media = vec![
             sample_media("camera_A", vec![kv_tag("camera", "alpha")]),
             sample_media("other_B", vec![simple_tag("other")]),
         ];
-        let state = app_state_with_media(media);
+        let (state, _tmp) = app_state_with_media(media);
         let router = crate::routes::router(state);
         let request = Request::builder()
             .method(Method::GET)
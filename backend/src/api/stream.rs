@@ -1,5 +1,6 @@
 use std::{
     cmp,
+    io::Cursor,
     path::{Path, PathBuf},
 };
 
@@ -8,25 +9,27 @@ use axum::{
     body::Body,
     extract::{Path as PathParam, Query, State},
     http::{
-        HeaderMap, StatusCode,
+        HeaderMap, HeaderValue, StatusCode,
         header::{
-            ACCEPT_RANGES, CONTENT_DISPOSITION, CONTENT_LENGTH, CONTENT_RANGE, CONTENT_TYPE, ETAG,
+            ACCEPT_RANGES, CACHE_CONTROL, CONTENT_DISPOSITION, CONTENT_LENGTH, CONTENT_RANGE,
+            CONTENT_TYPE, ETAG, IF_NONE_MATCH, IF_RANGE, LAST_MODIFIED,
         },
     },
-    response::Response,
+    response::{IntoResponse, Response},
 };
+use chrono::{DateTime, Utc};
 use mime_guess::MimeGuess;
 use serde::Deserialize;
 use tokio::{
     fs,
-    io::{AsyncReadExt, AsyncSeekExt},
+    io::{AsyncRead, AsyncReadExt, AsyncSeekExt, BufReader, Take},
 };
 use tokio_util::io::ReaderStream;
 use tracing::instrument;
 
 use crate::{
-    api::{ApiError, ErrorCode},
-    indexer::{MediaFile, MediaType},
+    api::{ApiError, ErrorCode, http_date, if_none_match, not_modified_since, validate_media_id},
+    indexer::{IndexerConfig, MediaFile, MediaType},
     routes::AppState,
 };
 
@@ -51,6 +54,7 @@ pub async fn media_stream(
     State(state): State<AppState>,
     headers: HeaderMap,
 ) -> Result<Response, ApiError> {
+    validate_media_id(&media_id)?;
     let disposition = params
         .disposition
         .as_deref()
@@ -72,63 +76,114 @@ pub async fn media_stream(
     }
     .ok_or_else(|| ApiError::not_found("media not found"))?;
 
-    let absolute_path = resolve_media_path(&state.config.media_root, &media.relative_path).await?;
+    let absolute_path = resolve_media_path(&state.config.indexer, &media.relative_path).await?;
     let metadata = fs::metadata(&absolute_path)
         .await
         .map_err(ApiError::internal_with_source)?;
     if !metadata.is_file() {
-        return Err(ApiError::not_found("media not found"));
+        return Err(ApiError::gone("media file no longer exists on disk"));
     }
 
     let file_size = metadata.len();
-    let range_header = headers
-        .get(axum::http::header::RANGE)
-        .and_then(|value| value.to_str().ok());
-    let range = parse_range(range_header, file_size)?;
+    let modified = metadata.modified().ok().map(DateTime::<Utc>::from);
+    let etag = stream_etag(&media, file_size, modified);
+    let cache_control = format!("private, max-age={}", state.config.stream.max_age_secs);
+    let mut response = Response::builder()
+        .header(ACCEPT_RANGES, "bytes")
+        .header(CACHE_CONTROL, cache_control);
+    if let Some(modified) = modified {
+        response = response.header(LAST_MODIFIED, http_date(modified));
+    }
 
-    let mut file = fs::File::open(&absolute_path)
-        .await
-        .map_err(ApiError::internal_with_source)?;
+    // If-None-Match takes precedence; If-Modified-Since only applies without it.
+    let not_modified = if headers.contains_key(IF_NONE_MATCH) {
+        if_none_match(&headers, &etag)
+    } else {
+        modified.is_some_and(|modified| not_modified_since(&headers, modified))
+    };
+    if not_modified {
+        return response
+            .status(StatusCode::NOT_MODIFIED)
+            .header(ETAG, etag)
+            .body(Body::empty())
+            .map_err(|err| ApiError::internal_with_source(anyhow!(err)));
+    }
 
-    let (status, body_length, body_stream) = match range {
-        StreamRange::Full => {
-            let stream = ReaderStream::new(file);
-            (StatusCode::OK, file_size, Body::from_stream(stream))
-        }
-        StreamRange::Partial { start, end } => {
-            let len = end - start + 1;
-            file.seek(std::io::SeekFrom::Start(start))
-                .await
-                .map_err(ApiError::internal_with_source)?;
-            let limited = file.take(len);
-            let stream = ReaderStream::new(limited);
-            (StatusCode::PARTIAL_CONTENT, len, Body::from_stream(stream))
+    // A stale If-Range validator means the client's partial copy is outdated: send it all.
+    let range_header = headers
+        .get(axum::http::header::RANGE)
+        .and_then(|value| value.to_str().ok())
+        .filter(|_| if_range_matches(&headers, &etag));
+    let range = match parse_range(range_header, file_size) {
+        Ok(range) => range,
+        // RFC 9110 §15.5.17: a 416 names the current length so the client can retry.
+        Err(err) if err.status() == StatusCode::RANGE_NOT_SATISFIABLE => {
+            let mut response = err.into_response();
+            response.headers_mut().insert(
+                CONTENT_RANGE,
+                HeaderValue::from_str(&format!("bytes */{file_size}"))
+                    .map_err(|err| ApiError::internal_with_source(anyhow!(err)))?,
+            );
+            return Ok(response);
         }
+        Err(err) => return Err(err),
     };
 
-    let content_type = derive_content_type(&media, &absolute_path);
+    let content_type = derive_content_type(&media, &absolute_path).await;
     let file_name = Path::new(&media.relative_path)
         .file_name()
         .and_then(|name| name.to_str())
         .unwrap_or("media");
-    let content_disposition = format!("{disposition}; filename=\"{file_name}\"");
-    let etag = format!("\"{}-{}\"", media.id, file_size);
-
-    let mut response = Response::builder()
-        .status(status)
-        .header(ACCEPT_RANGES, "bytes")
-        .header(CONTENT_DISPOSITION, content_disposition)
-        .header(CONTENT_TYPE, content_type.as_str())
-        .header(CONTENT_LENGTH, body_length.to_string())
-        .header(ETAG, etag);
+    response = response.header(
+        CONTENT_DISPOSITION,
+        content_disposition(&disposition, file_name),
+    );
 
-    let range_desc = match range {
-        StreamRange::Full => "full".to_string(),
+    let chunk_bytes = state.config.stream.chunk_bytes;
+    let (body_length, body_stream, range_desc) = match &range {
+        StreamRange::Full => {
+            let file = open_range(&absolute_path, 0, file_size).await?;
+            response = response
+                .status(StatusCode::OK)
+                .header(CONTENT_TYPE, content_type.as_str());
+            (
+                file_size,
+                chunked_body(file, chunk_bytes),
+                "full".to_string(),
+            )
+        }
         StreamRange::Partial { start, end } => {
-            response = response.header(CONTENT_RANGE, format!("bytes {start}-{end}/{file_size}"));
-            format!("{start}-{end}")
+            let len = end - start + 1;
+            let limited = open_range(&absolute_path, *start, len).await?;
+            response = response
+                .status(StatusCode::PARTIAL_CONTENT)
+                .header(CONTENT_TYPE, content_type.as_str())
+                .header(CONTENT_RANGE, format!("bytes {start}-{end}/{file_size}"));
+            (
+                len,
+                chunked_body(limited, chunk_bytes),
+                format!("{start}-{end}"),
+            )
+        }
+        StreamRange::Multipart(ranges) => {
+            let boundary = multipart_boundary(&etag);
+            let (len, reader) =
+                multipart_body(&absolute_path, ranges, file_size, &content_type, &boundary).await?;
+            response = response.status(StatusCode::PARTIAL_CONTENT).header(
+                CONTENT_TYPE,
+                format!("multipart/byteranges; boundary={boundary}"),
+            );
+            let desc = ranges
+                .iter()
+                .map(|(start, end)| format!("{start}-{end}"))
+                .collect::<Vec<_>>()
+                .join(",");
+            (len, chunked_body(reader, chunk_bytes), desc)
         }
     };
+    let response = response
+        .header(CONTENT_LENGTH, body_length.to_string())
+        .header(ETAG, etag);
 
     let span = tracing::Span::current();
     span.record("galarie.stream.bytes", body_length as i64);
@@ -140,16 +195,128 @@ pub async fn media_stream(
         .map_err(|err| ApiError::internal_with_source(anyhow!(err)))
 }
 
-async fn resolve_media_path(root: &Path, relative: &str) -> Result<PathBuf, ApiError> {
-    let root = root.to_path_buf();
-    let root_canonical = fs::canonicalize(&root)
+/// Read through a buffer of `chunk_bytes` so large files go out in few, large reads.
+pub(crate) fn chunked_body(reader: impl AsyncRead + Send + 'static, chunk_bytes: usize) -> Body {
+    let buffered = BufReader::with_capacity(chunk_bytes, reader);
+    Body::from_stream(ReaderStream::with_capacity(buffered, chunk_bytes))
+}
+
+async fn open_range(path: &Path, start: u64, len: u64) -> Result<Take<fs::File>, ApiError> {
+    let mut file = fs::File::open(path)
+        .await
+        .map_err(ApiError::internal_with_source)?;
+    if start > 0 {
+        file.seek(std::io::SeekFrom::Start(start))
+            .await
+            .map_err(ApiError::internal_with_source)?;
+    }
+    Ok(file.take(len))
+}
+
+/// Strong validator for the file's bytes. Ids survive in-place edits under
+/// `IdStrategy::PathOnly`, so the fingerprint and mtime are what tell a same-size
+/// rewrite apart; the mtime covers edits the indexer has not rescanned yet.
+fn stream_etag(media: &MediaFile, file_size: u64, modified: Option<DateTime<Utc>>) -> String {
+    let fingerprint = media
+        .hash
+        .as_deref()
+        .map_or("", |hash| &hash[..hash.len().min(16)]);
+    let modified = modified.map_or(0, |modified| modified.timestamp_micros());
+    format!("\"{}-{fingerprint}-{file_size}-{modified:x}\"", media.id)
+}
+
+/// Boundaries only need to be absent from the payload; mixing the ETag with the clock
+/// keeps them unpredictable enough for that without pulling in a RNG.
+fn multipart_boundary(etag: &str) -> String {
+    use sha1::{Digest, Sha1};
+
+    let nanos = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_nanos();
+    let mut hasher = Sha1::new();
+    hasher.update(etag.as_bytes());
+    hasher.update(nanos.to_le_bytes());
+    format!("galarie-{:x}", hasher.finalize())
+}
+
+/// Chain each part's headers and file slice into a single reader, returning its exact length.
+async fn multipart_body(
+    path: &Path,
+    ranges: &[(u64, u64)],
+    total: u64,
+    content_type: &str,
+    boundary: &str,
+) -> Result<(u64, Box<dyn AsyncRead + Send + Unpin>), ApiError> {
+    let mut length = 0;
+    let mut reader: Box<dyn AsyncRead + Send + Unpin> = Box::new(tokio::io::empty());
+    for (start, end) in ranges {
+        let part_header = format!(
+            "\r\n--{boundary}\r\nContent-Type: {content_type}\r\nContent-Range: bytes {start}-{end}/{total}\r\n\r\n"
+        );
+        let len = end - start + 1;
+        length += part_header.len() as u64 + len;
+        let slice = open_range(path, *start, len).await?;
+        reader = Box::new(reader.chain(Cursor::new(part_header)).chain(slice));
+    }
+    let closing = format!("\r\n--{boundary}--\r\n");
+    length += closing.len() as u64;
+    Ok((length, Box::new(reader.chain(Cursor::new(closing)))))
+}
+
+/// `Content-Disposition` value with a quoted ASCII `filename` every client understands.
+/// Names that do not survive that unchanged also get an RFC 5987 `filename*`, which
+/// browsers prefer, so non-ASCII names are saved intact.
+fn content_disposition(disposition: &str, file_name: &str) -> String {
+    let fallback: String = file_name
+        .chars()
+        .map(|ch| match ch {
+            ' '..='~' if ch != '"' && ch != '\\' => ch,
+            _ => '_',
+        })
+        .collect();
+    if fallback == file_name {
+        return format!("{disposition}; filename=\"{fallback}\"");
+    }
+
+    let mut encoded = String::with_capacity(file_name.len() * 3);
+    for byte in file_name.bytes() {
+        // RFC 5987 attr-char; everything else is percent-encoded.
+        if byte.is_ascii_alphanumeric() || b"!#$&+-.^_`|~".contains(&byte) {
+            encoded.push(char::from(byte));
+        } else {
+            encoded.push_str(&format!("%{byte:02X}"));
+        }
+    }
+    format!("{disposition}; filename=\"{fallback}\"; filename*=UTF-8''{encoded}")
+}
+
+/// `If-Range` uses strong comparison; a missing header places no condition on the range.
+fn if_range_matches(headers: &HeaderMap, etag: &str) -> bool {
+    match headers.get(IF_RANGE).and_then(|value| value.to_str().ok()) {
+        Some(validator) => validator.trim() == etag,
+        None => true,
+    }
+}
+
+/// Resolve an indexed relative path inside the media root it was scanned from.
+pub(crate) async fn resolve_media_path(
+    indexer: &IndexerConfig,
+    relative_path: &str,
+) -> Result<PathBuf, ApiError> {
+    // A root dropped from the config since the scan is as gone as a deleted file.
+    let (root, relative) = indexer
+        .locate(relative_path)
+        .ok_or_else(|| ApiError::gone("media file no longer exists on disk"))?;
+    let root_canonical = fs::canonicalize(root)
         .await
         .map_err(ApiError::internal_with_source)?;
     let candidate = root.join(relative);
     let candidate_canonical = match fs::canonicalize(&candidate).await {
         Ok(path) => path,
+        // Callers only resolve indexed media, so a missing file was deleted after the scan.
         Err(err) if err.kind() == std::io::ErrorKind::NotFound => {
-            return Err(ApiError::not_found("media not found"));
+            return Err(ApiError::gone("media file no longer exists on disk"));
         }
         Err(err) => return Err(ApiError::internal_with_source(err)),
     };
@@ -163,11 +330,23 @@ async fn resolve_media_path(root: &Path, relative: &str) -> Result<PathBuf, ApiE
     Ok(candidate_canonical)
 }
 
-fn derive_content_type(media: &MediaFile, path: &Path) -> String {
-    if let Some(guess) = MimeGuess::from_path(path).first_raw() {
+/// Bytes read from the start of a file when sniffing its type.
+const SNIFF_LEN: u64 = 8192;
+
+/// Prefer the extension; when it says nothing useful, sniff magic bytes before falling
+/// back to the indexed media type.
+async fn derive_content_type(media: &MediaFile, path: &Path) -> String {
+    if let Some(guess) = MimeGuess::from_path(path)
+        .first_raw()
+        .filter(|guess| *guess != mime_guess::mime::APPLICATION_OCTET_STREAM.as_ref())
+    {
         return guess.to_string();
     }
 
+    if let Some(sniffed) = sniff_content_type(path).await {
+        return sniffed;
+    }
+
     match media.media_type {
         MediaType::Image => "image/jpeg".into(),
         MediaType::Gif => "image/gif".into(),
@@ -178,12 +357,27 @@ fn derive_content_type(media: &MediaFile, path: &Path) -> String {
     }
 }
 
-#[derive(Clone, Copy, Debug)]
+async fn sniff_content_type(path: &Path) -> Option<String> {
+    let file = fs::File::open(path).await.ok()?;
+    let mut head = Vec::new();
+    file.take(SNIFF_LEN).read_to_end(&mut head).await.ok()?;
+    infer::get(&head).map(|kind| kind.mime_type().to_string())
+}
+
+#[derive(Clone, Debug)]
 enum StreamRange {
     Full,
-    Partial { start: u64, end: u64 },
+    Partial {
+        start: u64,
+        end: u64,
+    },
+    /// Two or more ranges, served as `multipart/byteranges`.
+    Multipart(Vec<(u64, u64)>),
 }
 
+/// Upper bound on ranges in one request, so a client can't ask for thousands of parts.
+const MAX_RANGES: usize = 16;
+
 fn parse_range(range_header: Option<&str>, total: u64) -> Result<StreamRange, ApiError> {
     let Some(value) = range_header else {
         return Ok(StreamRange::Full);
@@ -193,11 +387,37 @@ fn parse_range(range_header: Option<&str>, total: u64) -> Result<StreamRange, Ap
         return Err(ApiError::bad_request("range must be expressed in bytes"));
     }
 
-    let spec = &value[6..];
-    if spec.contains(',') {
-        return Err(ApiError::bad_request("multiple ranges are not supported"));
+    let specs: Vec<&str> = value[6..].split(',').map(str::trim).collect();
+    if specs.len() > MAX_RANGES {
+        return Err(ApiError::bad_request(format!(
+            "at most {MAX_RANGES} ranges may be requested at once"
+        )));
     }
 
+    // Unsatisfiable parts are dropped; only when none is left is the request a 416
+    // (RFC 9110, section 15.5.17). Malformed parts still reject the whole header.
+    let mut ranges = Vec::with_capacity(specs.len());
+    let mut unsatisfiable = None;
+    for spec in specs {
+        match parse_range_spec(spec, total) {
+            Ok(range) => ranges.push(range),
+            Err(err) if err.status() == StatusCode::RANGE_NOT_SATISFIABLE => {
+                unsatisfiable = Some(err);
+            }
+            Err(err) => return Err(err),
+        }
+    }
+    match ranges.as_slice() {
+        [] => Err(unsatisfiable.unwrap_or_else(|| ApiError::bad_request("no range requested"))),
+        [(start, end)] => Ok(StreamRange::Partial {
+            start: *start,
+            end: *end,
+        }),
+        _ => Ok(StreamRange::Multipart(ranges)),
+    }
+}
+
+fn parse_range_spec(spec: &str, total: u64) -> Result<(u64, u64), ApiError> {
     let (start, end) = if let Some(rest) = spec.strip_prefix('-') {
         let suffix: u64 = rest
             .parse()
@@ -239,13 +459,87 @@ fn parse_range(range_header: Option<&str>, total: u64) -> Result<StreamRange, Ap
         (start, capped_end)
     };
 
-    Ok(StreamRange::Partial { start, end })
+    Ok((start, end))
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[test]
+    fn ascii_file_names_are_quoted_as_is() {
+        let value = content_disposition("inline", "sunset_beach.jpg");
+        assert_eq!(value, "inline; filename=\"sunset_beach.jpg\"");
+        assert!(HeaderValue::from_str(&value).is_ok());
+    }
+
+    #[test]
+    fn quotes_in_file_names_are_replaced_and_encoded() {
+        let value = content_disposition("attachment", "my \"best\" shot.jpg");
+        assert_eq!(
+            value,
+            "attachment; filename=\"my _best_ shot.jpg\"; filename*=UTF-8''my%20%22best%22%20shot.jpg"
+        );
+        assert!(HeaderValue::from_str(&value).is_ok());
+    }
+
+    #[test]
+    fn utf8_file_names_get_an_rfc5987_filename() {
+        let value = content_disposition("attachment", "写真.jpg");
+        assert_eq!(
+            value,
+            "attachment; filename=\"__.jpg\"; filename*=UTF-8''%E5%86%99%E7%9C%9F.jpg"
+        );
+        assert!(value.is_ascii());
+        assert!(HeaderValue::from_str(&value).is_ok());
+    }
+
+    #[test]
+    fn if_range_requires_strong_etag_match() {
+        let mut headers = HeaderMap::new();
+        assert!(if_range_matches(&headers, "\"a-1\""));
+        headers.insert(IF_RANGE, "\"a-1\"".parse().unwrap());
+        assert!(if_range_matches(&headers, "\"a-1\""));
+        assert!(!if_range_matches(&headers, "\"a-2\""));
+        headers.insert(IF_RANGE, "W/\"a-1\"".parse().unwrap());
+        assert!(!if_range_matches(&headers, "\"a-1\""));
+    }
+
+    #[tokio::test]
+    async fn sniffs_content_type_when_extension_is_unhelpful() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("photo.bin");
+        image::DynamicImage::new_rgb8(4, 4)
+            .save_with_format(&path, image::ImageFormat::Jpeg)
+            .unwrap();
+        let media = MediaFile {
+            id: "photo".into(),
+            relative_path: "photo.bin".into(),
+            media_type: MediaType::Unknown,
+            tags: Vec::new(),
+            attributes: Default::default(),
+            filesize: 0,
+            dimensions: None,
+            duration_ms: None,
+            location: None,
+            thumbnail_path: None,
+            stream_path: None,
+            hash: None,
+            indexed_at: chrono::Utc::now(),
+            modified_at: None,
+            invalid_tokens: Vec::new(),
+        };
+
+        assert_eq!(derive_content_type(&media, &path).await, "image/jpeg");
+
+        let unknown = dir.path().join("notes.bin");
+        std::fs::write(&unknown, b"plain words").unwrap();
+        assert_eq!(
+            derive_content_type(&media, &unknown).await,
+            "application/octet-stream"
+        );
+    }
+
     #[test]
     fn parses_suffix_range() {
         let range = parse_range(Some("bytes=-500"), 1_000).expect("range");
@@ -270,9 +564,39 @@ mod tests {
         }
     }
 
+    #[test]
+    fn parses_multiple_ranges() {
+        let range = parse_range(Some("bytes=0-99, 500-599,-10"), 1_000).expect("range");
+        match range {
+            StreamRange::Multipart(ranges) => {
+                assert_eq!(ranges, vec![(0, 99), (500, 599), (990, 999)]);
+            }
+            _ => panic!("expected multipart range"),
+        }
+        let too_many = vec!["0-0"; MAX_RANGES + 1].join(",");
+        let err = parse_range(Some(&format!("bytes={too_many}")), 1_000).unwrap_err();
+        assert_eq!(err.status(), StatusCode::BAD_REQUEST);
+    }
+
     #[test]
     fn rejects_out_of_bounds_start() {
         let err = parse_range(Some("bytes=2000-"), 1_000).unwrap_err();
         assert_eq!(err.status(), StatusCode::RANGE_NOT_SATISFIABLE);
+        let err = parse_range(Some("bytes=2000-, 3000-3999"), 1_000).unwrap_err();
+        assert_eq!(err.status(), StatusCode::RANGE_NOT_SATISFIABLE);
+    }
+
+    #[test]
+    fn drops_unsatisfiable_parts_of_multiple_ranges() {
+        match parse_range(Some("bytes=0-9, 2000-, 20-29"), 1_000).expect("range") {
+            StreamRange::Multipart(ranges) => assert_eq!(ranges, vec![(0, 9), (20, 29)]),
+            _ => panic!("expected multipart range"),
+        }
+        match parse_range(Some("bytes=2000-, 990-"), 1_000).expect("range") {
+            StreamRange::Partial { start, end } => assert_eq!((start, end), (990, 999)),
+            _ => panic!("expected partial range"),
+        }
+        let err = parse_range(Some("bytes=0-9, x-1"), 1_000).unwrap_err();
+        assert_eq!(err.status(), StatusCode::BAD_REQUEST);
     }
 }
@@ -0,0 +1,170 @@
+use std::collections::{BTreeMap, HashMap};
+
+use axum::{
+    Json,
+    extract::{Query, State},
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    api::{
+        ApiError, ApiResult,
+        search::{parse_any_tags, parse_attributes, parse_case_sensitive, parse_tags},
+    },
+    routes::AppState,
+    services::search::{SearchQuery, SearchService, TagFacets},
+};
+
+#[derive(Debug, Deserialize, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct TagFacetParams {
+    pub tags: Option<String>,
+    pub any_tags: Option<String>,
+    pub case_sensitive: Option<String>,
+    #[serde(flatten)]
+    pub rest: HashMap<String, String>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TagFacetsResponse {
+    /// Simple tags ordered by descending count, then name.
+    pub tags: Vec<TagCount>,
+    /// Attribute keys mapped to each distinct value and its count.
+    pub attributes: BTreeMap<String, BTreeMap<String, usize>>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TagCount {
+    pub name: String,
+    pub count: usize,
+}
+
+/// Return the tag and attribute vocabulary of the media matching the optional
+/// `tags` / `anyTags` / `attributes[...]` filters.
+pub async fn tag_facets(
+    State(state): State<AppState>,
+    Query(params): Query<TagFacetParams>,
+) -> ApiResult<TagFacetsResponse> {
+    let tag_clauses = parse_tags(params.tags.as_deref()).map_err(ApiError::bad_request)?;
+    let any_tags = parse_any_tags(params.any_tags.as_deref()).map_err(ApiError::bad_request)?;
+    let case_sensitive =
+        parse_case_sensitive(params.case_sensitive.as_deref()).map_err(ApiError::bad_request)?;
+    let attributes = parse_attributes(&params.rest).map_err(ApiError::bad_request)?;
+    let query = SearchQuery::new(Vec::new(), attributes, 1, 1)
+        .with_tag_clauses(tag_clauses)
+        .with_any_tags(any_tags)
+        .with_tag_aliases(&state.config.indexer.tag_aliases)
+        .with_case_sensitive_attributes(case_sensitive);
+
+    let snapshot = state.snapshot.read().await;
+    let facets = SearchService::facets(&snapshot, &query);
+
+    Ok(Json(TagFacetsResponse::from(facets)))
+}
+
+impl From<TagFacets> for TagFacetsResponse {
+    fn from(value: TagFacets) -> Self {
+        let mut tags: Vec<TagCount> = value
+            .tags
+            .into_iter()
+            .map(|(name, count)| TagCount { name, count })
+            .collect();
+        // BTreeMap iteration is name-ordered, so a stable sort keeps names ascending on ties.
+        tags.sort_by_key(|tag| std::cmp::Reverse(tag.count));
+        Self {
+            tags,
+            attributes: value.attributes,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::test_support::{app_state_with_media, get, kv_tag, sample_media, simple_tag};
+    use axum::http::StatusCode;
+    use std::sync::Arc;
+    use tempfile::TempDir;
+
+    fn fixture_state() -> (AppState, TempDir) {
+        app_state_with_media(vec![
+            sample_media(
+                "a",
+                vec![
+                    simple_tag("sunset"),
+                    simple_tag("coast"),
+                    kv_tag("rating", "5"),
+                ],
+            ),
+            sample_media("b", vec![simple_tag("sunset"), kv_tag("rating", "4")]),
+            sample_media("c", vec![simple_tag("macro"), kv_tag("rating", "4")]),
+        ])
+    }
+
+    #[tokio::test]
+    async fn aggregates_tag_and_attribute_counts() {
+        let (state, _tmp) = fixture_state();
+        let (status, payload) = get(state, "/api/v1/tags").await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(
+            payload["tags"],
+            serde_json::json!([
+                {"name": "sunset", "count": 2},
+                {"name": "coast", "count": 1},
+                {"name": "macro", "count": 1},
+            ])
+        );
+        assert_eq!(
+            payload["attributes"],
+            serde_json::json!({"rating": {"4": 2, "5": 1}})
+        );
+    }
+
+    #[tokio::test]
+    async fn narrows_counts_to_the_search_context() {
+        let (state, _tmp) = fixture_state();
+        let (status, payload) = get(state, "/api/v1/tags?attributes[rating]=4").await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(
+            payload["tags"],
+            serde_json::json!([
+                {"name": "macro", "count": 1},
+                {"name": "sunset", "count": 1},
+            ])
+        );
+        assert_eq!(
+            payload["attributes"],
+            serde_json::json!({"rating": {"4": 2}})
+        );
+    }
+
+    #[tokio::test]
+    async fn narrows_counts_through_tag_aliases() {
+        let (mut state, _tmp) = fixture_state();
+        let mut config = (*state.config).clone();
+        config.indexer = config
+            .indexer
+            .with_tag_aliases("seaside=coast".parse().unwrap());
+        state.config = Arc::new(config);
+
+        let (status, payload) = get(state, "/api/v1/tags?tags=seaside").await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(
+            payload["tags"],
+            serde_json::json!([
+                {"name": "coast", "count": 1},
+                {"name": "sunset", "count": 1},
+            ])
+        );
+    }
+
+    #[tokio::test]
+    async fn rejects_malformed_tags_filter() {
+        let (state, _tmp) = fixture_state();
+        let (status, payload) = get(state, "/api/v1/tags?tags=-").await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert_eq!(payload["error"]["code"], "VALIDATION_FAILED");
+    }
+}
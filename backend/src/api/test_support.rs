@@ -0,0 +1,95 @@
+use std::{collections::HashMap, sync::Arc};
+
+use axum::{
+    body::Body,
+    http::{Method, Request, StatusCode},
+};
+use chrono::Utc;
+use http_body_util::BodyExt;
+use tempfile::{TempDir, tempdir};
+use tokio::sync::RwLock;
+use tower::ServiceExt;
+
+use crate::{
+    cache::{CacheSnapshot, CacheStore},
+    config::AppConfig,
+    indexer::{MediaFile, MediaType},
+    routes::AppState,
+    tags::{Tag, TagKind},
+};
+
+/// State serving `media`, rooted in a temp dir the caller keeps alive for the test.
+pub(crate) fn app_state_with_media(media: Vec<MediaFile>) -> (AppState, TempDir) {
+    let tmp = tempdir().unwrap();
+    let config = Arc::new(AppConfig::for_tests(tmp.path(), tmp.path()));
+    let cache_store = Arc::new(CacheStore::new(tmp.path()));
+    let snapshot = CacheSnapshot::new(media);
+    let state = AppState::new(config, cache_store, Arc::new(RwLock::new(snapshot)));
+    (state, tmp)
+}
+
+/// GET `uri` and decode the JSON body.
+pub(crate) async fn get(state: AppState, uri: &str) -> (StatusCode, serde_json::Value) {
+    let router = crate::routes::router(state);
+    let request = Request::builder()
+        .method(Method::GET)
+        .uri(uri)
+        .body(Body::empty())
+        .unwrap();
+    let response = router.oneshot(request).await.unwrap();
+    let status = response.status();
+    let body = response.into_body().collect().await.unwrap().to_bytes();
+    (status, serde_json::from_slice(&body).unwrap())
+}
+
+/// An image record carrying `tags`, with attributes derived from its key/value tags.
+pub(crate) fn sample_media(id: &str, tags: Vec<Tag>) -> MediaFile {
+    let mut attributes = HashMap::new();
+    for tag in &tags {
+        if matches!(tag.kind, TagKind::KeyValue)
+            && let Some(value) = &tag.value
+        {
+            attributes
+                .entry(tag.name.clone())
+                .or_insert_with(|| value.clone());
+        }
+    }
+
+    MediaFile {
+        id: id.to_string(),
+        relative_path: format!("{id}.png"),
+        media_type: MediaType::Image,
+        tags,
+        attributes,
+        filesize: 42,
+        dimensions: None,
+        duration_ms: None,
+        location: None,
+        thumbnail_path: Some(format!("/media/{id}/thumbnail")),
+        stream_path: None,
+        hash: None,
+        indexed_at: Utc::now(),
+        modified_at: None,
+        invalid_tokens: Vec::new(),
+    }
+}
+
+pub(crate) fn simple_tag(name: &str) -> Tag {
+    Tag {
+        raw_token: name.into(),
+        kind: TagKind::Simple,
+        name: name.to_lowercase(),
+        value: None,
+        normalized: name.to_lowercase(),
+    }
+}
+
+pub(crate) fn kv_tag(key: &str, value: &str) -> Tag {
+    Tag {
+        raw_token: format!("{key}-{value}"),
+        kind: TagKind::KeyValue,
+        name: key.to_lowercase(),
+        value: Some(value.to_lowercase()),
+        normalized: format!("{}={}", key.to_lowercase(), value.to_lowercase()),
+    }
+}
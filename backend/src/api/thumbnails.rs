@@ -1,157 +1,733 @@
 use anyhow::anyhow;
 use axum::{
+    Json,
     body::Body,
     extract::{Path, Query, State},
     http::{
-        StatusCode,
+        HeaderMap, Method, StatusCode,
         header::{CACHE_CONTROL, CONTENT_LENGTH, CONTENT_TYPE, ETAG},
     },
     response::Response,
 };
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
+use tokio::task::JoinSet;
 
 use crate::{
-    api::ApiError,
-    media::thumbnails::{ThumbnailGenerator, ThumbnailSize, ThumbnailSpec},
+    api::{ApiError, ApiResponse, ApiResult, if_none_match, validate_media_id},
+    indexer::{MediaFile, MediaType},
+    media::thumbnails::{
+        MissingRenderer, ThumbnailArtifact, ThumbnailFormat, ThumbnailGenerator, ThumbnailSize,
+        ThumbnailSpec, placeholder_png,
+    },
+    o11y::metrics,
     routes::AppState,
 };
 
+/// Marks responses carrying a placeholder tile rather than a rendered thumbnail.
+const THUMBNAIL_PLACEHOLDER: &str = "x-thumbnail-placeholder";
+
 #[derive(Debug, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct ThumbnailParams {
     pub size: Option<ThumbnailSize>,
+    /// `jpeg` (default) or `webp`.
+    pub format: Option<ThumbnailFormat>,
+    /// Keep GIF animation (served as `image/gif`); ignored for other media types.
+    #[serde(default)]
+    pub animated: bool,
+}
+
+#[derive(Debug, Deserialize, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct WarmParams {
+    pub size: Option<ThumbnailSize>,
+    pub format: Option<ThumbnailFormat>,
+}
+
+/// Progress of the most recent thumbnail pre-warming run.
+#[derive(Debug, Clone, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WarmStatus {
+    pub running: bool,
+    /// `None` when each media type got its configured default size.
+    pub size: Option<ThumbnailSize>,
+    pub total: usize,
+    pub completed: usize,
+    pub failed: usize,
+}
+
+fn thumbnail_spec(state: &AppState, media: &MediaFile) -> ThumbnailSpec {
+    ThumbnailSpec {
+        media_id: media.id.clone(),
+        // A root dropped from the config leaves an empty path, which fails like a deleted file.
+        source_path: state
+            .config
+            .indexer
+            .locate(&media.relative_path)
+            .map(|(root, relative)| root.join(relative))
+            .unwrap_or_default(),
+        media_type: media.media_type.clone(),
+    }
+}
+
+fn thumbnail_generator(state: &AppState) -> ThumbnailGenerator {
+    ThumbnailGenerator::new(state.config.cache_dir.clone())
+        .with_permits(state.thumbnail_permits.clone())
+        .with_inflight(state.thumbnail_inflight.clone())
+        .with_tools(
+            state.config.thumbnails.ffmpeg_path.clone(),
+            state.config.thumbnails.gifsicle_path.clone(),
+        )
+        .with_pdf_renderer(state.config.thumbnails.pdftoppm_path.clone())
+        .with_audio_waveform(state.config.thumbnails.audio_waveform)
+        .with_jpeg_quality(state.config.thumbnails.jpeg_quality)
+        .with_resize_filter(state.config.thumbnails.resize_filter)
 }
 
+/// Serve (generating on demand) a thumbnail. Also handles `HEAD`, and answers
+/// `304 Not Modified` when `If-None-Match` already names the thumbnail's ETag.
 pub async fn media_thumbnail(
+    method: Method,
+    headers: HeaderMap,
     Path(media_id): Path<String>,
     Query(params): Query<ThumbnailParams>,
     State(state): State<AppState>,
 ) -> Result<Response, ApiError> {
-    let size = params.size.unwrap_or(ThumbnailSize::Medium);
+    validate_media_id(&media_id)?;
 
-    let spec = {
+    let found = {
         let snapshot = state.snapshot.read().await;
         snapshot
             .media
             .iter()
             .find(|media| media.id == media_id)
-            .map(|media| ThumbnailSpec {
-                media_id: media.id.clone(),
-                source_path: state.config.media_root.join(&media.relative_path),
-                media_type: media.media_type.clone(),
-            })
+            .map(|media| (thumbnail_spec(&state, media), media.hash.clone()))
     };
 
-    let spec = match spec {
-        Some(spec) => spec,
+    let (spec, fingerprint) = match found {
+        Some(found) => found,
         None => return Err(ApiError::not_found("media not found")),
     };
+    let size = params
+        .size
+        .unwrap_or_else(|| state.config.thumbnails.default_size(&spec.media_type));
 
-    let generator = ThumbnailGenerator::new(state.config.cache_dir.clone());
-    let artifact = generator
-        .ensure_thumbnail(&spec, size)
-        .await
-        .map_err(ApiError::internal_with_source)?;
+    let format = if params.animated && spec.media_type == MediaType::Gif {
+        ThumbnailFormat::Gif
+    } else {
+        params.format.unwrap_or_default()
+    };
+
+    let generator = thumbnail_generator(&state);
+    let etag = thumbnail_etag(
+        &spec.media_id,
+        fingerprint.as_deref(),
+        &generator.variant(),
+        size,
+        format,
+    );
+    let builder = Response::builder()
+        .header(CACHE_CONTROL, "public, max-age=3600")
+        .header(ETAG, &etag);
+
+    if if_none_match(&headers, &etag) {
+        return builder
+            .status(StatusCode::NOT_MODIFIED)
+            .body(Body::empty())
+            .map_err(|err| ApiError::internal_with_source(anyhow!(err)));
+    }
+
+    let artifact = generator.ensure_thumbnail(&spec, size, format).await;
+    metrics::record_thumbnail(artifact.is_ok());
+    let artifact = match artifact {
+        Err(err) if state.config.thumbnails.placeholder => {
+            tracing::warn!(error = ?err, media_id = %spec.media_id, "serving placeholder thumbnail");
+            return placeholder_response(&method, &spec.media_type, size);
+        }
+        artifact => artifact,
+    };
+    let artifact = artifact.map_err(|err| match err.downcast_ref::<MissingRenderer>() {
+        Some(missing) => {
+            tracing::warn!(error = %missing, media_id = %spec.media_id, "thumbnail renderer unavailable");
+            ApiError::service_unavailable(missing.to_string())
+        }
+        None => ApiError::internal_with_source(err),
+    })?;
 
     let absolute = state.config.cache_dir.join(&artifact.relative_path);
-    let bytes = tokio::fs::read(&absolute)
-        .await
-        .map_err(ApiError::internal_with_source)?;
+    if let Some(cache) = &state.thumbnail_cache {
+        cache.touch(&absolute);
+    }
+    let (content_length, body) = if method == Method::HEAD {
+        let metadata = tokio::fs::metadata(&absolute)
+            .await
+            .map_err(ApiError::internal_with_source)?;
+        (metadata.len(), Body::empty())
+    } else {
+        let bytes = tokio::fs::read(&absolute)
+            .await
+            .map_err(ApiError::internal_with_source)?;
+        (bytes.len() as u64, Body::from(bytes))
+    };
 
-    let response = Response::builder()
+    let response = builder
         .status(StatusCode::OK)
         .header(CONTENT_TYPE, artifact.media_type)
-        .header(CACHE_CONTROL, "public, max-age=3600")
-        .header(ETAG, format!("\"{}-{}\"", spec.media_id, size.as_dir()))
-        .header(CONTENT_LENGTH, bytes.len().to_string())
-        .body(Body::from(bytes))
+        .header(CONTENT_LENGTH, content_length.to_string())
+        .body(body)
         .map_err(|err| ApiError::internal_with_source(anyhow!(err)))?;
 
     Ok(response)
 }
 
+/// Validator naming the thumbnail's source content and encoding variant as well as its
+/// id, so an in-place edit (which keeps the id under `IdStrategy::PathOnly`) or a new
+/// quality/filter setting changes it.
+fn thumbnail_etag(
+    media_id: &str,
+    fingerprint: Option<&str>,
+    variant: &str,
+    size: ThumbnailSize,
+    format: ThumbnailFormat,
+) -> String {
+    let fingerprint = fingerprint.map_or("", |hash| &hash[..hash.len().min(16)]);
+    format!(
+        "\"{media_id}-{fingerprint}-{}-{variant}.{}\"",
+        size.as_dir(),
+        format.extension()
+    )
+}
+
+/// Placeholder tile for a thumbnail that could not be rendered. It carries no ETag and
+/// must be revalidated, so clients pick up the real thumbnail once it can be generated.
+fn placeholder_response(
+    method: &Method,
+    media_type: &MediaType,
+    size: ThumbnailSize,
+) -> Result<Response, ApiError> {
+    let bytes = placeholder_png(media_type, size).map_err(ApiError::internal_with_source)?;
+    let content_length = bytes.len();
+    let body = if *method == Method::HEAD {
+        Body::empty()
+    } else {
+        Body::from(bytes)
+    };
+    Response::builder()
+        .status(StatusCode::OK)
+        .header(CONTENT_TYPE, "image/png")
+        .header(CONTENT_LENGTH, content_length.to_string())
+        .header(CACHE_CONTROL, "no-cache")
+        .header(THUMBNAIL_PLACEHOLDER, "true")
+        .body(body)
+        .map_err(|err| ApiError::internal_with_source(anyhow!(err)))
+}
+
+/// Generate thumbnails for every media item in the current snapshot in the background.
+/// Already-cached thumbnails are skipped by `ensure_thumbnail`'s existence check.
+pub async fn warm_thumbnails(
+    State(state): State<AppState>,
+    Query(params): Query<WarmParams>,
+) -> ApiResponse<WarmStatus> {
+    let format = params.format.unwrap_or_default();
+    let specs: Vec<ThumbnailSpec> = {
+        let snapshot = state.snapshot.read().await;
+        snapshot
+            .media
+            .iter()
+            .map(|media| thumbnail_spec(&state, media))
+            .collect()
+    };
+
+    let accepted = {
+        let mut status = state.thumbnail_warm.write().await;
+        if status.running {
+            return Err(ApiError::conflict(
+                "thumbnail warming is already in progress",
+            ));
+        }
+        *status = WarmStatus {
+            running: true,
+            size: params.size,
+            total: specs.len(),
+            completed: 0,
+            failed: 0,
+        };
+        status.clone()
+    };
+
+    tokio::spawn(async move {
+        let limit = state.config.thumbnails.concurrency.max(1);
+        let mut pending = JoinSet::new();
+        for spec in specs {
+            while pending.len() >= limit {
+                if let Some(result) = pending.join_next().await {
+                    record_warm_result(&state, result).await;
+                }
+            }
+            let generator = thumbnail_generator(&state);
+            let size = params
+                .size
+                .unwrap_or_else(|| state.config.thumbnails.default_size(&spec.media_type));
+            pending.spawn(async move { generator.ensure_thumbnail(&spec, size, format).await });
+        }
+        while let Some(result) = pending.join_next().await {
+            record_warm_result(&state, result).await;
+        }
+
+        {
+            let mut status = state.thumbnail_warm.write().await;
+            status.running = false;
+            tracing::info!(
+                completed = status.completed,
+                failed = status.failed,
+                "thumbnail warming finished"
+            );
+        }
+        // Warming can add a whole snapshot's worth of files at once.
+        if let Some(cache) = state.thumbnail_cache.clone() {
+            cache.enforce_budget_async().await;
+        }
+    });
+
+    Ok((StatusCode::ACCEPTED, Json(accepted)))
+}
+
+/// Report progress of the latest warming run.
+pub async fn warm_status(State(state): State<AppState>) -> ApiResult<WarmStatus> {
+    Ok(Json(state.thumbnail_warm.read().await.clone()))
+}
+
+async fn record_warm_result(
+    state: &AppState,
+    result: Result<anyhow::Result<ThumbnailArtifact>, tokio::task::JoinError>,
+) {
+    let ok = match result {
+        Ok(Ok(_)) => true,
+        Ok(Err(err)) => {
+            tracing::warn!(error = ?err, "failed to warm thumbnail");
+            false
+        }
+        Err(err) => {
+            tracing::warn!(error = %err, "thumbnail warming task panicked");
+            false
+        }
+    };
+    metrics::record_thumbnail(ok);
+    let mut status = state.thumbnail_warm.write().await;
+    if ok {
+        status.completed += 1;
+    } else {
+        status.failed += 1;
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
     use crate::{
+        api::test_support::sample_media,
         cache::CacheSnapshot,
-        config::{AppConfig, LogConfig, OtelConfig},
+        config::AppConfig,
         indexer::{MediaFile, MediaType},
         routes::AppState,
-        tags::{Tag, TagKind},
     };
     use axum::{
         body::Body,
-        http::{Method, Request},
+        http::{HeaderValue, Method, Request},
     };
-    use chrono::Utc;
     use http_body_util::BodyExt;
     use image::{DynamicImage, ImageBuffer, Rgb};
-    use std::{collections::HashMap as Map, net::SocketAddr, sync::Arc};
-    use tempfile::tempdir;
+    use std::{collections::HashMap as Map, path::PathBuf, sync::Arc};
+    use tempfile::{TempDir, tempdir};
     use tokio::sync::RwLock;
     use tower::ServiceExt;
 
     #[tokio::test]
     async fn serves_thumbnail_for_existing_media() {
-        let tmp = tempdir().unwrap();
-        let media_root = tmp.path().join("media");
-        tokio::fs::create_dir_all(&media_root).await.unwrap();
-        let cache_dir = tmp.path().join("cache");
-        tokio::fs::create_dir_all(&cache_dir).await.unwrap();
+        let (_tmp, media_root, cache_dir) = image_fixture();
+        let media = sample_image(&media_root, "sample");
+        let state = app_state(vec![media], media_root, cache_dir);
+        let router = crate::routes::router(state);
+        let request = Request::builder()
+            .method(Method::GET)
+            .uri("/api/v1/media/sample/thumbnail?size=small")
+            .body(Body::empty())
+            .unwrap();
 
-        let image_path = media_root.join("sample.png");
-        save_png(&image_path);
+        let response = router.oneshot(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.headers()[CONTENT_TYPE], "image/jpeg");
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        assert!(!body.is_empty());
+    }
 
+    #[tokio::test]
+    async fn unrenderable_media_gets_a_placeholder_when_enabled() {
+        let (_tmp, media_root, cache_dir) = image_fixture();
+        tokio::fs::write(media_root.join("notes.bin"), b"not an image")
+            .await
+            .unwrap();
         let media = MediaFile {
-            id: "sample".into(),
-            relative_path: "sample.png".into(),
-            media_type: MediaType::Image,
-            tags: vec![simple_tag("sample")],
-            attributes: Map::new(),
-            filesize: 0,
-            dimensions: None,
-            duration_ms: None,
-            thumbnail_path: Some("/media/sample/thumbnail".into()),
-            hash: None,
-            indexed_at: Utc::now(),
+            relative_path: "notes.bin".into(),
+            media_type: MediaType::Unknown,
+            ..sample_media("notes", Vec::new())
+        };
+        let state = app_state(vec![media], media_root, cache_dir);
+        let request = || {
+            Request::builder()
+                .method(Method::GET)
+                .uri("/api/v1/media/notes/thumbnail?size=small")
+                .body(Body::empty())
+                .unwrap()
+        };
+
+        let response = crate::routes::router(state.clone())
+            .oneshot(request())
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::INTERNAL_SERVER_ERROR);
+
+        let mut config = (*state.config).clone();
+        config.thumbnails.placeholder = true;
+        let state = AppState {
+            config: Arc::new(config),
+            ..state
         };
+        let response = crate::routes::router(state)
+            .oneshot(request())
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.headers()[THUMBNAIL_PLACEHOLDER], "true");
+        assert_eq!(response.headers()[CONTENT_TYPE], "image/png");
+        assert!(response.headers().get(ETAG).is_none());
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        let tile = image::load_from_memory(&body).unwrap();
+        assert_eq!(
+            (tile.width(), tile.height()),
+            ThumbnailSize::Small.as_dimensions()
+        );
+    }
 
-        let state = app_state(media, media_root, cache_dir);
+    #[tokio::test]
+    async fn omitted_size_uses_the_media_type_default() {
+        let (_tmp, media_root, cache_dir) = image_fixture();
+        let media = MediaFile {
+            relative_path: "clip.mp4".into(),
+            media_type: MediaType::Video,
+            ..sample_media("clip", Vec::new())
+        };
+        let mut state = app_state(vec![media], media_root, cache_dir);
+        let mut config = (*state.config).clone();
+        config.thumbnails.default_sizes = Map::from([(MediaType::Video, ThumbnailSize::Large)]);
+        state.config = Arc::new(config);
         let router = crate::routes::router(state);
+
+        // The ETag names the resolved size, so a matching validator proves which one was
+        // picked without rendering anything.
+        for (uri, etag) in [
+            (
+                "/api/v1/media/clip/thumbnail",
+                "\"clip--large-catmull-q75.jpg\"",
+            ),
+            (
+                "/api/v1/media/clip/thumbnail?size=small",
+                "\"clip--small-catmull-q75.jpg\"",
+            ),
+        ] {
+            let request = Request::builder()
+                .method(Method::GET)
+                .uri(uri)
+                .header(axum::http::header::IF_NONE_MATCH, etag)
+                .body(Body::empty())
+                .unwrap();
+            let response = router.clone().oneshot(request).await.unwrap();
+            assert_eq!(response.status(), StatusCode::NOT_MODIFIED, "{uri}");
+        }
+    }
+
+    #[tokio::test]
+    async fn serves_webp_thumbnail_when_requested() {
+        let (_tmp, media_root, cache_dir) = image_fixture();
+        let media = sample_image(&media_root, "sample");
+        let router = crate::routes::router(app_state(vec![media], media_root, cache_dir.clone()));
+
+        let mut etags = Vec::new();
+        for format in ["webp", "jpeg"] {
+            let request = Request::builder()
+                .method(Method::GET)
+                .uri(format!(
+                    "/api/v1/media/sample/thumbnail?size=small&format={format}"
+                ))
+                .body(Body::empty())
+                .unwrap();
+            let response = router.clone().oneshot(request).await.unwrap();
+            assert_eq!(response.status(), StatusCode::OK);
+            etags.push(response.headers()[ETAG].clone());
+            if format == "webp" {
+                assert_eq!(response.headers()[CONTENT_TYPE], "image/webp");
+                let body = response.into_body().collect().await.unwrap().to_bytes();
+                let decoded = image::load_from_memory_with_format(&body, image::ImageFormat::WebP)
+                    .expect("webp thumbnail decodes");
+                assert!(decoded.width() > 0 && decoded.width() <= 160);
+            }
+        }
+
+        assert_ne!(etags[0], etags[1]);
+        assert!(
+            cache_dir
+                .join("thumbnails/small/sample.catmull-q75.webp")
+                .exists()
+        );
+        assert!(
+            cache_dir
+                .join("thumbnails/small/sample.catmull-q75.jpg")
+                .exists()
+        );
+    }
+
+    #[tokio::test]
+    async fn changing_jpeg_quality_changes_the_etag() {
+        let (_tmp, media_root, cache_dir) = image_fixture();
+        let media = sample_image(&media_root, "sample");
+        let state = app_state(vec![media], media_root, cache_dir.clone());
+        let mut retuned = state.clone();
+        let mut config = (*state.config).clone();
+        config.thumbnails.jpeg_quality = 40;
+        retuned.config = Arc::new(config);
+
+        let mut etags = Vec::new();
+        for state in [state, retuned] {
+            let request = Request::builder()
+                .method(Method::GET)
+                .uri("/api/v1/media/sample/thumbnail?size=small")
+                .body(Body::empty())
+                .unwrap();
+            let response = crate::routes::router(state).oneshot(request).await.unwrap();
+            assert_eq!(response.status(), StatusCode::OK);
+            etags.push(response.headers()[ETAG].clone());
+        }
+
+        assert_eq!(etags[0], "\"sample--small-catmull-q75.jpg\"");
+        assert_eq!(etags[1], "\"sample--small-catmull-q40.jpg\"");
+        assert!(
+            cache_dir
+                .join("thumbnails/small/sample.catmull-q40.jpg")
+                .exists()
+        );
+    }
+
+    #[tokio::test]
+    async fn honors_if_none_match_and_head_requests() {
+        let (_tmp, media_root, cache_dir) = image_fixture();
+        let media = sample_image(&media_root, "sample");
+        let router = crate::routes::router(app_state(vec![media], media_root, cache_dir));
+        let uri = "/api/v1/media/sample/thumbnail?size=small";
+
         let request = Request::builder()
             .method(Method::GET)
-            .uri("/api/v1/media/sample/thumbnail?size=small")
+            .uri(uri)
+            .body(Body::empty())
+            .unwrap();
+        let response = router.clone().oneshot(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let etag = response.headers()[ETAG].clone();
+        let full_length = response.headers()[CONTENT_LENGTH].clone();
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        assert_eq!(body.len().to_string(), full_length.to_str().unwrap());
+
+        let request = Request::builder()
+            .method(Method::GET)
+            .uri(uri)
+            .header(axum::http::header::IF_NONE_MATCH, etag.clone())
             .body(Body::empty())
             .unwrap();
+        let response = router.clone().oneshot(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::NOT_MODIFIED);
+        assert_eq!(response.headers()[ETAG], etag);
+        assert_eq!(response.headers()[CACHE_CONTROL], "public, max-age=3600");
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        assert!(body.is_empty());
 
+        let request = Request::builder()
+            .method(Method::HEAD)
+            .uri(uri)
+            .body(Body::empty())
+            .unwrap();
         let response = router.oneshot(request).await.unwrap();
         assert_eq!(response.status(), StatusCode::OK);
-        assert_eq!(response.headers()[CONTENT_TYPE], "image/jpeg");
+        assert_eq!(response.headers()[CONTENT_LENGTH], full_length);
         let body = response.into_body().collect().await.unwrap().to_bytes();
-        assert!(!body.is_empty());
+        assert!(body.is_empty());
+    }
+
+    #[tokio::test]
+    async fn in_place_edits_refresh_the_thumbnail_and_its_etag() {
+        let (_tmp, media_root, cache_dir) = image_fixture();
+        let media = MediaFile {
+            hash: Some("0123456789abcdef0123456789abcdef01234567".into()),
+            ..sample_image(&media_root, "sample")
+        };
+        let source = media_root.join("sample.png");
+        let state = app_state(vec![media], media_root, cache_dir);
+        let snapshot = state.snapshot.clone();
+        let router = crate::routes::router(state);
+        let fetch = |etag: Option<HeaderValue>| {
+            let router = router.clone();
+            async move {
+                let mut request = Request::builder()
+                    .method(Method::GET)
+                    .uri("/api/v1/media/sample/thumbnail?size=small");
+                if let Some(etag) = etag {
+                    request = request.header(axum::http::header::IF_NONE_MATCH, etag);
+                }
+                router
+                    .oneshot(request.body(Body::empty()).unwrap())
+                    .await
+                    .unwrap()
+            }
+        };
+
+        let response = fetch(None).await;
+        let etag = response.headers()[ETAG].clone();
+        assert!(etag.to_str().unwrap().contains("0123456789abcdef"));
+
+        // Same path and id, new content: a wide image, stamped newer than the thumbnail.
+        let img: ImageBuffer<Rgb<u8>, Vec<u8>> = ImageBuffer::from_pixel(40, 10, Rgb([0, 0, 255]));
+        DynamicImage::ImageRgb8(img).save(&source).unwrap();
+        std::fs::File::options()
+            .write(true)
+            .open(&source)
+            .unwrap()
+            .set_modified(std::time::SystemTime::now() + std::time::Duration::from_secs(60))
+            .unwrap();
+        snapshot.write().await.media[0].hash =
+            Some("fedcba9876543210fedcba9876543210fedcba98".into());
+
+        let response = fetch(Some(etag.clone())).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_ne!(response.headers()[ETAG], etag);
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        let thumbnail = image::load_from_memory(&body).unwrap();
+        assert_eq!(thumbnail.width(), 4 * thumbnail.height());
+    }
+
+    #[tokio::test]
+    async fn queues_concurrent_generation_behind_permits() {
+        let (_tmp, media_root, cache_dir) = image_fixture();
+        let media: Vec<MediaFile> = (0..12)
+            .map(|index| sample_image(&media_root, &format!("image-{index}")))
+            .collect();
+        let ids: Vec<String> = media.iter().map(|item| item.id.clone()).collect();
+
+        let mut state = app_state(media, media_root, cache_dir);
+        let permits = Arc::new(tokio::sync::Semaphore::new(2));
+        state.thumbnail_permits = permits.clone();
+        let router = crate::routes::router(state);
+
+        let mut requests = tokio::task::JoinSet::new();
+        for id in ids {
+            let router = router.clone();
+            requests.spawn(async move {
+                let request = Request::builder()
+                    .method(Method::GET)
+                    .uri(format!("/api/v1/media/{id}/thumbnail?size=small"))
+                    .body(Body::empty())
+                    .unwrap();
+                router.oneshot(request).await.unwrap().status()
+            });
+        }
+        while let Some(status) = requests.join_next().await {
+            assert_eq!(status.unwrap(), StatusCode::OK);
+        }
+        assert_eq!(permits.available_permits(), 2, "permits are released");
+    }
+
+    #[tokio::test]
+    async fn warms_thumbnails_for_the_whole_snapshot() {
+        let (_tmp, media_root, cache_dir) = image_fixture();
+        let media: Vec<MediaFile> = (0..5)
+            .map(|index| sample_image(&media_root, &format!("image-{index}")))
+            .collect();
+        let router = crate::routes::router(app_state(media, media_root, cache_dir.clone()));
+
+        let request = Request::builder()
+            .method(Method::POST)
+            .uri("/api/v1/thumbnails/warm?size=small")
+            .body(Body::empty())
+            .unwrap();
+        let response = router.clone().oneshot(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::ACCEPTED);
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        let accepted: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(accepted["total"], 5);
+        assert_eq!(accepted["running"], true);
+
+        let status = tokio::time::timeout(std::time::Duration::from_secs(10), async {
+            loop {
+                let request = Request::builder()
+                    .uri("/api/v1/thumbnails/warm")
+                    .body(Body::empty())
+                    .unwrap();
+                let response = router.clone().oneshot(request).await.unwrap();
+                let body = response.into_body().collect().await.unwrap().to_bytes();
+                let status: serde_json::Value = serde_json::from_slice(&body).unwrap();
+                if status["running"] == false {
+                    return status;
+                }
+                tokio::time::sleep(std::time::Duration::from_millis(20)).await;
+            }
+        })
+        .await
+        .expect("warming finishes");
+
+        assert_eq!(status["completed"], 5);
+        assert_eq!(status["failed"], 0);
+        for index in 0..5 {
+            let path = cache_dir.join(format!("thumbnails/small/image-{index}.catmull-q75.jpg"));
+            assert!(path.exists(), "{} missing", path.display());
+        }
+    }
+
+    #[tokio::test]
+    async fn rejects_path_traversal_media_ids() {
+        let (tmp, media_root, cache_dir) = image_fixture();
+        let router = crate::routes::router(app_state(Vec::new(), media_root, cache_dir));
+
+        for id in ["..%2F..%2Fetc%2Fpasswd", "..", "a%00b", "a%5Cb", "a.b"] {
+            for endpoint in ["thumbnail", "stream"] {
+                let request = Request::builder()
+                    .method(Method::GET)
+                    .uri(format!("/api/v1/media/{id}/{endpoint}"))
+                    .body(Body::empty())
+                    .unwrap();
+                let response = router.clone().oneshot(request).await.unwrap();
+                assert_eq!(
+                    response.status(),
+                    StatusCode::BAD_REQUEST,
+                    "{endpoint} {id}"
+                );
+            }
+        }
+
+        let mut written = Vec::new();
+        for entry in walkdir::WalkDir::new(tmp.path()) {
+            let entry = entry.unwrap();
+            if entry.file_type().is_file() {
+                written.push(entry.into_path());
+            }
+        }
+        assert!(written.is_empty(), "unexpected files: {written:?}");
     }
 
     #[tokio::test]
     async fn returns_not_found_for_unknown_media() {
-        let tmp = tempdir().unwrap();
-        let state = app_state(
-            MediaFile {
-                id: "sample".into(),
-                relative_path: "missing.png".into(),
-                media_type: MediaType::Image,
-                tags: vec![],
-                attributes: Map::new(),
-                filesize: 0,
-                dimensions: None,
-                duration_ms: None,
-                thumbnail_path: Some("/media/sample/thumbnail".into()),
-                hash: None,
-                indexed_at: Utc::now(),
-            },
-            tmp.path().join("media"),
-            tmp.path().join("cache"),
-        );
+        let (_tmp, media_root, cache_dir) = image_fixture();
+        let media = MediaFile {
+            relative_path: "missing.png".into(),
+            ..sample_media("sample", Vec::new())
+        };
+        let state = app_state(vec![media], media_root, cache_dir);
         let router = crate::routes::router(state);
         let request = Request::builder()
             .method(Method::GET)
@@ -162,41 +738,59 @@ mod tests {
         assert_eq!(response.status(), StatusCode::NOT_FOUND);
     }
 
-    fn app_state(
-        media: MediaFile,
-        media_root: std::path::PathBuf,
-        cache_dir: std::path::PathBuf,
-    ) -> AppState {
-        let config = Arc::new(AppConfig {
-            media_root,
-            cache_dir: cache_dir.clone(),
-            listen_addr: SocketAddr::from(([127, 0, 0, 1], 0)),
-            environment: "test".into(),
-            otel: OtelConfig {
-                endpoint: None,
-                service_name: "test".into(),
-                disable_traces: true,
-                disable_logs: true,
-            },
-            log: LogConfig {
-                level: "info".into(),
-            },
-            cors_allowed_origins: Vec::new(),
-            frontend_dist_dir: None,
-        });
+    #[tokio::test]
+    async fn video_thumbnail_without_ffmpeg_is_service_unavailable() {
+        let (tmp, media_root, cache_dir) = image_fixture();
+        let media = MediaFile {
+            relative_path: "clip.mp4".into(),
+            media_type: MediaType::Video,
+            ..sample_media("clip", Vec::new())
+        };
+        let mut state = app_state(vec![media], media_root, cache_dir);
+        let mut config = (*state.config).clone();
+        config.thumbnails.ffmpeg_path = tmp.path().join("no-such-ffmpeg");
+        state.config = Arc::new(config);
+
+        let request = Request::builder()
+            .method(Method::GET)
+            .uri("/api/v1/media/clip/thumbnail")
+            .body(Body::empty())
+            .unwrap();
+        let response = crate::routes::router(state).oneshot(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE);
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(json["error"]["code"], "SERVICE_UNAVAILABLE");
+        assert!(
+            json["error"]["message"]
+                .as_str()
+                .unwrap()
+                .contains("no-such-ffmpeg"),
+            "{json}"
+        );
+    }
+
+    fn app_state(media: Vec<MediaFile>, media_root: PathBuf, cache_dir: PathBuf) -> AppState {
+        let config = Arc::new(AppConfig::for_tests(media_root, &cache_dir));
         let cache_store = Arc::new(crate::cache::CacheStore::new(&cache_dir));
-        let snapshot = CacheSnapshot::new(vec![media]);
+        let snapshot = CacheSnapshot::new(media);
         AppState::new(config, cache_store, Arc::new(RwLock::new(snapshot)))
     }
 
-    fn simple_tag(name: &str) -> Tag {
-        Tag {
-            raw_token: name.into(),
-            kind: TagKind::Simple,
-            name: name.to_lowercase(),
-            value: None,
-            normalized: name.to_lowercase(),
-        }
+    /// A temp dir with empty `media` and `cache` directories inside.
+    fn image_fixture() -> (TempDir, PathBuf, PathBuf) {
+        let tmp = tempdir().unwrap();
+        let media_root = tmp.path().join("media");
+        let cache_dir = tmp.path().join("cache");
+        std::fs::create_dir_all(&media_root).unwrap();
+        std::fs::create_dir_all(&cache_dir).unwrap();
+        (tmp, media_root, cache_dir)
+    }
+
+    /// Write `{id}.png` under `media_root` and return its record.
+    fn sample_image(media_root: &std::path::Path, id: &str) -> MediaFile {
+        save_png(&media_root.join(format!("{id}.png")));
+        sample_media(id, Vec::new())
     }
 
     fn save_png(path: &std::path::Path) {
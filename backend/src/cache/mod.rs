@@ -1,16 +1,26 @@
-use std::{fs, path::PathBuf};
+use std::{
+    fs,
+    io::Read,
+    path::{Path, PathBuf},
+    time::Duration,
+};
 
 use anyhow::{Context, Result};
 use chrono::{DateTime, Utc};
+use flate2::{Compression, read::GzDecoder, write::GzEncoder};
 use serde::{Deserialize, Serialize};
 
 use crate::indexer::MediaFile;
 
-const CACHE_VERSION: &str = "1.0.0";
+const CACHE_VERSION: &str = "1.1.0";
 const CACHE_FILENAME: &str = "index.json";
+const COMPRESSED_CACHE_FILENAME: &str = "index.json.gz";
+const GZIP_MAGIC: [u8; 2] = [0x1f, 0x8b];
+/// Temp files untouched for this long belong to a write that will never finish.
+pub const STALE_TEMP_FILE_AGE: Duration = Duration::from_secs(10 * 60);
 
 /// Snapshot of indexed media persisted to disk.
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 #[serde(rename_all = "camelCase")]
 pub struct CacheSnapshot {
     pub version: String,
@@ -28,43 +38,126 @@ impl CacheSnapshot {
     }
 }
 
+/// Canonical snapshot order: by id, then relative path should two ids ever collide.
+pub(crate) fn sort_media(media: &mut [MediaFile]) {
+    media.sort_by(|a, b| {
+        a.id.cmp(&b.id)
+            .then_with(|| a.relative_path.cmp(&b.relative_path))
+    });
+}
+
 /// JSON cache store that manages read/write lifecycle for the index snapshot.
 #[derive(Debug)]
 pub struct CacheStore {
     path: PathBuf,
+    compressed_path: PathBuf,
+    compress: bool,
 }
 
 impl CacheStore {
     /// Create a new store rooted at the provided cache directory.
     pub fn new(cache_dir: impl Into<PathBuf>) -> Self {
-        let mut path = cache_dir.into();
-        path.push(CACHE_FILENAME);
-        Self { path }
+        let cache_dir = cache_dir.into();
+        Self {
+            path: cache_dir.join(CACHE_FILENAME),
+            compressed_path: cache_dir.join(COMPRESSED_CACHE_FILENAME),
+            compress: false,
+        }
     }
 
-    /// Load the cache from disk if present and compatible with the current schema version.
-    pub fn load(&self) -> Result<Option<CacheSnapshot>> {
-        match fs::read_to_string(&self.path) {
-            Ok(contents) => {
-                let snapshot: CacheSnapshot =
-                    serde_json::from_str(&contents).context("failed to parse cache json")?;
-                if snapshot.version != CACHE_VERSION {
-                    anyhow::bail!(
-                        "cache schema mismatch (found {}, expected {})",
-                        snapshot.version,
-                        CACHE_VERSION
-                    );
-                }
-                Ok(Some(snapshot))
+    /// Write `index.json.gz` instead of plain `index.json`. Either format is still readable.
+    pub fn with_compression(mut self, compress: bool) -> Self {
+        self.compress = compress;
+        self
+    }
+
+    fn dir(&self) -> &Path {
+        self.path.parent().unwrap_or(Path::new("."))
+    }
+
+    /// Location the next snapshot will be written to.
+    fn target_path(&self) -> &Path {
+        if self.compress {
+            &self.compressed_path
+        } else {
+            &self.path
+        }
+    }
+
+    /// Read whichever snapshot file exists, preferring the configured format.
+    fn read_snapshot_bytes(&self) -> Result<Option<Vec<u8>>> {
+        let (preferred, fallback) = if self.compress {
+            (&self.compressed_path, &self.path)
+        } else {
+            (&self.path, &self.compressed_path)
+        };
+        for path in [preferred, fallback] {
+            match fs::read(path) {
+                Ok(bytes) => return Ok(Some(bytes)),
+                Err(err) if err.kind() == std::io::ErrorKind::NotFound => continue,
+                Err(err) => return Err(err.into()),
             }
-            Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(None),
-            Err(err) => Err(err.into()),
         }
+        Ok(None)
+    }
+
+    /// Load the cache from disk if present and compatible with the current schema version.
+    pub fn load(&self) -> Result<Option<CacheSnapshot>> {
+        self.read_snapshot_bytes()?.map(decode_snapshot).transpose()
+    }
+
+    /// Write the live snapshot to `path` as plain JSON, returning what was exported.
+    ///
+    /// Fails when no snapshot has been persisted yet.
+    pub fn export_to(&self, path: impl AsRef<Path>) -> Result<CacheSnapshot> {
+        let path = path.as_ref();
+        let snapshot = self
+            .load()?
+            .with_context(|| format!("no cache snapshot to export in {}", self.dir().display()))?;
+        let contents =
+            serde_json::to_vec_pretty(&snapshot).context("failed to serialize cache snapshot")?;
+        let tmp_path = path.with_extension(format!(
+            "{}.tmp",
+            Utc::now().timestamp_nanos_opt().unwrap_or(0)
+        ));
+        fs::write(&tmp_path, contents)
+            .with_context(|| format!("failed to write {}", tmp_path.display()))?;
+        fs::rename(&tmp_path, path)
+            .with_context(|| format!("failed to write {}", path.display()))?;
+        Ok(snapshot)
+    }
+
+    /// Replace the live snapshot with the one stored at `path` (plain or gzip JSON).
+    ///
+    /// The file must carry a compatible schema version; the live cache is swapped in
+    /// with a rename, so readers never observe a partially written file.
+    pub fn import_from(&self, path: impl AsRef<Path>) -> Result<CacheSnapshot> {
+        let path = path.as_ref();
+        let bytes = fs::read(path).with_context(|| format!("failed to read {}", path.display()))?;
+        let snapshot = decode_snapshot(bytes)
+            .with_context(|| format!("invalid snapshot {}", path.display()))?;
+        self.write_snapshot(&snapshot)?;
+        Ok(snapshot)
     }
 
     /// Persist the provided media list to disk, returning the snapshot that was written.
     pub fn persist(&self, media: Vec<MediaFile>) -> Result<CacheSnapshot> {
-        let snapshot = CacheSnapshot::new(media);
+        self.persist_as_of(media, Utc::now())
+    }
+
+    /// Like [`CacheStore::persist`], stamping the snapshot with a `generated_at`
+    /// chosen up front (e.g. one already promised to an API caller).
+    pub fn persist_as_of(
+        &self,
+        media: Vec<MediaFile>,
+        generated_at: DateTime<Utc>,
+    ) -> Result<CacheSnapshot> {
+        let mut snapshot = CacheSnapshot {
+            generated_at,
+            ..CacheSnapshot::new(media)
+        };
+        // Scan traversal order varies across filesystems and restarts; store a canonical one.
+        sort_media(&mut snapshot.media);
         self.write_snapshot(&snapshot)?;
         Ok(snapshot)
     }
@@ -87,6 +180,46 @@ impl CacheStore {
         }
     }
 
+    /// Remove `index.*.tmp` files left behind by interrupted writes whose last
+    /// modification is at least `min_age` ago, returning how many were removed.
+    ///
+    /// A write in progress keeps its temp file fresh, so a non-zero `min_age` never
+    /// races a concurrent writer; pass `Duration::ZERO` only once no write can be in
+    /// flight (e.g. after shutdown has drained).
+    pub fn sweep_temp_files(&self, min_age: Duration) -> Result<usize> {
+        let Some(dir) = self.path.parent() else {
+            return Ok(0);
+        };
+        let entries = match fs::read_dir(dir) {
+            Ok(entries) => entries,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(0),
+            Err(err) => return Err(err.into()),
+        };
+        let mut removed = 0;
+        for entry in entries {
+            let entry = entry?;
+            let name = entry.file_name();
+            let name = name.to_string_lossy();
+            if !(name.starts_with("index.") && name.ends_with(".tmp")) {
+                continue;
+            }
+            let age = entry
+                .metadata()
+                .and_then(|metadata| metadata.modified())
+                .map(|modified| modified.elapsed().unwrap_or_default())?;
+            if age < min_age {
+                continue;
+            }
+            match fs::remove_file(entry.path()) {
+                Ok(()) => removed += 1,
+                // Renamed into place or swept by someone else in the meantime.
+                Err(err) if err.kind() == std::io::ErrorKind::NotFound => {}
+                Err(err) => return Err(err.into()),
+            }
+        }
+        Ok(removed)
+    }
+
     fn rebuild_with<F>(&self, rebuild: F) -> Result<CacheSnapshot>
     where
         F: FnOnce() -> Result<Vec<MediaFile>>,
@@ -96,23 +229,89 @@ impl CacheStore {
     }
 
     fn write_snapshot(&self, snapshot: &CacheSnapshot) -> Result<()> {
-        if let Some(parent) = self.path.parent() {
+        let target = self.target_path();
+        if let Some(parent) = target.parent() {
             fs::create_dir_all(parent)?;
         }
 
-        let tmp_path = self.path.with_extension(format!(
+        let tmp_path = target.with_extension(format!(
             "{}.tmp",
             Utc::now().timestamp_nanos_opt().unwrap_or(0)
         ));
-        let json =
-            serde_json::to_string_pretty(snapshot).context("failed to serialize cache snapshot")?;
+        let contents = if self.compress {
+            let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+            serde_json::to_writer(&mut encoder, snapshot)
+                .context("failed to serialize cache snapshot")?;
+            encoder
+                .finish()
+                .context("failed to compress cache snapshot")?
+        } else {
+            serde_json::to_vec_pretty(snapshot).context("failed to serialize cache snapshot")?
+        };
 
-        fs::write(&tmp_path, json)?;
-        fs::rename(&tmp_path, &self.path)?;
+        fs::write(&tmp_path, contents)?;
+        fs::rename(&tmp_path, target)?;
+
+        // Drop the other format so a stale copy is never preferred on the next load.
+        let stale = if self.compress {
+            &self.path
+        } else {
+            &self.compressed_path
+        };
+        if let Err(err) = fs::remove_file(stale)
+            && err.kind() != std::io::ErrorKind::NotFound
+        {
+            tracing::warn!(path = %stale.display(), error = %err, "failed to remove stale cache file");
+        }
+        if let Err(err) = self.sweep_temp_files(STALE_TEMP_FILE_AGE) {
+            tracing::warn!(error = %err, "failed to sweep stale cache temp files");
+        }
         Ok(())
     }
 }
 
+/// Parse a snapshot file, sniffing the gzip header so a renamed or legacy file is
+/// still read correctly, and upgrade it to the current schema version.
+fn decode_snapshot(bytes: Vec<u8>) -> Result<CacheSnapshot> {
+    let contents = if bytes.starts_with(&GZIP_MAGIC) {
+        let mut decoded = Vec::new();
+        GzDecoder::new(bytes.as_slice())
+            .read_to_end(&mut decoded)
+            .context("failed to decompress cache")?;
+        decoded
+    } else {
+        bytes
+    };
+    let snapshot: CacheSnapshot =
+        serde_json::from_slice(&contents).context("failed to parse cache json")?;
+    let from = snapshot.version.clone();
+    let mut snapshot = migrate(snapshot, &from)?;
+    // Files written before snapshots were kept in canonical order.
+    sort_media(&mut snapshot.media);
+    Ok(snapshot)
+}
+
+/// Upgrade a snapshot written by an older, compatible schema version.
+///
+/// Versions not listed here are incompatible and force a full rebuild.
+fn migrate(mut snapshot: CacheSnapshot, from: &str) -> Result<CacheSnapshot> {
+    match from {
+        CACHE_VERSION => Ok(snapshot),
+        // 1.0.0 never populated `hash`; 1.1.0 stores the content fingerprint there. A missing
+        // fingerprint simply makes the next incremental scan re-read the file.
+        "1.0.0" => {
+            tracing::info!(from, to = CACHE_VERSION, "migrating cache snapshot");
+            snapshot.version = CACHE_VERSION.to_string();
+            Ok(snapshot)
+        }
+        _ => anyhow::bail!(
+            "cache schema mismatch (found {}, expected {})",
+            from,
+            CACHE_VERSION
+        ),
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -130,12 +329,71 @@ mod tests {
             filesize: 42,
             dimensions: None,
             duration_ms: None,
+            location: None,
             thumbnail_path: Some("/media/abc/thumbnail".into()),
+            stream_path: None,
             hash: None,
             indexed_at: Utc::now(),
+            modified_at: None,
+            invalid_tokens: Vec::new(),
         }
     }
 
+    #[test]
+    fn export_then_import_into_fresh_cache_dir() -> Result<()> {
+        let source_dir = tempdir()?;
+        let source = CacheStore::new(source_dir.path()).with_compression(true);
+        let written = source.persist(vec![sample_media()])?;
+
+        let export_dir = tempdir()?;
+        let export_path = export_dir.path().join("snapshot.json");
+        assert_eq!(source.export_to(&export_path)?, written);
+
+        let target_dir = tempdir()?;
+        let target = CacheStore::new(target_dir.path());
+        assert_eq!(target.import_from(&export_path)?, written);
+        assert_eq!(target.load()?, Some(written));
+        Ok(())
+    }
+
+    #[test]
+    fn snapshots_are_kept_in_id_order() -> Result<()> {
+        let dir = tempdir()?;
+        let store = CacheStore::new(dir.path());
+        let mut first = sample_media();
+        first.id = "b".into();
+        let mut second = sample_media();
+        second.id = "a".into();
+        let written = store.persist(vec![first.clone(), second.clone()])?;
+        assert_eq!(written.media, [second.clone(), first.clone()]);
+
+        // Older files may still list media in scan order.
+        let unsorted = CacheSnapshot {
+            media: vec![first.clone(), second.clone()],
+            ..written
+        };
+        fs::write(
+            dir.path().join(CACHE_FILENAME),
+            serde_json::to_vec(&unsorted)?,
+        )?;
+        assert_eq!(store.load()?.expect("snapshot").media, [second, first]);
+        Ok(())
+    }
+
+    #[test]
+    fn import_rejects_incompatible_version() -> Result<()> {
+        let dir = tempdir()?;
+        let path = dir.path().join("snapshot.json");
+        let mut snapshot = CacheSnapshot::new(vec![sample_media()]);
+        snapshot.version = "0.9.0".into();
+        fs::write(&path, serde_json::to_vec(&snapshot)?)?;
+
+        let store = CacheStore::new(dir.path().join("cache"));
+        assert!(store.import_from(&path).is_err());
+        assert!(store.load()?.is_none());
+        Ok(())
+    }
+
     #[test]
     fn persist_and_load_roundtrip() -> Result<()> {
         let dir = tempdir()?;
@@ -149,6 +407,100 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn sweep_removes_only_stale_temp_files() -> Result<()> {
+        let dir = tempdir()?;
+        let store = CacheStore::new(dir.path());
+        let stale = dir.path().join("index.1.tmp");
+        let fresh = dir.path().join("index.2.tmp");
+        let unrelated = dir.path().join("notes.tmp");
+        for path in [&stale, &fresh, &unrelated] {
+            fs::write(path, b"partial")?;
+        }
+        fs::File::options()
+            .write(true)
+            .open(&stale)?
+            .set_modified(std::time::SystemTime::now() - Duration::from_secs(3600))?;
+
+        assert_eq!(store.sweep_temp_files(STALE_TEMP_FILE_AGE)?, 1);
+        assert!(!stale.exists());
+        assert!(
+            fresh.exists(),
+            "a concurrent write's temp file must survive"
+        );
+        assert!(unrelated.exists());
+
+        assert_eq!(store.sweep_temp_files(Duration::ZERO)?, 1);
+        assert!(!fresh.exists());
+        Ok(())
+    }
+
+    #[test]
+    fn compressed_persist_and_load_roundtrip() -> Result<()> {
+        let dir = tempdir()?;
+        let store = CacheStore::new(dir.path()).with_compression(true);
+        store.persist(vec![sample_media()])?;
+
+        let written = fs::read(dir.path().join(COMPRESSED_CACHE_FILENAME))?;
+        assert!(written.starts_with(&GZIP_MAGIC));
+        assert!(!dir.path().join(CACHE_FILENAME).exists());
+
+        let loaded = store.load()?.expect("should load snapshot");
+        assert_eq!(loaded.media.len(), 1);
+        assert_eq!(loaded.media[0].relative_path, "foo/bar.jpg");
+        Ok(())
+    }
+
+    #[test]
+    fn compressed_store_reads_legacy_plain_cache() -> Result<()> {
+        let dir = tempdir()?;
+        CacheStore::new(dir.path()).persist(vec![sample_media()])?;
+
+        let store = CacheStore::new(dir.path()).with_compression(true);
+        let loaded = store.load()?.expect("should load legacy snapshot");
+        assert_eq!(loaded.media.len(), 1);
+
+        // The next write migrates to the compressed file and removes the legacy one.
+        store.persist(loaded.media)?;
+        assert!(dir.path().join(COMPRESSED_CACHE_FILENAME).exists());
+        assert!(!dir.path().join(CACHE_FILENAME).exists());
+        Ok(())
+    }
+
+    fn write_versioned(dir: &Path, version: &str) -> Result<()> {
+        let mut snapshot = CacheSnapshot::new(vec![sample_media()]);
+        snapshot.version = version.to_string();
+        fs::write(dir.join(CACHE_FILENAME), serde_json::to_vec(&snapshot)?)?;
+        Ok(())
+    }
+
+    #[test]
+    fn migrates_compatible_older_snapshot() -> Result<()> {
+        let dir = tempdir()?;
+        write_versioned(dir.path(), "1.0.0")?;
+
+        let store = CacheStore::new(dir.path());
+        let loaded = store.load()?.expect("should load migrated snapshot");
+        assert_eq!(loaded.version, CACHE_VERSION);
+        assert_eq!(loaded.media.len(), 1);
+
+        let reused = store.load_or_rebuild(|| Err(anyhow!("should not rebuild")))?;
+        assert_eq!(reused.media.len(), 1);
+        Ok(())
+    }
+
+    #[test]
+    fn rejects_incompatible_snapshot_version() -> Result<()> {
+        let dir = tempdir()?;
+        write_versioned(dir.path(), "0.9.0")?;
+
+        let store = CacheStore::new(dir.path());
+        assert!(store.load().is_err());
+        let rebuilt = store.load_or_rebuild(|| Ok(Vec::new()))?;
+        assert!(rebuilt.media.is_empty());
+        Ok(())
+    }
+
     #[test]
     fn load_or_rebuild_invokes_fallback_when_missing() -> Result<()> {
         let dir = tempdir()?;
@@ -0,0 +1,120 @@
+use std::{io::Write, path::Path};
+
+use anyhow::Result;
+
+use crate::{
+    cache::{CacheSnapshot, CacheStore},
+    indexer::{Indexer, IndexerConfig, MediaFile},
+};
+
+/// Scan `config.root` and write one row per file (path, media type, parsed tags, and
+/// rejected tokens) followed by the scan summary. Nothing is cached.
+pub fn index_dry_run(config: &IndexerConfig, out: &mut impl Write) -> Result<()> {
+    let (mut files, summary) = Indexer::scan_with_summary(config)?;
+    files.sort_by(|a, b| a.relative_path.cmp(&b.relative_path));
+
+    let header = ["PATH", "TYPE", "TAGS", "INVALID"].map(String::from);
+    let rows: Vec<[String; 4]> = files.iter().map(dry_run_row).collect();
+    let mut widths = header.clone().map(|cell| cell.len());
+    for row in &rows {
+        for (width, cell) in widths.iter_mut().zip(row) {
+            *width = (*width).max(cell.chars().count());
+        }
+    }
+
+    for row in std::iter::once(&header).chain(&rows) {
+        let line = row
+            .iter()
+            .zip(widths)
+            .map(|(cell, width)| format!("{cell:<width$}"))
+            .collect::<Vec<_>>()
+            .join("  ");
+        writeln!(out, "{}", line.trim_end())?;
+    }
+    writeln!(
+        out,
+        "\n{} indexed, {} skipped, {} errored",
+        summary.indexed, summary.skipped, summary.errored
+    )?;
+    for error in &summary.top_errors {
+        writeln!(out, "  {}: {}", error.category, error.count)?;
+    }
+    Ok(())
+}
+
+/// Copy the live snapshot of `store` to `path` and report what was written.
+pub fn export_cache(store: &CacheStore, path: &Path, out: &mut impl Write) -> Result<()> {
+    let snapshot = store.export_to(path)?;
+    write_snapshot_summary(out, "exported", &snapshot, path)
+}
+
+/// Replace the live snapshot of `store` with the file at `path` and report what was read.
+pub fn import_cache(store: &CacheStore, path: &Path, out: &mut impl Write) -> Result<()> {
+    let snapshot = store.import_from(path)?;
+    write_snapshot_summary(out, "imported", &snapshot, path)
+}
+
+fn write_snapshot_summary(
+    out: &mut impl Write,
+    verb: &str,
+    snapshot: &CacheSnapshot,
+    path: &Path,
+) -> Result<()> {
+    writeln!(
+        out,
+        "{verb} {} media (v{}, generated {}) via {}",
+        snapshot.media.len(),
+        snapshot.version,
+        snapshot.generated_at.to_rfc3339(),
+        path.display()
+    )?;
+    Ok(())
+}
+
+fn dry_run_row(media: &MediaFile) -> [String; 4] {
+    let tags = media
+        .tags
+        .iter()
+        .map(|tag| tag.normalized.as_str())
+        .collect::<Vec<_>>()
+        .join(",");
+    [
+        media.relative_path.clone(),
+        format!("{:?}", media.media_type).to_lowercase(),
+        tags,
+        media.invalid_tokens.join(","),
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    #[test]
+    fn dry_run_prints_classification_of_sample_media() {
+        let root = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("../sample-media");
+        let mut out = Vec::new();
+        index_dry_run(&IndexerConfig::new(root), &mut out).unwrap();
+        let out = String::from_utf8(out).unwrap();
+        let lines: Vec<&str> = out.lines().collect();
+
+        assert!(lines[0].starts_with("PATH"), "{out}");
+        let row = lines
+            .iter()
+            .find(|line| line.starts_with("sunset_coast+location-okinawa_rating-5.png"))
+            .expect("png row");
+        let cells: Vec<&str> = row.split_whitespace().collect();
+        assert_eq!(
+            cells[1..],
+            ["image", "sunset,coast,location=okinawa,rating=5"]
+        );
+        assert!(
+            lines
+                .iter()
+                .any(|line| line.starts_with("skate_session") && line.contains(" video ")),
+            "{out}"
+        );
+        assert!(out.contains("3 indexed, "), "{out}");
+    }
+}
@@ -1,11 +1,26 @@
 use std::{
-    fs,
-    net::SocketAddr,
+    collections::{BTreeMap, HashMap},
+    ffi::OsString,
+    fmt, fs,
+    net::{IpAddr, SocketAddr},
     path::{Path, PathBuf},
+    str::FromStr,
+    time::Duration,
 };
 
 use anyhow::{Context, Result, anyhow};
-use clap::Parser;
+use axum::http::{HeaderName, HeaderValue};
+use clap::{ArgMatches, Command, CommandFactory, Parser, Subcommand, parser::ValueSource};
+use ipnet::IpNet;
+
+use crate::{
+    api::{auth::ApiToken, client_ip::TrustedProxies},
+    cache::CacheStore,
+    indexer::{DEFAULT_LINK_BASE, IdStrategy, IndexerConfig, MediaRoot, MediaType},
+    media::thumbnails::{DEFAULT_JPEG_QUALITY, ResizeFilter, ThumbnailSize},
+    services::search::PageLimits,
+    tags::{TagAliases, TagParserConfig},
+};
 
 /// CLI / env configuration parsed at process startup.
 #[derive(Debug, Clone, Parser)]
@@ -16,12 +31,27 @@ use clap::Parser;
     disable_help_subcommand = true
 )]
 struct CliConfig {
-    /// Root directory containing tagged media files
-    #[arg(long, env = "GALARIE_MEDIA_ROOT")]
-    media_root: PathBuf,
+    #[command(subcommand)]
+    command: Option<CliCommand>,
+
+    /// TOML file whose keys (flag names, e.g. `media_root = "/srv/media"`) provide
+    /// defaults for any flag not set on the command line or in the environment
+    #[arg(long, env = "GALARIE_CONFIG")]
+    config: Option<PathBuf>,
+
+    /// Root directory containing tagged media files (required). Repeat or comma-separate
+    /// to index several; paths then start with each root's directory name, or with
+    /// `label` when given as `label=/path`
+    #[arg(long, env = "GALARIE_MEDIA_ROOT", global = true, value_delimiter = ',')]
+    media_root: Vec<PathBuf>,
 
     /// Directory for cache/temporary data
-    #[arg(long, env = "GALARIE_CACHE_DIR", default_value = "./.cache")]
+    #[arg(
+        long,
+        env = "GALARIE_CACHE_DIR",
+        default_value = "./.cache",
+        global = true
+    )]
     cache_dir: PathBuf,
 
     /// Address to bind the HTTP server to (e.g., 0.0.0.0:8080)
@@ -36,6 +66,14 @@ struct CliConfig {
     #[arg(long, env = "OTEL_SERVICE_NAME", default_value = "galarie-backend")]
     otel_service_name: String,
 
+    /// OTLP transport: `grpc` (port 4317) or `http` (OTLP/HTTP protobuf, port 4318)
+    #[arg(long, env = "OTEL_EXPORTER_OTLP_PROTOCOL", default_value = "grpc")]
+    otel_protocol: String,
+
+    /// Extra OTLP export headers, e.g. collector auth (comma-separated key=value)
+    #[arg(long, env = "OTEL_EXPORTER_OTLP_HEADERS", value_delimiter = ',')]
+    otel_headers: Vec<String>,
+
     /// Disable OTLP trace export even if an endpoint is set
     #[arg(long, env = "GALARIE_OTEL_DISABLE_TRACES", default_value_t = false)]
     otel_disable_traces: bool,
@@ -52,26 +90,330 @@ struct CliConfig {
     #[arg(long, env = "LOG_LEVEL", default_value = "info")]
     log_level: String,
 
-    /// Comma-separated list of allowed CORS origins
+    /// Stdout log style: `json`, `pretty` or `compact`
+    #[arg(long, env = "GALARIE_LOG_FORMAT", default_value = "json")]
+    log_format: String,
+
+    /// Comma-separated list of allowed CORS origins (`*` for any; empty disables CORS)
     #[arg(long, env = "GALARIE_CORS_ALLOWED_ORIGINS", value_delimiter = ',')]
     cors_allowed_origins: Vec<String>,
 
     /// Directory containing the built frontend assets
     #[arg(long, env = "GALARIE_FRONTEND_DIST_DIR")]
     frontend_dist_dir: Option<PathBuf>,
+
+    /// Store the index cache gzip-compressed as index.json.gz
+    #[arg(
+        long,
+        env = "GALARIE_CACHE_COMPRESS",
+        default_value_t = false,
+        global = true
+    )]
+    cache_compress: bool,
+
+    /// Maximum number of thumbnails generated concurrently; further requests queue
+    #[arg(long, env = "GALARIE_THUMBNAIL_CONCURRENCY", default_value_t = 4, value_parser = clap::value_parser!(u16).range(1..))]
+    thumbnail_concurrency: u16,
+
+    /// ffmpeg binary used for video and audio thumbnails
+    #[arg(long, env = "GALARIE_FFMPEG_PATH", default_value = "ffmpeg")]
+    ffmpeg_path: PathBuf,
+
+    /// gifsicle binary used for GIF thumbnails
+    #[arg(long, env = "GALARIE_GIFSICLE_PATH", default_value = "gifsicle")]
+    gifsicle_path: PathBuf,
+
+    /// Refuse to start when ffmpeg or gifsicle is missing instead of only warning
+    #[arg(long, env = "GALARIE_REQUIRE_MEDIA_TOOLS", default_value_t = false)]
+    require_media_tools: bool,
+
+    /// pdftoppm binary used to render the first page of PDFs for thumbnails
+    #[arg(long, env = "GALARIE_PDFTOPPM_PATH", default_value = "pdftoppm")]
+    pdftoppm_path: PathBuf,
+
+    /// Byte budget for generated thumbnails; least recently used files are evicted (0 = unlimited)
+    #[arg(long, env = "GALARIE_THUMBNAIL_CACHE_MAX_BYTES", default_value_t = 0)]
+    thumbnail_cache_max_bytes: u64,
+
+    /// Comma-separated per media type thumbnail sizes used when a request names none
+    /// (e.g., video=large,audio=small); other types default to medium
+    #[arg(long, env = "GALARIE_THUMBNAIL_DEFAULT_SIZES", value_delimiter = ',')]
+    thumbnail_default_sizes: Vec<String>,
+
+    /// Read size for streamed media bodies, in KiB
+    #[arg(long, env = "GALARIE_STREAM_CHUNK_KIB", default_value_t = 64, value_parser = clap::value_parser!(u32).range(1..=16_384))]
+    stream_chunk_kib: u32,
+
+    /// `max-age` (seconds) advertised in Cache-Control on streamed media
+    #[arg(long, env = "GALARIE_STREAM_MAX_AGE_SECS", default_value_t = 3600)]
+    stream_max_age_secs: u64,
+
+    /// Only use embedded cover art for audio thumbnails (no waveform fallback)
+    #[arg(long, env = "GALARIE_AUDIO_COVER_ONLY", default_value_t = false)]
+    audio_cover_only: bool,
+
+    /// JPEG/WebP quality of generated thumbnails (1-100); lower values give smaller files
+    #[arg(long, env = "GALARIE_THUMBNAIL_QUALITY", default_value_t = DEFAULT_JPEG_QUALITY, value_parser = clap::value_parser!(u8).range(1..=100))]
+    thumbnail_quality: u8,
+
+    /// Resampling filter for image thumbnails: nearest, triangle, catmull or lanczos3
+    #[arg(long, env = "GALARIE_THUMBNAIL_FILTER", default_value = "catmull")]
+    thumbnail_filter: String,
+
+    /// Serve a generic placeholder tile instead of an error when a thumbnail cannot be rendered
+    #[arg(long, env = "GALARIE_THUMBNAIL_PLACEHOLDER", default_value_t = false)]
+    thumbnail_placeholder: bool,
+
+    /// Maximum API requests per minute per client IP (0 disables rate limiting)
+    #[arg(long, env = "GALARIE_RATE_LIMIT_PER_MINUTE", default_value_t = 0)]
+    rate_limit_per_minute: u32,
+
+    /// Search page size used when a request omits `pageSize`
+    #[arg(long, env = "GALARIE_DEFAULT_PAGE_SIZE", default_value_t = 60)]
+    default_page_size: usize,
+
+    /// Largest `pageSize` a search request may ask for; bigger requests are clamped
+    #[arg(long, env = "GALARIE_MAX_PAGE_SIZE", default_value_t = 200)]
+    max_page_size: usize,
+
+    /// Largest accepted API request body, in bytes
+    #[arg(long, env = "GALARIE_MAX_REQUEST_BODY_BYTES", default_value_t = 1024 * 1024)]
+    max_request_body_bytes: usize,
+
+    /// Per-request handler timeout in seconds for API routes (0 disables)
+    #[arg(long, env = "GALARIE_REQUEST_TIMEOUT_SECS", default_value_t = 90)]
+    request_timeout_secs: u64,
+
+    /// Concurrent HTTP/2 streams allowed per client connection
+    #[arg(
+        long,
+        env = "GALARIE_HTTP2_MAX_CONCURRENT_STREAMS",
+        default_value_t = 200
+    )]
+    http2_max_concurrent_streams: u32,
+
+    /// Seconds between HTTP/2 keep-alive pings to idle clients (0 disables)
+    #[arg(
+        long,
+        env = "GALARIE_HTTP2_KEEP_ALIVE_INTERVAL_SECS",
+        default_value_t = 0
+    )]
+    http2_keep_alive_interval_secs: u64,
+
+    /// Set TCP_NODELAY on accepted connections
+    #[arg(long, env = "GALARIE_TCP_NODELAY", default_value_t = false)]
+    tcp_nodelay: bool,
+
+    /// Bearer token required on /api/v1 requests (unset disables authentication)
+    #[arg(long, env = "GALARIE_API_TOKEN")]
+    api_token: Option<String>,
+
+    /// Comma-separated proxy addresses or CIDR ranges whose X-Forwarded-For/Forwarded headers name the client
+    #[arg(long, env = "GALARIE_TRUSTED_PROXIES", value_delimiter = ',')]
+    trusted_proxies: Vec<String>,
+
+    /// Comma-separated extension to media type overrides (e.g., jxl=image,m4v=video)
+    #[arg(long, env = "GALARIE_MEDIA_EXTENSION_MAP", value_delimiter = ',')]
+    media_extension_map: Vec<String>,
+
+    /// Seconds between media root rescans (0 scans once at startup only)
+    #[arg(long, env = "GALARIE_POLL_INTERVAL_SECONDS", default_value_t = 30)]
+    poll_interval_seconds: u64,
+
+    /// Rescan on filesystem change notifications instead of fixed-interval polling
+    #[arg(long, env = "GALARIE_WATCH", default_value_t = false)]
+    watch: bool,
+
+    /// Index dotfiles and descend into hidden directories
+    #[arg(long, env = "GALARIE_INCLUDE_HIDDEN", default_value_t = false)]
+    include_hidden: bool,
+
+    /// ffprobe binary used to read audio/video durations while indexing
+    #[arg(long, env = "GALARIE_FFPROBE_PATH", default_value = "ffprobe")]
+    ffprobe_path: PathBuf,
+
+    /// Characters separating tags in filenames (whitespace always separates too)
+    #[arg(long, env = "GALARIE_TAG_DELIMITERS", default_value = "_+")]
+    tag_delimiters: String,
+
+    /// Characters separating a key from its value within a tag, tried in order
+    #[arg(long, env = "GALARIE_TAG_KV_SEPARATORS", default_value = ":-")]
+    tag_kv_separators: String,
+
+    /// Media id derivation: `path` (stable across edits) or `path-content` (new id on edit)
+    #[arg(long, env = "GALARIE_ID_STRATEGY", default_value = "path")]
+    id_strategy: String,
+
+    /// Also derive tags from the directory names in each media path
+    #[arg(long, env = "GALARIE_TAGS_FROM_DIRS", default_value_t = false)]
+    tags_from_dirs: bool,
+
+    /// Do not derive tags from filenames (combine with --tags-from-dirs)
+    #[arg(long, env = "GALARIE_IGNORE_FILENAME_TAGS", default_value_t = false)]
+    ignore_filename_tags: bool,
+
+    /// Comma-separated attribute keys parsed as key/value tags (empty accepts any key)
+    #[arg(long, env = "GALARIE_TAG_ATTRIBUTE_KEYS", value_delimiter = ',')]
+    tag_attribute_keys: Vec<String>,
+
+    /// File of `alias=canonical` lines; tags are indexed and searched under their canonical form
+    #[arg(long, env = "GALARIE_TAG_ALIASES")]
+    tag_aliases: Option<PathBuf>,
+
+    /// Deepest directory level to scan (1 = only files directly under the media root)
+    #[arg(long, env = "GALARIE_MAX_SCAN_DEPTH")]
+    max_scan_depth: Option<usize>,
+
+    /// Skip files smaller than this many bytes (0 indexes empty files too)
+    #[arg(long, env = "GALARIE_MIN_FILESIZE_BYTES", default_value_t = 1)]
+    min_filesize_bytes: u64,
+
+    /// Prefix for the thumbnail/stream links in media responses: a root-relative path or
+    /// an absolute URL (e.g. when served behind a proxy under another path)
+    #[arg(long, env = "GALARIE_LINK_BASE", default_value = DEFAULT_LINK_BASE)]
+    link_base: String,
+
+    /// Add camera, lens and capture date attributes from image EXIF metadata
+    #[arg(long, env = "GALARIE_READ_EXIF", default_value_t = false)]
+    read_exif: bool,
+
+    /// Follow symlinked directories and files (cycles and duplicate paths are skipped)
+    #[arg(long, env = "GALARIE_FOLLOW_SYMLINKS", default_value_t = false)]
+    follow_symlinks: bool,
+
+    /// Worker threads for per-file metadata extraction during scans (0 = one per CPU)
+    #[arg(long, env = "GALARIE_SCAN_CONCURRENCY", default_value_t = 0)]
+    scan_concurrency: u16,
+
+    /// Per-file timeout for ffprobe metadata extraction, in milliseconds
+    #[arg(long, env = "GALARIE_PROBE_TIMEOUT_MS", default_value_t = 5_000)]
+    probe_timeout_ms: u64,
+}
+
+impl CliConfig {
+    /// `--media-root`, which every command needs; clap cannot mark global flags required.
+    fn existing_media_roots(&self) -> Result<Vec<MediaRoot>> {
+        if self.media_root.is_empty() {
+            return Err(anyhow!("--media-root (or GALARIE_MEDIA_ROOT) is required"));
+        }
+        let roots = parse_media_roots(&self.media_root).context("invalid --media-root")?;
+        for root in &roots {
+            ensure_directory_exists(&root.path)
+                .with_context(|| format!("media root '{}' missing", root.path.display()))?;
+        }
+        Ok(roots)
+    }
+}
+
+/// One-off tasks run instead of the server.
+#[derive(Debug, Clone, Subcommand)]
+enum CliCommand {
+    /// Scan the media root and print how each file would be classified and tagged
+    Index {
+        /// Only report; no cache is written and no port is bound (currently required)
+        #[arg(long)]
+        dry_run: bool,
+    },
+    /// Write the cached index snapshot to a JSON file
+    Export {
+        /// Destination file
+        path: PathBuf,
+    },
+    /// Replace the cached index snapshot with one from a JSON file
+    Import {
+        /// Snapshot file written by `export`
+        path: PathBuf,
+    },
+}
+
+/// What the process was asked to do.
+#[derive(Debug)]
+pub enum Invocation {
+    /// Serve the API (no subcommand).
+    Serve(Box<AppConfig>),
+    /// `index --dry-run`: print the classification of the media root and exit.
+    IndexDryRun(Box<IndexerConfig>),
+    /// `export <path>`: copy the cached snapshot out of the cache dir.
+    ExportCache { store: CacheStore, path: PathBuf },
+    /// `import <path>`: swap a previously exported snapshot into the cache dir.
+    ImportCache { store: CacheStore, path: PathBuf },
+}
+
+impl Invocation {
+    pub fn load() -> Result<Self> {
+        Self::load_from(std::env::args_os())
+    }
+
+    /// Parse `args` (starting with the binary name) and the environment. Keys from
+    /// `--config <file.toml>` fill in only what neither the command line nor the
+    /// environment sets, so the precedence is CLI, env, file, then defaults.
+    pub fn load_from<I, T>(args: I) -> Result<Self>
+    where
+        I: IntoIterator<Item = T>,
+        T: Into<OsString>,
+    {
+        let mut args: Vec<OsString> = args.into_iter().map(Into::into).collect();
+        // Lenient first pass: required values may still come from the file.
+        let command = CliConfig::command().ignore_errors(true);
+        if let Ok(matches) = command.clone().try_get_matches_from(&args)
+            && let Some(path) = matches.get_one::<PathBuf>("config")
+        {
+            // Top-level flags must precede any subcommand.
+            let file_args = config_file_args(&command, &matches, path)?;
+            let at = args.len().min(1);
+            args.splice(at..at, file_args);
+        }
+
+        let cli = CliConfig::parse_from(args);
+        match cli.command.clone() {
+            None => Ok(Self::Serve(Box::new(AppConfig::try_from(cli)?))),
+            Some(CliCommand::Index { dry_run: true }) => {
+                cli.existing_media_roots()?;
+                // Classification only: skip duration probing.
+                Ok(Self::IndexDryRun(Box::new(
+                    indexer_config(&cli)?.with_ffprobe(None, Duration::ZERO),
+                )))
+            }
+            Some(CliCommand::Index { dry_run: false }) => Err(anyhow!(
+                "`index` only supports --dry-run; the server indexes the media root itself"
+            )),
+            Some(CliCommand::Export { path }) => Ok(Self::ExportCache {
+                store: cache_store(&cli),
+                path,
+            }),
+            Some(CliCommand::Import { path }) => Ok(Self::ImportCache {
+                store: cache_store(&cli),
+                path,
+            }),
+        }
+    }
 }
 
 /// Fully validated configuration shared across the application.
 #[derive(Debug, Clone)]
 pub struct AppConfig {
+    /// The (first) media root; every root is listed in `indexer.roots`.
     pub media_root: PathBuf,
     pub cache_dir: PathBuf,
+    pub cache_compress: bool,
     pub listen_addr: SocketAddr,
     pub otel: OtelConfig,
     pub log: LogConfig,
     pub environment: String,
     pub cors_allowed_origins: Vec<String>,
     pub frontend_dist_dir: Option<PathBuf>,
+    /// Per-client request budget for `/api`; `None` disables rate limiting.
+    pub rate_limit_per_minute: Option<u32>,
+    /// Required bearer token for `/api/v1`; `None` leaves the API open.
+    pub api_token: Option<ApiToken>,
+    /// Peers allowed to report the client address in forwarding headers.
+    pub trusted_proxies: TrustedProxies,
+    pub thumbnails: ThumbnailConfig,
+    pub stream: StreamConfig,
+    pub limits: RequestLimits,
+    pub page_limits: PageLimits,
+    pub indexer: IndexerConfig,
+    pub server: ServerConfig,
 }
 
 /// OpenTelemetry exporter configuration.
@@ -81,19 +423,304 @@ pub struct OtelConfig {
     pub service_name: String,
     pub disable_traces: bool,
     pub disable_logs: bool,
+    pub protocol: OtelProtocol,
+    pub headers: OtelHeaders,
+}
+
+/// Validated headers sent with every OTLP export request.
+///
+/// `Debug` lists header names only, since values usually carry collector credentials.
+#[derive(Clone, Default, PartialEq, Eq)]
+pub struct OtelHeaders(pub BTreeMap<String, String>);
+
+impl fmt::Debug for OtelHeaders {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_set().entries(self.0.keys()).finish()
+    }
+}
+
+/// Wire protocol used by the OTLP exporters.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum OtelProtocol {
+    #[default]
+    Grpc,
+    /// OTLP/HTTP with protobuf payloads.
+    Http,
+}
+
+impl OtelProtocol {
+    /// Endpoint for one signal (`traces`, `logs`). gRPC takes the collector address as
+    /// is; OTLP/HTTP treats it as a base URL and appends `/v1/<signal>`.
+    pub fn signal_endpoint(self, endpoint: &str, signal: &str) -> String {
+        match self {
+            Self::Grpc => endpoint.to_string(),
+            Self::Http => format!("{}/v1/{signal}", endpoint.trim_end_matches('/')),
+        }
+    }
+}
+
+impl FromStr for OtelProtocol {
+    type Err = anyhow::Error;
+
+    fn from_str(value: &str) -> Result<Self> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "grpc" => Ok(Self::Grpc),
+            "http" | "http/protobuf" => Ok(Self::Http),
+            other => Err(anyhow!(
+                "unknown OTLP protocol '{other}' (expected 'grpc' or 'http')"
+            )),
+        }
+    }
+}
+
+/// Thumbnail generation settings.
+#[derive(Debug, Clone)]
+pub struct ThumbnailConfig {
+    /// Upper bound on thumbnails generated at the same time.
+    pub concurrency: usize,
+    /// Video/audio renderer; those thumbnails answer 503 when it is missing.
+    pub ffmpeg_path: PathBuf,
+    /// GIF renderer; GIF thumbnails answer 503 when it is missing.
+    pub gifsicle_path: PathBuf,
+    /// Fail startup instead of warning when ffmpeg or gifsicle is missing.
+    pub require_media_tools: bool,
+    /// Optional PDF renderer; PDF thumbnails answer 503 when it is missing.
+    pub pdftoppm_path: PathBuf,
+    /// Draw a waveform when an audio file has no embedded cover art.
+    pub audio_waveform: bool,
+    /// Size cap for the thumbnail directory; `None` keeps every thumbnail.
+    pub cache_max_bytes: Option<u64>,
+    /// Size served when a request omits `size`; unlisted media types get medium.
+    pub default_sizes: HashMap<MediaType, ThumbnailSize>,
+    /// Answer failed or unsupported thumbnails with a placeholder tile instead of an error.
+    pub placeholder: bool,
+    /// Quality of the JPEG and WebP thumbnails encoded in-process (1–100).
+    pub jpeg_quality: u8,
+    /// Resampling filter used when scaling images down.
+    pub resize_filter: ResizeFilter,
+}
+
+impl ThumbnailConfig {
+    pub fn default_size(&self, media_type: &MediaType) -> ThumbnailSize {
+        self.default_sizes
+            .get(media_type)
+            .copied()
+            .unwrap_or(ThumbnailSize::Medium)
+    }
+}
+
+impl Default for ThumbnailConfig {
+    fn default() -> Self {
+        Self {
+            concurrency: 4,
+            ffmpeg_path: PathBuf::from("ffmpeg"),
+            gifsicle_path: PathBuf::from("gifsicle"),
+            require_media_tools: false,
+            pdftoppm_path: PathBuf::from("pdftoppm"),
+            audio_waveform: true,
+            cache_max_bytes: None,
+            default_sizes: HashMap::new(),
+            placeholder: false,
+            jpeg_quality: DEFAULT_JPEG_QUALITY,
+            resize_filter: ResizeFilter::default(),
+        }
+    }
+}
+
+impl ThumbnailConfig {
+    /// Startup check for ffmpeg and gifsicle. A missing tool only breaks thumbnails of
+    /// the media types that need it, so this warns unless the tools are required.
+    pub fn check_media_tools(&self) -> Result<()> {
+        let missing: Vec<String> = [&self.ffmpeg_path, &self.gifsicle_path]
+            .into_iter()
+            .filter(|tool| which::which(tool).is_err())
+            .map(|tool| tool.display().to_string())
+            .collect();
+        if missing.is_empty() {
+            return Ok(());
+        }
+        let missing = missing.join(", ");
+        if self.require_media_tools {
+            return Err(anyhow!("required media tools not found: {missing}"));
+        }
+        tracing::warn!(
+            %missing,
+            "media tools not found; video, audio, or GIF thumbnails will answer 503"
+        );
+        Ok(())
+    }
+}
+
+/// Media streaming settings.
+#[derive(Debug, Clone)]
+pub struct StreamConfig {
+    /// Buffer and chunk size used when reading media files into response bodies.
+    pub chunk_bytes: usize,
+    /// `Cache-Control: private, max-age=<n>` sent with media bodies.
+    pub max_age_secs: u64,
+}
+
+impl Default for StreamConfig {
+    fn default() -> Self {
+        Self {
+            chunk_bytes: 64 * 1024,
+            max_age_secs: 3600,
+        }
+    }
+}
+
+/// Guards applied to every `/api/v1` request.
+#[derive(Debug, Clone)]
+pub struct RequestLimits {
+    /// Bodies larger than this are rejected with `413`.
+    pub max_body_bytes: usize,
+    /// Handlers still running after this answer `503`; `None` never times out.
+    /// Keep it above the synchronous rebuild wait so `?wait=true` can finish.
+    pub timeout: Option<Duration>,
+}
+
+impl Default for RequestLimits {
+    fn default() -> Self {
+        Self {
+            max_body_bytes: 1024 * 1024,
+            timeout: Some(Duration::from_secs(90)),
+        }
+    }
+}
+
+/// Connection-level tuning applied by [`crate::server::serve`].
+#[derive(Debug, Clone)]
+pub struct ServerConfig {
+    /// Concurrent HTTP/2 streams allowed per connection.
+    pub http2_max_concurrent_streams: u32,
+    /// Interval between HTTP/2 keep-alive pings; `None` sends none.
+    pub http2_keep_alive_interval: Option<Duration>,
+    /// Disable Nagle's algorithm on accepted sockets.
+    pub tcp_nodelay: bool,
+}
+
+impl Default for ServerConfig {
+    fn default() -> Self {
+        Self {
+            http2_max_concurrent_streams: 200,
+            http2_keep_alive_interval: None,
+            tcp_nodelay: false,
+        }
+    }
 }
 
 /// Structured logging configuration.
 #[derive(Debug, Clone)]
 pub struct LogConfig {
     pub level: String,
+    pub format: LogFormat,
+}
+
+/// Style of the stdout log layer.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum LogFormat {
+    /// One JSON object per line, for log shippers.
+    #[default]
+    Json,
+    /// Multi-line, human-readable output for local development.
+    Pretty,
+    /// Single-line, human-readable output.
+    Compact,
+}
+
+impl FromStr for LogFormat {
+    type Err = anyhow::Error;
+
+    fn from_str(value: &str) -> Result<Self> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "json" => Ok(Self::Json),
+            "pretty" => Ok(Self::Pretty),
+            "compact" => Ok(Self::Compact),
+            other => Err(anyhow!(
+                "unknown log format '{other}' (expected 'json', 'pretty' or 'compact')"
+            )),
+        }
+    }
 }
 
 impl AppConfig {
     /// Parse CLI/env arguments and return a validated configuration.
     pub fn load() -> Result<Self> {
-        let cli = CliConfig::parse();
-        Self::try_from(cli)
+        Self::load_from(std::env::args_os())
+    }
+
+    /// Server configuration from `args`; see [`Invocation::load_from`].
+    pub fn load_from<I, T>(args: I) -> Result<Self>
+    where
+        I: IntoIterator<Item = T>,
+        T: Into<OsString>,
+    {
+        match Invocation::load_from(args)? {
+            Invocation::Serve(config) => Ok(*config),
+            other => Err(anyhow!("expected server arguments, got {other:?}")),
+        }
+    }
+
+    /// Warn when the media root and cache directory overlap. A cache inside the media
+    /// root is skipped by scans; a media root inside the cache risks mixing the two.
+    pub fn check_dir_layout(&self) {
+        let (Ok(media_root), Ok(cache_dir)) = (
+            self.media_root.canonicalize(),
+            self.cache_dir.canonicalize(),
+        ) else {
+            return;
+        };
+        if cache_dir.starts_with(&media_root) {
+            tracing::warn!(
+                media_root = %media_root.display(),
+                cache_dir = %cache_dir.display(),
+                "cache dir is inside the media root; it is excluded from scans"
+            );
+        } else if media_root.starts_with(&cache_dir) {
+            tracing::warn!(
+                media_root = %media_root.display(),
+                cache_dir = %cache_dir.display(),
+                "media root is inside the cache dir; move it out to keep cache files separate"
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+impl AppConfig {
+    /// Defaults for unit tests: telemetry off, loopback listener, and one media root.
+    pub(crate) fn for_tests(media_root: impl Into<PathBuf>, cache_dir: impl Into<PathBuf>) -> Self {
+        let media_root = media_root.into();
+        Self {
+            indexer: IndexerConfig::new(&media_root),
+            media_root,
+            cache_dir: cache_dir.into(),
+            cache_compress: false,
+            listen_addr: SocketAddr::from(([127, 0, 0, 1], 0)),
+            environment: "test".into(),
+            otel: OtelConfig {
+                endpoint: None,
+                service_name: "test".into(),
+                disable_traces: true,
+                disable_logs: true,
+                protocol: Default::default(),
+                headers: Default::default(),
+            },
+            log: LogConfig {
+                level: "info".into(),
+                format: Default::default(),
+            },
+            cors_allowed_origins: Vec::new(),
+            frontend_dist_dir: None,
+            rate_limit_per_minute: None,
+            api_token: None,
+            trusted_proxies: Default::default(),
+            thumbnails: Default::default(),
+            stream: Default::default(),
+            limits: Default::default(),
+            page_limits: Default::default(),
+            server: Default::default(),
+        }
     }
 }
 
@@ -101,15 +728,11 @@ impl TryFrom<CliConfig> for AppConfig {
     type Error = anyhow::Error;
 
     fn try_from(value: CliConfig) -> Result<Self> {
-        ensure_directory_exists(&value.media_root)
-            .with_context(|| format!("media root '{}' missing", value.media_root.display()))?;
+        let indexer = indexer_config(&value)?;
+        let media_root = indexer.root.clone();
         fs::create_dir_all(&value.cache_dir).with_context(|| {
             format!("failed to create cache dir '{}'", value.cache_dir.display())
         })?;
-        ensure_binary_exists("ffmpeg")
-            .context("required dependency 'ffmpeg' was not found in PATH")?;
-        ensure_binary_exists("gifsicle")
-            .context("required dependency 'gifsicle' was not found in PATH")?;
 
         let frontend_dist_dir = value.frontend_dist_dir.clone();
         if let Some(dir) = &frontend_dist_dir {
@@ -117,9 +740,18 @@ impl TryFrom<CliConfig> for AppConfig {
                 .with_context(|| format!("frontend dist directory '{}' missing", dir.display()))?;
         }
 
+        if value.default_page_size == 0 || value.default_page_size > value.max_page_size {
+            return Err(anyhow!(
+                "--default-page-size ({}) must be between 1 and --max-page-size ({})",
+                value.default_page_size,
+                value.max_page_size
+            ));
+        }
+
         Ok(Self {
-            media_root: value.media_root,
+            media_root,
             cache_dir: value.cache_dir,
+            cache_compress: value.cache_compress,
             listen_addr: value.listen_addr,
             environment: value.environment,
             otel: OtelConfig {
@@ -127,9 +759,16 @@ impl TryFrom<CliConfig> for AppConfig {
                 service_name: value.otel_service_name,
                 disable_traces: value.otel_disable_traces,
                 disable_logs: value.otel_disable_logs,
+                protocol: value
+                    .otel_protocol
+                    .parse()
+                    .context("invalid --otel-protocol")?,
+                headers: parse_otel_headers(&value.otel_headers)
+                    .context("invalid --otel-headers")?,
             },
             log: LogConfig {
                 level: value.log_level,
+                format: value.log_format.parse().context("invalid --log-format")?,
             },
             cors_allowed_origins: value
                 .cors_allowed_origins
@@ -137,10 +776,290 @@ impl TryFrom<CliConfig> for AppConfig {
                 .filter(|origin| !origin.is_empty())
                 .collect(),
             frontend_dist_dir,
+            rate_limit_per_minute: Some(value.rate_limit_per_minute).filter(|limit| *limit > 0),
+            api_token: value
+                .api_token
+                .filter(|token| !token.is_empty())
+                .map(ApiToken::new),
+            trusted_proxies: parse_trusted_proxies(&value.trusted_proxies)
+                .context("invalid --trusted-proxies")?,
+            thumbnails: ThumbnailConfig {
+                concurrency: usize::from(value.thumbnail_concurrency),
+                ffmpeg_path: value.ffmpeg_path,
+                gifsicle_path: value.gifsicle_path,
+                require_media_tools: value.require_media_tools,
+                pdftoppm_path: value.pdftoppm_path,
+                audio_waveform: !value.audio_cover_only,
+                cache_max_bytes: Some(value.thumbnail_cache_max_bytes).filter(|max| *max > 0),
+                default_sizes: parse_thumbnail_sizes(&value.thumbnail_default_sizes)
+                    .context("invalid --thumbnail-default-sizes")?,
+                placeholder: value.thumbnail_placeholder,
+                jpeg_quality: value.thumbnail_quality,
+                resize_filter: value
+                    .thumbnail_filter
+                    .parse()
+                    .context("invalid --thumbnail-filter")?,
+            },
+            stream: StreamConfig {
+                chunk_bytes: value.stream_chunk_kib as usize * 1024,
+                max_age_secs: value.stream_max_age_secs,
+            },
+            limits: RequestLimits {
+                max_body_bytes: value.max_request_body_bytes,
+                timeout: Some(value.request_timeout_secs)
+                    .filter(|secs| *secs > 0)
+                    .map(Duration::from_secs),
+            },
+            page_limits: PageLimits {
+                default_size: value.default_page_size,
+                max_size: value.max_page_size,
+            },
+            indexer,
+            server: ServerConfig {
+                http2_max_concurrent_streams: value.http2_max_concurrent_streams,
+                http2_keep_alive_interval: Some(value.http2_keep_alive_interval_secs)
+                    .filter(|secs| *secs > 0)
+                    .map(Duration::from_secs),
+                tcp_nodelay: value.tcp_nodelay,
+            },
         })
     }
 }
 
+/// Cache store for the `export` / `import` subcommands; the directory need not exist yet.
+fn cache_store(value: &CliConfig) -> CacheStore {
+    CacheStore::new(&value.cache_dir).with_compression(value.cache_compress)
+}
+
+/// Indexer settings derived from the scan and tag flags.
+fn indexer_config(value: &CliConfig) -> Result<IndexerConfig> {
+    let extension_overrides =
+        parse_extension_map(&value.media_extension_map).context("invalid --media-extension-map")?;
+    let tag_parser = parse_tag_parser_config(&value.tag_delimiters, &value.tag_kv_separators)
+        .context("invalid tag parser settings")?
+        .with_attribute_keys(&value.tag_attribute_keys);
+    let id_strategy: IdStrategy = value.id_strategy.parse().context("invalid --id-strategy")?;
+    let tag_aliases = match &value.tag_aliases {
+        Some(path) => TagAliases::load(path)?,
+        None => TagAliases::default(),
+    };
+    let indexer = IndexerConfig::new(PathBuf::new())
+        .with_roots(value.existing_media_roots()?)
+        .with_id_strategy(id_strategy)
+        .with_extension_overrides(extension_overrides)
+        .with_tag_parser(tag_parser)
+        .with_tag_aliases(tag_aliases)
+        .with_tags_from_dirs(value.tags_from_dirs)
+        .with_tags_from_filename(!value.ignore_filename_tags)
+        .with_include_hidden(value.include_hidden)
+        .with_max_depth(value.max_scan_depth)
+        .with_min_filesize(value.min_filesize_bytes)
+        .with_read_exif(value.read_exif)
+        .with_link_base(value.link_base.clone())
+        .with_follow_links(value.follow_symlinks)
+        .with_poll_interval(Duration::from_secs(value.poll_interval_seconds))
+        .with_watch_mode(value.watch)
+        .with_excluded_dir(value.cache_dir.clone())
+        .with_ffprobe(
+            Some(value.ffprobe_path.clone()),
+            Duration::from_millis(value.probe_timeout_ms),
+        );
+    Ok(match value.scan_concurrency {
+        0 => indexer,
+        workers => indexer.with_scan_concurrency(usize::from(workers)),
+    })
+}
+
+/// Translate a TOML config file into extra flags for every key the command line and
+/// environment left unset. Keys are flag names in snake_case or kebab-case.
+fn config_file_args(command: &Command, matches: &ArgMatches, path: &Path) -> Result<Vec<OsString>> {
+    let text = fs::read_to_string(path)
+        .with_context(|| format!("failed to read config file '{}'", path.display()))?;
+    let table: toml::Table = toml::from_str(&text)
+        .with_context(|| format!("invalid TOML in config file '{}'", path.display()))?;
+
+    let mut args = Vec::new();
+    for (key, value) in table {
+        let id = key.replace('-', "_");
+        let arg = command
+            .get_arguments()
+            .find(|arg| arg.get_id() == id.as_str() && arg.get_long().is_some())
+            .filter(|_| id != "config")
+            .ok_or_else(|| anyhow!("unknown key '{key}' in config file '{}'", path.display()))?;
+        if matches!(
+            matches.value_source(&id),
+            Some(ValueSource::CommandLine | ValueSource::EnvVariable)
+        ) {
+            continue;
+        }
+
+        let long = arg.get_long().expect("filtered on long flags");
+        let value = match value {
+            toml::Value::Array(items) => items
+                .iter()
+                .map(toml_scalar)
+                .collect::<Result<Vec<_>>>()
+                .map(|items| items.join(",")),
+            other => toml_scalar(&other),
+        }
+        .with_context(|| format!("invalid value for config key '{key}'"))?;
+        if arg.get_action().takes_values() {
+            args.push(format!("--{long}={value}").into());
+        } else {
+            match value.as_str() {
+                "true" => args.push(format!("--{long}").into()),
+                "false" => {}
+                _ => return Err(anyhow!("config key '{key}' must be true or false")),
+            }
+        }
+    }
+    Ok(args)
+}
+
+fn toml_scalar(value: &toml::Value) -> Result<String> {
+    match value {
+        toml::Value::String(text) => Ok(text.clone()),
+        toml::Value::Integer(number) => Ok(number.to_string()),
+        toml::Value::Float(number) => Ok(number.to_string()),
+        toml::Value::Boolean(flag) => Ok(flag.to_string()),
+        other => Err(anyhow!("unsupported config value {other}")),
+    }
+}
+
+/// `--media-root` values: plain paths, labelled by their directory name, or `label=path`.
+fn parse_media_roots(entries: &[PathBuf]) -> Result<Vec<MediaRoot>> {
+    let mut roots: Vec<MediaRoot> = Vec::new();
+    for entry in entries {
+        let labelled = entry
+            .to_str()
+            .and_then(|text| text.split_once('='))
+            .filter(|(label, _)| is_root_label(label));
+        let root = match labelled {
+            Some((label, path)) => MediaRoot {
+                label: label.to_string(),
+                path: PathBuf::from(path),
+            },
+            None => MediaRoot {
+                label: entry
+                    .file_name()
+                    .and_then(|name| name.to_str())
+                    .unwrap_or_default()
+                    .to_string(),
+                path: entry.clone(),
+            },
+        };
+        if entries.len() > 1 && !is_root_label(&root.label) {
+            return Err(anyhow!(
+                "cannot derive a label for '{}'; use label=path",
+                root.path.display()
+            ));
+        }
+        if roots.iter().any(|other| other.label == root.label) {
+            return Err(anyhow!(
+                "duplicate media root label '{}'; use label=path to tell them apart",
+                root.label
+            ));
+        }
+        roots.push(root);
+    }
+    Ok(roots)
+}
+
+fn is_root_label(label: &str) -> bool {
+    !label.is_empty()
+        && !label.starts_with('.')
+        && label
+            .chars()
+            .all(|ch| ch.is_alphanumeric() || matches!(ch, '-' | '_' | '.'))
+}
+
+fn parse_extension_map(entries: &[String]) -> Result<HashMap<String, MediaType>> {
+    let mut overrides = HashMap::new();
+    for entry in entries.iter().map(|entry| entry.trim()) {
+        if entry.is_empty() {
+            continue;
+        }
+        let (ext, media_type) = entry
+            .split_once('=')
+            .ok_or_else(|| anyhow!("expected ext=type but got '{entry}'"))?;
+        let ext = ext.trim().trim_start_matches('.').to_ascii_lowercase();
+        if ext.is_empty() {
+            return Err(anyhow!("missing extension in '{entry}'"));
+        }
+        overrides.insert(ext, media_type.parse::<MediaType>()?);
+    }
+    Ok(overrides)
+}
+
+fn parse_trusted_proxies(entries: &[String]) -> Result<TrustedProxies> {
+    let mut networks = Vec::new();
+    for entry in entries.iter().map(|entry| entry.trim()) {
+        if entry.is_empty() {
+            continue;
+        }
+        // A bare address trusts just that host.
+        let network = entry
+            .parse::<IpNet>()
+            .or_else(|_| entry.parse::<IpAddr>().map(IpNet::from))
+            .map_err(|_| anyhow!("expected an IP address or CIDR range but got '{entry}'"))?;
+        networks.push(network);
+    }
+    Ok(TrustedProxies::new(networks))
+}
+
+fn parse_thumbnail_sizes(entries: &[String]) -> Result<HashMap<MediaType, ThumbnailSize>> {
+    let mut sizes = HashMap::new();
+    for entry in entries.iter().map(|entry| entry.trim()) {
+        if entry.is_empty() {
+            continue;
+        }
+        let (media_type, size) = entry
+            .split_once('=')
+            .ok_or_else(|| anyhow!("expected type=size but got '{entry}'"))?;
+        sizes.insert(
+            media_type.parse::<MediaType>()?,
+            size.parse::<ThumbnailSize>()?,
+        );
+    }
+    Ok(sizes)
+}
+
+fn parse_otel_headers(entries: &[String]) -> Result<OtelHeaders> {
+    let mut headers = BTreeMap::new();
+    for entry in entries.iter().map(|entry| entry.trim()) {
+        if entry.is_empty() {
+            continue;
+        }
+        let (name, value) = entry
+            .split_once('=')
+            .ok_or_else(|| anyhow!("expected key=value but got '{entry}'"))?;
+        let name = HeaderName::from_str(name.trim())
+            .with_context(|| format!("invalid header name in '{entry}'"))?;
+        let value = value.trim();
+        HeaderValue::from_str(value)
+            .with_context(|| format!("invalid header value for '{name}'"))?;
+        headers.insert(name.as_str().to_string(), value.to_string());
+    }
+    Ok(OtelHeaders(headers))
+}
+
+fn parse_tag_parser_config(delimiters: &str, separators: &str) -> Result<TagParserConfig> {
+    let token_delimiters: Vec<char> = delimiters.chars().collect();
+    let kv_separators: Vec<char> = separators.chars().collect();
+    if token_delimiters.is_empty() {
+        return Err(anyhow!("at least one tag delimiter is required"));
+    }
+    if kv_separators.is_empty() {
+        return Err(anyhow!("at least one key/value separator is required"));
+    }
+    if let Some(shared) = kv_separators.iter().find(|c| token_delimiters.contains(c)) {
+        return Err(anyhow!(
+            "'{shared}' cannot be both a tag delimiter and a key/value separator"
+        ));
+    }
+    Ok(TagParserConfig::new(token_delimiters, kv_separators))
+}
+
 fn ensure_directory_exists(path: &Path) -> Result<()> {
     if path.exists() {
         return Ok(());
@@ -151,8 +1070,255 @@ fn ensure_directory_exists(path: &Path) -> Result<()> {
     ))
 }
 
-fn ensure_binary_exists(binary: &str) -> Result<()> {
-    which::which(binary)
-        .map(|_| ())
-        .with_context(|| format!("binary '{}' is required but was not found in PATH", binary))
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_tag_parser_settings() {
+        let config = parse_tag_parser_config(".", "=").unwrap();
+        assert_eq!(config, TagParserConfig::new(vec!['.'], vec!['=']));
+        assert_eq!(
+            parse_tag_parser_config("_+", ":-").unwrap(),
+            TagParserConfig::default()
+        );
+        assert!(parse_tag_parser_config("", "=").is_err());
+        assert!(parse_tag_parser_config("_-", ":-").is_err());
+    }
+
+    #[test]
+    fn parses_otel_protocol() {
+        assert_eq!("grpc".parse::<OtelProtocol>().unwrap(), OtelProtocol::Grpc);
+        assert_eq!("HTTP".parse::<OtelProtocol>().unwrap(), OtelProtocol::Http);
+        assert_eq!(
+            "http/protobuf".parse::<OtelProtocol>().unwrap(),
+            OtelProtocol::Http
+        );
+        assert!("udp".parse::<OtelProtocol>().is_err());
+
+        assert_eq!(
+            OtelProtocol::Http.signal_endpoint("http://collector:4318/", "traces"),
+            "http://collector:4318/v1/traces"
+        );
+        assert_eq!(
+            OtelProtocol::Grpc.signal_endpoint("http://collector:4317", "logs"),
+            "http://collector:4317"
+        );
+    }
+
+    #[test]
+    fn missing_media_tools_fail_startup_only_when_required() {
+        let mut thumbnails = ThumbnailConfig {
+            ffmpeg_path: PathBuf::from("/nonexistent/ffmpeg"),
+            gifsicle_path: PathBuf::from("/nonexistent/gifsicle"),
+            ..ThumbnailConfig::default()
+        };
+        assert!(thumbnails.check_media_tools().is_ok());
+
+        thumbnails.require_media_tools = true;
+        let err = thumbnails.check_media_tools().unwrap_err();
+        assert!(err.to_string().contains("/nonexistent/ffmpeg"), "{err}");
+    }
+
+    #[test]
+    fn layers_config_file_under_env_and_cli() {
+        let dir = tempfile::tempdir().unwrap();
+        let media_root = dir.path().join("media");
+        fs::create_dir_all(&media_root).unwrap();
+        let config_path = dir.path().join("galarie.toml");
+        fs::write(
+            &config_path,
+            format!(
+                r#"
+media_root = "{}"
+cache-dir = "{}"
+rate_limit_per_minute = 120
+stream_max_age_secs = 60
+tag_attribute_keys = ["rating", "year"]
+watch = true
+"#,
+                media_root.display(),
+                dir.path().join("cache").display()
+            ),
+        )
+        .unwrap();
+
+        // SAFETY: no other test reads or writes this variable.
+        unsafe { std::env::set_var("GALARIE_STREAM_MAX_AGE_SECS", "5") };
+        let config = AppConfig::load_from([
+            "galarie-backend".as_ref(),
+            "--config".as_ref(),
+            config_path.as_os_str(),
+            "--rate-limit-per-minute=30".as_ref(),
+        ]);
+        unsafe { std::env::remove_var("GALARIE_STREAM_MAX_AGE_SECS") };
+        let config = config.unwrap();
+
+        assert_eq!(config.media_root, media_root);
+        assert_eq!(config.cache_dir, dir.path().join("cache"));
+        assert!(config.indexer.watch_mode);
+        assert_eq!(config.rate_limit_per_minute, Some(30), "CLI beats the file");
+        assert_eq!(config.stream.max_age_secs, 5, "env beats the file");
+
+        fs::write(&config_path, "media_rot = \"/srv\"\n").unwrap();
+        let err = AppConfig::load_from([
+            "galarie-backend".as_ref(),
+            "--config".as_ref(),
+            config_path.as_os_str(),
+        ])
+        .unwrap_err();
+        assert!(err.to_string().contains("media_rot"), "{err}");
+    }
+
+    #[test]
+    fn parses_repeated_media_roots() {
+        let dir = tempfile::tempdir().unwrap();
+        for name in ["photos", "videos", "more"] {
+            fs::create_dir_all(dir.path().join(name).join("photos")).unwrap();
+        }
+        let root = |path: &str| dir.path().join(path).display().to_string();
+        let load = |roots: &[String]| {
+            let mut args = vec![
+                "galarie-backend".to_string(),
+                format!("--cache-dir={}", dir.path().join("cache").display()),
+            ];
+            args.extend(roots.iter().map(|root| format!("--media-root={root}")));
+            AppConfig::load_from(args)
+        };
+
+        let config = load(&[root("photos"), root("videos")]).unwrap();
+        assert_eq!(config.media_root, dir.path().join("photos"));
+        let labels: Vec<&str> = config
+            .indexer
+            .roots
+            .iter()
+            .map(|root| root.label.as_str())
+            .collect();
+        assert_eq!(labels, ["photos", "videos"]);
+        assert_eq!(
+            config.indexer.locate("videos/clip.mp4"),
+            Some((dir.path().join("videos").as_path(), "clip.mp4"))
+        );
+        assert_eq!(config.indexer.locate("music/song.mp3"), None);
+
+        // A lone root stays unlabelled.
+        let config = load(&[root("photos")]).unwrap();
+        assert!(config.indexer.roots.is_empty());
+
+        let err = load(&[root("photos"), root("more/photos")]).unwrap_err();
+        assert!(
+            format!("{err:#}").contains("duplicate media root label"),
+            "{err:#}"
+        );
+        let config = load(&[root("photos"), format!("archive={}", root("more/photos"))]).unwrap();
+        assert_eq!(config.indexer.roots[1].label, "archive");
+    }
+
+    #[test]
+    fn parses_poll_interval() {
+        let dir = tempfile::tempdir().unwrap();
+        let load = |interval: &str| {
+            AppConfig::load_from([
+                "galarie-backend".to_string(),
+                format!("--media-root={}", dir.path().display()),
+                format!("--cache-dir={}", dir.path().join("cache").display()),
+                format!("--poll-interval-seconds={interval}"),
+            ])
+            .unwrap()
+            .indexer
+            .poll_interval
+        };
+
+        assert_eq!(load("5"), Duration::from_secs(5));
+        assert_eq!(load("0"), Duration::ZERO);
+    }
+
+    #[test]
+    fn rejects_default_page_size_above_max() {
+        let dir = tempfile::tempdir().unwrap();
+        let load = |extra: &[&str]| {
+            let mut args = vec![
+                "galarie-backend".to_string(),
+                format!("--media-root={}", dir.path().display()),
+                format!("--cache-dir={}", dir.path().join("cache").display()),
+            ];
+            args.extend(extra.iter().map(|arg| arg.to_string()));
+            AppConfig::load_from(args)
+        };
+
+        let config = load(&["--default-page-size=24", "--max-page-size=48"]).unwrap();
+        assert_eq!(
+            config.page_limits,
+            PageLimits {
+                default_size: 24,
+                max_size: 48
+            }
+        );
+        let err = load(&["--default-page-size=100", "--max-page-size=50"]).unwrap_err();
+        assert!(err.to_string().contains("--default-page-size"), "{err}");
+    }
+
+    #[test]
+    fn parses_log_format() {
+        assert_eq!("json".parse::<LogFormat>().unwrap(), LogFormat::Json);
+        assert_eq!("Pretty".parse::<LogFormat>().unwrap(), LogFormat::Pretty);
+        assert_eq!("compact".parse::<LogFormat>().unwrap(), LogFormat::Compact);
+        assert!("logfmt".parse::<LogFormat>().is_err());
+    }
+
+    #[test]
+    fn parses_otel_headers() {
+        let headers = parse_otel_headers(&[
+            "Authorization=Basic abc==".into(),
+            " x-honeycomb-team = key ".into(),
+            "".into(),
+        ])
+        .unwrap();
+        assert_eq!(
+            headers.0.get("authorization").map(String::as_str),
+            Some("Basic abc==")
+        );
+        assert_eq!(
+            headers.0.get("x-honeycomb-team").map(String::as_str),
+            Some("key")
+        );
+        assert_eq!(
+            format!("{headers:?}"),
+            r#"{"authorization", "x-honeycomb-team"}"#
+        );
+    }
+
+    #[test]
+    fn rejects_malformed_otel_headers() {
+        assert!(parse_otel_headers(&["authorization".into()]).is_err());
+        assert!(parse_otel_headers(&["=value".into()]).is_err());
+        assert!(parse_otel_headers(&["bad header=value".into()]).is_err());
+    }
+
+    #[test]
+    fn parses_trusted_proxies() {
+        let proxies =
+            parse_trusted_proxies(&["10.0.0.0/8".into(), " 192.0.2.1 ".into(), "".into()]).unwrap();
+        assert!(proxies.contains("10.1.2.3".parse().unwrap()));
+        assert!(proxies.contains("192.0.2.1".parse().unwrap()));
+        assert!(!proxies.contains("192.0.2.2".parse().unwrap()));
+        assert!(parse_trusted_proxies(&["10.0.0.0/33".into()]).is_err());
+        assert!(parse_trusted_proxies(&["proxy.local".into()]).is_err());
+    }
+
+    #[test]
+    fn parses_extension_map_entries() {
+        let overrides =
+            parse_extension_map(&["jxl=image".into(), ".M4V=Video".into(), "".into()]).unwrap();
+        assert_eq!(overrides.get("jxl"), Some(&MediaType::Image));
+        assert_eq!(overrides.get("m4v"), Some(&MediaType::Video));
+        assert_eq!(overrides.len(), 2);
+    }
+
+    #[test]
+    fn rejects_malformed_extension_map_entries() {
+        assert!(parse_extension_map(&["jxl".into()]).is_err());
+        assert!(parse_extension_map(&["=image".into()]).is_err());
+        assert!(parse_extension_map(&["jxl=sculpture".into()]).is_err());
+    }
 }
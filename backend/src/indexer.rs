@@ -1,20 +1,47 @@
 use std::{
-    collections::HashMap,
-    path::{Path, PathBuf},
+    collections::{HashMap, HashSet},
+    fs::File,
+    io::{Read, Seek, SeekFrom},
+    path::{Component, Path, PathBuf},
+    process::{Command, Stdio},
+    str::FromStr,
     time::{Duration, Instant},
 };
 
-use anyhow::{Context, Result, bail};
+use anyhow::{Context, Result, anyhow, bail};
 use chrono::{DateTime, Utc};
+use ignore::gitignore::{Gitignore, GitignoreBuilder};
+use notify::{RecommendedWatcher, RecursiveMode, Watcher};
+use rayon::prelude::*;
 use serde::{Deserialize, Serialize};
 use tokio::{sync::mpsc, task::JoinHandle, time};
+use tokio_util::sync::CancellationToken;
 use tracing::instrument;
+use utoipa::ToSchema;
 use walkdir::{DirEntry, WalkDir};
 
-use crate::tags::{Tag, TagKind, parse_filename_tokens};
+use crate::{
+    cache::CacheSnapshot,
+    tags::{
+        Tag, TagAliases, TagKind, TagParseResult, TagParserConfig, normalize_tag_text,
+        parse_filename_tokens,
+    },
+};
+
+/// Name of the gitignore-style file read from the media root on every scan.
+pub const IGNORE_FILENAME: &str = ".galarieignore";
+
+/// Bytes sampled from each end of a file when computing its content fingerprint.
+const FINGERPRINT_SAMPLE_BYTES: u64 = 64 * 1024;
+
+/// Route prefix the API is mounted under, used for generated media links by default.
+pub const DEFAULT_LINK_BASE: &str = "/api/v1";
+
+/// Quiet period after the last filesystem event before a watch-triggered rescan.
+const WATCH_DEBOUNCE: Duration = Duration::from_millis(250);
 
 /// Representation of a media file discovered on disk.
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, ToSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct MediaFile {
     pub id: String,
@@ -25,13 +52,32 @@ pub struct MediaFile {
     pub filesize: u64,
     pub dimensions: Option<Dimensions>,
     pub duration_ms: Option<u64>,
+    /// Where the photo was taken, from EXIF GPS.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub location: Option<GeoPoint>,
     pub thumbnail_path: Option<String>,
+    /// Link serving the original file, built like `thumbnail_path`.
+    pub stream_path: Option<String>,
     pub hash: Option<String>,
     pub indexed_at: DateTime<Utc>,
+    /// File modification time; `indexed_at` is when the file was scanned.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub modified_at: Option<DateTime<Utc>>,
+    /// Filename tokens that could not be parsed into tags.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub invalid_tokens: Vec<String>,
+}
+
+/// WGS 84 coordinates in decimal degrees.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct GeoPoint {
+    pub latitude: f64,
+    pub longitude: f64,
 }
 
-/// Placeholder for image/video dimensions. Populated once metadata extraction lands.
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+/// Pixel dimensions read from the media header (images and GIFs).
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, ToSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct Dimensions {
     pub width: u32,
@@ -39,7 +85,7 @@ pub struct Dimensions {
 }
 
 /// Supported media types. `Unknown` is used internally until richer detection ships.
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq, Hash, ToSchema)]
 #[serde(rename_all = "lowercase")]
 pub enum MediaType {
     Image,
@@ -47,12 +93,65 @@ pub enum MediaType {
     Video,
     Audio,
     Pdf,
+    #[default]
     Unknown,
 }
 
-impl Default for MediaType {
-    fn default() -> Self {
-        Self::Unknown
+impl FromStr for MediaType {
+    type Err = anyhow::Error;
+
+    fn from_str(value: &str) -> Result<Self> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "image" => Ok(Self::Image),
+            "gif" => Ok(Self::Gif),
+            "video" => Ok(Self::Video),
+            "audio" => Ok(Self::Audio),
+            "pdf" => Ok(Self::Pdf),
+            other => Err(anyhow!("unknown media type '{other}'")),
+        }
+    }
+}
+
+/// How media ids are derived.
+///
+/// `PathOnly` keeps an id stable across content edits, so bookmarks survive a
+/// re-export, but a replaced file is indistinguishable from the original.
+/// `PathPlusContentHash` gives edited or replaced files a fresh id, which lets
+/// clients drop stale thumbnails and caches, at the cost of breaking links on every
+/// edit. Both change the id when a file is moved or renamed.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum IdStrategy {
+    #[default]
+    PathOnly,
+    PathPlusContentHash,
+}
+
+impl IdStrategy {
+    /// Derive the id for the file at `relative` whose content fingerprint is `hash`.
+    pub fn media_id(self, relative: &Path, hash: &str) -> String {
+        use sha1::{Digest, Sha1};
+
+        let mut hasher = Sha1::new();
+        hasher.update(relative_to_string(relative).as_bytes());
+        if self == Self::PathPlusContentHash {
+            hasher.update([0]);
+            hasher.update(hash.as_bytes());
+        }
+        format!("{:x}", hasher.finalize())
+    }
+}
+
+impl FromStr for IdStrategy {
+    type Err = anyhow::Error;
+
+    fn from_str(value: &str) -> Result<Self> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "path" => Ok(Self::PathOnly),
+            "path-content" => Ok(Self::PathPlusContentHash),
+            other => Err(anyhow!(
+                "unknown id strategy '{other}' (expected 'path' or 'path-content')"
+            )),
+        }
     }
 }
 
@@ -61,6 +160,7 @@ impl Default for MediaType {
 pub enum IndexEvent {
     Snapshot {
         files: Vec<MediaFile>,
+        summary: ScanSummary,
         scanned_at: DateTime<Utc>,
         duration: Duration,
     },
@@ -69,18 +169,152 @@ pub enum IndexEvent {
     },
 }
 
-/// Configuration for the polling-based filesystem watcher.
+/// Number of error categories kept in [`ScanSummary::top_errors`].
+const TOP_ERROR_CATEGORIES: usize = 5;
+
+/// Per-scan tally of what happened to every file the walk encountered.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ScanSummary {
+    pub indexed: usize,
+    /// Files deliberately left out, e.g. extensions that map to no media type.
+    pub skipped: usize,
+    /// Entries that could not be read or parsed.
+    pub errored: usize,
+    /// Most frequent error categories, most common first.
+    pub top_errors: Vec<ErrorCount>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ErrorCount {
+    pub category: String,
+    pub count: usize,
+}
+
+impl ScanSummary {
+    fn tally(indexed: usize, skipped: usize, errors: &[String]) -> Self {
+        let mut counts: HashMap<&str, usize> = HashMap::new();
+        for category in errors {
+            *counts.entry(category).or_default() += 1;
+        }
+        let mut top_errors: Vec<ErrorCount> = counts
+            .into_iter()
+            .map(|(category, count)| ErrorCount {
+                category: category.to_string(),
+                count,
+            })
+            .collect();
+        top_errors.sort_by(|a, b| b.count.cmp(&a.count).then(a.category.cmp(&b.category)));
+        top_errors.truncate(TOP_ERROR_CATEGORIES);
+        Self {
+            indexed,
+            skipped,
+            errored: errors.len(),
+            top_errors,
+        }
+    }
+}
+
+/// Marks files the indexer skips on purpose rather than because something failed.
+#[derive(Debug, thiserror::Error)]
+enum SkippedFile {
+    #[error("unsupported media type")]
+    UnsupportedMediaType,
+    #[error("smaller than the minimum file size")]
+    BelowMinFilesize,
+}
+
+/// Group an error by the underlying I/O error kind when there is one.
+fn error_category(err: &anyhow::Error) -> String {
+    err.chain()
+        .find_map(|cause| cause.downcast_ref::<std::io::Error>())
+        .map(|io| format!("io: {:?}", io.kind()))
+        .unwrap_or_else(|| "other".to_string())
+}
+
+/// One of several directories indexed into the same snapshot. Relative paths of its
+/// files start with `label/`, so equal paths under different roots stay distinct.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MediaRoot {
+    pub label: String,
+    pub path: PathBuf,
+}
+
+/// Configuration for the filesystem indexer loop.
 #[derive(Debug, Clone)]
 pub struct IndexerConfig {
+    /// The media root; with several [`roots`](Self::roots), the first of them.
     pub root: PathBuf,
+    /// Labelled roots when more than one directory is indexed. Empty means only `root`
+    /// is scanned and relative paths carry no label.
+    pub roots: Vec<MediaRoot>,
+    /// Delay between rescans; zero scans once at startup and never polls again.
     pub poll_interval: Duration,
+    /// Rescan on filesystem notifications instead of every `poll_interval`; falls back
+    /// to polling when the watcher cannot be set up (e.g. inotify watches exhausted).
+    pub watch_mode: bool,
+    /// Lowercased extension (without the leading dot) to media type, consulted
+    /// before the built-in extension table.
+    pub extension_overrides: HashMap<String, MediaType>,
+    /// Index dotfiles and descend into hidden directories.
+    pub include_hidden: bool,
+    /// Deepest directory level scanned, where `1` means files directly under the
+    /// root; `None` is unbounded.
+    pub max_depth: Option<usize>,
+    /// Add `camera`, `lens` and `date` attributes from the EXIF metadata of images.
+    pub read_exif: bool,
+    /// Files smaller than this many bytes are skipped, e.g. zero-byte placeholders and
+    /// downloads that are still being written.
+    pub min_filesize: u64,
+    /// Descend into symlinked directories and index symlinked files. Each real
+    /// directory and file is visited once, so symlink cycles terminate.
+    pub follow_links: bool,
+    /// `ffprobe` binary used to read audio/video durations; `None` skips probing.
+    pub ffprobe_path: Option<PathBuf>,
+    /// Upper bound for a single `ffprobe` invocation.
+    pub probe_timeout: Duration,
+    /// Worker threads deriving per-file metadata during a scan; `1` scans serially.
+    pub scan_concurrency: usize,
+    pub tag_parser: TagParserConfig,
+    /// Simple tags are stored under their canonical alias so facets collapse.
+    pub tag_aliases: TagAliases,
+    pub id_strategy: IdStrategy,
+    /// Also parse tags from each directory component of the relative path.
+    pub tags_from_dirs: bool,
+    /// Parse tags from the filename stem; disable to tag purely by directory.
+    pub tags_from_filename: bool,
+    /// Directories never scanned or watched even when they sit under `root`, such as
+    /// the cache directory holding generated thumbnails.
+    pub excluded_dirs: Vec<PathBuf>,
+    /// Prefix of the generated `thumbnail_path` / `stream_path` links: a root-relative
+    /// path such as `/api/v1` or an absolute URL, without a trailing slash.
+    pub link_base: String,
 }
 
 impl IndexerConfig {
     pub fn new(root: impl Into<PathBuf>) -> Self {
         Self {
             root: root.into(),
+            roots: Vec::new(),
             poll_interval: Duration::from_secs(30),
+            watch_mode: false,
+            extension_overrides: HashMap::new(),
+            include_hidden: false,
+            max_depth: None,
+            min_filesize: 1,
+            read_exif: false,
+            follow_links: false,
+            ffprobe_path: None,
+            probe_timeout: Duration::from_secs(5),
+            scan_concurrency: std::thread::available_parallelism().map_or(1, |n| n.get()),
+            tag_parser: TagParserConfig::default(),
+            tag_aliases: TagAliases::default(),
+            tags_from_dirs: false,
+            tags_from_filename: true,
+            id_strategy: IdStrategy::default(),
+            excluded_dirs: Vec::new(),
+            link_base: DEFAULT_LINK_BASE.to_string(),
         }
     }
 
@@ -88,11 +322,154 @@ impl IndexerConfig {
         self.poll_interval = interval;
         self
     }
+
+    pub fn with_watch_mode(mut self, watch_mode: bool) -> Self {
+        self.watch_mode = watch_mode;
+        self
+    }
+
+    pub fn with_extension_overrides(mut self, overrides: HashMap<String, MediaType>) -> Self {
+        self.extension_overrides = overrides
+            .into_iter()
+            .map(|(ext, media_type)| {
+                (
+                    ext.trim().trim_start_matches('.').to_ascii_lowercase(),
+                    media_type,
+                )
+            })
+            .collect();
+        self
+    }
+
+    pub fn with_include_hidden(mut self, include_hidden: bool) -> Self {
+        self.include_hidden = include_hidden;
+        self
+    }
+
+    pub fn with_max_depth(mut self, max_depth: Option<usize>) -> Self {
+        self.max_depth = max_depth;
+        self
+    }
+
+    pub fn with_min_filesize(mut self, min_filesize: u64) -> Self {
+        self.min_filesize = min_filesize;
+        self
+    }
+
+    /// Index every root in `roots`; a single root is indexed unlabelled like [`Self::new`].
+    pub fn with_roots(mut self, roots: Vec<MediaRoot>) -> Self {
+        if let Some(first) = roots.first() {
+            self.root = first.path.clone();
+        }
+        self.roots = if roots.len() > 1 { roots } else { Vec::new() };
+        self
+    }
+
+    /// Directories to scan, each with the label prefixed to its relative paths.
+    fn scan_roots(&self) -> Vec<(Option<&str>, &Path)> {
+        if self.roots.is_empty() {
+            return vec![(None, self.root.as_path())];
+        }
+        self.roots
+            .iter()
+            .map(|root| (Some(root.label.as_str()), root.path.as_path()))
+            .collect()
+    }
+
+    /// Map an indexed relative path back to its root directory and the path inside it.
+    /// `None` when the path names a root that is no longer configured.
+    pub fn locate<'a>(&'a self, relative_path: &'a str) -> Option<(&'a Path, &'a str)> {
+        if self.roots.is_empty() {
+            return Some((self.root.as_path(), relative_path));
+        }
+        let (label, rest) = relative_path.split_once('/')?;
+        self.roots
+            .iter()
+            .find(|root| root.label == label)
+            .map(|root| (root.path.as_path(), rest))
+    }
+
+    pub fn with_link_base(mut self, link_base: impl Into<String>) -> Self {
+        self.link_base = link_base.into().trim_end_matches('/').to_string();
+        self
+    }
+
+    pub fn with_read_exif(mut self, read_exif: bool) -> Self {
+        self.read_exif = read_exif;
+        self
+    }
+
+    pub fn with_follow_links(mut self, follow_links: bool) -> Self {
+        self.follow_links = follow_links;
+        self
+    }
+
+    pub fn with_tag_parser(mut self, tag_parser: TagParserConfig) -> Self {
+        self.tag_parser = tag_parser;
+        self
+    }
+
+    pub fn with_tag_aliases(mut self, tag_aliases: TagAliases) -> Self {
+        self.tag_aliases = tag_aliases;
+        self
+    }
+
+    pub fn with_id_strategy(mut self, id_strategy: IdStrategy) -> Self {
+        self.id_strategy = id_strategy;
+        self
+    }
+
+    pub fn with_tags_from_dirs(mut self, tags_from_dirs: bool) -> Self {
+        self.tags_from_dirs = tags_from_dirs;
+        self
+    }
+
+    pub fn with_tags_from_filename(mut self, tags_from_filename: bool) -> Self {
+        self.tags_from_filename = tags_from_filename;
+        self
+    }
+
+    pub fn with_excluded_dir(mut self, dir: impl Into<PathBuf>) -> Self {
+        self.excluded_dirs.push(dir.into());
+        self
+    }
+
+    /// Excluded directories as configured plus their canonical forms, so both the
+    /// walker's and the watcher's view of a path can be matched.
+    fn excluded_roots(&self) -> Vec<PathBuf> {
+        let mut roots = Vec::new();
+        for dir in &self.excluded_dirs {
+            roots.push(dir.clone());
+            if let Ok(real) = dir.canonicalize()
+                && real != *dir
+            {
+                roots.push(real);
+            }
+        }
+        roots
+    }
+
+    pub fn with_scan_concurrency(mut self, workers: usize) -> Self {
+        self.scan_concurrency = workers.max(1);
+        self
+    }
+
+    pub fn with_ffprobe(mut self, ffprobe_path: Option<PathBuf>, timeout: Duration) -> Self {
+        self.ffprobe_path = ffprobe_path;
+        self.probe_timeout = timeout;
+        self
+    }
+
+    /// `{link_base}/media/{id}/{endpoint}`, e.g. the thumbnail or stream route.
+    fn media_link(&self, media_id: &str, endpoint: &str) -> String {
+        format!("{}/media/{media_id}/{endpoint}", self.link_base)
+    }
 }
 
 /// Handle to the background indexer task.
 pub struct IndexerHandle {
     join_handle: JoinHandle<()>,
+    shutdown: CancellationToken,
 }
 
 impl IndexerHandle {
@@ -100,139 +477,446 @@ impl IndexerHandle {
     pub fn abort(self) {
         self.join_handle.abort();
     }
+
+    /// Ask the loop to stop at its next safe point (after any scan in progress has been
+    /// emitted) and wait up to `timeout` for it. Returns `false` if the task had to be
+    /// aborted instead.
+    pub async fn shutdown(mut self, timeout: Duration) -> bool {
+        self.shutdown.cancel();
+        match time::timeout(timeout, &mut self.join_handle).await {
+            Ok(_) => true,
+            Err(_) => {
+                tracing::warn!(?timeout, "indexer did not stop in time, aborting");
+                self.join_handle.abort();
+                false
+            }
+        }
+    }
 }
 
-/// Filesystem indexer that periodically scans the media root.
+/// Filesystem indexer that rescans the media root periodically or on change.
 pub struct Indexer;
 
 impl Indexer {
-    /// Spawn the polling loop on the Tokio runtime.
-    pub fn spawn(config: IndexerConfig) -> (IndexerHandle, mpsc::Receiver<IndexEvent>) {
+    /// Spawn the polling (or watching) loop on the Tokio runtime. Its first scan is
+    /// incremental against `previous`, typically the snapshot loaded from the cache.
+    pub fn spawn(
+        config: IndexerConfig,
+        previous: Vec<MediaFile>,
+    ) -> (IndexerHandle, mpsc::Receiver<IndexEvent>) {
         let (tx, rx) = mpsc::channel(4);
+        let shutdown = CancellationToken::new();
+        let loop_shutdown = shutdown.clone();
         let handle = tokio::spawn(async move {
-            if let Err(err) = run_loop(config, tx).await {
+            if let Err(err) = run_loop(config, previous, tx, loop_shutdown).await {
                 tracing::error!(error = ?err, "indexer loop terminated with error");
             }
         });
         (
             IndexerHandle {
                 join_handle: handle,
+                shutdown,
             },
             rx,
         )
     }
 
-    /// Run a one-off filesystem scan (useful for tests or manual rebuilds).
+    /// Run a one-off filesystem scan with default settings (useful for tests).
     pub fn scan_once(root: impl AsRef<Path>) -> Result<Vec<MediaFile>> {
-        scan_media(root.as_ref())
+        scan_media(&IndexerConfig::new(root.as_ref()), &[])
+    }
+
+    /// Run a one-off filesystem scan honoring the provided configuration.
+    pub fn scan(config: &IndexerConfig) -> Result<Vec<MediaFile>> {
+        scan_media(config, &[])
+    }
+
+    /// Like [`Indexer::scan`], also reporting skip and error counts.
+    pub fn scan_with_summary(config: &IndexerConfig) -> Result<(Vec<MediaFile>, ScanSummary)> {
+        scan_media_with_summary(config, &[])
+    }
+
+    /// Rescan the media root, reusing the probed dimensions and duration of entries in
+    /// `previous` whose content fingerprint is unchanged.
+    pub fn scan_incremental(
+        previous: &CacheSnapshot,
+        config: &IndexerConfig,
+    ) -> Result<Vec<MediaFile>> {
+        scan_media(config, &previous.media)
     }
 }
 
-async fn run_loop(config: IndexerConfig, mut tx: mpsc::Sender<IndexEvent>) -> Result<()> {
-    emit_snapshot(&config, &mut tx).await?;
-    let mut interval = time::interval(config.poll_interval);
+async fn run_loop(
+    config: IndexerConfig,
+    mut previous: Vec<MediaFile>,
+    mut tx: mpsc::Sender<IndexEvent>,
+    shutdown: CancellationToken,
+) -> Result<()> {
+    emit_snapshot(&config, &mut previous, &mut tx).await?;
+    if shutdown.is_cancelled() {
+        return Ok(());
+    }
+
+    if config.watch_mode {
+        match watch_loop(&config, &mut previous, &mut tx, &shutdown).await {
+            Ok(()) => return Ok(()),
+            Err(err) => {
+                tracing::warn!(error = ?err, "filesystem watch unavailable, falling back to polling");
+            }
+        }
+    }
+
+    if config.poll_interval.is_zero() {
+        tracing::info!("periodic rescans disabled; keeping the startup snapshot");
+        shutdown.cancelled().await;
+        return Ok(());
+    }
 
+    let mut interval = time::interval(config.poll_interval);
     loop {
-        interval.tick().await;
+        tokio::select! {
+            biased;
+            _ = shutdown.cancelled() => break,
+            _ = interval.tick() => {}
+        }
         if tx.is_closed() {
             break;
         }
-        if let Err(err) = emit_snapshot(&config, &mut tx).await {
-            let _ = tx
-                .send(IndexEvent::Error {
-                    message: err.to_string(),
-                })
-                .await;
-        }
+        rescan(&config, &mut previous, &mut tx).await;
     }
 
     Ok(())
 }
 
-#[instrument(skip(config, tx), err)]
-async fn emit_snapshot(config: &IndexerConfig, tx: &mut mpsc::Sender<IndexEvent>) -> Result<()> {
-    let root = config.root.clone();
+/// Rescan after filesystem notifications settle. Returns `Ok` once the receiver is
+/// gone and `Err` when watching fails, so the caller can fall back to polling.
+async fn watch_loop(
+    config: &IndexerConfig,
+    previous: &mut Vec<MediaFile>,
+    tx: &mut mpsc::Sender<IndexEvent>,
+    shutdown: &CancellationToken,
+) -> Result<()> {
+    let (event_tx, mut event_rx) = mpsc::unbounded_channel();
+    let mut watcher: RecommendedWatcher = notify::recommended_watcher(move |event| {
+        let _ = event_tx.send(event);
+    })
+    .context("failed to create filesystem watcher")?;
+    for (_, root) in config.scan_roots() {
+        watcher
+            .watch(root, RecursiveMode::Recursive)
+            .with_context(|| format!("failed to watch '{}'", root.display()))?;
+        tracing::info!(media_root = %root.display(), "watching media root for changes");
+    }
+    let excluded = config.excluded_roots();
+
+    loop {
+        let event = tokio::select! {
+            event = event_rx.recv() => event,
+            _ = tx.closed() => return Ok(()),
+            _ = shutdown.cancelled() => return Ok(()),
+        };
+        match event {
+            Some(Ok(event)) if event.kind.is_access() => continue,
+            // Thumbnail and cache writes must not trigger rescans of their own.
+            Some(Ok(event))
+                if !event.paths.is_empty()
+                    && event
+                        .paths
+                        .iter()
+                        .all(|path| excluded.iter().any(|dir| path.starts_with(dir))) =>
+            {
+                continue;
+            }
+            Some(Ok(_)) => {}
+            Some(Err(err)) => return Err(err).context("filesystem watcher failed"),
+            None => bail!("filesystem watcher stopped"),
+        }
+
+        // Coalesce bursts (copies, batch renames) into a single rescan.
+        while let Ok(Some(_)) = time::timeout(WATCH_DEBOUNCE, event_rx.recv()).await {}
+        if tx.is_closed() || shutdown.is_cancelled() {
+            return Ok(());
+        }
+        rescan(config, previous, tx).await;
+    }
+}
+
+/// Incrementally rescan, reporting failures as `IndexEvent::Error`.
+async fn rescan(
+    config: &IndexerConfig,
+    previous: &mut Vec<MediaFile>,
+    tx: &mut mpsc::Sender<IndexEvent>,
+) {
+    if let Err(err) = emit_snapshot(config, previous, tx).await {
+        let _ = tx
+            .send(IndexEvent::Error {
+                message: err.to_string(),
+            })
+            .await;
+    }
+}
+
+/// Scan (incrementally against `previous`) and emit a snapshot. `previous` becomes the
+/// scanned files, or is kept as it was when the scan fails.
+#[instrument(skip(config, previous, tx), err)]
+async fn emit_snapshot(
+    config: &IndexerConfig,
+    previous: &mut Vec<MediaFile>,
+    tx: &mut mpsc::Sender<IndexEvent>,
+) -> Result<()> {
+    let config = config.clone();
     let started = Instant::now();
 
     let span = tracing::Span::current();
-    let files = tokio::task::spawn_blocking(move || span.in_scope(|| scan_media(&root))).await??;
+    let baseline = std::mem::take(previous);
+    let (baseline, scanned) = tokio::task::spawn_blocking(move || {
+        let scanned = span.in_scope(|| scan_media_with_summary(&config, &baseline));
+        (baseline, scanned)
+    })
+    .await?;
+    let (files, summary) = match scanned {
+        Ok(scanned) => scanned,
+        Err(err) => {
+            *previous = baseline;
+            return Err(err);
+        }
+    };
 
     let event = IndexEvent::Snapshot {
-        files,
+        files: files.clone(),
+        summary,
         scanned_at: Utc::now(),
         duration: started.elapsed(),
     };
     let _ = tx.send(event).await;
+    *previous = files;
     Ok(())
 }
 
-#[instrument(skip(root), fields(media_root = %root.display()), err)]
-fn scan_media(root: &Path) -> Result<Vec<MediaFile>> {
-    if !root.exists() {
-        anyhow::bail!(
-            "media root '{}' does not exist",
-            root.as_os_str().to_string_lossy()
-        );
-    }
+fn scan_media(config: &IndexerConfig, previous: &[MediaFile]) -> Result<Vec<MediaFile>> {
+    scan_media_with_summary(config, previous).map(|(files, _)| files)
+}
 
-    let mut files = Vec::new();
+#[instrument(skip(config, previous), fields(media_root = %config.root.display()), err)]
+fn scan_media_with_summary(
+    config: &IndexerConfig,
+    previous: &[MediaFile],
+) -> Result<(Vec<MediaFile>, ScanSummary)> {
     let indexed_at = Utc::now();
+    let previous: HashMap<&str, &MediaFile> = previous
+        .iter()
+        .map(|media| (media.relative_path.as_str(), media))
+        .collect();
+
+    let excluded = config.excluded_roots();
+    let ffprobe = resolve_ffprobe(config);
+    let mut entries = Vec::new();
+    let mut errors = Vec::new();
+    for (label, root) in config.scan_roots() {
+        if !root.exists() {
+            anyhow::bail!(
+                "media root '{}' does not exist",
+                root.as_os_str().to_string_lossy()
+            );
+        }
+        walk_root(config, root, label, &excluded, &mut entries, &mut errors);
+    }
+
+    // The walk stays serial; hashing, decoding headers, and ffprobe fan out per file.
+    let parent = tracing::Span::current();
+    let build = |entry: &ScanEntry| {
+        parent.in_scope(|| {
+            build_media_file(config, ffprobe.as_deref(), &previous, entry, indexed_at)
+                .inspect_err(|err| {
+                    tracing::warn!(path = %entry.rel_display, error = ?err, "skipping media file due to error");
+                })
+        })
+    };
+    let results: Vec<Result<MediaFile>> = if config.scan_concurrency <= 1 || entries.len() <= 1 {
+        entries.iter().map(build).collect()
+    } else {
+        let pool = rayon::ThreadPoolBuilder::new()
+            .num_threads(config.scan_concurrency)
+            .thread_name(|index| format!("galarie-scan-{index}"))
+            .build()
+            .context("failed to start scan worker pool")?;
+        pool.install(|| entries.par_iter().map(build).collect())
+    };
+
+    let mut files = Vec::with_capacity(results.len());
+    let mut skipped = 0;
+    for result in results {
+        match result {
+            Ok(media) => files.push(media),
+            Err(err) if err.is::<SkippedFile>() => skipped += 1,
+            Err(err) => errors.push(error_category(&err)),
+        }
+    }
+    files.sort_by(|a, b| a.relative_path.cmp(&b.relative_path));
+
+    let summary = ScanSummary::tally(files.len(), skipped, &errors);
+    Ok((files, summary))
+}
+
+/// A file found by the walk, waiting for its metadata to be derived.
+struct ScanEntry<'a> {
+    entry: DirEntry,
+    root: &'a Path,
+    label: Option<&'a str>,
+    /// Labelled relative path, for logs.
+    rel_display: String,
+}
+
+/// Walk one media root, collecting its files into `entries` and walk failures into `errors`.
+fn walk_root<'a>(
+    config: &IndexerConfig,
+    root: &'a Path,
+    label: Option<&'a str>,
+    excluded: &[PathBuf],
+    entries: &mut Vec<ScanEntry<'a>>,
+    errors: &mut Vec<String>,
+) {
+    let ignore_rules = load_ignore_rules(root);
+    let mut walker = WalkDir::new(root).follow_links(config.follow_links);
+    if let Some(max_depth) = config.max_depth {
+        walker = walker.max_depth(max_depth);
+    }
+    // Real paths already visited; only tracked when following links, where one
+    // directory or file can be reached through several paths.
+    let mut visited: HashSet<PathBuf> = HashSet::new();
+    let walker = walker.into_iter().filter_entry(|entry| {
+        if entry.depth() > 0 {
+            if !config.include_hidden && is_hidden(entry) {
+                return false;
+            }
+            if ignore_rules
+                .matched(entry.path(), entry.file_type().is_dir())
+                .is_ignore()
+            {
+                return false;
+            }
+            if entry.file_type().is_dir() && is_excluded_dir(entry.path(), excluded) {
+                tracing::debug!(path = %entry.path().display(), "skipping excluded directory");
+                return false;
+            }
+        }
+        if !config.follow_links {
+            return true;
+        }
+        match entry.path().canonicalize() {
+            Ok(real) => {
+                let first_visit = visited.insert(real);
+                if !first_visit {
+                    tracing::debug!(path = %entry.path().display(), "skipping already visited path");
+                }
+                first_visit
+            }
+            // Dangling links surface as walk errors below.
+            Err(_) => true,
+        }
+    });
 
-    for entry in WalkDir::new(root).into_iter() {
+    let mut truncated_dirs = 0usize;
+    for entry in walker {
         let entry = match entry {
             Ok(entry) => entry,
+            Err(err) if err.loop_ancestor().is_some() => {
+                tracing::debug!(error = %err, "skipping symlink cycle");
+                continue;
+            }
             Err(err) => {
                 tracing::warn!(error = %err, "failed to read directory entry");
+                errors.push(match err.io_error() {
+                    Some(io) => format!("io: {:?}", io.kind()),
+                    None => "walk".to_string(),
+                });
                 continue;
             }
         };
 
-        let rel_display = entry
-            .path()
-            .strip_prefix(root)
-            .map(|p| p.display().to_string())
-            .unwrap_or_else(|_| entry.path().display().to_string());
+        let rel_display = match (entry.path().strip_prefix(root), label) {
+            (Ok(relative), Some(label)) => format!("{label}/{}", relative.display()),
+            (Ok(relative), None) => relative.display().to_string(),
+            (Err(_), _) => entry.path().display().to_string(),
+        };
 
         if !entry.file_type().is_file() {
-            continue;
-        }
-
-        match build_media_file(root, &entry, indexed_at, &rel_display) {
-            Ok(media_file) => files.push(media_file),
-            Err(err) => {
-                tracing::warn!(path = %rel_display, error = ?err, "skipping media file due to error");
+            if entry.file_type().is_dir() && Some(entry.depth()) == config.max_depth {
+                truncated_dirs += 1;
             }
+            continue;
         }
+        entries.push(ScanEntry {
+            entry,
+            root,
+            label,
+            rel_display,
+        });
+    }
+    if truncated_dirs > 0 {
+        tracing::warn!(
+            truncated_dirs,
+            max_depth = config.max_depth,
+            "skipped directories beyond the maximum scan depth"
+        );
     }
-
-    Ok(files)
 }
 
-#[instrument(skip(root, entry, indexed_at, rel_display), fields(path = %rel_display))]
+#[instrument(
+    skip(config, ffprobe, previous, scan_entry, indexed_at),
+    fields(path = %scan_entry.rel_display)
+)]
 fn build_media_file(
-    root: &Path,
-    entry: &DirEntry,
+    config: &IndexerConfig,
+    ffprobe: Option<&Path>,
+    previous: &HashMap<&str, &MediaFile>,
+    scan_entry: &ScanEntry<'_>,
     indexed_at: DateTime<Utc>,
-    rel_display: &str,
 ) -> Result<MediaFile> {
+    let ScanEntry {
+        entry,
+        root,
+        label,
+        rel_display,
+    } = scan_entry;
     let relative = entry
         .path()
         .strip_prefix(root)
         .context("entry not under media root")?;
+    // Ids hash the labelled path too, so equal paths under two roots get distinct ids.
+    let labelled = match label {
+        Some(label) => Path::new(label).join(relative),
+        None => relative.to_path_buf(),
+    };
 
-    let relative_path = relative_to_string(relative);
+    let relative_path = relative_to_string(&labelled);
     let metadata = entry.metadata().context("failed to read metadata")?;
     let filesize = metadata.len();
-    let media_type = detect_media_type(entry.path());
+    let modified_at = metadata.modified().ok().map(DateTime::<Utc>::from);
+    if filesize < config.min_filesize {
+        // Zero-byte placeholders and copies still in flight; a later scan picks them up.
+        tracing::debug!(path = %rel_display, filesize, "skipping file below minimum size");
+        return Err(SkippedFile::BelowMinFilesize.into());
+    }
+    let media_type = detect_media_type(entry.path(), &config.extension_overrides);
     if matches!(media_type, MediaType::Unknown) {
-        bail!("unsupported media type");
+        return Err(SkippedFile::UnsupportedMediaType.into());
     }
-    let stem = entry
-        .path()
-        .file_stem()
-        .and_then(|stem| stem.to_str())
-        .unwrap_or_default();
-    let parse_result = parse_filename_tokens(stem);
+    let hash = content_fingerprint(entry.path(), filesize)?;
+    let media_id = config.id_strategy.media_id(&labelled, &hash);
+    // Unchanged content keeps only what is costly to probe; tags, attributes and links
+    // depend on the config and are re-derived so changed settings apply on restart.
+    let unchanged = previous
+        .get(relative_path.as_str())
+        .filter(|existing| {
+            existing.id == media_id
+                && existing.hash.as_deref() == Some(hash.as_str())
+                && existing.media_type == media_type
+        })
+        .inspect(
+            |_| tracing::debug!(path = %rel_display, "media file unchanged since previous scan"),
+        );
+    let parse_result = parse_path_tags(relative, config);
     if !parse_result.invalid_tokens.is_empty() {
         tracing::warn!(
             path = %rel_display,
@@ -240,11 +924,51 @@ fn build_media_file(
             "ignored invalid tag tokens"
         );
     }
-    let attributes = build_attributes_from_tags(&parse_result.tags);
-
-    tracing::info!(path = %rel_display,"scanned media file {}", relative_path);
+    let mut attributes = build_attributes_from_tags(&parse_result.tags);
+    let exif = match media_type {
+        MediaType::Image if config.read_exif => read_exif(entry.path())
+            .inspect_err(|err| {
+                tracing::warn!(path = %rel_display, error = ?err, "failed to read EXIF metadata");
+            })
+            .ok()
+            .flatten(),
+        _ => None,
+    };
+    if let Some(exif) = &exif {
+        // Filename tags are the user's explicit choice, so they win over EXIF.
+        for (key, value) in &exif.attributes {
+            attributes
+                .entry(key.to_string())
+                .or_insert_with(|| value.clone());
+        }
+    }
+    let dimensions = match (unchanged, &media_type) {
+        (Some(existing), _) => existing.dimensions.clone(),
+        (None, MediaType::Image | MediaType::Gif) => read_image_dimensions(entry.path())
+            .inspect_err(|err| {
+                tracing::warn!(path = %rel_display, error = ?err, "failed to read image dimensions");
+            })
+            .ok()
+            .or_else(|| exif.as_ref().and_then(|exif| exif.dimensions.clone())),
+        (None, _) => None,
+    };
+    let duration_ms = match (unchanged, &media_type, ffprobe) {
+        (Some(existing), _, _) => existing.duration_ms,
+        (None, MediaType::Video | MediaType::Audio, Some(ffprobe)) => probe_duration_ms(
+            ffprobe,
+            entry.path(),
+            config.probe_timeout,
+        )
+        .inspect_err(|err| {
+            tracing::warn!(path = %rel_display, error = ?err, "failed to probe media duration");
+        })
+        .ok(),
+        (None, _, _) => None,
+    };
 
-    let media_id = stable_id(relative);
+    if unchanged.is_none() {
+        tracing::info!(path = %rel_display,"scanned media file {}", relative_path);
+    }
 
     Ok(MediaFile {
         id: media_id.clone(),
@@ -253,69 +977,385 @@ fn build_media_file(
         tags: parse_result.tags,
         attributes,
         filesize,
-        dimensions: None,
-        duration_ms: None,
-        thumbnail_path: Some(format!("/media/{media_id}/thumbnail")),
-        hash: None,
-        indexed_at,
+        dimensions,
+        duration_ms,
+        location: exif.as_ref().and_then(|exif| exif.location),
+        thumbnail_path: Some(config.media_link(&media_id, "thumbnail")),
+        stream_path: Some(config.media_link(&media_id, "stream")),
+        hash: Some(hash),
+        indexed_at: unchanged.map_or(indexed_at, |existing| existing.indexed_at),
+        // A touch without a content change still moves the file in mtime order.
+        modified_at,
+        invalid_tokens: parse_result.invalid_tokens,
     })
 }
 
-fn detect_media_type(path: &Path) -> MediaType {
-    let Some(ext) = path.extension().and_then(|ext| ext.to_str()) else {
-        return MediaType::Unknown;
-    };
+/// Cheap content fingerprint: SHA-1 over the file size plus the first and last
+/// [`FINGERPRINT_SAMPLE_BYTES`] (or the whole file when it is smaller than both samples).
+fn content_fingerprint(path: &Path, filesize: u64) -> Result<String> {
+    use sha1::{Digest, Sha1};
 
-    match ext.to_ascii_lowercase().as_str() {
-        "jpg" | "jpeg" | "png" | "webp" | "bmp" | "heic" | "tiff" => MediaType::Image,
-        "gif" => MediaType::Gif,
-        "mp4" | "mov" | "mkv" | "webm" | "avi" => MediaType::Video,
-        "mp3" | "wav" | "flac" | "aac" | "ogg" => MediaType::Audio,
-        "pdf" => MediaType::Pdf,
-        _ => MediaType::Unknown,
+    let mut file = File::open(path).context("failed to open file for fingerprinting")?;
+    let mut hasher = Sha1::new();
+    hasher.update(filesize.to_le_bytes());
+
+    let mut buffer = Vec::with_capacity(FINGERPRINT_SAMPLE_BYTES as usize);
+    if filesize <= FINGERPRINT_SAMPLE_BYTES * 2 {
+        file.read_to_end(&mut buffer)?;
+        hasher.update(&buffer);
+    } else {
+        (&mut file)
+            .take(FINGERPRINT_SAMPLE_BYTES)
+            .read_to_end(&mut buffer)?;
+        hasher.update(&buffer);
+        buffer.clear();
+        file.seek(SeekFrom::End(-(FINGERPRINT_SAMPLE_BYTES as i64)))?;
+        file.read_to_end(&mut buffer)?;
+        hasher.update(&buffer);
     }
+
+    Ok(format!("{:x}", hasher.finalize()))
 }
 
-fn stable_id(relative: &Path) -> String {
-    use sha1::{Digest, Sha1};
+/// What [`read_exif`] extracts from an image.
+struct ExifMetadata {
+    /// `camera`, `lens` and the capture `date` (`YYYY-MM-DD`), when recorded.
+    attributes: Vec<(&'static str, String)>,
+    /// Pixel dimensions recorded by the camera.
+    dimensions: Option<Dimensions>,
+    location: Option<GeoPoint>,
+}
 
-    let normalized = relative_to_string(relative);
-    let mut hasher = Sha1::new();
-    hasher.update(normalized.as_bytes());
-    format!("{:x}", hasher.finalize())
+/// Read camera, lens, capture date and dimensions from EXIF. `Ok(None)` when the file
+/// carries no EXIF at all, which is common and not worth a warning.
+fn read_exif(path: &Path) -> Result<Option<ExifMetadata>> {
+    use exif::{In, Tag as ExifTag, Value};
+
+    let file = File::open(path).context("failed to open image for EXIF")?;
+    let exif = match exif::Reader::new().read_from_container(&mut std::io::BufReader::new(file)) {
+        Ok(exif) => exif,
+        Err(exif::Error::NotFound(_)) => return Ok(None),
+        Err(err) => return Err(err).context("failed to parse EXIF"),
+    };
+    let ascii = |tag: ExifTag| -> Option<String> {
+        match &exif.get_field(tag, In::PRIMARY)?.value {
+            Value::Ascii(values) => values
+                .iter()
+                .map(|value| String::from_utf8_lossy(value).trim().to_string())
+                .find(|value| !value.is_empty()),
+            _ => None,
+        }
+    };
+    let uint = |tag: ExifTag| exif.get_field(tag, In::PRIMARY)?.value.get_uint(0);
+
+    let mut attributes = Vec::new();
+    let make = ascii(ExifTag::Make);
+    let camera = match (make, ascii(ExifTag::Model)) {
+        // Most vendors repeat the make in the model ("Canon" / "Canon EOS R5").
+        (Some(make), Some(model)) if model.to_lowercase().starts_with(&make.to_lowercase()) => {
+            Some(model)
+        }
+        (Some(make), Some(model)) => Some(format!("{make} {model}")),
+        (make, model) => model.or(make),
+    };
+    if let Some(camera) = camera {
+        attributes.push(("camera", camera));
+    }
+    if let Some(lens) = ascii(ExifTag::LensModel) {
+        attributes.push(("lens", lens));
+    }
+    let captured = [ExifTag::DateTimeOriginal, ExifTag::DateTime]
+        .into_iter()
+        .find_map(|tag| match &exif.get_field(tag, In::PRIMARY)?.value {
+            Value::Ascii(values) => exif::DateTime::from_ascii(values.first()?).ok(),
+            _ => None,
+        });
+    if let Some(captured) = captured {
+        attributes.push((
+            "date",
+            format!(
+                "{:04}-{:02}-{:02}",
+                captured.year, captured.month, captured.day
+            ),
+        ));
+    }
+    let dimensions = match (
+        uint(ExifTag::PixelXDimension),
+        uint(ExifTag::PixelYDimension),
+    ) {
+        (Some(width), Some(height)) if width > 0 && height > 0 => {
+            Some(Dimensions { width, height })
+        }
+        _ => None,
+    };
+
+    let location = match (
+        gps_degrees(&exif, ExifTag::GPSLatitude, ExifTag::GPSLatitudeRef, b'S'),
+        gps_degrees(&exif, ExifTag::GPSLongitude, ExifTag::GPSLongitudeRef, b'W'),
+    ) {
+        (Some(latitude), Some(longitude)) if latitude.abs() <= 90.0 && longitude.abs() <= 180.0 => {
+            Some(GeoPoint {
+                latitude,
+                longitude,
+            })
+        }
+        _ => None,
+    };
+
+    Ok(Some(ExifMetadata {
+        attributes,
+        dimensions,
+        location,
+    }))
 }
 
-fn relative_to_string(path: &Path) -> String {
-    let mut normalized = path.to_string_lossy().to_string();
-    if std::path::MAIN_SEPARATOR != '/' {
-        normalized = normalized.replace(std::path::MAIN_SEPARATOR, "/");
+/// Decimal degrees from an EXIF GPS degrees/minutes/seconds triple, negated when the
+/// reference tag names the `negative` hemisphere.
+fn gps_degrees(
+    exif: &exif::Exif,
+    value_tag: exif::Tag,
+    ref_tag: exif::Tag,
+    negative: u8,
+) -> Option<f64> {
+    let exif::Value::Rational(parts) = &exif.get_field(value_tag, exif::In::PRIMARY)?.value else {
+        return None;
+    };
+    let degrees = parts
+        .iter()
+        .take(3)
+        .zip([1.0, 60.0, 3600.0])
+        .map(|(part, divisor)| part.to_f64() / divisor)
+        .sum::<f64>();
+    if !degrees.is_finite() {
+        return None;
     }
-    normalized
+    let negated = match &exif.get_field(ref_tag, exif::In::PRIMARY)?.value {
+        exif::Value::Ascii(values) => values
+            .first()
+            .and_then(|value| value.first())
+            .is_some_and(|hemisphere| hemisphere.eq_ignore_ascii_case(&negative)),
+        _ => false,
+    };
+    Some(if negated { -degrees } else { degrees })
 }
 
-fn build_attributes_from_tags(tags: &[Tag]) -> HashMap<String, String> {
-    let mut attributes = HashMap::new();
-    for tag in tags {
-        if matches!(tag.kind, TagKind::KeyValue) {
-            if let Some(value) = &tag.value {
-                attributes
-                    .entry(tag.name.clone())
-                    .or_insert_with(|| value.clone());
-            }
+/// Read width/height from the image header without decoding pixel data.
+fn read_image_dimensions(path: &Path) -> Result<Dimensions> {
+    let (width, height) = image::ImageReader::open(path)
+        .context("failed to open image")?
+        .with_guessed_format()
+        .context("failed to guess image format")?
+        .into_dimensions()
+        .context("failed to read image header")?;
+    Ok(Dimensions { width, height })
+}
+
+/// Locate the configured `ffprobe` once per scan so a missing binary warns only once.
+fn resolve_ffprobe(config: &IndexerConfig) -> Option<PathBuf> {
+    let configured = config.ffprobe_path.as_ref()?;
+    match which::which(configured) {
+        Ok(path) => Some(path),
+        Err(err) => {
+            tracing::warn!(
+                ffprobe = %configured.display(),
+                error = %err,
+                "ffprobe not available, media durations will not be indexed"
+            );
+            None
         }
     }
-    attributes
 }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use anyhow::anyhow;
-    use std::time::Duration;
-    use tempfile::tempdir;
-    use tokio::time::timeout;
+/// Read the container duration via `ffprobe`, killing the process after `timeout`.
+fn probe_duration_ms(ffprobe: &Path, path: &Path, timeout: Duration) -> Result<u64> {
+    let mut child = Command::new(ffprobe)
+        .args(["-v", "error", "-show_entries", "format=duration"])
+        .args(["-of", "default=noprint_wrappers=1:nokey=1"])
+        .arg(path)
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .spawn()
+        .context("failed to start ffprobe")?;
 
-    #[tokio::test]
+    let started = Instant::now();
+    while child.try_wait()?.is_none() {
+        if started.elapsed() >= timeout {
+            let _ = child.kill();
+            let _ = child.wait();
+            bail!("ffprobe timed out after {} ms", timeout.as_millis());
+        }
+        std::thread::sleep(Duration::from_millis(10));
+    }
+
+    let output = child.wait_with_output()?;
+    if !output.status.success() {
+        bail!("ffprobe exited with {}", output.status);
+    }
+    let seconds: f64 = String::from_utf8_lossy(&output.stdout)
+        .trim()
+        .parse()
+        .context("ffprobe returned a non-numeric duration")?;
+    if !seconds.is_finite() || seconds < 0.0 {
+        bail!("ffprobe returned an invalid duration {seconds}");
+    }
+    Ok((seconds * 1000.0).round() as u64)
+}
+
+fn detect_media_type(path: &Path, overrides: &HashMap<String, MediaType>) -> MediaType {
+    let Some(ext) = path.extension().and_then(|ext| ext.to_str()) else {
+        return MediaType::Unknown;
+    };
+
+    let ext = ext.to_ascii_lowercase();
+    if let Some(media_type) = overrides.get(&ext) {
+        return media_type.clone();
+    }
+
+    match ext.as_str() {
+        "jpg" | "jpeg" | "png" | "webp" | "bmp" | "heic" | "tiff" => MediaType::Image,
+        "gif" => MediaType::Gif,
+        "mp4" | "mov" | "mkv" | "webm" | "avi" => MediaType::Video,
+        "mp3" | "wav" | "flac" | "aac" | "ogg" => MediaType::Audio,
+        "pdf" => MediaType::Pdf,
+        _ => MediaType::Unknown,
+    }
+}
+
+fn is_excluded_dir(path: &Path, excluded: &[PathBuf]) -> bool {
+    if excluded.is_empty() {
+        return false;
+    }
+    if excluded.iter().any(|dir| dir == path) {
+        return true;
+    }
+    path.canonicalize()
+        .is_ok_and(|real| excluded.contains(&real))
+}
+
+/// Build the matcher for `.galarieignore`; a missing or unreadable file ignores nothing.
+fn load_ignore_rules(root: &Path) -> Gitignore {
+    let path = root.join(IGNORE_FILENAME);
+    if !path.is_file() {
+        return Gitignore::empty();
+    }
+
+    let mut builder = GitignoreBuilder::new(root);
+    if let Some(err) = builder.add(&path) {
+        tracing::warn!(error = %err, path = %path.display(), "failed to parse ignore file");
+    }
+    builder.build().unwrap_or_else(|err| {
+        tracing::warn!(error = %err, path = %path.display(), "failed to build ignore rules");
+        Gitignore::empty()
+    })
+}
+
+fn is_hidden(entry: &DirEntry) -> bool {
+    entry
+        .file_name()
+        .to_str()
+        .is_some_and(|name| name.starts_with('.'))
+}
+
+/// Whether `id` could have been produced by the indexer and is safe to embed in a
+/// filesystem path: non-empty, bounded, and limited to `[A-Za-z0-9_-]` (no separators,
+/// `..`, or NUL).
+pub fn is_valid_media_id(id: &str) -> bool {
+    !id.is_empty()
+        && id.len() <= 128
+        && id
+            .bytes()
+            .all(|byte| byte.is_ascii_alphanumeric() || byte == b'-' || byte == b'_')
+}
+
+fn relative_to_string(path: &Path) -> String {
+    let mut normalized = path.to_string_lossy().to_string();
+    if std::path::MAIN_SEPARATOR != '/' {
+        normalized = normalized.replace(std::path::MAIN_SEPARATOR, "/");
+    }
+    normalized
+}
+
+/// Collect tags from the filename stem and, when enabled, the directory names above it.
+///
+/// Filename tags come first so their attributes win over a directory's; directories
+/// follow from the innermost outwards. Tags are deduplicated by normalized form.
+fn parse_path_tags(relative: &Path, config: &IndexerConfig) -> TagParseResult {
+    let mut sources = Vec::new();
+    if config.tags_from_filename
+        && let Some(stem) = relative.file_stem().and_then(|stem| stem.to_str())
+    {
+        sources.push(stem);
+    }
+    if config.tags_from_dirs
+        && let Some(parent) = relative.parent()
+    {
+        sources.extend(
+            parent
+                .components()
+                .rev()
+                .filter_map(|component| match component {
+                    Component::Normal(name) => name.to_str(),
+                    _ => None,
+                }),
+        );
+    }
+
+    let mut merged = TagParseResult::default();
+    let mut seen = HashSet::new();
+    for source in sources {
+        let parsed = parse_filename_tokens(source, &config.tag_parser);
+        merged.tags.extend(
+            apply_tag_aliases(parsed.tags, &config.tag_aliases)
+                .into_iter()
+                .filter(|tag| seen.insert(tag.normalized.clone())),
+        );
+        merged.invalid_tokens.extend(parsed.invalid_tokens);
+    }
+    merged
+}
+
+/// Rename simple tags to their canonical alias, dropping the duplicates that leaves.
+fn apply_tag_aliases(tags: Vec<Tag>, aliases: &TagAliases) -> Vec<Tag> {
+    let mut seen = HashSet::new();
+    tags.into_iter()
+        .map(|mut tag| {
+            if tag.kind == TagKind::Simple {
+                let canonical = aliases
+                    .canonical(&normalize_tag_text(tag.raw_token.trim()))
+                    .to_string();
+                tag.name.clone_from(&canonical);
+                tag.normalized = canonical;
+            }
+            tag
+        })
+        .filter(|tag| seen.insert(tag.normalized.clone()))
+        .collect()
+}
+
+fn build_attributes_from_tags(tags: &[Tag]) -> HashMap<String, String> {
+    let mut attributes = HashMap::new();
+    for tag in tags {
+        if matches!(tag.kind, TagKind::KeyValue)
+            && let Some(value) = &tag.value
+        {
+            attributes
+                .entry(tag.name.clone())
+                .or_insert_with(|| value.clone());
+        }
+    }
+    attributes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anyhow::anyhow;
+    use std::time::Duration;
+    use tempfile::tempdir;
+    use tokio::time::timeout;
+
+    #[tokio::test]
     async fn scan_once_discovers_files() -> Result<()> {
         let dir = tempdir()?;
         let root = dir.path();
@@ -329,6 +1369,474 @@ mod tests {
         Ok(())
     }
 
+    #[tokio::test]
+    async fn scan_once_populates_tags_and_attributes() -> Result<()> {
+        let dir = tempdir()?;
+        let root = dir.path();
+        std::fs::write(
+            root.join("sunset_coast+location-okinawa_rating-5.png"),
+            b"png",
+        )?;
+
+        let files = Indexer::scan_once(root)?;
+        assert_eq!(files.len(), 1);
+        assert_eq!(
+            files[0]
+                .tags
+                .iter()
+                .map(|tag| tag.normalized.as_str())
+                .collect::<Vec<_>>(),
+            vec!["sunset", "coast", "location=okinawa", "rating=5"]
+        );
+        assert_eq!(
+            files[0].attributes,
+            HashMap::from([
+                ("location".to_string(), "okinawa".to_string()),
+                ("rating".to_string(), "5".to_string()),
+            ])
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn scan_merges_directory_tags_when_enabled() -> Result<()> {
+        let dir = tempdir()?;
+        let root = dir.path();
+        std::fs::create_dir_all(root.join("holiday/beach"))?;
+        std::fs::write(root.join("holiday/beach/pic.jpg"), b"jpg")?;
+        std::fs::write(root.join("holiday/beach/beach_rating-4.jpg"), b"jpg")?;
+
+        let tags_of = |files: &[MediaFile], path: &str| {
+            files
+                .iter()
+                .find(|file| file.relative_path == path)
+                .map(|file| {
+                    file.tags
+                        .iter()
+                        .map(|tag| tag.normalized.clone())
+                        .collect::<Vec<_>>()
+                })
+                .unwrap_or_default()
+        };
+
+        let files = Indexer::scan(&IndexerConfig::new(root))?;
+        assert_eq!(tags_of(&files, "holiday/beach/pic.jpg"), vec!["pic"]);
+
+        let files = Indexer::scan(&IndexerConfig::new(root).with_tags_from_dirs(true))?;
+        assert_eq!(
+            tags_of(&files, "holiday/beach/pic.jpg"),
+            vec!["pic", "beach", "holiday"]
+        );
+        assert_eq!(
+            tags_of(&files, "holiday/beach/beach_rating-4.jpg"),
+            vec!["beach", "rating=4", "holiday"]
+        );
+
+        let files = Indexer::scan(
+            &IndexerConfig::new(root)
+                .with_tags_from_dirs(true)
+                .with_tags_from_filename(false),
+        )?;
+        assert_eq!(
+            tags_of(&files, "holiday/beach/pic.jpg"),
+            vec!["beach", "holiday"]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn stores_aliased_tags_under_their_canonical_form() -> Result<()> {
+        let dir = tempdir()?;
+        let root = dir.path();
+        std::fs::write(root.join("nyc_night.jpg"), b"night")?;
+        std::fs::write(root.join("NY_nyc.jpg"), b"both")?;
+        let tags_of = |files: &[MediaFile], path: &str| {
+            files
+                .iter()
+                .find(|file| file.relative_path == path)
+                .map(|file| {
+                    file.tags
+                        .iter()
+                        .map(|tag| tag.normalized.clone())
+                        .collect::<Vec<_>>()
+                })
+                .unwrap_or_default()
+        };
+
+        let aliased = IndexerConfig::new(root).with_tag_aliases("nyc=newyork\nny=newyork".parse()?);
+        let files = Indexer::scan(&aliased)?;
+        assert_eq!(tags_of(&files, "nyc_night.jpg"), vec!["newyork", "night"]);
+        assert_eq!(tags_of(&files, "NY_nyc.jpg"), vec!["newyork"]);
+
+        // Unchanged files pick up an edited alias file on the next incremental scan.
+        let previous = CacheSnapshot::new(files);
+        let files = Indexer::scan_incremental(&previous, &IndexerConfig::new(root))?;
+        assert_eq!(tags_of(&files, "nyc_night.jpg"), vec!["nyc", "night"]);
+        // Spellings merged under one canonical tag come back once the alias is gone.
+        assert_eq!(tags_of(&files, "NY_nyc.jpg"), vec!["ny", "nyc"]);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn scan_honors_extension_overrides() -> Result<()> {
+        let dir = tempdir()?;
+        let root = dir.path();
+        std::fs::write(root.join("photo.jxl"), b"jxl")?;
+        std::fs::write(root.join("other.JXL"), b"jxl")?;
+
+        assert!(Indexer::scan_once(root)?.is_empty());
+
+        let config = IndexerConfig::new(root)
+            .with_extension_overrides(HashMap::from([("jxl".to_string(), MediaType::Image)]));
+        let mut files = Indexer::scan(&config)?;
+        files.sort_by(|a, b| a.relative_path.cmp(&b.relative_path));
+        assert_eq!(files.len(), 2);
+        assert_eq!(files[0].relative_path, "other.JXL");
+        assert_eq!(files[1].relative_path, "photo.jxl");
+        assert!(files.iter().all(|file| file.media_type == MediaType::Image));
+        Ok(())
+    }
+
+    #[test]
+    fn parallel_scan_matches_serial_scan() -> Result<()> {
+        let dir = tempdir()?;
+        let root = dir.path();
+        for folder in ["alpha", "beta/nested", "gamma"] {
+            std::fs::create_dir_all(root.join(folder))?;
+            for index in 0..40 {
+                std::fs::write(
+                    root.join(folder)
+                        .join(format!("shot_{index}+rating-{}.jpg", index % 5)),
+                    format!("{folder}-{index}"),
+                )?;
+            }
+        }
+
+        let summarize = |files: Vec<MediaFile>| {
+            files
+                .into_iter()
+                .map(|media| (media.relative_path, media.id, media.hash, media.tags))
+                .collect::<Vec<_>>()
+        };
+        let serial = summarize(Indexer::scan(
+            &IndexerConfig::new(root).with_scan_concurrency(1),
+        )?);
+        let parallel = summarize(Indexer::scan(
+            &IndexerConfig::new(root).with_scan_concurrency(8),
+        )?);
+
+        assert_eq!(serial.len(), 120);
+        assert_eq!(serial, parallel);
+        assert!(serial.windows(2).all(|pair| pair[0].0 < pair[1].0));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn scan_skips_hidden_entries_by_default() -> Result<()> {
+        let dir = tempdir()?;
+        let root = dir.path();
+        std::fs::create_dir_all(root.join(".git/objects"))?;
+        std::fs::write(root.join(".git/objects/blob.jpg"), b"git")?;
+        std::fs::write(root.join(".hidden.jpg"), b"hidden")?;
+        std::fs::write(root.join("photo.jpg"), b"photo")?;
+
+        let files = Indexer::scan_once(root)?;
+        assert_eq!(files.len(), 1);
+        assert_eq!(files[0].relative_path, "photo.jpg");
+
+        let config = IndexerConfig::new(root).with_include_hidden(true);
+        assert_eq!(Indexer::scan(&config)?.len(), 3);
+        Ok(())
+    }
+
+    #[test]
+    fn scan_stops_at_max_depth() -> Result<()> {
+        let dir = tempdir()?;
+        let root = dir.path();
+        std::fs::create_dir_all(root.join("one/two"))?;
+        std::fs::write(root.join("top.jpg"), b"top")?;
+        std::fs::write(root.join("one/middle.jpg"), b"middle")?;
+        std::fs::write(root.join("one/two/deep.jpg"), b"deep")?;
+
+        let paths = |config: IndexerConfig| -> Result<Vec<String>> {
+            Ok(Indexer::scan(&config)?
+                .into_iter()
+                .map(|media| media.relative_path)
+                .collect())
+        };
+        assert_eq!(paths(IndexerConfig::new(root))?.len(), 3);
+        assert_eq!(
+            paths(IndexerConfig::new(root).with_max_depth(Some(1)))?,
+            vec!["top.jpg"]
+        );
+        assert_eq!(
+            paths(IndexerConfig::new(root).with_max_depth(Some(2)))?,
+            vec!["one/middle.jpg", "top.jpg"]
+        );
+        Ok(())
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn scan_follows_symlinks_without_looping() -> Result<()> {
+        let dir = tempdir()?;
+        let root = dir.path();
+        std::fs::create_dir_all(root.join("album"))?;
+        std::fs::write(root.join("album/photo.jpg"), b"photo")?;
+        std::fs::write(root.join("top.jpg"), b"top")?;
+        // A cycle back to the root, plus a second path to the same directory.
+        std::os::unix::fs::symlink(root, root.join("album/loop"))?;
+        std::os::unix::fs::symlink(root.join("album"), root.join("alias"))?;
+
+        let (files, summary) = Indexer::scan_with_summary(&IndexerConfig::new(root))?;
+        assert_eq!(files.len(), 2, "symlinks are ignored by default");
+        assert_eq!(summary.errored, 0);
+
+        let (files, summary) =
+            Indexer::scan_with_summary(&IndexerConfig::new(root).with_follow_links(true))?;
+        assert_eq!(files.len(), 2, "{files:#?}");
+        let mut real_paths: Vec<PathBuf> = files
+            .iter()
+            .map(|media| root.join(&media.relative_path).canonicalize())
+            .collect::<std::io::Result<_>>()?;
+        real_paths.sort();
+        real_paths.dedup();
+        assert_eq!(real_paths.len(), 2, "each real file is indexed once");
+        assert_eq!(summary.errored, 0);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn scan_skips_cache_dir_nested_in_media_root() -> Result<()> {
+        let dir = tempdir()?;
+        let root = dir.path();
+        let cache_dir = root.join("cache");
+        std::fs::create_dir_all(cache_dir.join("thumbnails/small"))?;
+        std::fs::write(cache_dir.join("thumbnails/small/abc.jpg"), b"thumb")?;
+        std::fs::write(root.join("photo.jpg"), b"photo")?;
+
+        let files = Indexer::scan(&IndexerConfig::new(root).with_excluded_dir(&cache_dir))?;
+        let paths: Vec<_> = files.iter().map(|f| f.relative_path.as_str()).collect();
+        assert_eq!(paths, vec!["photo.jpg"]);
+
+        // Matched by real path too, e.g. when the cache dir is configured through a symlink.
+        std::os::unix::fs::symlink(&cache_dir, dir.path().join("cache-link"))?;
+        let files = Indexer::scan(
+            &IndexerConfig::new(root).with_excluded_dir(dir.path().join("cache-link")),
+        )?;
+        assert_eq!(files.len(), 1, "{files:#?}");
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn scan_applies_ignore_file_patterns() -> Result<()> {
+        let dir = tempdir()?;
+        let root = dir.path();
+        std::fs::create_dir_all(root.join("proxies/nested"))?;
+        std::fs::create_dir_all(root.join("renders"))?;
+        std::fs::write(root.join("proxies/nested/clip.mp4"), b"proxy")?;
+        std::fs::write(root.join("renders/draft.tmp"), b"tmp")?;
+        std::fs::write(root.join("renders/final.png"), b"png")?;
+        std::fs::write(root.join("keep.png"), b"png")?;
+        std::fs::write(root.join("drop.png"), b"png")?;
+        std::fs::write(
+            root.join(IGNORE_FILENAME),
+            "*.tmp\nproxies/**\n*.png\n!keep.png\n!renders/final.png\n",
+        )?;
+
+        let mut files = Indexer::scan(
+            &IndexerConfig::new(root)
+                .with_extension_overrides(HashMap::from([("tmp".to_string(), MediaType::Image)])),
+        )?;
+        files.sort_by(|a, b| a.relative_path.cmp(&b.relative_path));
+        let paths: Vec<_> = files.iter().map(|f| f.relative_path.as_str()).collect();
+        assert_eq!(paths, vec!["keep.png", "renders/final.png"]);
+
+        // Rules are re-read on every scan.
+        std::fs::write(root.join(IGNORE_FILENAME), "*.tmp\n")?;
+        assert_eq!(Indexer::scan_once(root)?.len(), 4);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn scan_reads_image_dimensions() -> Result<()> {
+        let dir = tempdir()?;
+        let root = dir.path();
+        image::RgbImage::new(12, 7).save(root.join("fixture.png"))?;
+        std::fs::write(root.join("broken.png"), b"not a png")?;
+
+        let mut files = Indexer::scan_once(root)?;
+        files.sort_by(|a, b| a.relative_path.cmp(&b.relative_path));
+        assert_eq!(files.len(), 2, "unreadable headers must not skip the file");
+        assert_eq!(files[0].relative_path, "broken.png");
+        assert_eq!(files[0].dimensions, None);
+        assert_eq!(
+            files[1].dimensions,
+            Some(Dimensions {
+                width: 12,
+                height: 7
+            })
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn scan_probes_video_duration_with_real_ffprobe() -> Result<()> {
+        let Ok(ffprobe_path) = which::which("ffprobe") else {
+            eprintln!("skipping duration test because ffprobe is not installed");
+            return Ok(());
+        };
+
+        let dir = tempdir()?;
+        let name = "skate_session+type-video_rating-3.mp4";
+        std::fs::copy(
+            Path::new(env!("CARGO_MANIFEST_DIR"))
+                .join("../sample-media")
+                .join(name),
+            dir.path().join(name),
+        )?;
+
+        let config = IndexerConfig::new(dir.path())
+            .with_ffprobe(Some(ffprobe_path), Duration::from_secs(10));
+        let files = Indexer::scan(&config)?;
+        assert_eq!(files.len(), 1);
+        assert!(files[0].duration_ms.is_some_and(|ms| ms > 0));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn scan_tolerates_missing_ffprobe() -> Result<()> {
+        let dir = tempdir()?;
+        std::fs::write(dir.path().join("clip.mp4"), b"not a video")?;
+
+        let config = IndexerConfig::new(dir.path()).with_ffprobe(
+            Some(PathBuf::from("/nonexistent/ffprobe")),
+            Duration::from_secs(1),
+        );
+        let files = Indexer::scan(&config)?;
+        assert_eq!(files.len(), 1);
+        assert_eq!(files[0].duration_ms, None);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn scan_incremental_reuses_unchanged_entries() -> Result<()> {
+        let dir = tempdir()?;
+        let root = dir.path();
+        std::fs::write(root.join("stable.jpg"), b"stable")?;
+        std::fs::write(root.join("edited.jpg"), b"original")?;
+
+        let config = IndexerConfig::new(root);
+        let previous = CacheSnapshot::new(Indexer::scan(&config)?);
+        assert!(previous.media.iter().all(|media| media.hash.is_some()));
+
+        std::thread::sleep(Duration::from_millis(10));
+        std::fs::write(root.join("edited.jpg"), b"modified content")?;
+        std::fs::write(root.join("added.jpg"), b"new")?;
+
+        let files = Indexer::scan_incremental(&previous, &config)?;
+        let find = |files: &[MediaFile], path: &str| {
+            files
+                .iter()
+                .find(|media| media.relative_path == path)
+                .cloned()
+                .expect("media present")
+        };
+
+        assert_eq!(files.len(), 3);
+        assert_eq!(
+            find(&files, "stable.jpg"),
+            find(&previous.media, "stable.jpg")
+        );
+        let edited_before = find(&previous.media, "edited.jpg");
+        let edited_after = find(&files, "edited.jpg");
+        assert_ne!(edited_after.indexed_at, edited_before.indexed_at);
+        assert_ne!(edited_after.hash, edited_before.hash);
+        assert_eq!(edited_after.filesize, 16);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn scan_incremental_applies_changed_tag_settings() -> Result<()> {
+        let dir = tempdir()?;
+        let root = dir.path();
+        std::fs::create_dir_all(root.join("trips"))?;
+        std::fs::write(root.join("trips/sunset.jpg"), b"stable")?;
+        let normalized = |files: &[MediaFile]| -> Vec<String> {
+            files[0]
+                .tags
+                .iter()
+                .map(|tag| tag.normalized.clone())
+                .collect()
+        };
+
+        let previous = CacheSnapshot::new(Indexer::scan(&IndexerConfig::new(root))?);
+        assert_eq!(normalized(&previous.media), ["sunset"]);
+
+        let files = Indexer::scan_incremental(
+            &previous,
+            &IndexerConfig::new(root).with_tags_from_dirs(true),
+        )?;
+        assert_eq!(normalized(&files), ["sunset", "trips"]);
+        assert_eq!(files[0].indexed_at, previous.media[0].indexed_at);
+        Ok(())
+    }
+
+    #[test]
+    fn path_only_ids_survive_edits_but_not_renames() -> Result<()> {
+        let dir = tempdir()?;
+        let root = dir.path();
+        let config = IndexerConfig::new(root);
+        std::fs::write(root.join("shot.png"), b"original")?;
+        let original = Indexer::scan(&config)?.remove(0).id;
+
+        std::fs::write(root.join("shot.png"), b"edited content")?;
+        assert_eq!(Indexer::scan(&config)?.remove(0).id, original);
+
+        std::fs::rename(root.join("shot.png"), root.join("moved.png"))?;
+        assert_ne!(Indexer::scan(&config)?.remove(0).id, original);
+        Ok(())
+    }
+
+    #[test]
+    fn content_hash_ids_change_on_edit_and_rename() -> Result<()> {
+        let dir = tempdir()?;
+        let root = dir.path();
+        let config = IndexerConfig::new(root).with_id_strategy(IdStrategy::PathPlusContentHash);
+        std::fs::write(root.join("shot.png"), b"original")?;
+        let original = Indexer::scan(&config)?.remove(0).id;
+        assert_eq!(Indexer::scan(&config)?.remove(0).id, original);
+        assert_ne!(
+            original,
+            IdStrategy::PathOnly.media_id(Path::new("shot.png"), "ignored")
+        );
+
+        std::fs::write(root.join("shot.png"), b"edited content")?;
+        let edited = Indexer::scan(&config)?.remove(0).id;
+        assert_ne!(edited, original);
+
+        std::fs::rename(root.join("shot.png"), root.join("moved.png"))?;
+        let moved = Indexer::scan(&config)?.remove(0).id;
+        assert_ne!(moved, edited);
+        assert!(is_valid_media_id(&moved));
+        Ok(())
+    }
+
+    #[test]
+    fn fingerprint_samples_both_ends_of_large_files() -> Result<()> {
+        let dir = tempdir()?;
+        let path = dir.path().join("large.bin");
+        let mut bytes = vec![0u8; (FINGERPRINT_SAMPLE_BYTES * 3) as usize];
+        std::fs::write(&path, &bytes)?;
+        let original = content_fingerprint(&path, bytes.len() as u64)?;
+
+        *bytes.last_mut().unwrap() = 1;
+        std::fs::write(&path, &bytes)?;
+        let tail_changed = content_fingerprint(&path, bytes.len() as u64)?;
+        assert_ne!(original, tail_changed);
+        Ok(())
+    }
+
     #[tokio::test]
     async fn spawn_emits_snapshot_events() -> Result<()> {
         let dir = tempdir()?;
@@ -336,6 +1844,7 @@ mod tests {
 
         let (handle, mut rx) = Indexer::spawn(
             IndexerConfig::new(dir.path()).with_poll_interval(Duration::from_millis(10)),
+            Vec::new(),
         );
 
         let event = timeout(Duration::from_secs(1), rx.recv())
@@ -353,6 +1862,309 @@ mod tests {
         Ok(())
     }
 
+    #[tokio::test]
+    async fn spawn_reuses_the_given_media_on_the_first_scan() -> Result<()> {
+        let dir = tempdir()?;
+        std::fs::write(dir.path().join("foo.gif"), b"bytes")?;
+        let config = IndexerConfig::new(dir.path()).with_poll_interval(Duration::ZERO);
+        let mut cached = Indexer::scan(&config)?;
+        // A marker no scan derives for a GIF, so it survives only if the entry is reused.
+        cached[0].duration_ms = Some(42);
+
+        let (handle, mut rx) = Indexer::spawn(config, cached);
+        let event = timeout(Duration::from_secs(1), rx.recv())
+            .await?
+            .ok_or_else(|| anyhow!("indexer channel closed"))?;
+        match event {
+            IndexEvent::Snapshot { files, .. } => assert_eq!(files[0].duration_ms, Some(42)),
+            IndexEvent::Error { message } => panic!("expected snapshot, got error: {message}"),
+        }
+
+        handle.abort();
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn failed_rescan_keeps_the_previous_files() -> Result<()> {
+        let dir = tempdir()?;
+        let root = dir.path().join("library");
+        std::fs::create_dir(&root)?;
+        std::fs::write(root.join("foo.gif"), b"bytes")?;
+        let config = IndexerConfig::new(&root);
+        let files = Indexer::scan(&config)?;
+        let mut previous = files.clone();
+        let (mut tx, mut rx) = mpsc::channel(4);
+
+        std::fs::rename(&root, dir.path().join("unmounted"))?;
+        rescan(&config, &mut previous, &mut tx).await;
+        assert!(matches!(rx.recv().await, Some(IndexEvent::Error { .. })));
+        assert_eq!(previous, files, "the next scan stays incremental");
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn zero_poll_interval_scans_only_at_startup() -> Result<()> {
+        let dir = tempdir()?;
+        std::fs::write(dir.path().join("foo.gif"), b"bytes")?;
+
+        let (handle, mut rx) = Indexer::spawn(
+            IndexerConfig::new(dir.path()).with_poll_interval(Duration::ZERO),
+            Vec::new(),
+        );
+        let event = timeout(Duration::from_secs(1), rx.recv())
+            .await?
+            .ok_or_else(|| anyhow!("indexer channel closed"))?;
+        assert!(matches!(event, IndexEvent::Snapshot { .. }));
+
+        std::fs::write(dir.path().join("bar.gif"), b"bytes")?;
+        assert!(
+            timeout(Duration::from_millis(200), rx.recv())
+                .await
+                .is_err(),
+            "no rescan and the channel stays open"
+        );
+
+        handle.abort();
+        Ok(())
+    }
+
+    /// A small JPEG whose APP1 segment carries the given EXIF fields (none without fields).
+    fn jpeg_with_exif(fields: &[exif::Field]) -> Result<Vec<u8>> {
+        let mut jpeg = std::io::Cursor::new(Vec::new());
+        image::DynamicImage::new_rgb8(8, 8).write_to(&mut jpeg, image::ImageFormat::Jpeg)?;
+        let jpeg = jpeg.into_inner();
+        if fields.is_empty() {
+            return Ok(jpeg);
+        }
+
+        let mut tiff = std::io::Cursor::new(Vec::new());
+        let mut writer = exif::experimental::Writer::new();
+        for field in fields {
+            writer.push_field(field);
+        }
+        writer.write(&mut tiff, false)?;
+        let tiff = tiff.into_inner();
+        let mut out = jpeg[..2].to_vec();
+        out.extend_from_slice(&[0xFF, 0xE1]);
+        out.extend_from_slice(&(2 + 6 + tiff.len() as u16).to_be_bytes());
+        out.extend_from_slice(b"Exif\0\0");
+        out.extend_from_slice(&tiff);
+        out.extend_from_slice(&jpeg[2..]);
+        Ok(out)
+    }
+
+    #[test]
+    fn reads_exif_attributes_when_enabled() -> Result<()> {
+        use exif::{Field, In, Tag as ExifTag, Value};
+
+        let ascii = |tag, text: &str| Field {
+            tag,
+            ifd_num: In::PRIMARY,
+            value: Value::Ascii(vec![text.as_bytes().to_vec()]),
+        };
+        let dms = |tag, [degrees, minutes, seconds]: [u32; 3]| Field {
+            tag,
+            ifd_num: In::PRIMARY,
+            value: Value::Rational(vec![
+                (degrees, 1).into(),
+                (minutes, 1).into(),
+                (seconds * 100, 100).into(),
+            ]),
+        };
+        let dir = tempdir()?;
+        std::fs::write(
+            dir.path().join("holiday_camera-phone.jpg"),
+            jpeg_with_exif(&[
+                ascii(ExifTag::Make, "Canon"),
+                ascii(ExifTag::Model, "Canon EOS R5"),
+                ascii(ExifTag::DateTime, "2024:05:01 10:30:00"),
+            ])?,
+        )?;
+        std::fs::write(
+            dir.path().join("beach.jpg"),
+            jpeg_with_exif(&[
+                ascii(ExifTag::Make, "FUJIFILM"),
+                ascii(ExifTag::Model, "X-T4"),
+                ascii(ExifTag::GPSLatitudeRef, "S"),
+                dms(ExifTag::GPSLatitude, [26, 12, 36]),
+                ascii(ExifTag::GPSLongitudeRef, "E"),
+                dms(ExifTag::GPSLongitude, [127, 40, 48]),
+            ])?,
+        )?;
+        std::fs::write(dir.path().join("plain.jpg"), jpeg_with_exif(&[])?)?;
+
+        let config = IndexerConfig::new(dir.path()).with_read_exif(true);
+        let files = Indexer::scan(&config)?;
+        let attributes = |path: &str| {
+            files
+                .iter()
+                .find(|media| media.relative_path == path)
+                .map(|media| media.attributes.clone())
+                .unwrap()
+        };
+        assert_eq!(attributes("beach.jpg")["camera"], "FUJIFILM X-T4");
+        let location = files
+            .iter()
+            .find_map(|media| media.location)
+            .expect("GPS location");
+        assert!((location.latitude + 26.21).abs() < 1e-9);
+        assert!((location.longitude - 127.68).abs() < 1e-9);
+        // Filename tags win over EXIF.
+        let holiday = attributes("holiday_camera-phone.jpg");
+        assert_eq!(holiday["camera"], "phone");
+        assert_eq!(holiday["date"], "2024-05-01");
+        assert!(!attributes("plain.jpg").contains_key("camera"));
+
+        let files = Indexer::scan(&IndexerConfig::new(dir.path()))?;
+        let beach = files
+            .iter()
+            .find(|media| media.relative_path == "beach.jpg");
+        assert!(!beach.unwrap().attributes.contains_key("camera"));
+        Ok(())
+    }
+
+    #[test]
+    fn skips_files_below_min_filesize() -> Result<()> {
+        let dir = tempdir()?;
+        std::fs::write(dir.path().join("complete.png"), b"bytes")?;
+        std::fs::write(dir.path().join("placeholder.png"), b"")?;
+
+        let files = Indexer::scan_once(dir.path())?;
+        let paths: Vec<&str> = files.iter().map(|f| f.relative_path.as_str()).collect();
+        assert_eq!(paths, ["complete.png"]);
+
+        let (files, summary) =
+            Indexer::scan_with_summary(&IndexerConfig::new(dir.path()).with_min_filesize(0))?;
+        assert_eq!(files.len(), 2);
+        assert_eq!(summary.skipped, 0);
+        Ok(())
+    }
+
+    #[test]
+    fn generates_media_links_from_the_link_base() -> Result<()> {
+        let dir = tempdir()?;
+        std::fs::write(dir.path().join("sunset.png"), b"bytes")?;
+
+        let files = Indexer::scan_once(dir.path())?;
+        let id = &files[0].id;
+        assert_eq!(
+            files[0].thumbnail_path.as_deref(),
+            Some(format!("/api/v1/media/{id}/thumbnail").as_str())
+        );
+        assert_eq!(
+            files[0].stream_path.as_deref(),
+            Some(format!("/api/v1/media/{id}/stream").as_str())
+        );
+
+        // Unchanged entries carried over from a previous scan pick up the new base too.
+        let config = IndexerConfig::new(dir.path()).with_link_base("https://cdn.example/galarie/");
+        let files = scan_media(&config, &files)?;
+        assert_eq!(
+            files[0].thumbnail_path.as_deref(),
+            Some(format!("https://cdn.example/galarie/media/{id}/thumbnail").as_str())
+        );
+        assert_eq!(
+            files[0].stream_path.as_deref(),
+            Some(format!("https://cdn.example/galarie/media/{id}/stream").as_str())
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn snapshot_event_reports_skipped_files() -> Result<()> {
+        let dir = tempdir()?;
+        std::fs::write(dir.path().join("foo.gif"), b"bytes")?;
+        std::fs::write(dir.path().join("notes.txt"), b"not media")?;
+        std::fs::write(dir.path().join("empty.xyz"), b"")?;
+
+        let (handle, mut rx) = Indexer::spawn(
+            IndexerConfig::new(dir.path()).with_poll_interval(Duration::from_secs(60)),
+            Vec::new(),
+        );
+        let event = timeout(Duration::from_secs(1), rx.recv())
+            .await?
+            .ok_or_else(|| anyhow!("indexer channel closed"))?;
+        match event {
+            IndexEvent::Snapshot { files, summary, .. } => {
+                assert_eq!(files.len(), 1);
+                assert_eq!(summary.indexed, 1);
+                assert_eq!(summary.skipped, 2);
+                assert_eq!(summary.errored, 0);
+                assert!(summary.top_errors.is_empty());
+            }
+            IndexEvent::Error { message } => panic!("expected snapshot, got error: {message}"),
+        }
+
+        handle.abort();
+        Ok(())
+    }
+
+    #[test]
+    fn summary_ranks_error_categories() {
+        let missing = anyhow::Error::new(std::io::Error::from(std::io::ErrorKind::NotFound))
+            .context("failed to read metadata");
+        let denied = anyhow::Error::new(std::io::Error::from(std::io::ErrorKind::PermissionDenied));
+        let errors = vec![
+            error_category(&denied),
+            error_category(&missing),
+            error_category(&denied),
+            error_category(&anyhow!("bad header")),
+        ];
+
+        let summary = ScanSummary::tally(3, 1, &errors);
+        assert_eq!(summary.errored, 4);
+        assert_eq!(
+            summary.top_errors,
+            vec![
+                ErrorCount {
+                    category: "io: PermissionDenied".into(),
+                    count: 2
+                },
+                ErrorCount {
+                    category: "io: NotFound".into(),
+                    count: 1
+                },
+                ErrorCount {
+                    category: "other".into(),
+                    count: 1
+                },
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn watch_mode_rescans_on_file_creation() -> Result<()> {
+        let dir = tempdir()?;
+        std::fs::write(dir.path().join("first.gif"), b"bytes")?;
+
+        let (handle, mut rx) = Indexer::spawn(
+            IndexerConfig::new(dir.path())
+                .with_poll_interval(Duration::from_secs(60))
+                .with_watch_mode(true),
+            Vec::new(),
+        );
+
+        let initial = timeout(Duration::from_secs(1), rx.recv())
+            .await?
+            .ok_or_else(|| anyhow!("indexer channel closed"))?;
+        assert!(matches!(initial, IndexEvent::Snapshot { ref files, .. } if files.len() == 1));
+
+        // Give the watcher a moment to register before touching the tree.
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        std::fs::write(dir.path().join("second.png"), b"bytes")?;
+
+        let event = timeout(Duration::from_secs(5), rx.recv())
+            .await?
+            .ok_or_else(|| anyhow!("indexer channel closed"))?;
+        match event {
+            IndexEvent::Snapshot { files, .. } => assert_eq!(files.len(), 2),
+            IndexEvent::Error { message } => panic!("expected snapshot, got error: {message}"),
+        }
+
+        handle.abort();
+        Ok(())
+    }
+
     #[tokio::test]
     async fn scan_once_ignores_unknown_media() -> Result<()> {
         let dir = tempdir()?;
@@ -1,9 +1,11 @@
 pub mod api;
 pub mod cache;
+pub mod cli;
 pub mod config;
 pub mod indexer;
 pub mod media;
 pub mod o11y;
 pub mod routes;
+pub mod server;
 pub mod services;
 pub mod tags;
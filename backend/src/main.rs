@@ -1,78 +1,82 @@
-use std::sync::Arc;
+use std::{sync::Arc, time::Duration};
 
 use anyhow::Result;
 use galarie_backend::{
-    cache::CacheStore,
-    config::AppConfig,
-    indexer::{IndexEvent, Indexer, IndexerConfig},
+    cache::{CacheStore, STALE_TEMP_FILE_AGE},
+    cli,
+    config::Invocation,
+    indexer::Indexer,
     o11y,
     routes::{self, AppState},
+    server,
 };
 use tokio::sync::RwLock;
 
+/// Upper bound for each step of draining background work after the server stops.
+const SHUTDOWN_GRACE: Duration = Duration::from_secs(10);
+
 #[tokio::main]
 async fn main() -> Result<()> {
-    let config = Arc::new(AppConfig::load()?);
+    let config = match Invocation::load()? {
+        Invocation::Serve(config) => Arc::new(*config),
+        Invocation::IndexDryRun(indexer) => {
+            return cli::index_dry_run(&indexer, &mut std::io::stdout().lock());
+        }
+        Invocation::ExportCache { store, path } => {
+            return cli::export_cache(&store, &path, &mut std::io::stdout().lock());
+        }
+        Invocation::ImportCache { store, path } => {
+            return cli::import_cache(&store, &path, &mut std::io::stdout().lock());
+        }
+    };
     let _telemetry = o11y::TelemetryGuard::init(&config)?;
+    o11y::metrics::init();
 
     tracing::info!("starting Galarie backend with config {:?}", config);
-
-    let cache_store = Arc::new(CacheStore::new(config.cache_dir.clone()));
-    let media_root_for_cache = config.media_root.clone();
-    let initial_snapshot =
-        cache_store.load_or_rebuild(|| Indexer::scan_once(&media_root_for_cache))?;
+    config.thumbnails.check_media_tools()?;
+    config.check_dir_layout();
+
+    let cache_store =
+        Arc::new(CacheStore::new(config.cache_dir.clone()).with_compression(config.cache_compress));
+    match cache_store.sweep_temp_files(STALE_TEMP_FILE_AGE) {
+        Ok(0) => {}
+        Ok(removed) => tracing::info!(removed, "removed stale cache temp files"),
+        Err(err) => tracing::warn!(error = %err, "failed to sweep stale cache temp files"),
+    }
+    let initial_snapshot = cache_store.load_or_rebuild(|| Indexer::scan(&config.indexer))?;
+    o11y::metrics::set_cache_items(initial_snapshot.media.len());
+    // Seeds the first background scan so unchanged files are not re-derived after a restart.
+    let indexed_media = initial_snapshot.media.clone();
     let snapshot_state = Arc::new(RwLock::new(initial_snapshot));
 
     let state = AppState::new(config.clone(), cache_store.clone(), snapshot_state.clone());
-    let (indexer_handle, mut index_events) =
-        Indexer::spawn(IndexerConfig::new(config.media_root.clone()));
-
-    let cache_store_for_task = cache_store.clone();
-    let snapshot_state_for_task = snapshot_state.clone();
-    tokio::spawn(async move {
-        while let Some(event) = index_events.recv().await {
-            match event {
-                IndexEvent::Snapshot {
-                    files,
-                    duration,
-                    scanned_at,
-                } => {
-                    let elapsed_ms = duration.as_millis();
-                    let file_count = files.len();
-
-                    tracing::info!(
-                        elapsed_ms,
-                        file_count = file_count,
-                        scanned_at = %scanned_at.to_rfc3339(),
-                        "filesystem scan complete in {elapsed_ms} ms, found {file_count} files",
-                    );
-
-                    match cache_store_for_task.persist(files) {
-                        Ok(snapshot) => {
-                            *snapshot_state_for_task.write().await = snapshot.clone();
-                            tracing::info!("filesystem scan persisted to cache");
-                        }
-                        Err(err) => {
-                            tracing::error!(error = %err, "failed to persist cache snapshot");
-                        }
-                    }
-                }
-                IndexEvent::Error { message } => {
-                    tracing::warn!(%message, "indexer error");
-                }
-            }
-        }
-    });
+    state.mark_initialized();
+    let thumbnail_sweeper = state
+        .thumbnail_cache
+        .clone()
+        .map(|cache| cache.spawn_sweeper(galarie_backend::media::thumbnail_cache::SWEEP_INTERVAL));
+    let (indexer_handle, index_events) = Indexer::spawn(config.indexer.clone(), indexed_media);
+
+    let index_consumer = state.spawn_index_consumer(index_events);
 
     let listener = tokio::net::TcpListener::bind(config.listen_addr).await?;
     tracing::info!(addr = %config.listen_addr, "HTTP server listening");
 
-    axum::serve(listener, routes::router(state))
-        .with_graceful_shutdown(shutdown_signal())
-        .await?;
-
-    // Ensure the indexer task stops when the server exits.
-    indexer_handle.abort();
+    server::serve(listener, routes::router(state.clone()), &config.server, {
+        let shutdown = state.shutdown.clone();
+        async move {
+            shutdown_signal().await;
+            shutdown.cancel();
+        }
+    })
+    .await?;
+
+    state
+        .drain(indexer_handle, index_consumer, SHUTDOWN_GRACE)
+        .await;
+    if let Some(sweeper) = thumbnail_sweeper {
+        sweeper.abort();
+    }
 
     Ok(())
 }
@@ -0,0 +1,178 @@
+//! Streaming ZIP writer for media exports.
+//!
+//! Media files are already compressed, so entries are written uncompressed ("stored")
+//! with a trailing data descriptor: each file is read once and never buffered whole.
+//! Archives are plain ZIP (no ZIP64), so callers keep them under [`MAX_ZIP_BYTES`] and
+//! [`MAX_ZIP_ENTRIES`].
+
+use std::{io, path::PathBuf};
+
+use chrono::{DateTime, Datelike, Timelike, Utc};
+use crc32fast::Hasher;
+use tokio::{
+    fs::File,
+    io::{AsyncReadExt, AsyncWrite, AsyncWriteExt},
+};
+
+/// Largest offset a ZIP without ZIP64 extensions can address.
+pub const MAX_ZIP_BYTES: u64 = u32::MAX as u64;
+/// Largest entry count a ZIP without ZIP64 extensions can record.
+pub const MAX_ZIP_ENTRIES: usize = u16::MAX as usize;
+
+const LOCAL_HEADER_SIGNATURE: u32 = 0x0403_4b50;
+const DATA_DESCRIPTOR_SIGNATURE: u32 = 0x0807_4b50;
+const CENTRAL_HEADER_SIGNATURE: u32 = 0x0201_4b50;
+const END_OF_CENTRAL_DIRECTORY_SIGNATURE: u32 = 0x0605_4b50;
+/// ZIP 2.0: the oldest version that understands data descriptors.
+const ZIP_VERSION: u16 = 20;
+/// Bit 3: sizes and CRC follow the data; bit 11: names are UTF-8.
+const FLAGS: u16 = 0x0008 | 0x0800;
+const CHUNK_SIZE: usize = 64 * 1024;
+
+/// One file to add to an archive.
+#[derive(Debug, Clone)]
+pub struct ArchiveEntry {
+    /// Path inside the archive, `/`-separated.
+    pub name: String,
+    pub path: PathBuf,
+    /// Bytes copied from `path`; a file that grew since is truncated to this length.
+    pub size: u64,
+    pub modified: DateTime<Utc>,
+}
+
+struct CentralRecord {
+    name: String,
+    crc: u32,
+    size: u32,
+    offset: u32,
+    time: u16,
+    date: u16,
+}
+
+/// Write `entries` as a ZIP archive to `writer`, flushing as each chunk is produced.
+pub async fn write_zip<W: AsyncWrite + Unpin>(
+    mut writer: W,
+    entries: &[ArchiveEntry],
+) -> io::Result<()> {
+    if entries.len() > MAX_ZIP_ENTRIES {
+        return Err(io::Error::other("too many archive entries"));
+    }
+    let mut offset: u64 = 0;
+    let mut records = Vec::with_capacity(entries.len());
+    let mut buffer = vec![0; CHUNK_SIZE];
+
+    for entry in entries {
+        let (time, date) = dos_datetime(entry.modified);
+        let header_offset = zip32(offset)?;
+        let mut header = Vec::with_capacity(30 + entry.name.len());
+        put_u32(&mut header, LOCAL_HEADER_SIGNATURE);
+        put_u16(&mut header, ZIP_VERSION);
+        put_u16(&mut header, FLAGS);
+        put_u16(&mut header, 0); // stored
+        put_u16(&mut header, time);
+        put_u16(&mut header, date);
+        put_u32(&mut header, 0); // CRC, sizes: in the data descriptor
+        put_u32(&mut header, 0);
+        put_u32(&mut header, 0);
+        put_u16(&mut header, name_len(&entry.name)?);
+        put_u16(&mut header, 0);
+        header.extend_from_slice(entry.name.as_bytes());
+        writer.write_all(&header).await?;
+        offset += header.len() as u64;
+
+        let mut file = File::open(&entry.path).await?.take(entry.size);
+        let mut hasher = Hasher::new();
+        let mut copied: u64 = 0;
+        loop {
+            let read = file.read(&mut buffer).await?;
+            if read == 0 {
+                break;
+            }
+            hasher.update(&buffer[..read]);
+            writer.write_all(&buffer[..read]).await?;
+            copied += read as u64;
+        }
+        offset += copied;
+        let crc = hasher.finalize();
+        let size = zip32(copied)?;
+
+        let mut descriptor = Vec::with_capacity(16);
+        put_u32(&mut descriptor, DATA_DESCRIPTOR_SIGNATURE);
+        put_u32(&mut descriptor, crc);
+        put_u32(&mut descriptor, size);
+        put_u32(&mut descriptor, size);
+        writer.write_all(&descriptor).await?;
+        offset += descriptor.len() as u64;
+
+        records.push(CentralRecord {
+            name: entry.name.clone(),
+            crc,
+            size,
+            offset: header_offset,
+            time,
+            date,
+        });
+    }
+
+    let directory_offset = zip32(offset)?;
+    let mut directory = Vec::new();
+    for record in &records {
+        put_u32(&mut directory, CENTRAL_HEADER_SIGNATURE);
+        put_u16(&mut directory, ZIP_VERSION);
+        put_u16(&mut directory, ZIP_VERSION);
+        put_u16(&mut directory, FLAGS);
+        put_u16(&mut directory, 0);
+        put_u16(&mut directory, record.time);
+        put_u16(&mut directory, record.date);
+        put_u32(&mut directory, record.crc);
+        put_u32(&mut directory, record.size);
+        put_u32(&mut directory, record.size);
+        put_u16(&mut directory, name_len(&record.name)?);
+        put_u16(&mut directory, 0); // extra field
+        put_u16(&mut directory, 0); // comment
+        put_u16(&mut directory, 0); // disk number
+        put_u16(&mut directory, 0); // internal attributes
+        put_u32(&mut directory, 0); // external attributes
+        put_u32(&mut directory, record.offset);
+        directory.extend_from_slice(record.name.as_bytes());
+    }
+    let directory_size = zip32(directory.len() as u64)?;
+    zip32(offset + directory.len() as u64)?;
+
+    put_u32(&mut directory, END_OF_CENTRAL_DIRECTORY_SIGNATURE);
+    put_u16(&mut directory, 0);
+    put_u16(&mut directory, 0);
+    put_u16(&mut directory, records.len() as u16);
+    put_u16(&mut directory, records.len() as u16);
+    put_u32(&mut directory, directory_size);
+    put_u32(&mut directory, directory_offset);
+    put_u16(&mut directory, 0);
+    writer.write_all(&directory).await?;
+    writer.shutdown().await
+}
+
+fn put_u16(buffer: &mut Vec<u8>, value: u16) {
+    buffer.extend_from_slice(&value.to_le_bytes());
+}
+
+fn put_u32(buffer: &mut Vec<u8>, value: u32) {
+    buffer.extend_from_slice(&value.to_le_bytes());
+}
+
+fn zip32(value: u64) -> io::Result<u32> {
+    u32::try_from(value).map_err(|_| io::Error::other("archive exceeds the 4 GiB ZIP limit"))
+}
+
+fn name_len(name: &str) -> io::Result<u16> {
+    u16::try_from(name.len()).map_err(|_| io::Error::other("archive entry name is too long"))
+}
+
+/// MS-DOS time and date fields; DOS dates start in 1980, so earlier times are clamped.
+fn dos_datetime(value: DateTime<Utc>) -> (u16, u16) {
+    if value.year() < 1980 {
+        return (0, (1 << 5) | 1);
+    }
+    let time = (value.hour() << 11) | (value.minute() << 5) | (value.second() / 2);
+    let date = ((value.year().min(2107) as u32 - 1980) << 9) | (value.month() << 5) | value.day();
+    (time as u16, date as u16)
+}
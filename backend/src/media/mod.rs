@@ -1 +1,3 @@
+pub mod archive;
+pub mod thumbnail_cache;
 pub mod thumbnails;
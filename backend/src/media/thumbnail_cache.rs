@@ -0,0 +1,177 @@
+use std::{
+    fs::File,
+    path::{Path, PathBuf},
+    sync::Arc,
+    time::{Duration, SystemTime},
+};
+
+use anyhow::{Context, Result};
+use tokio::{task, time::MissedTickBehavior};
+use walkdir::WalkDir;
+
+use crate::media::thumbnails::THUMBNAIL_ROOT;
+
+/// How often the background sweeper re-checks the budget.
+pub const SWEEP_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Keeps the on-disk thumbnail directory under a byte budget by evicting the least
+/// recently used files. Recency is approximated by mtime, which is bumped on every serve.
+#[derive(Debug, Clone)]
+pub struct ThumbnailCache {
+    root: PathBuf,
+    max_bytes: u64,
+}
+
+/// Files removed by a single budget enforcement pass.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Eviction {
+    pub removed: usize,
+    pub freed_bytes: u64,
+}
+
+impl ThumbnailCache {
+    pub fn new(cache_dir: impl AsRef<Path>, max_bytes: u64) -> Self {
+        Self {
+            root: cache_dir.as_ref().join(THUMBNAIL_ROOT),
+            max_bytes,
+        }
+    }
+
+    /// Mark a thumbnail as recently used.
+    pub fn touch(&self, path: &Path) {
+        let touched = File::options()
+            .write(true)
+            .open(path)
+            .and_then(|file| file.set_modified(SystemTime::now()));
+        if let Err(err) = touched {
+            tracing::debug!(error = %err, path = %path.display(), "failed to touch thumbnail");
+        }
+    }
+
+    /// Delete the oldest thumbnails until the directory fits in the budget.
+    pub fn enforce_budget(&self) -> Result<Eviction> {
+        if !self.root.exists() {
+            return Ok(Eviction::default());
+        }
+
+        let mut entries = Vec::new();
+        let mut total: u64 = 0;
+        for entry in WalkDir::new(&self.root) {
+            let entry = entry.context("failed to walk thumbnail cache")?;
+            if !entry.file_type().is_file() {
+                continue;
+            }
+            // Files vanish when a concurrent sweep or generation renames them; skip those.
+            let Ok(metadata) = entry.metadata() else {
+                continue;
+            };
+            let modified = metadata.modified().unwrap_or(SystemTime::UNIX_EPOCH);
+            total += metadata.len();
+            entries.push((modified, metadata.len(), entry.into_path()));
+        }
+
+        let mut eviction = Eviction::default();
+        if total <= self.max_bytes {
+            return Ok(eviction);
+        }
+
+        entries.sort_by_key(|(modified, _, _)| *modified);
+        for (_, len, path) in entries {
+            if total <= self.max_bytes {
+                break;
+            }
+            match std::fs::remove_file(&path) {
+                Ok(()) => {
+                    total -= len;
+                    eviction.removed += 1;
+                    eviction.freed_bytes += len;
+                }
+                Err(err) => {
+                    tracing::warn!(error = %err, path = %path.display(), "failed to evict thumbnail")
+                }
+            }
+        }
+
+        tracing::info!(
+            removed = eviction.removed,
+            freed_bytes = eviction.freed_bytes,
+            remaining_bytes = total,
+            "evicted thumbnails over cache budget"
+        );
+        Ok(eviction)
+    }
+
+    /// Run [`Self::enforce_budget`] off the async runtime.
+    pub async fn enforce_budget_async(self: Arc<Self>) {
+        match task::spawn_blocking(move || self.enforce_budget()).await {
+            Ok(Ok(_)) => {}
+            Ok(Err(err)) => tracing::warn!(error = ?err, "thumbnail cache eviction failed"),
+            Err(err) => tracing::warn!(error = %err, "thumbnail cache eviction panicked"),
+        }
+    }
+
+    /// Periodically enforce the budget for the lifetime of the process.
+    pub fn spawn_sweeper(self: Arc<Self>, interval: Duration) -> task::JoinHandle<()> {
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(interval);
+            ticker.set_missed_tick_behavior(MissedTickBehavior::Delay);
+            loop {
+                ticker.tick().await;
+                self.clone().enforce_budget_async().await;
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    fn write_thumbnail(root: &Path, name: &str, len: usize, age: Duration) -> PathBuf {
+        let path = root.join(THUMBNAIL_ROOT).join("small").join(name);
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(&path, vec![0u8; len]).unwrap();
+        File::options()
+            .write(true)
+            .open(&path)
+            .unwrap()
+            .set_modified(SystemTime::now() - age)
+            .unwrap();
+        path
+    }
+
+    #[test]
+    fn evicts_least_recently_used_thumbnails_over_budget() {
+        let dir = tempdir().unwrap();
+        let oldest = write_thumbnail(dir.path(), "a.jpg", 400, Duration::from_secs(300));
+        let older = write_thumbnail(dir.path(), "b.jpg", 400, Duration::from_secs(200));
+        let touched = write_thumbnail(dir.path(), "c.jpg", 400, Duration::from_secs(400));
+        let newest = write_thumbnail(dir.path(), "d.jpg", 400, Duration::from_secs(100));
+
+        let cache = ThumbnailCache::new(dir.path(), 1_000);
+        cache.touch(&touched);
+        let eviction = cache.enforce_budget().unwrap();
+
+        assert_eq!(
+            eviction,
+            Eviction {
+                removed: 2,
+                freed_bytes: 800
+            }
+        );
+        assert!(!oldest.exists());
+        assert!(!older.exists());
+        assert!(touched.exists(), "touching a thumbnail keeps it fresh");
+        assert!(newest.exists());
+    }
+
+    #[test]
+    fn leaves_cache_alone_within_budget() {
+        let dir = tempdir().unwrap();
+        let path = write_thumbnail(dir.path(), "a.jpg", 400, Duration::from_secs(10));
+        let cache = ThumbnailCache::new(dir.path(), 1_000);
+        assert_eq!(cache.enforce_budget().unwrap(), Eviction::default());
+        assert!(path.exists());
+    }
+}
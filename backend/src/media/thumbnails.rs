@@ -1,22 +1,37 @@
 use std::{
+    collections::HashMap,
+    io::Write,
     path::{Path, PathBuf},
+    sync::{
+        Arc,
+        atomic::{AtomicUsize, Ordering},
+    },
     time::Duration,
 };
 
 use anyhow::{Context, Result};
-use image::{DynamicImage, ImageFormat, ImageReader, imageops::FilterType};
+use image::{
+    DynamicImage, ImageFormat, ImageReader, codecs::jpeg::JpegEncoder, imageops::FilterType,
+};
 use serde::{Deserialize, Serialize};
-use tokio::{process::Command, task, time::timeout};
+use tokio::{
+    process::Command,
+    sync::{Mutex, Semaphore},
+    task,
+    time::timeout,
+};
 use tracing::instrument;
 
 use crate::indexer::MediaType;
 
 #[allow(dead_code)]
 const DEFAULT_TIMEOUT: Duration = Duration::from_secs(20);
-#[allow(dead_code)]
-const THUMBNAIL_ROOT: &str = "thumbnails";
-#[allow(dead_code)]
-const THUMBNAIL_EXT: &str = ".jpg";
+pub(crate) const THUMBNAIL_ROOT: &str = "thumbnails";
+/// Matches the `image` crate's own JPEG default.
+pub const DEFAULT_JPEG_QUALITY: u8 = 75;
+/// Audio decoded for waveforms is downmixed to mono at this rate and capped in length.
+const WAVEFORM_SAMPLE_RATE: u32 = 8_000;
+const WAVEFORM_MAX_SECONDS: u32 = 600;
 
 /// Default thumbnail sizes supported by the backend.
 #[allow(dead_code)]
@@ -46,6 +61,105 @@ impl ThumbnailSize {
     }
 }
 
+impl std::str::FromStr for ThumbnailSize {
+    type Err = anyhow::Error;
+
+    fn from_str(value: &str) -> Result<Self> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "small" => Ok(Self::Small),
+            "medium" => Ok(Self::Medium),
+            "large" => Ok(Self::Large),
+            other => Err(anyhow::anyhow!("unknown thumbnail size '{other}'")),
+        }
+    }
+}
+
+/// Resampling filter used when scaling images down, from fastest to sharpest.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ResizeFilter {
+    Nearest,
+    Triangle,
+    #[default]
+    CatmullRom,
+    Lanczos3,
+}
+
+impl ResizeFilter {
+    fn filter_type(self) -> FilterType {
+        match self {
+            ResizeFilter::Nearest => FilterType::Nearest,
+            ResizeFilter::Triangle => FilterType::Triangle,
+            ResizeFilter::CatmullRom => FilterType::CatmullRom,
+            ResizeFilter::Lanczos3 => FilterType::Lanczos3,
+        }
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            ResizeFilter::Nearest => "nearest",
+            ResizeFilter::Triangle => "triangle",
+            ResizeFilter::CatmullRom => "catmull",
+            ResizeFilter::Lanczos3 => "lanczos3",
+        }
+    }
+}
+
+impl std::str::FromStr for ResizeFilter {
+    type Err = anyhow::Error;
+
+    fn from_str(value: &str) -> Result<Self> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "nearest" => Ok(Self::Nearest),
+            "triangle" => Ok(Self::Triangle),
+            "catmull" | "catmullrom" | "catmull-rom" => Ok(Self::CatmullRom),
+            "lanczos3" => Ok(Self::Lanczos3),
+            other => Err(anyhow::anyhow!(
+                "unknown resize filter '{other}' (expected nearest, triangle, catmull or lanczos3)"
+            )),
+        }
+    }
+}
+
+/// Encoding of the generated thumbnail file.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ThumbnailFormat {
+    #[default]
+    Jpeg,
+    /// Lossy WebP at the JPEG quality setting; smaller than JPEG for the same detail.
+    Webp,
+    /// Resized animated GIF, kept frame-for-frame from gifsicle. Selected through the
+    /// `animated` flag rather than `format`, so it is not deserializable.
+    #[serde(skip)]
+    Gif,
+}
+
+impl ThumbnailFormat {
+    pub fn extension(self) -> &'static str {
+        match self {
+            ThumbnailFormat::Jpeg => "jpg",
+            ThumbnailFormat::Webp => "webp",
+            ThumbnailFormat::Gif => "gif",
+        }
+    }
+
+    pub fn media_type(self) -> &'static str {
+        match self {
+            ThumbnailFormat::Jpeg => "image/jpeg",
+            ThumbnailFormat::Webp => "image/webp",
+            ThumbnailFormat::Gif => "image/gif",
+        }
+    }
+
+    fn image_format(self) -> ImageFormat {
+        match self {
+            ThumbnailFormat::Jpeg => ImageFormat::Jpeg,
+            ThumbnailFormat::Webp => ImageFormat::WebP,
+            ThumbnailFormat::Gif => ImageFormat::Gif,
+        }
+    }
+}
+
 /// Describes the thumbnail artifact generated for a media file.
 #[allow(dead_code)]
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -66,13 +180,114 @@ pub struct ThumbnailSpec {
     pub media_type: MediaType,
 }
 
+/// Raised when the external renderer a media type depends on is not installed.
+#[derive(Debug, thiserror::Error)]
+#[error("{tool} is required to render {media_type:?} thumbnails but was not found")]
+pub struct MissingRenderer {
+    pub tool: PathBuf,
+    pub media_type: MediaType,
+}
+
+/// Fail with [`MissingRenderer`] before spawning `tool` if it is not installed, so
+/// callers can tell an absent dependency apart from a file it could not render.
+fn require_tool(tool: &Path, media_type: MediaType) -> Result<()> {
+    if which::which(tool).is_err() {
+        return Err(MissingRenderer {
+            tool: tool.to_path_buf(),
+            media_type,
+        }
+        .into());
+    }
+    Ok(())
+}
+
+/// Single-flight registry so concurrent requests for the same thumbnail wait on one
+/// generation instead of each decoding the source and racing on the output path.
+#[derive(Debug, Default)]
+pub struct InFlightThumbnails {
+    flights: std::sync::Mutex<HashMap<PathBuf, Arc<Mutex<()>>>>,
+    generated: AtomicUsize,
+}
+
+impl InFlightThumbnails {
+    /// Number of thumbnails actually generated (as opposed to served from disk).
+    pub fn generated(&self) -> usize {
+        self.generated.load(Ordering::Relaxed)
+    }
+
+    /// Join the flight for `target`, starting one if none is in the air.
+    fn board<'a>(&'a self, target: &'a Path) -> FlightGuard<'a> {
+        let flight = self
+            .flights
+            .lock()
+            .expect("thumbnail flight map poisoned")
+            .entry(target.to_path_buf())
+            .or_default()
+            .clone();
+        FlightGuard {
+            inflight: self,
+            target,
+            flight: Some(flight),
+        }
+    }
+
+    /// Every flight handle is cloned and dropped under the map lock, so a count of one
+    /// means nobody is generating or waiting and the entry can go. Removing it earlier
+    /// would let a newcomer start a second flight beside waiters still on this one.
+    fn land(&self, target: &Path, flight: Arc<Mutex<()>>) {
+        let mut flights = self.flights.lock().expect("thumbnail flight map poisoned");
+        drop(flight);
+        if flights
+            .get(target)
+            .is_some_and(|flight| Arc::strong_count(flight) == 1)
+        {
+            flights.remove(target);
+        }
+    }
+}
+
+/// A caller's seat on a flight; leaving it, successful or not, lands the flight once
+/// the last caller is gone.
+struct FlightGuard<'a> {
+    inflight: &'a InFlightThumbnails,
+    target: &'a Path,
+    flight: Option<Arc<Mutex<()>>>,
+}
+
+impl FlightGuard<'_> {
+    async fn lock(&self) -> tokio::sync::MutexGuard<'_, ()> {
+        self.flight
+            .as_ref()
+            .expect("flight is present until drop")
+            .lock()
+            .await
+    }
+}
+
+impl Drop for FlightGuard<'_> {
+    fn drop(&mut self) {
+        if let Some(flight) = self.flight.take() {
+            self.inflight.land(self.target, flight);
+        }
+    }
+}
+
 /// Coordinates on-disk thumbnail generation for images, GIFs, and videos.
 #[allow(dead_code)]
 pub struct ThumbnailGenerator {
     cache_dir: PathBuf,
     ffmpeg_path: PathBuf,
     gifsicle_path: PathBuf,
+    pdftoppm_path: PathBuf,
+    /// Render a waveform for audio files without embedded cover art.
+    audio_waveform: bool,
+    /// 1–100; applies to JPEGs and WebPs encoded here, not to ffmpeg's video poster frames.
+    jpeg_quality: u8,
+    resize_filter: ResizeFilter,
     timeout: Duration,
+    /// Shared cap on concurrent generations; cache hits never wait on it.
+    permits: Option<Arc<Semaphore>>,
+    inflight: Arc<InFlightThumbnails>,
 }
 
 #[allow(dead_code)]
@@ -82,7 +297,13 @@ impl ThumbnailGenerator {
             cache_dir: cache_dir.into(),
             ffmpeg_path: PathBuf::from("ffmpeg"),
             gifsicle_path: PathBuf::from("gifsicle"),
+            pdftoppm_path: PathBuf::from("pdftoppm"),
+            audio_waveform: true,
+            jpeg_quality: DEFAULT_JPEG_QUALITY,
+            resize_filter: ResizeFilter::default(),
             timeout: DEFAULT_TIMEOUT,
+            permits: None,
+            inflight: Arc::default(),
         }
     }
 
@@ -96,16 +317,58 @@ impl ThumbnailGenerator {
         self
     }
 
+    /// Poppler's `pdftoppm`, used to rasterize the first page of PDFs.
+    pub fn with_pdf_renderer(mut self, pdftoppm_path: impl Into<PathBuf>) -> Self {
+        self.pdftoppm_path = pdftoppm_path.into();
+        self
+    }
+
+    /// When disabled, audio thumbnails only come from embedded cover art.
+    pub fn with_audio_waveform(mut self, enabled: bool) -> Self {
+        self.audio_waveform = enabled;
+        self
+    }
+
+    /// JPEG and WebP quality, clamped to 1–100; lower values trade detail for smaller files.
+    pub fn with_jpeg_quality(mut self, quality: u8) -> Self {
+        self.jpeg_quality = quality.clamp(1, 100);
+        self
+    }
+
+    pub fn with_resize_filter(mut self, filter: ResizeFilter) -> Self {
+        self.resize_filter = filter;
+        self
+    }
+
     pub fn with_timeout(mut self, timeout: Duration) -> Self {
         self.timeout = timeout;
         self
     }
 
+    pub fn with_permits(mut self, permits: Arc<Semaphore>) -> Self {
+        self.permits = Some(permits);
+        self
+    }
+
+    /// Share in-flight deduplication across generator instances (e.g. per request).
+    pub fn with_inflight(mut self, inflight: Arc<InFlightThumbnails>) -> Self {
+        self.inflight = inflight;
+        self
+    }
+
+    /// Encoding settings baked into generated files, e.g. `catmull-q75`. Part of the cache
+    /// file name so changing them regenerates thumbnails instead of serving old ones;
+    /// files left under a previous variant age out through the cache budget.
+    pub fn variant(&self) -> String {
+        format!("{}-q{}", self.resize_filter.as_str(), self.jpeg_quality)
+    }
+
     /// Ensure a thumbnail exists on disk, generating it if missing. Returns the artifact metadata.
-    #[instrument(skip(self, spec, size), err(Debug), fields(
+    #[instrument(skip(self, spec, size, format), err(Debug), fields(
             galarie.media.id = %spec.media_id,
             galarie.media.type = ?spec.media_type,
             galarie.thumbnail.size = ?size,
+            galarie.thumbnail.format = ?format,
             galarie.thumbnail.path,
             galarie.thumbnail.cached,
     ))]
@@ -113,28 +376,46 @@ impl ThumbnailGenerator {
         &self,
         spec: &ThumbnailSpec,
         size: ThumbnailSize,
+        format: ThumbnailFormat,
     ) -> Result<ThumbnailArtifact> {
-        let (target_path, relative_path) = self.thumbnail_paths(&spec.media_id, size);
+        // The id becomes a file name below, so never let it carry path components.
+        if !crate::indexer::is_valid_media_id(&spec.media_id) {
+            anyhow::bail!(
+                "refusing to build a thumbnail path for media id {:?}",
+                spec.media_id
+            );
+        }
+        let (target_path, relative_path) = self.thumbnail_paths(&spec.media_id, size, format);
         tracing::Span::current()
-            .record("galarie.thumbnail.path", &target_path.display().to_string());
+            .record("galarie.thumbnail.path", target_path.display().to_string());
         // Specifying default value in instrument macro and updating results in duplicate fields.
         tracing::Span::current().record("galarie.thumbnail.cached", false);
 
-        if tokio::fs::try_exists(&target_path).await.with_context(|| {
-            format!(
-                "Failed to check existance of {} for thumbnail",
-                target_path.display()
-            )
-        })? {
+        if is_cached(&target_path, &spec.source_path).await? {
             tracing::Span::current().record("galarie.thumbnail.cached", true);
-            return Ok(ThumbnailArtifact {
-                relative_path,
-                media_type: "image/jpeg",
-                width: size.as_dimensions().0,
-                height: size.as_dimensions().1,
-            });
+            return describe_artifact(&target_path, relative_path, format).await;
         }
 
+        let flight = self.inflight.board(&target_path);
+        let _flight = flight.lock().await;
+        // Another caller may have generated it while we waited on the flight.
+        if is_cached(&target_path, &spec.source_path).await? {
+            tracing::Span::current().record("galarie.thumbnail.cached", true);
+            return describe_artifact(&target_path, relative_path, format).await;
+        }
+
+        // Queue behind other generations rather than forking unbounded ffmpeg/gifsicle runs.
+        let _permit = match &self.permits {
+            Some(permits) => Some(
+                permits
+                    .clone()
+                    .acquire_owned()
+                    .await
+                    .context("thumbnail semaphore closed")?,
+            ),
+            None => None,
+        };
+
         if let Some(parent) = target_path.parent() {
             tokio::fs::create_dir_all(parent)
                 .await
@@ -142,64 +423,82 @@ impl ThumbnailGenerator {
         }
 
         match spec.media_type {
-            MediaType::Image | MediaType::Pdf => {
-                self.generate_static_thumbnail(&spec.source_path, &target_path, size)
+            MediaType::Image => {
+                self.generate_static_thumbnail(&spec.source_path, &target_path, size, format)
+                    .await?;
+            }
+            MediaType::Pdf => {
+                self.generate_pdf_thumbnail(&spec.source_path, &target_path, size, format)
                     .await?;
             }
             MediaType::Gif => {
-                self.generate_gif_thumbnail(&spec.source_path, &target_path, size)
+                self.generate_gif_thumbnail(&spec.source_path, &target_path, size, format)
                     .await?;
             }
             MediaType::Video => {
-                self.generate_video_thumbnail(&spec.source_path, &target_path, size)
+                self.generate_video_thumbnail(&spec.source_path, &target_path, size, format)
+                    .await?;
+            }
+            MediaType::Audio => {
+                self.generate_audio_thumbnail(&spec.source_path, &target_path, size, format)
                     .await?;
             }
             _ => {
                 // fallback to static thumbnail logic
-                self.generate_static_thumbnail(&spec.source_path, &target_path, size)
+                self.generate_static_thumbnail(&spec.source_path, &target_path, size, format)
                     .await?;
             }
         }
+        self.inflight.generated.fetch_add(1, Ordering::Relaxed);
 
-        Ok(ThumbnailArtifact {
-            relative_path,
-            media_type: "image/jpeg",
-            width: size.as_dimensions().0,
-            height: size.as_dimensions().1,
-        })
+        describe_artifact(&target_path, relative_path, format).await
     }
 
-    fn thumbnail_paths(&self, media_id: &str, size: ThumbnailSize) -> (PathBuf, PathBuf) {
+    fn thumbnail_paths(
+        &self,
+        media_id: &str,
+        size: ThumbnailSize,
+        format: ThumbnailFormat,
+    ) -> (PathBuf, PathBuf) {
         let relative = PathBuf::from(THUMBNAIL_ROOT)
             .join(size.as_dir())
-            .join(format!("{media_id}{THUMBNAIL_EXT}"));
+            .join(format!(
+                "{media_id}.{}.{}",
+                self.variant(),
+                format.extension()
+            ));
         (self.cache_dir.join(&relative), relative)
     }
 
-    #[instrument(skip(self, source, target, size), err(Debug))]
+    #[instrument(skip(self, source, target, size, format), err(Debug))]
     async fn generate_static_thumbnail(
         &self,
         source: &Path,
         target: &Path,
         size: ThumbnailSize,
+        format: ThumbnailFormat,
     ) -> Result<()> {
         let source = source.to_owned();
         let target = target.to_owned();
         let (width, height) = size.as_dimensions();
+        let (filter, quality) = (self.resize_filter, self.jpeg_quality);
         task::spawn_blocking(move || -> Result<()> {
             let reader = ImageReader::open(&source)
                 .and_then(|r| r.with_guessed_format())
                 .with_context(|| format!("failed to open image {source:?}"))?;
             let img = reader.decode().context("failed to decode image")?;
-            let resized = resize_image(img, width, height);
-            save_as_jpeg(resized, &target)?;
+            let resized = resize_image(img, width, height, filter);
+            // Write aside and rename so readers never observe a partially written file.
+            let tmp_path = target.with_extension(format!("tmp.{}", format.extension()));
+            save_thumbnail(resized, &tmp_path, format, quality)?;
+            std::fs::rename(&tmp_path, &target).context("failed to move thumbnail into place")?;
             Ok(())
         })
         .await??;
         Ok(())
     }
 
-    #[instrument(skip(self, source, target, size), err(Debug), fields(
+    #[instrument(skip(self, source, target, size, format), err(Debug), fields(
             galarie.thumbnail.generate_command,
     ))]
     async fn generate_gif_thumbnail(
@@ -207,7 +506,9 @@ impl ThumbnailGenerator {
         source: &Path,
         target: &Path,
         size: ThumbnailSize,
+        format: ThumbnailFormat,
     ) -> Result<()> {
+        require_tool(&self.gifsicle_path, MediaType::Gif)?;
         let (width, height) = size.as_dimensions();
         let output_tmp = target.with_extension("gif.tmp");
 
@@ -222,7 +523,7 @@ impl ThumbnailGenerator {
 
         tracing::Span::current().record(
             "galarie.thumbnail.generate_command",
-            &format!("{:?}", command),
+            format!("{:?}", command),
         );
 
         let status = timeout(self.timeout, command.status())
@@ -232,14 +533,184 @@ impl ThumbnailGenerator {
         if !status.success() {
             anyhow::bail!("gifsicle failed to process {:?}", source);
         }
-        // Convert the GIF output to JPEG for consistency.
-        self.generate_static_thumbnail(&output_tmp, target, size)
+        if format == ThumbnailFormat::Gif {
+            tokio::fs::rename(&output_tmp, target).await?;
+            return Ok(());
+        }
+        // Convert the GIF output to the requested still format for consistency.
+        self.generate_static_thumbnail(&output_tmp, target, size, format)
             .await?;
         tokio::fs::remove_file(output_tmp).await.ok();
         Ok(())
     }
 
-    #[instrument(skip(self, source, target, size), err(Debug), fields(
+    #[instrument(skip(self, source, target, size, format), err(Debug), fields(
+            galarie.thumbnail.generate_command,
+    ))]
+    async fn generate_pdf_thumbnail(
+        &self,
+        source: &Path,
+        target: &Path,
+        size: ThumbnailSize,
+        format: ThumbnailFormat,
+    ) -> Result<()> {
+        require_tool(&self.pdftoppm_path, MediaType::Pdf)?;
+
+        let (width, height) = size.as_dimensions();
+        // pdftoppm appends `.png` to this prefix when `-singlefile` is set.
+        let output_prefix = target.with_extension("page");
+        let output_tmp = target.with_extension("page.png");
+
+        let mut command = Command::new(&self.pdftoppm_path);
+        command
+            .arg("-f")
+            .arg("1")
+            .arg("-l")
+            .arg("1")
+            .arg("-singlefile")
+            .arg("-png")
+            .arg("-scale-to")
+            .arg(width.max(height).to_string())
+            .arg(source)
+            .arg(&output_prefix);
+
+        tracing::Span::current().record(
+            "galarie.thumbnail.generate_command",
+            format!("{:?}", command),
+        );
+
+        let status = timeout(self.timeout, command.status())
+            .await
+            .context("pdftoppm timed out")?
+            .context("pdftoppm failed to start")?;
+        if !status.success() {
+            anyhow::bail!("pdftoppm failed to render first page of {:?}", source);
+        }
+
+        let result = self
+            .generate_static_thumbnail(&output_tmp, target, size, format)
+            .await;
+        tokio::fs::remove_file(output_tmp).await.ok();
+        result
+    }
+
+    /// Prefer embedded cover art; otherwise draw a waveform unless that fallback is disabled.
+    #[instrument(skip(self, source, target, size, format), err(Debug))]
+    async fn generate_audio_thumbnail(
+        &self,
+        source: &Path,
+        target: &Path,
+        size: ThumbnailSize,
+        format: ThumbnailFormat,
+    ) -> Result<()> {
+        require_tool(&self.ffmpeg_path, MediaType::Audio)?;
+        let cover_tmp = target.with_extension("cover");
+        let cover = self.extract_cover_art(source, &cover_tmp).await;
+        let raster_tmp = match cover {
+            Ok(()) => cover_tmp,
+            Err(err) if self.audio_waveform => {
+                tracing::debug!(error = ?err, "no usable cover art, rendering waveform");
+                tokio::fs::remove_file(&cover_tmp).await.ok();
+                let waveform_tmp = target.with_extension("waveform.png");
+                self.render_waveform(source, &waveform_tmp, size).await?;
+                waveform_tmp
+            }
+            Err(err) => {
+                tokio::fs::remove_file(&cover_tmp).await.ok();
+                return Err(err.context("audio file has no embedded cover art"));
+            }
+        };
+
+        let result = self
+            .generate_static_thumbnail(&raster_tmp, target, size, format)
+            .await;
+        tokio::fs::remove_file(raster_tmp).await.ok();
+        result
+    }
+
+    /// Copy the attached picture stream out as-is; the image decoder sniffs its format.
+    async fn extract_cover_art(&self, source: &Path, output: &Path) -> Result<()> {
+        let mut command = Command::new(&self.ffmpeg_path);
+        command
+            .arg("-hide_banner")
+            .arg("-loglevel")
+            .arg("error")
+            .arg("-y")
+            .arg("-i")
+            .arg(source)
+            .arg("-map")
+            .arg("0:v:0")
+            .arg("-an")
+            .arg("-vcodec")
+            .arg("copy")
+            .arg("-frames:v")
+            .arg("1")
+            .arg("-f")
+            .arg("image2")
+            .arg(output)
+            .kill_on_drop(true);
+
+        let status = timeout(self.timeout, command.status())
+            .await
+            .context("ffmpeg timed out")?
+            .context("ffmpeg failed to start. command may not exists")?;
+        if !status.success() {
+            anyhow::bail!("ffmpeg found no cover art in {:?}", source);
+        }
+        Ok(())
+    }
+
+    async fn render_waveform(
+        &self,
+        source: &Path,
+        output: &Path,
+        size: ThumbnailSize,
+    ) -> Result<()> {
+        let mut command = Command::new(&self.ffmpeg_path);
+        command
+            .arg("-hide_banner")
+            .arg("-loglevel")
+            .arg("error")
+            .arg("-i")
+            .arg(source)
+            .arg("-t")
+            .arg(WAVEFORM_MAX_SECONDS.to_string())
+            .arg("-vn")
+            .arg("-ac")
+            .arg("1")
+            .arg("-ar")
+            .arg(WAVEFORM_SAMPLE_RATE.to_string())
+            .arg("-f")
+            .arg("s16le")
+            .arg("-")
+            .stdin(std::process::Stdio::null())
+            .kill_on_drop(true);
+
+        let decoded = timeout(self.timeout, command.output())
+            .await
+            .context("ffmpeg timed out")?
+            .context("ffmpeg failed to start. command may not exists")?;
+        if !decoded.status.success() {
+            anyhow::bail!("ffmpeg failed to decode audio from {:?}", source);
+        }
+
+        let samples: Vec<i16> = decoded
+            .stdout
+            .chunks_exact(2)
+            .map(|pair| i16::from_le_bytes([pair[0], pair[1]]))
+            .collect();
+        let (width, height) = size.as_dimensions();
+        let output = output.to_owned();
+        task::spawn_blocking(move || -> Result<()> {
+            draw_waveform(&samples, width, height / 2)
+                .save_with_format(&output, ImageFormat::Png)
+                .context("failed to write waveform")
+        })
+        .await??;
+        Ok(())
+    }
+
+    #[instrument(skip(self, source, target, size, format), err(Debug), fields(
             galarie.thumbnail.generate_command,
     ))]
     async fn generate_video_thumbnail(
@@ -247,7 +718,9 @@ impl ThumbnailGenerator {
         source: &Path,
         target: &Path,
         size: ThumbnailSize,
+        format: ThumbnailFormat,
     ) -> Result<()> {
+        require_tool(&self.ffmpeg_path, MediaType::Video)?;
         let (width, height) = size.as_dimensions();
         let scale_filter = format!(
             "scale=w={width}:h={height}:force_original_aspect_ratio=decrease,pad={width}:{height}:(ow-iw)/2:(oh-ih)/2"
@@ -270,7 +743,7 @@ impl ThumbnailGenerator {
 
         tracing::Span::current().record(
             "galarie.thumbnail.generate_command",
-            &format!("{:?}", command),
+            format!("{:?}", command),
         );
 
         let status = timeout(self.timeout, command.status())
@@ -282,21 +755,159 @@ impl ThumbnailGenerator {
             anyhow::bail!("ffmpeg failed to generate poster frame for {:?}", source);
         }
 
-        tokio::fs::rename(&tmp_path, target).await?;
+        match format {
+            ThumbnailFormat::Jpeg => tokio::fs::rename(&tmp_path, target).await?,
+            _ => {
+                // ffmpeg emits a JPEG poster frame; re-encode it into the requested format.
+                let result = self
+                    .generate_static_thumbnail(&tmp_path, target, size, format)
+                    .await;
+                tokio::fs::remove_file(&tmp_path).await.ok();
+                result?;
+            }
+        }
         Ok(())
     }
 }
 
-#[allow(dead_code)]
-fn resize_image(img: DynamicImage, width: u32, height: u32) -> DynamicImage {
-    img.resize(width, height, FilterType::CatmullRom)
+/// A thumbnail older than its source is stale: media ids survive in-place edits, so the
+/// path alone cannot tell an edited file from the one the thumbnail was made from.
+async fn is_cached(target: &Path, source: &Path) -> Result<bool> {
+    let thumbnail = match tokio::fs::metadata(target).await {
+        Ok(metadata) => metadata,
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(false),
+        Err(err) => {
+            return Err(err).with_context(|| {
+                format!(
+                    "Failed to check existance of {} for thumbnail",
+                    target.display()
+                )
+            });
+        }
+    };
+    // Without a readable source there is nothing newer to render; keep serving the cache.
+    let source_modified = tokio::fs::metadata(source)
+        .await
+        .and_then(|metadata| metadata.modified());
+    Ok(match (source_modified, thumbnail.modified()) {
+        (Ok(source), Ok(thumbnail)) => thumbnail >= source,
+        _ => true,
+    })
 }
 
-#[allow(dead_code)]
-fn save_as_jpeg(image: DynamicImage, target: &Path) -> Result<()> {
-    image
-        .save_with_format(target, ImageFormat::Jpeg)
-        .context("failed to write jpeg thumbnail")
+/// Read the actual output dimensions, which may be smaller than the size box.
+async fn describe_artifact(
+    target: &Path,
+    relative_path: PathBuf,
+    format: ThumbnailFormat,
+) -> Result<ThumbnailArtifact> {
+    let path = target.to_owned();
+    let (width, height) = task::spawn_blocking(move || image::image_dimensions(&path))
+        .await?
+        .with_context(|| format!("failed to read dimensions of {}", target.display()))?;
+    Ok(ThumbnailArtifact {
+        relative_path,
+        media_type: format.media_type(),
+        width,
+        height,
+    })
+}
+
+/// Fit the image into the box, preserving aspect ratio; images already inside it are kept as-is.
+fn resize_image(img: DynamicImage, width: u32, height: u32, filter: ResizeFilter) -> DynamicImage {
+    if img.width() <= width && img.height() <= height {
+        return img;
+    }
+    img.resize(width, height, filter.filter_type())
+}
+
+/// Draw one peak-amplitude bar per column, mirrored around the horizontal center line.
+fn draw_waveform(samples: &[i16], width: u32, height: u32) -> image::RgbImage {
+    const BACKGROUND: image::Rgb<u8> = image::Rgb([24, 24, 32]);
+    const FOREGROUND: image::Rgb<u8> = image::Rgb([96, 165, 250]);
+
+    let mut canvas = image::RgbImage::from_pixel(width, height, BACKGROUND);
+    let center = height / 2;
+    let chunk = samples.len().div_ceil(width as usize).max(1);
+    for (x, column) in samples.chunks(chunk).enumerate().take(width as usize) {
+        let peak = column
+            .iter()
+            .map(|sample| sample.unsigned_abs())
+            .max()
+            .unwrap_or(0);
+        let half = (u32::from(peak) * center / u32::from(i16::MAX.unsigned_abs())).max(1);
+        for y in center.saturating_sub(half)..(center + half).min(height) {
+            canvas.put_pixel(x as u32, y, FOREGROUND);
+        }
+    }
+    canvas
+}
+
+/// PNG tile standing in for a thumbnail that could not be rendered: a flat square in a
+/// per-media-type color with a lighter card in the middle as a generic file icon.
+pub fn placeholder_png(media_type: &MediaType, size: ThumbnailSize) -> Result<Vec<u8>> {
+    let background = match media_type {
+        MediaType::Image | MediaType::Gif => image::Rgb([71, 85, 105]),
+        MediaType::Video => image::Rgb([88, 28, 135]),
+        MediaType::Audio => image::Rgb([22, 101, 52]),
+        MediaType::Pdf => image::Rgb([153, 27, 27]),
+        MediaType::Unknown => image::Rgb([55, 65, 81]),
+    };
+    const CARD: image::Rgb<u8> = image::Rgb([226, 232, 240]);
+
+    let (width, height) = size.as_dimensions();
+    let mut canvas = image::RgbImage::from_pixel(width, height, background);
+    let (card_w, card_h) = (width * 3 / 8, height / 2);
+    let (left, top) = ((width - card_w) / 2, (height - card_h) / 2);
+    for y in top..top + card_h {
+        for x in left..left + card_w {
+            canvas.put_pixel(x, y, CARD);
+        }
+    }
+
+    let mut bytes = std::io::Cursor::new(Vec::new());
+    DynamicImage::ImageRgb8(canvas)
+        .write_to(&mut bytes, ImageFormat::Png)
+        .context("failed to encode placeholder thumbnail")?;
+    Ok(bytes.into_inner())
+}
+
+fn save_thumbnail(
+    image: DynamicImage,
+    target: &Path,
+    format: ThumbnailFormat,
+    jpeg_quality: u8,
+) -> Result<()> {
+    // JPEG has no alpha channel, and the WebP encoder only accepts 8-bit RGB(A).
+    match format {
+        ThumbnailFormat::Webp => {
+            let (width, height) = (image.width(), image.height());
+            // Opaque images skip the alpha plane entirely.
+            let encoded = if image.color().has_alpha() {
+                let pixels = image.into_rgba8();
+                webp::Encoder::from_rgba(&pixels, width, height)
+                    .encode_simple(false, f32::from(jpeg_quality))
+            } else {
+                let pixels = image.into_rgb8();
+                webp::Encoder::from_rgb(&pixels, width, height)
+                    .encode_simple(false, f32::from(jpeg_quality))
+            }
+            .map_err(|err| anyhow::anyhow!("failed to encode webp thumbnail: {err:?}"))?;
+            std::fs::write(target, &*encoded).context("failed to write webp thumbnail")
+        }
+        ThumbnailFormat::Jpeg => {
+            let file = std::fs::File::create(target)
+                .with_context(|| format!("failed to create {}", target.display()))?;
+            let mut writer = std::io::BufWriter::new(file);
+            DynamicImage::ImageRgb8(image.into_rgb8())
+                .write_with_encoder(JpegEncoder::new_with_quality(&mut writer, jpeg_quality))
+                .context("failed to write jpg thumbnail")?;
+            writer.flush().context("failed to write jpg thumbnail")
+        }
+        ThumbnailFormat::Gif => DynamicImage::ImageRgba8(image.into_rgba8())
+            .save_with_format(target, format.image_format())
+            .with_context(|| format!("failed to write {} thumbnail", format.extension())),
+    }
 }
 
 #[cfg(test)]
@@ -337,7 +948,7 @@ mod tests {
             media_type: MediaType::Image,
         };
         let artifact = generator
-            .ensure_thumbnail(&spec, ThumbnailSize::Small)
+            .ensure_thumbnail(&spec, ThumbnailSize::Small, ThumbnailFormat::Jpeg)
             .await?;
         let final_path = dir.path().join(&artifact.relative_path);
         assert!(tokio::fs::try_exists(&final_path).await?);
@@ -345,6 +956,267 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn resize_keeps_small_images_at_native_size() {
+        let small = DynamicImage::new_rgb8(50, 30);
+        let resized = resize_image(small, 160, 160, ResizeFilter::default());
+        assert_eq!((resized.width(), resized.height()), (50, 30));
+
+        let large = DynamicImage::new_rgb8(800, 400);
+        let resized = resize_image(large, 160, 160, ResizeFilter::default());
+        assert_eq!((resized.width(), resized.height()), (160, 80));
+    }
+
+    #[test]
+    fn parses_each_resize_filter() -> Result<()> {
+        for (name, filter) in [
+            ("nearest", ResizeFilter::Nearest),
+            ("triangle", ResizeFilter::Triangle),
+            ("catmull", ResizeFilter::CatmullRom),
+            ("Lanczos3", ResizeFilter::Lanczos3),
+        ] {
+            assert_eq!(name.parse::<ResizeFilter>()?, filter);
+            let resized = resize_image(DynamicImage::new_rgb8(800, 400), 160, 160, filter);
+            assert_eq!((resized.width(), resized.height()), (160, 80));
+        }
+        assert!("bicubic".parse::<ResizeFilter>().is_err());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn lower_jpeg_quality_yields_smaller_thumbnails() -> Result<()> {
+        let dir = tempdir()?;
+        let spec = ThumbnailSpec {
+            media_id: "png-fixture".into(),
+            source_path: fixture("sunset_coast+location-okinawa_rating-5.png"),
+            media_type: MediaType::Image,
+        };
+        let mut sizes = Vec::new();
+        for quality in [95, 20] {
+            let cache_dir = dir.path().join(format!("q{quality}"));
+            let artifact = ThumbnailGenerator::new(&cache_dir)
+                .with_jpeg_quality(quality)
+                .ensure_thumbnail(&spec, ThumbnailSize::Large, ThumbnailFormat::Jpeg)
+                .await?;
+            sizes.push(std::fs::metadata(cache_dir.join(&artifact.relative_path))?.len());
+        }
+        assert!(
+            sizes[1] < sizes[0],
+            "quality 20 should be smaller: {sizes:?}"
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn webp_thumbnails_are_smaller_than_jpeg() -> Result<()> {
+        let dir = tempdir()?;
+        let generator = ThumbnailGenerator::new(dir.path());
+        let spec = ThumbnailSpec {
+            media_id: "png-fixture".into(),
+            source_path: fixture("sunset_coast+location-okinawa_rating-5.png"),
+            media_type: MediaType::Image,
+        };
+        let mut sizes = Vec::new();
+        for format in [ThumbnailFormat::Jpeg, ThumbnailFormat::Webp] {
+            let artifact = generator
+                .ensure_thumbnail(&spec, ThumbnailSize::Large, format)
+                .await?;
+            let path = dir.path().join(&artifact.relative_path);
+            assert_thumbnail(&path, ThumbnailSize::Large)?;
+            sizes.push(std::fs::metadata(path)?.len());
+        }
+        assert!(sizes[1] < sizes[0], "webp should be smaller: {sizes:?}");
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn encoding_settings_key_the_cache_path() -> Result<()> {
+        let dir = tempdir()?;
+        let spec = ThumbnailSpec {
+            media_id: "png-fixture".into(),
+            source_path: fixture("sunset_coast+location-okinawa_rating-5.png"),
+            media_type: MediaType::Image,
+        };
+        let generators = [
+            ThumbnailGenerator::new(dir.path()),
+            ThumbnailGenerator::new(dir.path()).with_jpeg_quality(40),
+            ThumbnailGenerator::new(dir.path()).with_resize_filter(ResizeFilter::Nearest),
+        ];
+        let mut paths = Vec::new();
+        for generator in &generators {
+            let artifact = generator
+                .ensure_thumbnail(&spec, ThumbnailSize::Small, ThumbnailFormat::Jpeg)
+                .await?;
+            assert!(dir.path().join(&artifact.relative_path).exists());
+            paths.push(artifact.relative_path);
+        }
+        assert_eq!(
+            paths,
+            [
+                "thumbnails/small/png-fixture.catmull-q75.jpg",
+                "thumbnails/small/png-fixture.catmull-q40.jpg",
+                "thumbnails/small/png-fixture.nearest-q75.jpg",
+            ]
+            .map(PathBuf::from)
+        );
+        // Each setting was rendered rather than served from another's file.
+        assert_eq!(generators[0].inflight.generated(), 1);
+        assert_eq!(generators[1].inflight.generated(), 1);
+        assert_eq!(generators[2].inflight.generated(), 1);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn artifact_reports_actual_output_dimensions() -> Result<()> {
+        let dir = tempdir()?;
+        let source = dir.path().join("tiny.png");
+        DynamicImage::new_rgb8(50, 30).save(&source)?;
+        let generator = ThumbnailGenerator::new(dir.path().join("cache"));
+        let spec = ThumbnailSpec {
+            media_id: "tiny".into(),
+            source_path: source,
+            media_type: MediaType::Image,
+        };
+
+        for _ in 0..2 {
+            // Second pass is served from disk and must report the same dimensions.
+            let artifact = generator
+                .ensure_thumbnail(&spec, ThumbnailSize::Large, ThumbnailFormat::Jpeg)
+                .await?;
+            assert_eq!((artifact.width, artifact.height), (50, 30));
+        }
+
+        let spec = ThumbnailSpec {
+            media_id: "png-fixture".into(),
+            source_path: fixture("sunset_coast+location-okinawa_rating-5.png"),
+            media_type: MediaType::Image,
+        };
+        let (source_w, source_h) = image::image_dimensions(&spec.source_path)?;
+        let artifact = generator
+            .ensure_thumbnail(&spec, ThumbnailSize::Small, ThumbnailFormat::Jpeg)
+            .await?;
+        assert_eq!(artifact.width.max(artifact.height), 160);
+        let expected_ratio = source_w as f64 / source_h as f64;
+        let actual_ratio = artifact.width as f64 / artifact.height as f64;
+        assert!((expected_ratio - actual_ratio).abs() < 0.05);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn rejects_media_ids_with_path_components() -> Result<()> {
+        let dir = tempdir()?;
+        let generator = ThumbnailGenerator::new(dir.path().join("cache"));
+        let spec = ThumbnailSpec {
+            media_id: "../../escaped".into(),
+            source_path: fixture("sunset_coast+location-okinawa_rating-5.png"),
+            media_type: MediaType::Image,
+        };
+        let result = generator
+            .ensure_thumbnail(&spec, ThumbnailSize::Small, ThumbnailFormat::Jpeg)
+            .await;
+        assert!(result.is_err());
+        assert!(!dir.path().join("escaped.jpg").exists());
+        assert!(!dir.path().join("cache").exists());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn concurrent_requests_share_one_generation() -> Result<()> {
+        let dir = tempdir()?;
+        let inflight = Arc::new(InFlightThumbnails::default());
+        let spec = ThumbnailSpec {
+            media_id: "png-fixture".into(),
+            source_path: fixture("sunset_coast+location-okinawa_rating-5.png"),
+            media_type: MediaType::Image,
+        };
+
+        let mut calls = task::JoinSet::new();
+        for _ in 0..10 {
+            let generator = ThumbnailGenerator::new(dir.path()).with_inflight(inflight.clone());
+            let spec = spec.clone();
+            calls.spawn(async move {
+                generator
+                    .ensure_thumbnail(&spec, ThumbnailSize::Small, ThumbnailFormat::Jpeg)
+                    .await
+            });
+        }
+        while let Some(result) = calls.join_next().await {
+            let artifact = result??;
+            assert_thumbnail(
+                &dir.path().join(artifact.relative_path),
+                ThumbnailSize::Small,
+            )?;
+        }
+
+        assert_eq!(inflight.generated(), 1);
+        assert!(inflight.flights.lock().unwrap().is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn waiters_keep_a_failed_flight_for_newcomers() {
+        let inflight = InFlightThumbnails::default();
+        let target = Path::new("thumbnails/small/id.jpg");
+        let seat = |guard: &FlightGuard<'_>| guard.flight.clone().unwrap();
+
+        let first = inflight.board(target);
+        let waiter = inflight.board(target);
+        assert!(Arc::ptr_eq(&seat(&first), &seat(&waiter)));
+        // The first generation fails while the waiter is still queued on its flight.
+        drop(first);
+        let newcomer = inflight.board(target);
+        assert!(
+            Arc::ptr_eq(&seat(&waiter), &seat(&newcomer)),
+            "the newcomer queues behind the waiter instead of generating beside it"
+        );
+
+        drop(waiter);
+        drop(newcomer);
+        assert!(inflight.flights.lock().unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn retries_after_failed_concurrent_generation() -> Result<()> {
+        let dir = tempdir()?;
+        let source = dir.path().join("broken.png");
+        std::fs::write(&source, b"not a png")?;
+        let inflight = Arc::new(InFlightThumbnails::default());
+        let spec = ThumbnailSpec {
+            media_id: "retry-fixture".into(),
+            source_path: source.clone(),
+            media_type: MediaType::Image,
+        };
+        let cache = dir.path().join("cache");
+
+        let mut calls = task::JoinSet::new();
+        for _ in 0..4 {
+            let generator = ThumbnailGenerator::new(&cache).with_inflight(inflight.clone());
+            let spec = spec.clone();
+            calls.spawn(async move {
+                generator
+                    .ensure_thumbnail(&spec, ThumbnailSize::Small, ThumbnailFormat::Jpeg)
+                    .await
+            });
+        }
+        while let Some(result) = calls.join_next().await {
+            assert!(result?.is_err());
+        }
+        assert!(inflight.flights.lock().unwrap().is_empty());
+
+        std::fs::copy(
+            fixture("sunset_coast+location-okinawa_rating-5.png"),
+            &source,
+        )?;
+        let artifact = ThumbnailGenerator::new(&cache)
+            .with_inflight(inflight.clone())
+            .ensure_thumbnail(&spec, ThumbnailSize::Small, ThumbnailFormat::Jpeg)
+            .await?;
+        assert_thumbnail(&cache.join(artifact.relative_path), ThumbnailSize::Small)?;
+        assert_eq!(inflight.generated(), 1);
+        assert!(inflight.flights.lock().unwrap().is_empty());
+        Ok(())
+    }
+
     #[tokio::test]
     async fn generates_thumbnail_for_gif_with_real_gifsicle() -> Result<()> {
         let Some(gifsicle_path) = find_tool("gifsicle") else {
@@ -363,7 +1235,7 @@ mod tests {
             media_type: MediaType::Gif,
         };
         let artifact = generator
-            .ensure_thumbnail(&spec, ThumbnailSize::Medium)
+            .ensure_thumbnail(&spec, ThumbnailSize::Medium, ThumbnailFormat::Jpeg)
             .await?;
         let final_path = dir.path().join(&artifact.relative_path);
         assert!(tokio::fs::try_exists(&final_path).await?);
@@ -371,6 +1243,184 @@ mod tests {
         Ok(())
     }
 
+    #[tokio::test]
+    async fn keeps_gif_animation_with_real_gifsicle() -> Result<()> {
+        let Some(gifsicle_path) = find_tool("gifsicle") else {
+            eprintln!("skipping animated GIF thumbnail test because gifsicle is not installed");
+            return Ok(());
+        };
+
+        let dir = tempdir()?;
+        let generator = ThumbnailGenerator::new(dir.path())
+            .with_tools("ffmpeg", gifsicle_path)
+            .with_timeout(Duration::from_secs(10));
+        let spec = ThumbnailSpec {
+            media_id: "gif-fixture".into(),
+            source_path: fixture("macro_leaf+subject-nature_rating-4.gif"),
+            media_type: MediaType::Gif,
+        };
+        let artifact = generator
+            .ensure_thumbnail(&spec, ThumbnailSize::Small, ThumbnailFormat::Gif)
+            .await?;
+        assert_eq!(artifact.media_type, "image/gif");
+        assert_eq!(
+            artifact
+                .relative_path
+                .extension()
+                .and_then(|ext| ext.to_str()),
+            Some("gif")
+        );
+
+        let file = std::io::BufReader::new(std::fs::File::open(
+            dir.path().join(&artifact.relative_path),
+        )?);
+        let frames =
+            image::AnimationDecoder::into_frames(image::codecs::gif::GifDecoder::new(file)?)
+                .collect_frames()?;
+        assert!(frames.len() > 1, "expected an animated GIF");
+        Ok(())
+    }
+
+    /// Single-page PDF with a filled rectangle and a valid cross-reference table.
+    fn write_sample_pdf(path: &Path) -> Result<()> {
+        let content = "0.2 0.4 0.8 rg 20 20 160 100 re f";
+        let objects = [
+            "<< /Type /Catalog /Pages 2 0 R >>".to_string(),
+            "<< /Type /Pages /Kids [3 0 R] /Count 1 >>".to_string(),
+            "<< /Type /Page /Parent 2 0 R /MediaBox [0 0 200 140] /Contents 4 0 R >>".to_string(),
+            format!(
+                "<< /Length {} >>\nstream\n{content}\nendstream",
+                content.len()
+            ),
+        ];
+        let mut pdf = String::from("%PDF-1.4\n");
+        let mut offsets = Vec::new();
+        for (index, object) in objects.iter().enumerate() {
+            offsets.push(pdf.len());
+            pdf.push_str(&format!("{} 0 obj\n{object}\nendobj\n", index + 1));
+        }
+        let xref = pdf.len();
+        pdf.push_str(&format!(
+            "xref\n0 {}\n0000000000 65535 f \n",
+            objects.len() + 1
+        ));
+        for offset in offsets {
+            pdf.push_str(&format!("{offset:010} 00000 n \n"));
+        }
+        pdf.push_str(&format!(
+            "trailer\n<< /Size {} /Root 1 0 R >>\nstartxref\n{xref}\n%%EOF\n",
+            objects.len() + 1
+        ));
+        std::fs::write(path, pdf)?;
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn generates_thumbnail_for_pdf_with_real_pdftoppm() -> Result<()> {
+        let Some(pdftoppm_path) = find_tool("pdftoppm") else {
+            eprintln!("skipping PDF thumbnail test because pdftoppm is not installed");
+            return Ok(());
+        };
+
+        let dir = tempdir()?;
+        let source = dir.path().join("sample.pdf");
+        write_sample_pdf(&source)?;
+        let generator = ThumbnailGenerator::new(dir.path().join("cache"))
+            .with_pdf_renderer(pdftoppm_path)
+            .with_timeout(Duration::from_secs(10));
+        let spec = ThumbnailSpec {
+            media_id: "pdf-fixture".into(),
+            source_path: source,
+            media_type: MediaType::Pdf,
+        };
+        let artifact = generator
+            .ensure_thumbnail(&spec, ThumbnailSize::Small, ThumbnailFormat::Jpeg)
+            .await?;
+        let final_path = dir.path().join("cache").join(&artifact.relative_path);
+        assert_thumbnail(&final_path, ThumbnailSize::Small)?;
+        assert_eq!(artifact.width, 160);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn reports_missing_pdf_renderer() -> Result<()> {
+        let dir = tempdir()?;
+        let source = dir.path().join("sample.pdf");
+        write_sample_pdf(&source)?;
+        let generator = ThumbnailGenerator::new(dir.path().join("cache"))
+            .with_pdf_renderer(dir.path().join("no-such-pdftoppm"));
+        let spec = ThumbnailSpec {
+            media_id: "pdf-fixture".into(),
+            source_path: source,
+            media_type: MediaType::Pdf,
+        };
+        let err = generator
+            .ensure_thumbnail(&spec, ThumbnailSize::Small, ThumbnailFormat::Jpeg)
+            .await
+            .unwrap_err();
+        assert!(err.downcast_ref::<MissingRenderer>().is_some(), "{err:?}");
+        Ok(())
+    }
+
+    #[test]
+    fn waveform_bars_follow_sample_peaks() {
+        let mut samples = vec![0i16; 100];
+        samples.extend(std::iter::repeat_n(i16::MAX, 100));
+        let canvas = draw_waveform(&samples, 2, 40);
+        assert_eq!(canvas.dimensions(), (2, 40));
+
+        let filled = |x: u32| {
+            (0..40)
+                .filter(|y| canvas.get_pixel(x, *y) != &image::Rgb([24, 24, 32]))
+                .count()
+        };
+        assert_eq!(filled(0), 2, "silence draws a hairline");
+        assert_eq!(filled(1), 40, "full scale fills the column");
+    }
+
+    #[tokio::test]
+    async fn generates_thumbnail_from_embedded_cover_art_with_real_ffmpeg() -> Result<()> {
+        let Some(ffmpeg_path) = find_tool("ffmpeg") else {
+            eprintln!("skipping audio thumbnail test because ffmpeg is not installed");
+            return Ok(());
+        };
+
+        let dir = tempdir()?;
+        let cover = dir.path().join("cover.png");
+        DynamicImage::new_rgb8(300, 300).save(&cover)?;
+        let source = dir.path().join("song.mp3");
+        let status = std::process::Command::new(&ffmpeg_path)
+            .args(["-hide_banner", "-loglevel", "error", "-y"])
+            .args(["-f", "lavfi", "-i", "sine=frequency=440:duration=1"])
+            .arg("-i")
+            .arg(&cover)
+            .args(["-map", "0:a", "-map", "1:v", "-c:v", "png"])
+            .args(["-disposition:v", "attached_pic", "-id3v2_version", "3"])
+            .arg(&source)
+            .status()?;
+        if !status.success() {
+            eprintln!("skipping audio thumbnail test because ffmpeg cannot encode mp3");
+            return Ok(());
+        }
+
+        let generator = ThumbnailGenerator::new(dir.path().join("cache"))
+            .with_tools(ffmpeg_path, "gifsicle")
+            .with_audio_waveform(false)
+            .with_timeout(Duration::from_secs(10));
+        let spec = ThumbnailSpec {
+            media_id: "audio-fixture".into(),
+            source_path: source,
+            media_type: MediaType::Audio,
+        };
+        let artifact = generator
+            .ensure_thumbnail(&spec, ThumbnailSize::Small, ThumbnailFormat::Jpeg)
+            .await?;
+        let final_path = dir.path().join("cache").join(&artifact.relative_path);
+        assert!(tokio::fs::metadata(&final_path).await?.len() > 0);
+        assert_thumbnail(&final_path, ThumbnailSize::Small)?;
+        Ok(())
+    }
+
     #[tokio::test]
     async fn generates_thumbnail_for_video_with_real_ffmpeg() -> Result<()> {
         let Some(ffmpeg_path) = find_tool("ffmpeg") else {
@@ -389,7 +1439,7 @@ mod tests {
             media_type: MediaType::Video,
         };
         let artifact = generator
-            .ensure_thumbnail(&spec, ThumbnailSize::Large)
+            .ensure_thumbnail(&spec, ThumbnailSize::Large, ThumbnailFormat::Jpeg)
             .await?;
         let final_path = dir.path().join(&artifact.relative_path);
         assert!(tokio::fs::try_exists(&final_path).await?);
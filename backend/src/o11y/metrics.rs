@@ -0,0 +1,91 @@
+use std::{
+    sync::OnceLock,
+    time::{Duration, Instant},
+};
+
+use axum::{
+    body::Body,
+    extract::MatchedPath,
+    http::{Request, header::CONTENT_TYPE},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use metrics_exporter_prometheus::{Matcher, PrometheusBuilder, PrometheusHandle};
+
+pub const HTTP_REQUESTS_TOTAL: &str = "galarie_http_requests_total";
+pub const SEARCH_DURATION_SECONDS: &str = "galarie_search_duration_seconds";
+pub const SCANS_TOTAL: &str = "galarie_index_scans_total";
+pub const SCAN_DURATION_SECONDS: &str = "galarie_index_scan_duration_seconds";
+pub const CACHE_ITEMS: &str = "galarie_cache_items";
+pub const THUMBNAILS_TOTAL: &str = "galarie_thumbnails_total";
+
+const LATENCY_BUCKETS: &[f64] = &[
+    0.001, 0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0, 30.0, 60.0,
+];
+
+static HANDLE: OnceLock<PrometheusHandle> = OnceLock::new();
+
+/// Install the process-wide Prometheus recorder (idempotent) and return its handle.
+pub fn init() -> &'static PrometheusHandle {
+    HANDLE.get_or_init(|| {
+        let recorder = PrometheusBuilder::new()
+            .set_buckets_for_metric(Matcher::Suffix("_duration_seconds".into()), LATENCY_BUCKETS)
+            .expect("latency buckets are non-empty")
+            .build_recorder();
+        let handle = recorder.handle();
+        if let Err(err) = metrics::set_global_recorder(recorder) {
+            tracing::warn!(error = %err, "metrics recorder already installed");
+        }
+        handle
+    })
+}
+
+/// `GET /metrics` in the Prometheus text exposition format.
+pub async fn render() -> Response {
+    (
+        [(CONTENT_TYPE, "text/plain; version=0.0.4")],
+        init().render(),
+    )
+        .into_response()
+}
+
+/// Middleware counting requests by matched route and status code.
+pub async fn track_http(req: Request<Body>, next: Next) -> Response {
+    // Fall back to a fixed label for unmatched paths to keep cardinality bounded.
+    let route = req
+        .extensions()
+        .get::<MatchedPath>()
+        .map(|path| path.as_str().to_string())
+        .unwrap_or_else(|| "unmatched".to_string());
+    let method = req.method().to_string();
+
+    let response = next.run(req).await;
+    metrics::counter!(
+        HTTP_REQUESTS_TOTAL,
+        "method" => method,
+        "route" => route,
+        "status" => response.status().as_u16().to_string(),
+    )
+    .increment(1);
+    response
+}
+
+pub fn record_search(started: Instant) {
+    metrics::histogram!(SEARCH_DURATION_SECONDS).record(started.elapsed().as_secs_f64());
+}
+
+/// Record a finished scan; `trigger` is `poll` or `manual`.
+pub fn record_scan(trigger: &'static str, duration: Duration, success: bool) {
+    let outcome = if success { "success" } else { "failure" };
+    metrics::counter!(SCANS_TOTAL, "trigger" => trigger, "outcome" => outcome).increment(1);
+    metrics::histogram!(SCAN_DURATION_SECONDS, "trigger" => trigger).record(duration.as_secs_f64());
+}
+
+pub fn set_cache_items(count: usize) {
+    metrics::gauge!(CACHE_ITEMS).set(count as f64);
+}
+
+pub fn record_thumbnail(success: bool) {
+    let outcome = if success { "success" } else { "failure" };
+    metrics::counter!(THUMBNAILS_TOTAL, "outcome" => outcome).increment(1);
+}
@@ -1,19 +1,26 @@
+use std::{collections::HashMap, str::FromStr};
+
 use anyhow::Result;
+use axum::http::{HeaderMap, HeaderName, HeaderValue};
 use opentelemetry::{KeyValue, global, trace::TracerProvider as _};
 use opentelemetry_appender_tracing::layer::OpenTelemetryTracingBridge;
-use opentelemetry_otlp::{LogExporter, SpanExporter, WithExportConfig};
+use opentelemetry_otlp::{
+    LogExporter, SpanExporter, WithExportConfig, WithHttpConfig, WithTonicConfig,
+    tonic_types::metadata::MetadataMap,
+};
 use opentelemetry_sdk::{
     self as sdk,
     logs::{SdkLogger, SdkLoggerProvider},
     resource::Resource,
 };
-use tracing::{info, level_filters::LevelFilter, warn};
+use tracing::{Subscriber, info, level_filters::LevelFilter, warn};
 use tracing_opentelemetry::OpenTelemetryLayer;
 use tracing_subscriber::{
-    EnvFilter, Layer, Registry, filter::Targets, layer::SubscriberExt, util::SubscriberInitExt,
+    EnvFilter, Layer, Registry, filter::Targets, layer::SubscriberExt, registry::LookupSpan,
+    util::SubscriberInitExt,
 };
 
-use crate::config::AppConfig;
+use crate::config::{AppConfig, LogFormat, OtelHeaders, OtelProtocol};
 
 pub struct TelemetryGuard {
     tracer_provider: Option<sdk::trace::SdkTracerProvider>,
@@ -37,11 +44,11 @@ impl TelemetryGuard {
                     logger_provider,
                 } = pipelines;
 
-                init_with_layers(env_filter, trace_layer, log_layer)?;
+                init_with_layers(env_filter, config.log.format, trace_layer, log_layer)?;
                 info!(
                     tracing_enabled = traces_active,
                     logging_enabled = logs_active,
-                    "OpenTelemetry export enabled (json stdout retained)"
+                    "OpenTelemetry export enabled (stdout retained)"
                 );
                 Ok(Self {
                     tracer_provider,
@@ -51,13 +58,7 @@ impl TelemetryGuard {
             None => {
                 tracing_subscriber::registry()
                     .with(env_filter)
-                    .with(
-                        tracing_subscriber::fmt::layer()
-                            .with_target(false)
-                            .with_file(false)
-                            .with_line_number(false)
-                            .json(),
-                    )
+                    .with(fmt_layer(config.log.format))
                     .try_init()?;
                 Ok(Self {
                     tracer_provider: None,
@@ -70,15 +71,15 @@ impl TelemetryGuard {
 
 impl Drop for TelemetryGuard {
     fn drop(&mut self) {
-        if let Some(provider) = self.tracer_provider.take() {
-            if let Err(err) = provider.shutdown() {
-                warn!(error = ?err, "failed to shutdown tracer provider cleanly");
-            }
+        if let Some(provider) = self.tracer_provider.take()
+            && let Err(err) = provider.shutdown()
+        {
+            warn!(error = ?err, "failed to shutdown tracer provider cleanly");
         }
-        if let Some(provider) = self.logger_provider.take() {
-            if let Err(err) = provider.shutdown() {
-                warn!(error = ?err, "failed to shutdown logger provider cleanly");
-            }
+        if let Some(provider) = self.logger_provider.take()
+            && let Err(err) = provider.shutdown()
+        {
+            warn!(error = ?err, "failed to shutdown logger provider cleanly");
         }
     }
 }
@@ -95,6 +96,7 @@ fn build_otel_pipelines(config: &AppConfig) -> Result<Option<OtelPipelines>> {
         Some(endpoint) if !endpoint.trim().is_empty() => endpoint.clone(),
         _ => return Ok(None),
     };
+    let protocol = config.otel.protocol;
 
     let resource = Resource::builder()
         .with_service_name(config.otel.service_name.clone())
@@ -108,10 +110,19 @@ fn build_otel_pipelines(config: &AppConfig) -> Result<Option<OtelPipelines>> {
     let mut tracer_provider = None;
 
     if !config.otel.disable_traces {
-        let span_exporter = SpanExporter::builder()
-            .with_tonic()
-            .with_endpoint(endpoint.clone())
-            .build()?;
+        let span_endpoint = protocol.signal_endpoint(&endpoint, "traces");
+        let span_exporter = match protocol {
+            OtelProtocol::Grpc => SpanExporter::builder()
+                .with_tonic()
+                .with_endpoint(span_endpoint)
+                .with_metadata(grpc_metadata(&config.otel.headers)?)
+                .build()?,
+            OtelProtocol::Http => SpanExporter::builder()
+                .with_http()
+                .with_endpoint(span_endpoint)
+                .with_headers(http_headers(&config.otel.headers))
+                .build()?,
+        };
 
         let provider = sdk::trace::SdkTracerProvider::builder()
             .with_resource(resource.clone())
@@ -128,10 +139,19 @@ fn build_otel_pipelines(config: &AppConfig) -> Result<Option<OtelPipelines>> {
     let mut logger_provider = None;
 
     if !config.otel.disable_logs {
-        let log_exporter = LogExporter::builder()
-            .with_tonic()
-            .with_endpoint(endpoint)
-            .build()?;
+        let log_endpoint = protocol.signal_endpoint(&endpoint, "logs");
+        let log_exporter = match protocol {
+            OtelProtocol::Grpc => LogExporter::builder()
+                .with_tonic()
+                .with_endpoint(log_endpoint)
+                .with_metadata(grpc_metadata(&config.otel.headers)?)
+                .build()?,
+            OtelProtocol::Http => LogExporter::builder()
+                .with_http()
+                .with_endpoint(log_endpoint)
+                .with_headers(http_headers(&config.otel.headers))
+                .build()?,
+        };
 
         let provider = SdkLoggerProvider::builder()
             .with_resource(resource)
@@ -153,38 +173,60 @@ fn build_otel_pipelines(config: &AppConfig) -> Result<Option<OtelPipelines>> {
     }))
 }
 
+fn grpc_metadata(headers: &OtelHeaders) -> Result<MetadataMap> {
+    let mut map = HeaderMap::new();
+    for (name, value) in &headers.0 {
+        map.insert(HeaderName::from_str(name)?, HeaderValue::from_str(value)?);
+    }
+    Ok(MetadataMap::from_headers(map))
+}
+
+fn http_headers(headers: &OtelHeaders) -> HashMap<String, String> {
+    headers
+        .0
+        .iter()
+        .map(|(name, value)| (name.clone(), value.clone()))
+        .collect()
+}
+
+/// Stdout layer in the configured style.
+fn fmt_layer<S>(format: LogFormat) -> Box<dyn Layer<S> + Send + Sync>
+where
+    S: Subscriber + for<'span> LookupSpan<'span>,
+{
+    let layer = tracing_subscriber::fmt::layer()
+        .with_target(false)
+        .with_file(false)
+        .with_line_number(false);
+    match format {
+        LogFormat::Json => layer.json().boxed(),
+        LogFormat::Pretty => layer.pretty().boxed(),
+        LogFormat::Compact => layer.compact().boxed(),
+    }
+}
+
 fn init_with_layers(
     env_filter: EnvFilter,
+    format: LogFormat,
     trace_layer: Option<OpenTelemetryLayer<Registry, sdk::trace::Tracer>>,
     log_layer: Option<OpenTelemetryTracingBridge<SdkLoggerProvider, SdkLogger>>,
 ) -> Result<(), tracing_subscriber::util::TryInitError> {
     match (trace_layer, log_layer) {
-        (Some(trace_layer), Some(log_layer)) => tracing_subscriber::registry()
-            .with(trace_layer)
-            .with(log_layer)
-            // For OTLP endpoint connection error, prepare stdout log
-            .with(
-                tracing_subscriber::fmt::layer()
-                    .with_target(false)
-                    .with_file(false)
-                    .with_line_number(false)
-                    .json()
-                    .with_filter(
-                        Targets::new().with_target("opentelemetry_sdk", LevelFilter::INFO),
-                    ),
-            )
-            .with(env_filter)
-            .try_init(),
+        (Some(trace_layer), Some(log_layer)) => {
+            tracing_subscriber::registry()
+                .with(trace_layer)
+                .with(log_layer)
+                // For OTLP endpoint connection error, prepare stdout log
+                .with(fmt_layer(format).with_filter(
+                    Targets::new().with_target("opentelemetry_sdk", LevelFilter::INFO),
+                ))
+                .with(env_filter)
+                .try_init()
+        }
         (Some(trace_layer), None) => tracing_subscriber::registry()
             .with(trace_layer)
             .with(env_filter)
-            .with(
-                tracing_subscriber::fmt::layer()
-                    .with_target(false)
-                    .with_file(false)
-                    .with_line_number(false)
-                    .json(),
-            )
+            .with(fmt_layer(format))
             .try_init(),
         (None, Some(log_layer)) => tracing_subscriber::registry()
             .with(log_layer)
@@ -1,30 +1,61 @@
 use std::{
-    sync::Arc,
+    convert::Infallible,
+    sync::{
+        Arc,
+        atomic::{AtomicBool, Ordering},
+    },
     time::{Duration, Instant},
 };
 
-use anyhow::Error;
+use anyhow::{Error, anyhow};
 use axum::{
     Json, Router,
-    extract::{MatchedPath, State},
-    http::{HeaderValue, StatusCode},
+    error_handling::HandleErrorLayer,
+    extract::{MatchedPath, Query, State},
+    http::{
+        HeaderMap, HeaderValue, StatusCode,
+        header::{ETAG, IF_MATCH},
+    },
     middleware,
-    routing::{get, post},
+    response::{
+        IntoResponse, Response,
+        sse::{Event, KeepAlive, Sse},
+    },
+    routing::{any, get, post},
 };
-use serde::Serialize;
-use tokio::{sync::RwLock, task};
+use chrono::{DateTime, Utc};
+use futures_util::{Stream, StreamExt};
+use serde::{Deserialize, Serialize};
+use tokio::{
+    sync::{Mutex, RwLock, Semaphore, broadcast, mpsc},
+    task::{self, JoinHandle},
+    time,
+};
+use tokio_util::sync::CancellationToken;
+use tower::{ServiceBuilder, timeout::TimeoutLayer};
 use tower_http::{
-    cors::{AllowOrigin, Any, CorsLayer},
+    cors::{AllowHeaders, AllowMethods, AllowOrigin, Any, CorsLayer},
+    limit::RequestBodyLimitLayer,
+    request_id::{MakeRequestUuid, PropagateRequestIdLayer, SetRequestIdLayer},
     services::{ServeDir, ServeFile},
     trace::{MakeSpan, OnRequest, OnResponse, TraceLayer},
 };
 use tracing::{Instrument, Span, field, instrument};
 
 use crate::{
-    api::{self, ApiResponse, ApiResult, search, stream, thumbnails},
+    api::{
+        self, ApiError, ApiResult, archive,
+        client_ip::ClientIp,
+        detail, openapi,
+        rate_limit::RateLimiter,
+        search, stream, tags,
+        thumbnails::{self, WarmStatus},
+    },
     cache::{CacheSnapshot, CacheStore},
-    config::AppConfig,
-    indexer::Indexer,
+    config::{AppConfig, RequestLimits},
+    indexer::{IndexEvent, Indexer, IndexerHandle, MediaFile, ScanSummary},
+    media::{thumbnail_cache::ThumbnailCache, thumbnails::InFlightThumbnails},
+    o11y::metrics,
 };
 
 /// Shared application state cloned into each request handler.
@@ -33,64 +64,323 @@ pub struct AppState {
     pub config: Arc<AppConfig>,
     pub cache_store: Arc<CacheStore>,
     pub snapshot: Arc<RwLock<CacheSnapshot>>,
+    /// Held for the duration of a rebuild so scans and snapshot swaps never overlap.
+    pub rebuild_lock: Arc<Mutex<()>>,
+    pub scan_status: Arc<RwLock<ScanStatus>>,
+    /// Caps concurrent thumbnail generation across requests.
+    pub thumbnail_permits: Arc<Semaphore>,
+    pub thumbnail_inflight: Arc<InFlightThumbnails>,
+    pub thumbnail_warm: Arc<RwLock<WarmStatus>>,
+    /// Present when a thumbnail cache budget is configured.
+    pub thumbnail_cache: Option<Arc<ThumbnailCache>>,
+    /// Set once a snapshot has been loaded or rebuilt; gates `/healthz/ready`.
+    pub initialized: Arc<AtomicBool>,
+    /// Announces every persisted snapshot to `/api/v1/index/events` subscribers.
+    pub index_updates: broadcast::Sender<IndexUpdate>,
+    /// Cancelled when the server begins shutting down, ending long-lived responses
+    /// (event streams) so graceful shutdown is not held open by idle subscribers.
+    pub shutdown: CancellationToken,
     pub boot_instant: Instant,
 }
 
+/// Outcome of the most recent scan, whether polled or manually triggered.
+#[derive(Debug, Clone, Default)]
+pub struct ScanStatus {
+    pub last_scan_ms: Option<u64>,
+    pub last_scan_error: Option<String>,
+    pub last_scan_summary: Option<ScanSummary>,
+    /// Set while a manually triggered rebuild is running.
+    pub rebuild_in_progress: bool,
+}
+
+/// Payload of an `/api/v1/index/events` `snapshot` event.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct IndexUpdate {
+    pub generated_at: DateTime<Utc>,
+    pub item_count: usize,
+}
+
+impl From<&CacheSnapshot> for IndexUpdate {
+    fn from(snapshot: &CacheSnapshot) -> Self {
+        Self {
+            generated_at: snapshot.generated_at,
+            item_count: snapshot.media.len(),
+        }
+    }
+}
+
+/// Buffered updates per subscriber; a slower one skips ahead to the newest.
+const INDEX_UPDATE_CAPACITY: usize = 16;
+
+impl ScanStatus {
+    pub fn record_success(&mut self, duration: Duration, summary: ScanSummary) {
+        self.last_scan_ms = Some(duration.as_millis() as u64);
+        self.last_scan_error = None;
+        self.last_scan_summary = Some(summary);
+    }
+
+    pub fn record_failure(&mut self, error: impl ToString) {
+        self.last_scan_error = Some(error.to_string());
+    }
+}
+
 impl AppState {
+    /// Apply snapshots from the background indexer until its channel closes.
+    pub fn spawn_index_consumer(&self, mut events: mpsc::Receiver<IndexEvent>) -> JoinHandle<()> {
+        let state = self.clone();
+        task::spawn(async move {
+            while let Some(event) = events.recv().await {
+                state.apply_index_event(event).await;
+            }
+        })
+    }
+
+    async fn apply_index_event(&self, event: IndexEvent) {
+        match event {
+            IndexEvent::Snapshot {
+                mut files,
+                summary,
+                duration,
+                scanned_at,
+            } => {
+                let elapsed_ms = duration.as_millis();
+                let file_count = files.len();
+
+                tracing::info!(
+                    elapsed_ms,
+                    file_count = file_count,
+                    skipped = summary.skipped,
+                    errored = summary.errored,
+                    top_errors = ?summary.top_errors,
+                    scanned_at = %scanned_at.to_rfc3339(),
+                    "filesystem scan complete in {elapsed_ms} ms, found {file_count} files",
+                );
+
+                // Don't swap snapshots underneath a manual rebuild.
+                let _rebuild_guard = self.rebuild_lock.lock().await;
+                let kept = refuse_empty_scan(&files, &*self.snapshot.read().await);
+                if let Err(err) = kept {
+                    metrics::record_scan("poll", duration, false);
+                    self.scan_status.write().await.record_failure(&err);
+                    return;
+                }
+                // A poll that found nothing new keeps `generatedAt`, and with it the search
+                // ETags, the cache file and subscribers' views, as they are.
+                crate::cache::sort_media(&mut files);
+                if files == self.snapshot.read().await.media {
+                    metrics::record_scan("poll", duration, true);
+                    self.mark_initialized();
+                    self.scan_status
+                        .write()
+                        .await
+                        .record_success(duration, summary);
+                    tracing::debug!("filesystem scan found no changes");
+                    return;
+                }
+                match self.cache_store.persist(files) {
+                    Ok(snapshot) => {
+                        metrics::record_scan("poll", duration, true);
+                        metrics::set_cache_items(snapshot.media.len());
+                        let update = IndexUpdate::from(&snapshot);
+                        *self.snapshot.write().await = snapshot;
+                        self.mark_initialized();
+                        // Announced only once live, so a subscriber refetching on the event
+                        // sees it. No subscribers is not an error.
+                        let _ = self.index_updates.send(update);
+                        self.scan_status
+                            .write()
+                            .await
+                            .record_success(duration, summary);
+                        tracing::info!("filesystem scan persisted to cache");
+                    }
+                    Err(err) => {
+                        tracing::error!(error = %err, "failed to persist cache snapshot");
+                        metrics::record_scan("poll", duration, false);
+                        self.scan_status.write().await.record_failure(&err);
+                    }
+                }
+            }
+            IndexEvent::Error { message } => {
+                tracing::warn!(%message, "indexer error");
+                self.scan_status.write().await.record_failure(message);
+            }
+        }
+    }
+
+    /// Stop background indexing once the server has stopped accepting requests: let a
+    /// running scan finish and persist, wait for manual rebuilds, then remove temp files
+    /// left by any interrupted cache write. Each wait is bounded by `grace`.
+    pub async fn drain(&self, indexer: IndexerHandle, consumer: JoinHandle<()>, grace: Duration) {
+        indexer.shutdown(grace).await;
+        if time::timeout(grace, consumer).await.is_err() {
+            tracing::warn!(?grace, "timed out applying the final index snapshot");
+        }
+        match time::timeout(grace, self.rebuild_lock.lock()).await {
+            Ok(_guard) => match self.cache_store.sweep_temp_files(Duration::ZERO) {
+                Ok(0) => {}
+                Ok(removed) => tracing::info!(removed, "removed leftover cache temp files"),
+                Err(err) => tracing::warn!(error = %err, "failed to remove cache temp files"),
+            },
+            Err(_) => tracing::warn!(?grace, "manual index rebuild still running at shutdown"),
+        }
+    }
+
     pub fn new(
         config: Arc<AppConfig>,
         cache_store: Arc<CacheStore>,
         snapshot: Arc<RwLock<CacheSnapshot>>,
     ) -> Self {
+        let thumbnail_permits = Arc::new(Semaphore::new(config.thumbnails.concurrency.max(1)));
+        let thumbnail_cache = config
+            .thumbnails
+            .cache_max_bytes
+            .map(|max_bytes| Arc::new(ThumbnailCache::new(&config.cache_dir, max_bytes)));
         Self {
             config,
             cache_store,
             snapshot,
+            rebuild_lock: Arc::new(Mutex::new(())),
+            scan_status: Arc::new(RwLock::new(ScanStatus::default())),
+            thumbnail_permits,
+            thumbnail_inflight: Arc::default(),
+            thumbnail_warm: Arc::default(),
+            thumbnail_cache,
+            initialized: Arc::new(AtomicBool::new(false)),
+            index_updates: broadcast::channel(INDEX_UPDATE_CAPACITY).0,
+            shutdown: CancellationToken::new(),
             boot_instant: Instant::now(),
         }
     }
+
+    /// Record that the served snapshot reflects the library, making the backend ready.
+    pub fn mark_initialized(&self) {
+        self.initialized.store(true, Ordering::Release);
+    }
+
+    pub fn is_initialized(&self) -> bool {
+        self.initialized.load(Ordering::Acquire)
+    }
 }
 
+const X_REQUEST_ID: &str = "x-request-id";
+
 /// Build the Axum router with shared layers and routes.
 pub fn router(state: AppState) -> Router {
     let cors = build_cors_layer(&state.config.cors_allowed_origins);
 
     let api_routes = Router::new()
         .route("/media", get(search::media_search))
-        .route("/media/{id}/thumbnail", get(thumbnails::media_thumbnail))
+        .route("/media/invalid", get(detail::invalid_media))
+        .route("/media/archive", get(archive::media_archive))
+        .route("/media/duplicates", get(detail::duplicate_media))
+        .route("/media/{id}", get(detail::media_detail))
+        .route("/media/{id}/related", get(detail::related_media))
+        .route(
+            "/media/{id}/thumbnail",
+            get(thumbnails::media_thumbnail).head(thumbnails::media_thumbnail),
+        )
         .route("/media/{id}/stream", get(stream::media_stream))
+        .route("/tags", get(tags::tag_facets))
+        .route(
+            "/thumbnails/warm",
+            post(thumbnails::warm_thumbnails).get(thumbnails::warm_status),
+        )
         .route("/index/rebuild", post(trigger_rebuild))
-        .layer(cors)
+        .route("/index/events", get(index_events))
+        .route("/openapi.json", get(openapi::openapi_json));
+    // Inside the CORS layer so preflight requests are answered without a token.
+    let api_routes = match &state.config.api_token {
+        Some(token) => api_routes.layer(middleware::from_fn_with_state(
+            Arc::new(token.clone()),
+            api::auth::require_api_token,
+        )),
+        None => api_routes,
+    };
+    let api_routes = match cors {
+        Some(cors) => api_routes.layer(cors),
+        None => api_routes,
+    };
+    let api_routes = match state.config.rate_limit_per_minute {
+        Some(per_minute) => api_routes.layer(middleware::from_fn_with_state(
+            Arc::new(RateLimiter::new(per_minute)),
+            api::rate_limit::enforce_rate_limit,
+        )),
+        None => api_routes,
+    };
+    let api_routes = with_request_limits(api_routes, &state.config.limits)
         .fallback(api::fallback_handler)
         .layer(middleware::from_fn(api::ensure_error_envelope))
+        // Outermost first: assign an id, open the span with it, then echo it back inside
+        // the span so the completion log can read it off the response.
+        .layer(PropagateRequestIdLayer::x_request_id())
         .layer(
             TraceLayer::new_for_http()
                 .make_span_with(HttpMakeSpan)
                 .on_request(LogOnRequest)
                 .on_response(LogOnResponse),
-        );
+        )
+        .layer(SetRequestIdLayer::x_request_id(MakeRequestUuid));
 
     let router = Router::new()
         .route("/healthz", get(healthz))
+        .route("/healthz/live", get(healthz_live))
+        .route("/healthz/ready", get(healthz_ready))
         .nest("/api/v1", api_routes)
+        // Keep unknown API paths on the JSON envelope rather than the SPA fallback below.
+        .route("/api", any(api::fallback_handler))
+        .route("/api/{*rest}", any(api::fallback_handler))
+        .route("/metrics", get(metrics::render))
+        .layer(middleware::from_fn(metrics::track_http))
+        // Outermost so the access log and the rate limiter both see the resolved address.
+        .layer(middleware::from_fn_with_state(
+            Arc::new(state.config.trusted_proxies.clone()),
+            api::client_ip::resolve_client_ip,
+        ))
         .with_state(state.clone());
 
     if let Some(frontend_dist_dir) = &state.config.frontend_dist_dir {
+        // Unknown paths fall back to index.html so client-side routes survive a reload.
         let frontend_service = ServeDir::new(frontend_dist_dir)
             .fallback(ServeFile::new(frontend_dist_dir.join("index.html")));
 
-        router.nest_service("/ui", frontend_service)
+        router.fallback_service(frontend_service)
     } else {
         router
     }
 }
 
-fn build_cors_layer(origins: &[String]) -> CorsLayer {
+/// Cap request body size and handler run time; both surface as error envelopes once
+/// `ensure_error_envelope` wraps the result.
+fn with_request_limits<S>(routes: Router<S>, limits: &RequestLimits) -> Router<S>
+where
+    S: Clone + Send + Sync + 'static,
+{
+    let routes = routes.layer(RequestBodyLimitLayer::new(limits.max_body_bytes));
+    match limits.timeout {
+        Some(timeout) => routes.layer(
+            ServiceBuilder::new()
+                .layer(HandleErrorLayer::new(api::handle_layer_error))
+                .layer(TimeoutLayer::new(timeout)),
+        ),
+        None => routes,
+    }
+}
+
+/// Build the CORS layer: no origins means same-origin only (no layer), `*` allows any
+/// origin without credentials, and an explicit list allows those origins with credentials.
+fn build_cors_layer(origins: &[String]) -> Option<CorsLayer> {
     if origins.is_empty() {
-        return CorsLayer::new()
-            .allow_origin(Any)
-            .allow_methods(Any)
-            .allow_headers(Any);
+        return None;
+    }
+
+    if origins.iter().any(|origin| origin == "*") {
+        return Some(
+            CorsLayer::new()
+                .allow_origin(Any)
+                .allow_methods(Any)
+                .allow_headers(Any),
+        );
     }
 
     let mut allowed = Vec::new();
@@ -102,16 +392,18 @@ fn build_cors_layer(origins: &[String]) -> CorsLayer {
     }
 
     if allowed.is_empty() {
-        CorsLayer::new()
-            .allow_origin(Any)
-            .allow_methods(Any)
-            .allow_headers(Any)
-    } else {
+        tracing::warn!("no valid cors origins configured, cors disabled");
+        return None;
+    }
+
+    // Wildcards are not allowed alongside credentials, so mirror the preflight instead.
+    Some(
         CorsLayer::new()
             .allow_origin(AllowOrigin::list(allowed))
-            .allow_methods(Any)
-            .allow_headers(Any)
-    }
+            .allow_methods(AllowMethods::mirror_request())
+            .allow_headers(AllowHeaders::mirror_request())
+            .allow_credentials(true),
+    )
 }
 
 /// JSON payload returned by `/healthz`.
@@ -123,11 +415,20 @@ struct HealthResponse {
     uptime_seconds: f64,
     cache_items: usize,
     cache_generated_at: String,
+    #[serde(rename = "lastScanMs")]
+    last_scan_ms: Option<u64>,
+    #[serde(rename = "rebuildInProgress")]
+    rebuild_in_progress: bool,
+    #[serde(rename = "lastScanError")]
+    last_scan_error: Option<String>,
+    #[serde(rename = "lastScanSummary")]
+    last_scan_summary: Option<ScanSummary>,
 }
 
 #[instrument(skip(state))]
 async fn healthz(State(state): State<AppState>) -> ApiResult<HealthResponse> {
     let snapshot = state.snapshot.read().await;
+    let scan_status = state.scan_status.read().await.clone();
     Ok(Json(HealthResponse {
         status: "ok",
         media_root: state.config.media_root.display().to_string(),
@@ -135,42 +436,219 @@ async fn healthz(State(state): State<AppState>) -> ApiResult<HealthResponse> {
         uptime_seconds: state.boot_instant.elapsed().as_secs_f64(),
         cache_items: snapshot.media.len(),
         cache_generated_at: snapshot.generated_at.to_rfc3339(),
+        last_scan_ms: scan_status.last_scan_ms,
+        rebuild_in_progress: scan_status.rebuild_in_progress,
+        last_scan_error: scan_status.last_scan_error,
+        last_scan_summary: scan_status.last_scan_summary,
     }))
 }
 
-#[instrument(skip(state))]
-async fn trigger_rebuild(State(state): State<AppState>) -> ApiResponse<serde_json::Value> {
+/// JSON payload returned by the liveness and readiness probes.
+#[derive(Serialize)]
+struct ProbeResponse {
+    status: &'static str,
+}
+
+/// Liveness: answers as soon as the process serves HTTP.
+async fn healthz_live() -> ApiResult<ProbeResponse> {
+    Ok(Json(ProbeResponse { status: "ok" }))
+}
+
+/// Readiness: `503` until the first snapshot has been loaded or rebuilt, so traffic is
+/// not routed to a backend serving an empty index.
+async fn healthz_ready(State(state): State<AppState>) -> ApiResult<ProbeResponse> {
+    if !state.is_initialized() {
+        return Err(ApiError::service_unavailable(
+            "media index has not been loaded yet",
+        ));
+    }
+    Ok(Json(ProbeResponse { status: "ok" }))
+}
+
+/// Check an `If-Match` header against the snapshot's `generatedAt`, which acts as its
+/// version. Accepts the timestamp quoted like an ETag or bare, and `*`.
+fn if_match_satisfied(headers: &HeaderMap, generated_at: DateTime<Utc>) -> Result<bool, String> {
+    let Some(value) = headers.get(IF_MATCH) else {
+        return Ok(true);
+    };
+    let value = value
+        .to_str()
+        .map_err(|_| "If-Match must be a valid header string".to_string())?;
+    for candidate in value.split(',').map(str::trim) {
+        if candidate == "*" {
+            return Ok(true);
+        }
+        let candidate = candidate.trim_start_matches("W/").trim_matches('"');
+        let version = DateTime::parse_from_rfc3339(candidate)
+            .map_err(|_| format!("If-Match '{candidate}' is not an RFC 3339 timestamp"))?;
+        if version == generated_at {
+            return Ok(true);
+        }
+    }
+    Ok(false)
+}
+
+/// Longest a `?wait=true` rebuild request blocks before answering 503.
+const SYNC_REBUILD_TIMEOUT: Duration = Duration::from_secs(60);
+
+#[derive(Debug, Default, Deserialize)]
+struct RebuildParams {
+    /// Scan inline and answer `200` once the new snapshot is live.
+    #[serde(default)]
+    wait: bool,
+}
+
+#[instrument(skip(state, headers))]
+async fn trigger_rebuild(
+    State(state): State<AppState>,
+    Query(params): Query<RebuildParams>,
+    headers: HeaderMap,
+) -> Result<Response, ApiError> {
     let cache_store = state.cache_store.clone();
     let snapshot_state = state.snapshot.clone();
+    let scan_status = state.scan_status.clone();
+    let initialized = state.initialized.clone();
+    let index_updates = state.index_updates.clone();
     let media_root = state.config.media_root.clone();
+    let indexer_config = state.config.indexer.clone();
+    let rebuild_guard = state
+        .rebuild_lock
+        .clone()
+        .try_lock_owned()
+        .map_err(|_| ApiError::conflict("an index rebuild is already in progress"))?;
+    // Checked under the rebuild lock so no other rebuild can move the snapshot on
+    // between this check and the scan below.
+    let current = state.snapshot.read().await.generated_at;
+    if !if_match_satisfied(&headers, current).map_err(ApiError::bad_request)? {
+        return Err(ApiError::conflict(format!(
+            "index snapshot has moved on (current generatedAt {})",
+            current.to_rfc3339()
+        )));
+    }
+    // Promised to the caller up front so it can poll `/healthz` for completion.
+    let generated_at = Utc::now();
+    scan_status.write().await.rebuild_in_progress = true;
 
-    task::spawn(async move {
+    let rebuild = task::spawn(async move {
+        let _rebuild_guard = rebuild_guard;
         let span = tracing::info_span!("api_triggerred_index", media_root = %media_root.display());
 
-        let root_for_scan = media_root.clone();
-        if let Err(err) = async move {
+        let started = Instant::now();
+        match async move {
             let parent = tracing::Span::current();
-            let files = tokio::task::spawn_blocking(move || {
-                parent.in_scope(|| Indexer::scan_once(&root_for_scan))
+            let (files, summary) = tokio::task::spawn_blocking(move || {
+                parent.in_scope(|| Indexer::scan_with_summary(&indexer_config))
             })
             .await??;
-            let snapshot = cache_store.persist(files)?;
+            refuse_empty_scan(&files, &*snapshot_state.read().await)?;
+            let snapshot = cache_store.persist_as_of(files, generated_at)?;
+            let item_count = snapshot.media.len();
+            metrics::set_cache_items(item_count);
+            let update = IndexUpdate::from(&snapshot);
             *snapshot_state.write().await = snapshot;
-            Result::<(), Error>::Ok(())
+            initialized.store(true, Ordering::Release);
+            let _ = index_updates.send(update);
+            Result::<(ScanSummary, usize), Error>::Ok((summary, item_count))
         }
         .instrument(span)
         .await
         {
-            tracing::error!(error = %err, "manual index rebuild failed");
-        } else {
-            tracing::info!("manual index rebuild completed");
+            Err(err) => {
+                tracing::error!(error = %err, "manual index rebuild failed");
+                metrics::record_scan("manual", started.elapsed(), false);
+                let mut status = scan_status.write().await;
+                status.rebuild_in_progress = false;
+                status.record_failure(&err);
+                None
+            }
+            Ok((summary, item_count)) => {
+                tracing::info!(?summary, "manual index rebuild completed");
+                metrics::record_scan("manual", started.elapsed(), true);
+                let mut status = scan_status.write().await;
+                status.rebuild_in_progress = false;
+                status.record_success(started.elapsed(), summary);
+                Some((item_count, started.elapsed()))
+            }
         }
     });
 
+    if params.wait {
+        // On timeout the rebuild keeps running detached, still holding the lock.
+        return match time::timeout(SYNC_REBUILD_TIMEOUT, rebuild).await {
+            Ok(Ok(Some((item_count, duration)))) => Ok(Json(serde_json::json!({
+                "status": "completed",
+                "itemCount": item_count,
+                "durationMs": duration.as_millis() as u64,
+                "generatedAt": generated_at.to_rfc3339(),
+            }))
+            .into_response()),
+            Ok(Ok(None)) => Err(ApiError::internal(
+                "index rebuild failed; see lastScanError in /healthz",
+            )),
+            Ok(Err(err)) => {
+                tracing::error!(error = %err, "manual index rebuild task panicked");
+                Err(ApiError::internal("index rebuild failed"))
+            }
+            Err(_) => Err(ApiError::service_unavailable(format!(
+                "index rebuild did not finish within {}s and continues in the background",
+                SYNC_REBUILD_TIMEOUT.as_secs()
+            ))),
+        };
+    }
+
+    let etag = HeaderValue::from_str(&format!("\"{}\"", generated_at.to_rfc3339()))
+        .expect("RFC 3339 timestamps are valid header values");
     Ok((
         StatusCode::ACCEPTED,
-        Json(serde_json::json!({"status": "queued"})),
-    ))
+        [(ETAG, etag)],
+        Json(serde_json::json!({
+            "status": "queued",
+            "generatedAt": generated_at.to_rfc3339(),
+        })),
+    )
+        .into_response())
+}
+
+/// An empty scan after a populated one is far more likely an unmounted removable or
+/// network share than a library deleted in one go, so it must not replace `current`.
+fn refuse_empty_scan(files: &[MediaFile], current: &CacheSnapshot) -> Result<(), Error> {
+    let previous = current.media.len();
+    if files.is_empty() && previous > 0 {
+        tracing::warn!(
+            previous,
+            "filesystem scan found no media; keeping the previous snapshot"
+        );
+        return Err(anyhow!(
+            "scan found no media (previously {previous}); kept the previous snapshot"
+        ));
+    }
+    Ok(())
+}
+
+/// Server-Sent Events stream with a `snapshot` event (generatedAt, itemCount) for each
+/// snapshot persisted by a scan or rebuild from now on, ending at server shutdown.
+async fn index_events(
+    State(state): State<AppState>,
+) -> Sse<impl Stream<Item = Result<Event, Infallible>>> {
+    let updates =
+        futures_util::stream::unfold(state.index_updates.subscribe(), |mut updates| async move {
+            loop {
+                match updates.recv().await {
+                    Ok(update) => return Some((update, updates)),
+                    // Only the latest snapshot matters, so skipped ones are not replayed.
+                    Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                        tracing::debug!(skipped, "index events subscriber lagged");
+                    }
+                    Err(broadcast::error::RecvError::Closed) => return None,
+                }
+            }
+        })
+        .filter_map(
+            |update| async move { Event::default().event("snapshot").json_data(update).ok() },
+        )
+        .map(Ok)
+        .take_until(state.shutdown.cancelled_owned());
+    Sse::new(updates).keep_alive(KeepAlive::default())
 }
 
 #[derive(Clone)]
@@ -191,18 +669,32 @@ impl<B> MakeSpan<B> for HttpMakeSpan {
             http.route = %matched_path,
             url.path = request.uri().path(),
             url.query = field::Empty,
+            client.address = field::Empty,
+            http.request_id = field::Empty,
             http.response.status_code = field::Empty,
             http.latency_ms = field::Empty
         );
 
         if let Some(query) = request.uri().query() {
-            span.record("url.query", &field::display(query));
+            span.record("url.query", field::display(query));
+        }
+        if let Some(ClientIp(client)) = request.extensions().get::<ClientIp>() {
+            span.record("client.address", field::display(client));
+        }
+        if let Some(request_id) = request_id(request.headers()) {
+            span.record("http.request_id", request_id);
         }
 
         span
     }
 }
 
+fn request_id(headers: &HeaderMap) -> Option<&str> {
+    headers
+        .get(X_REQUEST_ID)
+        .and_then(|value| value.to_str().ok())
+}
+
 #[derive(Clone)]
 struct LogOnRequest;
 
@@ -231,13 +723,14 @@ impl<B> OnResponse<B> for LogOnResponse {
     fn on_response(self, response: &axum::http::Response<B>, latency: Duration, span: &Span) {
         let status_code = response.status().as_u16();
 
-        span.record("http.response.status_code", &field::display(status_code));
-        span.record("http.latency_ms", &field::display(latency.as_millis()));
+        span.record("http.response.status_code", field::display(status_code));
+        span.record("http.latency_ms", field::display(latency.as_millis()));
 
         tracing::info!(
             parent: span,
             http.latency_ms = %latency.as_millis(),
             http.response.status_code = %status_code,
+            http.request_id = request_id(response.headers()).unwrap_or_default(),
             "HTTP request completed with status {} in {} ms",
             status_code,
             latency.as_millis()
@@ -250,42 +743,26 @@ mod tests {
     use super::*;
     use axum::{
         body::Body,
+        extract::ConnectInfo,
         http::{Method, Request},
     };
     use http_body_util::BodyExt;
     use serde_json::Value;
     use std::path::PathBuf;
-    use std::{fs, os::unix::fs::PermissionsExt, time::Duration};
+    use std::{fs, net::SocketAddr, os::unix::fs::PermissionsExt, time::Duration};
     use tempfile::tempdir;
     use tokio::time::timeout;
     use tower::ServiceExt;
 
-    use crate::config::{LogConfig, OtelConfig};
+    use crate::{
+        api::{ErrorCode, client_ip::TrustedProxies},
+        indexer::IndexerConfig,
+    };
 
     fn sample_media_root() -> PathBuf {
         PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("../sample-media")
     }
 
-    fn test_config(media_root: PathBuf, cache_dir: PathBuf) -> AppConfig {
-        AppConfig {
-            media_root,
-            cache_dir,
-            listen_addr: "127.0.0.1:0".parse().unwrap(),
-            environment: "test".into(),
-            otel: OtelConfig {
-                endpoint: None,
-                service_name: "test-service".into(),
-                disable_traces: true,
-                disable_logs: true,
-            },
-            log: LogConfig {
-                level: "info".into(),
-            },
-            cors_allowed_origins: Vec::new(),
-            frontend_dist_dir: None,
-        }
-    }
-
     async fn post_rebuild(app: &mut Router) -> StatusCode {
         let request = Request::builder()
             .method(Method::POST)
@@ -300,7 +777,10 @@ mod tests {
     async fn rebuild_endpoint_updates_cache_snapshot() {
         let media_root = sample_media_root();
         let cache_dir = tempdir().unwrap();
-        let config = Arc::new(test_config(media_root, cache_dir.path().to_path_buf()));
+        let config = Arc::new(AppConfig::for_tests(
+            media_root,
+            cache_dir.path().to_path_buf(),
+        ));
         let cache_store = Arc::new(CacheStore::new(cache_dir.path()));
         let initial_snapshot = CacheSnapshot::new(Vec::new());
         let snapshot_state = Arc::new(RwLock::new(initial_snapshot));
@@ -323,12 +803,440 @@ mod tests {
         .expect("rebuild did not complete in time");
     }
 
+    #[tokio::test]
+    async fn healthz_reports_scan_status_after_rebuild() {
+        let cache_dir = tempdir().unwrap();
+        let config = Arc::new(AppConfig::for_tests(
+            sample_media_root(),
+            cache_dir.path().to_path_buf(),
+        ));
+        let cache_store = Arc::new(CacheStore::new(cache_dir.path()));
+        let snapshot_state = Arc::new(RwLock::new(CacheSnapshot::new(Vec::new())));
+        let state = AppState::new(config, cache_store, snapshot_state);
+        let rebuild_lock = state.rebuild_lock.clone();
+        let scan_status = state.scan_status.clone();
+        let mut app = router(state);
+
+        let (status, body) = get_text(&app, "/healthz").await;
+        assert_eq!(status, StatusCode::OK);
+        let json: Value = serde_json::from_str(&body).unwrap();
+        assert_eq!(json["lastScanMs"], Value::Null);
+        assert_eq!(json["rebuildInProgress"], false);
+        assert_eq!(json["lastScanError"], Value::Null);
+
+        // Poll scans also hold the rebuild lock while persisting; that is not a rebuild.
+        let persisting = rebuild_lock.clone().lock_owned().await;
+        let (_, body) = get_text(&app, "/healthz").await;
+        let json: Value = serde_json::from_str(&body).unwrap();
+        assert_eq!(json["rebuildInProgress"], false);
+        drop(persisting);
+
+        scan_status.write().await.rebuild_in_progress = true;
+        let (_, body) = get_text(&app, "/healthz").await;
+        let json: Value = serde_json::from_str(&body).unwrap();
+        assert_eq!(json["rebuildInProgress"], true);
+        scan_status.write().await.rebuild_in_progress = false;
+
+        assert_eq!(post_rebuild(&mut app).await, StatusCode::ACCEPTED);
+        let idle = timeout(Duration::from_secs(2), rebuild_lock.lock())
+            .await
+            .expect("rebuild did not finish in time");
+        drop(idle);
+        let (_, body) = get_text(&app, "/healthz").await;
+        let json: Value = serde_json::from_str(&body).unwrap();
+        assert!(json["lastScanMs"].is_u64());
+        assert_eq!(json["rebuildInProgress"], false);
+        assert_eq!(json["lastScanError"], Value::Null);
+        assert!(json["cache_items"].as_u64().unwrap() >= 3);
+    }
+
+    #[tokio::test]
+    async fn serves_generated_openapi_document() {
+        let cache_dir = tempdir().unwrap();
+        let config = Arc::new(AppConfig::for_tests(
+            sample_media_root(),
+            cache_dir.path().to_path_buf(),
+        ));
+        let cache_store = Arc::new(CacheStore::new(cache_dir.path()));
+        let snapshot_state = Arc::new(RwLock::new(CacheSnapshot::new(Vec::new())));
+        let app = router(AppState::new(config, cache_store, snapshot_state));
+
+        let (status, body) = get_text(&app, "/api/v1/openapi.json").await;
+        assert_eq!(status, StatusCode::OK);
+        let doc: Value = serde_json::from_str(&body).unwrap();
+        assert!(doc["paths"]["/api/v1/media"]["get"].is_object());
+        let schemas = &doc["components"]["schemas"];
+        assert!(schemas["ErrorResponse"].is_object());
+        assert!(schemas["MediaSearchResponse"].is_object());
+        let codes = schemas["ErrorCode"]["enum"].as_array().unwrap();
+        for code in [
+            ErrorCode::ValidationFailed,
+            ErrorCode::Unauthorized,
+            ErrorCode::Forbidden,
+            ErrorCode::MethodNotAllowed,
+            ErrorCode::UnsupportedMediaType,
+            ErrorCode::ResourceNotFound,
+            ErrorCode::Gone,
+            ErrorCode::Conflict,
+            ErrorCode::TooManyRequests,
+            ErrorCode::InternalServerError,
+            ErrorCode::ServiceUnavailable,
+        ] {
+            assert!(codes.contains(&serde_json::to_value(code).unwrap()));
+        }
+    }
+
+    #[tokio::test]
+    async fn index_events_stream_announces_rebuilds() {
+        let cache_dir = tempdir().unwrap();
+        let config = Arc::new(AppConfig::for_tests(
+            sample_media_root(),
+            cache_dir.path().to_path_buf(),
+        ));
+        let cache_store = Arc::new(CacheStore::new(cache_dir.path()));
+        let snapshot_state = Arc::new(RwLock::new(CacheSnapshot::new(Vec::new())));
+        let state = AppState::new(config, cache_store, snapshot_state);
+        let shutdown = state.shutdown.clone();
+        let mut app = router(state);
+
+        let request = Request::builder()
+            .uri("/api/v1/index/events")
+            .body(Body::empty())
+            .unwrap();
+        let response = app.clone().oneshot(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.headers()["content-type"], "text/event-stream");
+        let mut body = response.into_body();
+
+        assert_eq!(post_rebuild(&mut app).await, StatusCode::ACCEPTED);
+        let frame = timeout(Duration::from_secs(2), body.frame())
+            .await
+            .expect("no index event in time")
+            .unwrap()
+            .unwrap();
+        let text = String::from_utf8(frame.into_data().unwrap().to_vec()).unwrap();
+        assert!(text.starts_with("event: snapshot\n"), "{text}");
+        let data = text
+            .lines()
+            .find_map(|line| line.strip_prefix("data: "))
+            .expect("data line");
+        let update: Value = serde_json::from_str(data).unwrap();
+        assert_eq!(update["itemCount"], 3);
+        let generated_at: DateTime<Utc> = update["generatedAt"].as_str().unwrap().parse().unwrap();
+
+        // The event only goes out once the snapshot is served.
+        let request = Request::builder()
+            .uri("/api/v1/media")
+            .body(Body::empty())
+            .unwrap();
+        let response = app.clone().oneshot(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(
+            response.headers()[axum::http::header::LAST_MODIFIED],
+            api::http_date(generated_at).as_str()
+        );
+        let media = response.into_body().collect().await.unwrap().to_bytes();
+        let media: Value = serde_json::from_slice(&media).unwrap();
+        assert_eq!(media["total"], 3);
+
+        shutdown.cancel();
+        let end = timeout(Duration::from_secs(1), body.frame())
+            .await
+            .expect("stream did not end on shutdown");
+        assert!(end.is_none());
+    }
+
+    #[tokio::test]
+    async fn failed_or_empty_scans_keep_the_previous_snapshot() {
+        let cache_dir = tempdir().unwrap();
+        let config = Arc::new(AppConfig::for_tests(
+            sample_media_root(),
+            cache_dir.path().to_path_buf(),
+        ));
+        let cache_store = Arc::new(CacheStore::new(cache_dir.path()));
+        let snapshot_state = Arc::new(RwLock::new(CacheSnapshot::new(Vec::new())));
+        let state = AppState::new(config, cache_store.clone(), snapshot_state.clone());
+        let files = Indexer::scan_once(sample_media_root()).unwrap();
+        let scan = |files: Vec<crate::indexer::MediaFile>| IndexEvent::Snapshot {
+            files,
+            summary: ScanSummary::default(),
+            scanned_at: Utc::now(),
+            duration: Duration::from_millis(1),
+        };
+
+        state.apply_index_event(scan(files.clone())).await;
+        let generated_at = snapshot_state.read().await.generated_at;
+
+        state
+            .apply_index_event(IndexEvent::Error {
+                message: "media root does not exist".into(),
+            })
+            .await;
+        state.apply_index_event(scan(Vec::new())).await;
+
+        assert_eq!(snapshot_state.read().await.media.len(), files.len());
+        let persisted = cache_store.load().unwrap().unwrap();
+        assert_eq!(persisted.media.len(), files.len());
+        assert_eq!(persisted.generated_at, generated_at);
+        let status = state.scan_status.read().await;
+        assert!(
+            status
+                .last_scan_error
+                .as_deref()
+                .is_some_and(|error| error.contains("kept the previous snapshot"))
+        );
+    }
+
+    #[tokio::test]
+    async fn unchanged_scans_keep_the_snapshot_and_stay_quiet() {
+        let cache_dir = tempdir().unwrap();
+        let config = Arc::new(AppConfig::for_tests(
+            sample_media_root(),
+            cache_dir.path().to_path_buf(),
+        ));
+        let cache_store = Arc::new(CacheStore::new(cache_dir.path()));
+        let snapshot_state = Arc::new(RwLock::new(CacheSnapshot::new(Vec::new())));
+        let state = AppState::new(config, cache_store.clone(), snapshot_state.clone());
+        let mut updates = state.index_updates.subscribe();
+        let files = Indexer::scan_once(sample_media_root()).unwrap();
+        let scan = |files: Vec<crate::indexer::MediaFile>| IndexEvent::Snapshot {
+            files,
+            summary: ScanSummary::default(),
+            scanned_at: Utc::now(),
+            duration: Duration::from_millis(1),
+        };
+
+        state.apply_index_event(scan(files.clone())).await;
+        assert!(updates.try_recv().is_ok());
+        let generated_at = snapshot_state.read().await.generated_at;
+
+        state.apply_index_event(scan(files)).await;
+        assert!(updates.try_recv().is_err());
+        assert_eq!(snapshot_state.read().await.generated_at, generated_at);
+        let persisted = cache_store.load().unwrap().unwrap();
+        assert_eq!(persisted.generated_at, generated_at);
+        assert!(state.scan_status.read().await.last_scan_error.is_none());
+    }
+
+    #[tokio::test]
+    async fn manual_rebuild_of_an_empty_root_keeps_the_previous_snapshot() {
+        let cache_dir = tempdir().unwrap();
+        let unmounted = tempdir().unwrap();
+        let config = Arc::new(AppConfig::for_tests(
+            unmounted.path().to_path_buf(),
+            cache_dir.path().to_path_buf(),
+        ));
+        let cache_store = Arc::new(CacheStore::new(cache_dir.path()));
+        let files = Indexer::scan_once(sample_media_root()).unwrap();
+        let previous = cache_store.persist(files.clone()).unwrap();
+        let generated_at = previous.generated_at;
+        let snapshot_state = Arc::new(RwLock::new(previous));
+        let state = AppState::new(config, cache_store.clone(), snapshot_state.clone());
+        let app = router(state.clone());
+
+        let request = Request::builder()
+            .method(Method::POST)
+            .uri("/api/v1/index/rebuild?wait=true")
+            .body(Body::empty())
+            .unwrap();
+        let response = app.oneshot(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::INTERNAL_SERVER_ERROR);
+
+        assert_eq!(snapshot_state.read().await.media.len(), files.len());
+        let persisted = cache_store.load().unwrap().unwrap();
+        assert_eq!(persisted.media.len(), files.len());
+        assert_eq!(persisted.generated_at, generated_at);
+        let status = state.scan_status.read().await;
+        assert!(
+            status
+                .last_scan_error
+                .as_deref()
+                .is_some_and(|error| error.contains("kept the previous snapshot"))
+        );
+    }
+
+    #[tokio::test]
+    async fn readiness_waits_for_the_first_snapshot() {
+        let cache_dir = tempdir().unwrap();
+        let config = Arc::new(AppConfig::for_tests(
+            sample_media_root(),
+            cache_dir.path().to_path_buf(),
+        ));
+        let cache_store = Arc::new(CacheStore::new(cache_dir.path()));
+        let snapshot_state = Arc::new(RwLock::new(CacheSnapshot::new(Vec::new())));
+        let state = AppState::new(config, cache_store, snapshot_state);
+        let app = router(state);
+
+        let (status, _) = get_text(&app, "/healthz/live").await;
+        assert_eq!(status, StatusCode::OK);
+        let (status, body) = get_text(&app, "/healthz/ready").await;
+        assert_eq!(status, StatusCode::SERVICE_UNAVAILABLE);
+        let json: Value = serde_json::from_str(&body).unwrap();
+        assert_eq!(json["error"]["code"], "SERVICE_UNAVAILABLE");
+
+        let request = Request::builder()
+            .method(Method::POST)
+            .uri("/api/v1/index/rebuild?wait=true")
+            .body(Body::empty())
+            .unwrap();
+        let response = app.clone().oneshot(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let (status, body) = get_text(&app, "/healthz/ready").await;
+        assert_eq!(status, StatusCode::OK);
+        let json: Value = serde_json::from_str(&body).unwrap();
+        assert_eq!(json["status"], "ok");
+    }
+
+    #[tokio::test]
+    async fn rebuild_endpoint_rejects_concurrent_rebuilds() {
+        let cache_dir = tempdir().unwrap();
+        let config = Arc::new(AppConfig::for_tests(
+            sample_media_root(),
+            cache_dir.path().to_path_buf(),
+        ));
+        let cache_store = Arc::new(CacheStore::new(cache_dir.path()));
+        let snapshot_state = Arc::new(RwLock::new(CacheSnapshot::new(Vec::new())));
+        let state = AppState::new(config, cache_store, snapshot_state.clone());
+        let rebuild_lock = state.rebuild_lock.clone();
+        let mut app = router(state);
+
+        // Simulate a rebuild that is still running.
+        let running = rebuild_lock.clone().lock_owned().await;
+        let request = Request::builder()
+            .method(Method::POST)
+            .uri("/api/v1/index/rebuild")
+            .body(Body::empty())
+            .unwrap();
+        let response = app.clone().oneshot(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::CONFLICT);
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        let json: Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(json["error"]["code"], "CONFLICT");
+        drop(running);
+
+        assert_eq!(post_rebuild(&mut app).await, StatusCode::ACCEPTED);
+        assert_eq!(post_rebuild(&mut app).await, StatusCode::CONFLICT);
+
+        // The lock is released once the accepted rebuild finishes.
+        let _idle = timeout(Duration::from_secs(2), rebuild_lock.lock())
+            .await
+            .expect("rebuild did not release the lock");
+        assert!(snapshot_state.read().await.media.len() >= 3);
+    }
+
+    async fn post_rebuild_if_match(app: &Router, if_match: &str) -> (StatusCode, Value) {
+        let request = Request::builder()
+            .method(Method::POST)
+            .uri("/api/v1/index/rebuild")
+            .header(IF_MATCH, if_match)
+            .body(Body::empty())
+            .unwrap();
+        let response = app.clone().oneshot(request).await.unwrap();
+        let status = response.status();
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        (status, serde_json::from_slice(&body).unwrap())
+    }
+
+    #[tokio::test]
+    async fn rebuild_with_wait_returns_completed_snapshot() {
+        let cache_dir = tempdir().unwrap();
+        let media_root = sample_media_root();
+        let expected = Indexer::scan(&IndexerConfig::new(&media_root))
+            .unwrap()
+            .len();
+        let config = Arc::new(AppConfig::for_tests(
+            media_root,
+            cache_dir.path().to_path_buf(),
+        ));
+        let snapshot_state = Arc::new(RwLock::new(CacheSnapshot::new(Vec::new())));
+        let app = router(AppState::new(
+            config,
+            Arc::new(CacheStore::new(cache_dir.path())),
+            snapshot_state.clone(),
+        ));
+
+        let request = Request::builder()
+            .method(Method::POST)
+            .uri("/api/v1/index/rebuild?wait=true")
+            .body(Body::empty())
+            .unwrap();
+        let response = app.oneshot(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        let json: Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(json["status"], "completed");
+        assert_eq!(json["itemCount"], expected);
+        assert!(json["durationMs"].is_u64());
+        // The snapshot is already live when the response arrives.
+        assert_eq!(snapshot_state.read().await.media.len(), expected);
+    }
+
+    #[tokio::test]
+    async fn rebuild_if_match_accepts_current_snapshot_version() {
+        let cache_dir = tempdir().unwrap();
+        let config = Arc::new(AppConfig::for_tests(
+            sample_media_root(),
+            cache_dir.path().to_path_buf(),
+        ));
+        let snapshot_state = Arc::new(RwLock::new(CacheSnapshot::new(Vec::new())));
+        let current = snapshot_state.read().await.generated_at;
+        let state = AppState::new(
+            config,
+            Arc::new(CacheStore::new(cache_dir.path())),
+            snapshot_state.clone(),
+        );
+        let rebuild_lock = state.rebuild_lock.clone();
+        let app = router(state);
+
+        let (status, json) =
+            post_rebuild_if_match(&app, &format!("\"{}\"", current.to_rfc3339())).await;
+        assert_eq!(status, StatusCode::ACCEPTED);
+        assert_eq!(json["status"], "queued");
+        let promised = DateTime::parse_from_rfc3339(json["generatedAt"].as_str().unwrap()).unwrap();
+
+        let _idle = timeout(Duration::from_secs(2), rebuild_lock.lock())
+            .await
+            .expect("rebuild did not finish in time");
+        let snapshot = snapshot_state.read().await;
+        assert_eq!(snapshot.generated_at, promised);
+        assert!(snapshot.media.len() >= 3);
+    }
+
+    #[tokio::test]
+    async fn rebuild_if_match_rejects_stale_snapshot_version() {
+        let cache_dir = tempdir().unwrap();
+        let config = Arc::new(AppConfig::for_tests(
+            sample_media_root(),
+            cache_dir.path().to_path_buf(),
+        ));
+        let snapshot_state = Arc::new(RwLock::new(CacheSnapshot::new(Vec::new())));
+        let stale = snapshot_state.read().await.generated_at - chrono::Duration::seconds(30);
+        let app = router(AppState::new(
+            config,
+            Arc::new(CacheStore::new(cache_dir.path())),
+            snapshot_state.clone(),
+        ));
+
+        let (status, json) = post_rebuild_if_match(&app, &stale.to_rfc3339()).await;
+        assert_eq!(status, StatusCode::CONFLICT);
+        assert_eq!(json["error"]["code"], "CONFLICT");
+        assert!(snapshot_state.read().await.media.is_empty());
+
+        let (status, json) = post_rebuild_if_match(&app, "\"not-a-version\"").await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert_eq!(json["error"]["code"], "VALIDATION_FAILED");
+    }
+
     #[cfg(unix)]
     #[tokio::test]
     async fn rebuild_endpoint_handles_persist_failure() {
         let media_root = sample_media_root();
         let cache_dir = tempdir().unwrap();
-        let config = Arc::new(test_config(media_root, cache_dir.path().to_path_buf()));
+        let config = Arc::new(AppConfig::for_tests(
+            media_root,
+            cache_dir.path().to_path_buf(),
+        ));
         let cache_store = Arc::new(CacheStore::new(cache_dir.path()));
         let initial_snapshot = CacheSnapshot::new(Vec::new());
         let snapshot_state = Arc::new(RwLock::new(initial_snapshot));
@@ -347,11 +1255,307 @@ mod tests {
         fs::set_permissions(cache_dir.path(), fs::Permissions::from_mode(0o755)).unwrap();
     }
 
+    fn frontend_app() -> (tempfile::TempDir, Router) {
+        let dist = tempdir().unwrap();
+        fs::write(dist.path().join("index.html"), "<html>app shell</html>").unwrap();
+        fs::create_dir(dist.path().join("assets")).unwrap();
+        fs::write(dist.path().join("assets/app.js"), "console.log('app');").unwrap();
+
+        let mut config = AppConfig::for_tests(sample_media_root(), dist.path().to_path_buf());
+        config.frontend_dist_dir = Some(dist.path().to_path_buf());
+        let cache_store = Arc::new(CacheStore::new(dist.path()));
+        let snapshot_state = Arc::new(RwLock::new(CacheSnapshot::new(Vec::new())));
+        let app = router(AppState::new(Arc::new(config), cache_store, snapshot_state));
+        (dist, app)
+    }
+
+    async fn get_text(app: &Router, uri: &str) -> (StatusCode, String) {
+        let request = Request::builder()
+            .method(Method::GET)
+            .uri(uri)
+            .body(Body::empty())
+            .unwrap();
+        let response = app.clone().oneshot(request).await.unwrap();
+        let status = response.status();
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        (status, String::from_utf8(body.to_vec()).unwrap())
+    }
+
+    #[tokio::test]
+    async fn serves_frontend_assets_with_spa_fallback() {
+        let (_dist, app) = frontend_app();
+
+        let (status, body) = get_text(&app, "/assets/app.js").await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body, "console.log('app');");
+
+        let (status, body) = get_text(&app, "/gallery/some/deep/route").await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body, "<html>app shell</html>");
+
+        let (status, _) = get_text(&app, "/healthz").await;
+        assert_eq!(status, StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn api_misses_keep_error_envelope_with_frontend() {
+        let (_dist, app) = frontend_app();
+
+        for uri in ["/api/v1/missing", "/api/v2/media"] {
+            let (status, body) = get_text(&app, uri).await;
+            assert_eq!(status, StatusCode::NOT_FOUND, "{uri}");
+            let json: Value = serde_json::from_str(&body).unwrap();
+            assert_eq!(json["error"]["code"], "RESOURCE_NOT_FOUND", "{uri}");
+        }
+    }
+
+    #[tokio::test]
+    async fn rate_limit_rejects_excess_requests_with_envelope() {
+        let cache_dir = tempdir().unwrap();
+        let mut config = AppConfig::for_tests(sample_media_root(), cache_dir.path().to_path_buf());
+        config.rate_limit_per_minute = Some(2);
+        let cache_store = Arc::new(CacheStore::new(cache_dir.path()));
+        let snapshot_state = Arc::new(RwLock::new(CacheSnapshot::new(Vec::new())));
+        let app = router(AppState::new(Arc::new(config), cache_store, snapshot_state));
+
+        for _ in 0..2 {
+            let (status, _) = get_text(&app, "/api/v1/media").await;
+            assert_eq!(status, StatusCode::OK);
+        }
+
+        let request = Request::builder()
+            .method(Method::GET)
+            .uri("/api/v1/media")
+            .body(Body::empty())
+            .unwrap();
+        let response = app.clone().oneshot(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+        let retry_after: u64 = response.headers()[axum::http::header::RETRY_AFTER]
+            .to_str()
+            .unwrap()
+            .parse()
+            .unwrap();
+        assert!((1..=30).contains(&retry_after));
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        let json: Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(json["error"]["code"], "TOO_MANY_REQUESTS");
+
+        let (status, _) = get_text(&app, "/healthz").await;
+        assert_eq!(status, StatusCode::OK, "health checks are not rate limited");
+    }
+
+    #[tokio::test]
+    async fn rate_limit_keys_on_forwarded_client_only_behind_trusted_proxies() {
+        let cache_dir = tempdir().unwrap();
+        let mut config = AppConfig::for_tests(sample_media_root(), cache_dir.path().to_path_buf());
+        config.rate_limit_per_minute = Some(1);
+        config.trusted_proxies = TrustedProxies::new(vec!["10.0.0.0/8".parse().unwrap()]);
+        let cache_store = Arc::new(CacheStore::new(cache_dir.path()));
+        let snapshot_state = Arc::new(RwLock::new(CacheSnapshot::new(Vec::new())));
+        let app = router(AppState::new(Arc::new(config), cache_store, snapshot_state));
+
+        let send = |peer: &str, forwarded_for: &str| {
+            let request = Request::builder()
+                .method(Method::GET)
+                .uri("/api/v1/media")
+                .header("x-forwarded-for", forwarded_for)
+                .extension(ConnectInfo(SocketAddr::new(peer.parse().unwrap(), 40000)))
+                .body(Body::empty())
+                .unwrap();
+            let app = app.clone();
+            async move { app.oneshot(request).await.unwrap().status() }
+        };
+
+        // Behind the trusted proxy each forwarded client gets its own bucket.
+        assert_eq!(send("10.0.0.1", "203.0.113.1").await, StatusCode::OK);
+        assert_eq!(send("10.0.0.1", "203.0.113.2").await, StatusCode::OK);
+        assert_eq!(
+            send("10.0.0.1", "203.0.113.1").await,
+            StatusCode::TOO_MANY_REQUESTS
+        );
+
+        // A direct client cannot dodge its budget by spoofing the header.
+        assert_eq!(send("198.51.100.7", "203.0.113.3").await, StatusCode::OK);
+        assert_eq!(
+            send("198.51.100.7", "203.0.113.4").await,
+            StatusCode::TOO_MANY_REQUESTS
+        );
+    }
+
+    #[tokio::test]
+    async fn oversized_body_is_rejected_with_envelope() {
+        let cache_dir = tempdir().unwrap();
+        let mut config = AppConfig::for_tests(sample_media_root(), cache_dir.path().to_path_buf());
+        config.limits.max_body_bytes = 16;
+        let cache_store = Arc::new(CacheStore::new(cache_dir.path()));
+        let snapshot_state = Arc::new(RwLock::new(CacheSnapshot::new(Vec::new())));
+        let app = router(AppState::new(Arc::new(config), cache_store, snapshot_state));
+
+        let request = Request::builder()
+            .method(Method::POST)
+            .uri("/api/v1/index/rebuild")
+            .header(axum::http::header::CONTENT_LENGTH, 64)
+            .body(Body::from(vec![b'x'; 64]))
+            .unwrap();
+        let response = app.oneshot(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::PAYLOAD_TOO_LARGE);
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        let json: Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(json["error"]["code"], "VALIDATION_FAILED");
+    }
+
+    #[tokio::test]
+    async fn extractor_rejections_are_wrapped_in_envelope() {
+        let app = Router::new()
+            .route(
+                "/echo",
+                post(|Json(value): Json<Value>| async move { Json(value) }),
+            )
+            .layer(middleware::from_fn(api::ensure_error_envelope));
+
+        let request = Request::builder()
+            .method(Method::POST)
+            .uri("/echo")
+            .header(axum::http::header::CONTENT_TYPE, "text/plain")
+            .body(Body::from("{}"))
+            .unwrap();
+        let response = app.clone().oneshot(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::UNSUPPORTED_MEDIA_TYPE);
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        let json: Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(json["error"]["code"], "UNSUPPORTED_MEDIA_TYPE");
+        assert!(
+            json["error"]["message"]
+                .as_str()
+                .unwrap()
+                .contains("Content-Type")
+        );
+
+        let request = Request::builder()
+            .method(Method::POST)
+            .uri("/echo")
+            .header(axum::http::header::CONTENT_TYPE, "application/json")
+            .body(Body::from("{not json"))
+            .unwrap();
+        let response = app.oneshot(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        let json: Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(json["error"]["code"], "VALIDATION_FAILED");
+
+        let cache_dir = tempdir().unwrap();
+        let config = AppConfig::for_tests(sample_media_root(), cache_dir.path().to_path_buf());
+        let cache_store = Arc::new(CacheStore::new(cache_dir.path()));
+        let snapshot_state = Arc::new(RwLock::new(CacheSnapshot::new(Vec::new())));
+        let app = router(AppState::new(Arc::new(config), cache_store, snapshot_state));
+        let request = Request::builder()
+            .method(Method::POST)
+            .uri("/api/v1/index/rebuild?wait=maybe")
+            .body(Body::empty())
+            .unwrap();
+        let response = app.oneshot(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        let json: Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(json["error"]["code"], "VALIDATION_FAILED");
+        assert!(json["error"]["message"].as_str().unwrap().contains("wait"));
+    }
+
+    #[tokio::test]
+    async fn slow_handler_times_out_with_envelope() {
+        let limits = RequestLimits {
+            timeout: Some(Duration::from_millis(20)),
+            ..RequestLimits::default()
+        };
+        let app = with_request_limits(
+            Router::new().route(
+                "/slow",
+                get(|| async {
+                    time::sleep(Duration::from_secs(30)).await;
+                    "done"
+                }),
+            ),
+            &limits,
+        )
+        .layer(middleware::from_fn(api::ensure_error_envelope));
+
+        let request = Request::builder()
+            .method(Method::GET)
+            .uri("/slow")
+            .body(Body::empty())
+            .unwrap();
+        let response = timeout(Duration::from_secs(5), app.oneshot(request))
+            .await
+            .expect("timeout layer answers before the handler finishes")
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE);
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        let json: Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(json["error"]["code"], "SERVICE_UNAVAILABLE");
+    }
+
+    #[tokio::test]
+    async fn api_responses_carry_request_id() {
+        let cache_dir = tempdir().unwrap();
+        let config = AppConfig::for_tests(sample_media_root(), cache_dir.path().to_path_buf());
+        let cache_store = Arc::new(CacheStore::new(cache_dir.path()));
+        let snapshot_state = Arc::new(RwLock::new(CacheSnapshot::new(Vec::new())));
+        let app = router(AppState::new(Arc::new(config), cache_store, snapshot_state));
+
+        let request = Request::builder()
+            .method(Method::GET)
+            .uri("/api/v1/media")
+            .body(Body::empty())
+            .unwrap();
+        let response = app.clone().oneshot(request).await.unwrap();
+        let generated = response.headers()[X_REQUEST_ID].to_str().unwrap();
+        assert_eq!(generated.len(), 36, "generated ids are UUIDs: {generated}");
+
+        let request = Request::builder()
+            .method(Method::GET)
+            .uri("/api/v1/media/0123456789abcdef0123456789abcdef01234567")
+            .header(X_REQUEST_ID, "client-supplied-42")
+            .body(Body::empty())
+            .unwrap();
+        let response = app.oneshot(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+        assert_eq!(response.headers()[X_REQUEST_ID], "client-supplied-42");
+    }
+
+    #[tokio::test]
+    async fn metrics_endpoint_exports_request_and_search_counters() {
+        metrics::init();
+        let cache_dir = tempdir().unwrap();
+        let config = Arc::new(AppConfig::for_tests(
+            sample_media_root(),
+            cache_dir.path().to_path_buf(),
+        ));
+        let cache_store = Arc::new(CacheStore::new(cache_dir.path()));
+        let snapshot_state = Arc::new(RwLock::new(CacheSnapshot::new(Vec::new())));
+        let app = router(AppState::new(config, cache_store, snapshot_state));
+
+        assert_eq!(get_text(&app, "/healthz").await.0, StatusCode::OK);
+        assert_eq!(get_text(&app, "/api/v1/media").await.0, StatusCode::OK);
+
+        let (status, body) = get_text(&app, "/metrics").await;
+        assert_eq!(status, StatusCode::OK);
+        assert!(body.contains(metrics::HTTP_REQUESTS_TOTAL), "{body}");
+        assert!(body.contains(r#"route="/healthz""#), "{body}");
+        assert!(body.contains(r#"route="/api/v1/media""#), "{body}");
+        assert!(
+            body.contains(&format!("{}_bucket", metrics::SEARCH_DURATION_SECONDS)),
+            "{body}"
+        );
+    }
+
     #[tokio::test]
     async fn fallback_returns_standard_error() {
         let media_root = sample_media_root();
         let cache_dir = tempdir().unwrap();
-        let config = Arc::new(test_config(media_root, cache_dir.path().to_path_buf()));
+        let config = Arc::new(AppConfig::for_tests(
+            media_root,
+            cache_dir.path().to_path_buf(),
+        ));
         let cache_store = Arc::new(CacheStore::new(cache_dir.path()));
         let snapshot_state = Arc::new(RwLock::new(CacheSnapshot::new(Vec::new())));
 
@@ -380,7 +1584,10 @@ mod tests {
     async fn method_not_allowed_returns_standard_error() {
         let media_root = sample_media_root();
         let cache_dir = tempdir().unwrap();
-        let config = Arc::new(test_config(media_root, cache_dir.path().to_path_buf()));
+        let config = Arc::new(AppConfig::for_tests(
+            media_root,
+            cache_dir.path().to_path_buf(),
+        ));
         let cache_store = Arc::new(CacheStore::new(cache_dir.path()));
         let snapshot_state = Arc::new(RwLock::new(CacheSnapshot::new(Vec::new())));
 
@@ -1,3 +1,5 @@
 pub mod search;
 
-pub use search::{SearchQuery, SearchResult, SearchService};
+pub use search::{
+    SearchQuery, SearchResult, SearchService, SortField, SortOrder, SortSpec, TagClause, TagFacets,
+};
@@ -1,19 +1,282 @@
-use std::collections::{HashMap, HashSet};
+use std::{
+    cmp::Ordering,
+    collections::{BTreeMap, HashMap, HashSet},
+    path::Path,
+};
 
 use tracing::instrument;
+use unicode_normalization::UnicodeNormalization;
 
-use crate::{cache::CacheSnapshot, indexer::MediaFile, tags::TagKind};
+use crate::{
+    cache::CacheSnapshot,
+    indexer::{GeoPoint, MediaFile},
+    tags::{TagAliases, TagKind, normalize_tag_text},
+};
 
-const DEFAULT_PAGE_SIZE: usize = 60;
-const MAX_PAGE_SIZE: usize = 200;
+/// Shared key/value tags (e.g. `location=okinawa`) say more about relatedness than
+/// shared simple tags, so they count this many times as much.
+const RELATED_KEY_VALUE_WEIGHT: usize = 2;
+
+/// Page size applied when a query omits one, and the largest page a query may request.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PageLimits {
+    pub default_size: usize,
+    pub max_size: usize,
+}
+
+impl PageLimits {
+    /// Resolve a requested page size: 0 means the default, larger sizes are clamped.
+    pub fn page_size(&self, requested: usize) -> usize {
+        if requested == 0 {
+            self.default_size
+        } else {
+            requested.min(self.max_size)
+        }
+    }
+}
+
+impl Default for PageLimits {
+    fn default() -> Self {
+        Self {
+            default_size: 60,
+            max_size: 200,
+        }
+    }
+}
+
+/// Field used to order search results. Ties are always broken by id.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum SortField {
+    #[default]
+    Id,
+    Name,
+    Filesize,
+    IndexedAt,
+    /// File modification time; media without one go last.
+    ModifiedAt,
+    Duration,
+    /// Pseudo-random but reproducible: the same seed always yields the same order.
+    Random {
+        seed: u64,
+    },
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum SortOrder {
+    #[default]
+    Asc,
+    Desc,
+}
+
+impl SortOrder {
+    fn apply(self, ordering: Ordering) -> Ordering {
+        match self {
+            SortOrder::Asc => ordering,
+            SortOrder::Desc => ordering.reverse(),
+        }
+    }
+}
+
+/// Requested result ordering; defaults to id ascending.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct SortSpec {
+    pub field: SortField,
+    pub order: SortOrder,
+}
+
+impl SortSpec {
+    pub fn new(field: SortField, order: SortOrder) -> Self {
+        Self { field, order }
+    }
+
+    /// Total order over media for this spec. Items lacking the sort value go last.
+    fn compare(&self, a: &MediaFile, b: &MediaFile) -> Ordering {
+        let primary = match self.field {
+            SortField::Id => return self.order.apply(a.id.cmp(&b.id)),
+            SortField::Name => self.order.apply(file_name(a).cmp(&file_name(b))),
+            SortField::Filesize => self.order.apply(a.filesize.cmp(&b.filesize)),
+            SortField::IndexedAt => self.order.apply(a.indexed_at.cmp(&b.indexed_at)),
+            SortField::ModifiedAt => self.missing_last(a.modified_at, b.modified_at),
+            SortField::Duration => self.missing_last(a.duration_ms, b.duration_ms),
+            SortField::Random { seed } => self
+                .order
+                .apply(shuffle_key(seed, &a.id).cmp(&shuffle_key(seed, &b.id))),
+        };
+        primary.then_with(|| a.id.cmp(&b.id))
+    }
+
+    fn missing_last<T: Ord>(&self, a: Option<T>, b: Option<T>) -> Ordering {
+        match (a, b) {
+            (Some(a), Some(b)) => self.order.apply(a.cmp(&b)),
+            (Some(_), None) => Ordering::Less,
+            (None, Some(_)) => Ordering::Greater,
+            (None, None) => Ordering::Equal,
+        }
+    }
+}
+
+/// Seeded position of `id` in a shuffled order: FNV-1a over the id, mixed with the
+/// seed through the SplitMix64 finalizer. Stable across releases and platforms, so
+/// pages and cursors for one seed stay consistent without materializing a permutation.
+fn shuffle_key(seed: u64, id: &str) -> u64 {
+    let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+    for byte in id.bytes() {
+        hash ^= u64::from(byte);
+        hash = hash.wrapping_mul(0x0100_0000_01b3);
+    }
+    let mut z = hash ^ seed.wrapping_mul(0x9e37_79b9_7f4a_7c15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    z ^ (z >> 31)
+}
+
+/// How `tags` query terms are compared with simple tag names. Key/value tag names
+/// and attribute filters always match exactly.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum TagMatch {
+    #[default]
+    Exact,
+    /// A simple tag matches when it contains the term.
+    Substring,
+    /// A simple tag matches within a small Levenshtein distance of the term: one edit
+    /// for terms of up to four characters, two for longer ones.
+    Fuzzy,
+}
+
+impl TagMatch {
+    fn accepts(self, tag: &str, term: &str) -> bool {
+        match self {
+            TagMatch::Exact => tag == term,
+            TagMatch::Substring => tag.contains(term),
+            TagMatch::Fuzzy => {
+                let threshold = if term.chars().count() <= 4 { 1 } else { 2 };
+                strsim::levenshtein(tag, term) <= threshold
+            }
+        }
+    }
+}
+
+/// One comma-separated term of the `tags` query: matches when the media carries any of
+/// `alternatives`, inverted when `negated`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TagClause {
+    alternatives: Vec<String>,
+    negated: bool,
+}
+
+impl TagClause {
+    /// Build a clause, returning `None` when no alternative survives normalization.
+    pub fn new<S: AsRef<str>>(
+        alternatives: impl IntoIterator<Item = S>,
+        negated: bool,
+    ) -> Option<Self> {
+        let alternatives: Vec<String> = alternatives
+            .into_iter()
+            .filter_map(normalize_token)
+            .collect();
+        if alternatives.is_empty() {
+            None
+        } else {
+            Some(Self {
+                alternatives,
+                negated,
+            })
+        }
+    }
+
+    /// Widen every alternative to all of its alias spellings.
+    pub fn with_aliases(mut self, aliases: &TagAliases) -> Self {
+        let mut expanded: Vec<String> = Vec::with_capacity(self.alternatives.len());
+        for term in self
+            .alternatives
+            .iter()
+            .flat_map(|term| aliases.expand(term))
+        {
+            if !expanded.contains(&term) {
+                expanded.push(term);
+            }
+        }
+        self.alternatives = expanded;
+        self
+    }
+
+    pub fn alternatives(&self) -> &[String] {
+        &self.alternatives
+    }
+
+    pub fn negated(&self) -> bool {
+        self.negated
+    }
+
+    fn matches(&self, tag_set: &HashSet<&str>, simple_tags: &[&str], mode: TagMatch) -> bool {
+        let present = self.alternatives.iter().any(|term| {
+            tag_set.contains(term.as_str())
+                || (mode != TagMatch::Exact
+                    && simple_tags.iter().any(|tag| mode.accepts(tag, term)))
+        });
+        present != self.negated
+    }
+}
+
+/// Longitude/latitude rectangle in decimal degrees. `min_lon > max_lon` describes a box
+/// crossing the antimeridian.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BoundingBox {
+    min_lon: f64,
+    min_lat: f64,
+    max_lon: f64,
+    max_lat: f64,
+}
+
+// Construction rejects NaN, so equality is reflexive.
+impl Eq for BoundingBox {}
+
+impl BoundingBox {
+    /// Validate a box; `None` when a coordinate is out of range or `min_lat > max_lat`.
+    pub fn new(min_lon: f64, min_lat: f64, max_lon: f64, max_lat: f64) -> Option<Self> {
+        let lon_ok = |lon: f64| (-180.0..=180.0).contains(&lon);
+        let lat_ok = |lat: f64| (-90.0..=90.0).contains(&lat);
+        (lon_ok(min_lon)
+            && lon_ok(max_lon)
+            && lat_ok(min_lat)
+            && lat_ok(max_lat)
+            && min_lat <= max_lat)
+            .then_some(Self {
+                min_lon,
+                min_lat,
+                max_lon,
+                max_lat,
+            })
+    }
+
+    pub fn contains(&self, point: &GeoPoint) -> bool {
+        let lat_ok = (self.min_lat..=self.max_lat).contains(&point.latitude);
+        let lon_ok = if self.min_lon <= self.max_lon {
+            (self.min_lon..=self.max_lon).contains(&point.longitude)
+        } else {
+            point.longitude >= self.min_lon || point.longitude <= self.max_lon
+        };
+        lat_ok && lon_ok
+    }
+}
 
 /// Normalized search input used by the backend.
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct SearchQuery {
-    required_tags: Vec<String>,
+    tag_clauses: Vec<TagClause>,
+    any_tags: Option<TagClause>,
     attribute_filters: HashMap<String, HashSet<String>>,
+    /// `attribute_filters` with values in their original case, for case-sensitive matching.
+    exact_attribute_filters: HashMap<String, HashSet<String>>,
+    case_sensitive_attributes: bool,
     page: usize,
+    /// As requested; resolved against `page_limits` by [`SearchQuery::page_size`].
     page_size: usize,
+    page_limits: PageLimits,
+    cursor: Option<String>,
+    sort: SortSpec,
+    tag_match: TagMatch,
+    bbox: Option<BoundingBox>,
 }
 
 impl SearchQuery {
@@ -23,32 +286,117 @@ impl SearchQuery {
         page: usize,
         page_size: usize,
     ) -> Self {
-        let required_tags = tags.into_iter().filter_map(normalize_token).collect();
-
-        let attribute_filters = attributes
+        let tag_clauses = tags
             .into_iter()
-            .filter_map(|(key, values)| {
-                let key = normalize_token(key)?;
-                let value_set: HashSet<String> =
-                    values.into_iter().filter_map(normalize_token).collect();
-                if value_set.is_empty() {
-                    None
-                } else {
-                    Some((key, value_set))
-                }
-            })
+            .filter_map(|tag| TagClause::new([tag], false))
             .collect();
 
+        let mut attribute_filters = HashMap::new();
+        let mut exact_attribute_filters = HashMap::new();
+        for (key, values) in attributes {
+            let Some(key) = normalize_token(key) else {
+                continue;
+            };
+            let exact: HashSet<String> = values
+                .iter()
+                .map(|value| value.trim())
+                .filter(|value| !value.is_empty())
+                .map(|value| value.nfc().collect())
+                .collect();
+            if exact.is_empty() {
+                continue;
+            }
+            attribute_filters.insert(
+                key.clone(),
+                exact.iter().filter_map(normalize_token).collect(),
+            );
+            exact_attribute_filters.insert(key, exact);
+        }
+
         Self {
-            required_tags,
+            tag_clauses,
+            any_tags: None,
             attribute_filters,
+            exact_attribute_filters,
+            case_sensitive_attributes: false,
             page: normalize_page(page),
-            page_size: normalize_page_size(page_size),
+            page_size,
+            page_limits: PageLimits::default(),
+            cursor: None,
+            sort: SortSpec::default(),
+            tag_match: TagMatch::default(),
+            bbox: None,
         }
     }
 
-    pub fn required_tags(&self) -> &[String] {
-        &self.required_tags
+    /// Add structured tag clauses (OR alternatives / negation) on top of the plain tags.
+    pub fn with_tag_clauses(mut self, clauses: impl IntoIterator<Item = TagClause>) -> Self {
+        self.tag_clauses.extend(clauses);
+        self
+    }
+
+    /// Additionally require at least one of `tags`; blank terms are ignored.
+    pub fn with_any_tags<S: AsRef<str>>(mut self, tags: impl IntoIterator<Item = S>) -> Self {
+        self.any_tags = TagClause::new(tags, false);
+        self
+    }
+
+    /// Compare attribute values exactly as written in the filename instead of
+    /// case-insensitively. Tag filters are unaffected.
+    pub fn with_case_sensitive_attributes(mut self, case_sensitive: bool) -> Self {
+        self.case_sensitive_attributes = case_sensitive;
+        self
+    }
+
+    /// Let each tag term set so far also match its aliases, so `nyc` finds media tagged
+    /// `new-york` and vice versa.
+    pub fn with_tag_aliases(mut self, aliases: &TagAliases) -> Self {
+        if aliases.is_empty() {
+            return self;
+        }
+        self.tag_clauses = self
+            .tag_clauses
+            .into_iter()
+            .map(|clause| clause.with_aliases(aliases))
+            .collect();
+        self.any_tags = self.any_tags.map(|clause| clause.with_aliases(aliases));
+        self
+    }
+
+    pub fn with_tag_match(mut self, tag_match: TagMatch) -> Self {
+        self.tag_match = tag_match;
+        self
+    }
+
+    /// Resolve the page size against deployment-specific limits instead of the defaults.
+    pub fn with_page_limits(mut self, page_limits: PageLimits) -> Self {
+        self.page_limits = page_limits;
+        self
+    }
+
+    pub fn with_sort(mut self, sort: SortSpec) -> Self {
+        self.sort = sort;
+        self
+    }
+
+    /// Only keep media located inside `bbox`; media without a location are dropped.
+    pub fn with_bbox(mut self, bbox: Option<BoundingBox>) -> Self {
+        self.bbox = bbox;
+        self
+    }
+
+    /// Resume after the given media id instead of using offset pagination.
+    pub fn with_cursor(mut self, cursor: Option<String>) -> Self {
+        self.cursor = cursor.filter(|cursor| !cursor.is_empty());
+        self
+    }
+
+    pub fn tag_clauses(&self) -> &[TagClause] {
+        &self.tag_clauses
+    }
+
+    pub fn any_tags(&self) -> Option<&TagClause> {
+        self.any_tags.as_ref()
     }
 
     pub fn attribute_filters(&self) -> &HashMap<String, HashSet<String>> {
@@ -60,17 +408,71 @@ impl SearchQuery {
     }
 
     pub fn page_size(&self) -> usize {
-        self.page_size
+        self.page_limits.page_size(self.page_size)
+    }
+
+    pub fn cursor(&self) -> Option<&str> {
+        self.cursor.as_deref()
+    }
+
+    pub fn sort(&self) -> SortSpec {
+        self.sort
+    }
+
+    pub fn tag_match(&self) -> TagMatch {
+        self.tag_match
+    }
+
+    pub fn bbox(&self) -> Option<BoundingBox> {
+        self.bbox
+    }
+
+    /// Canonical text of everything that shapes the result page: equal for queries that
+    /// are equivalent up to attribute ordering, so it can key caches and ETags.
+    pub fn cache_key(&self) -> String {
+        let attributes = if self.case_sensitive_attributes {
+            &self.exact_attribute_filters
+        } else {
+            &self.attribute_filters
+        };
+        let attributes: BTreeMap<&str, Vec<&str>> = attributes
+            .iter()
+            .map(|(key, values)| {
+                let mut values: Vec<&str> = values.iter().map(String::as_str).collect();
+                values.sort_unstable();
+                (key.as_str(), values)
+            })
+            .collect();
+        format!(
+            "tags={:?};any={:?};attributes={attributes:?};case={};match={:?};bbox={:?};sort={:?};page={};size={};cursor={:?}",
+            self.tag_clauses,
+            self.any_tags,
+            self.case_sensitive_attributes,
+            self.tag_match,
+            self.bbox,
+            self.sort,
+            self.page,
+            self.page_size(),
+            self.cursor,
+        )
     }
 }
 
 impl Default for SearchQuery {
     fn default() -> Self {
         Self {
-            required_tags: Vec::new(),
+            tag_clauses: Vec::new(),
+            any_tags: None,
             attribute_filters: HashMap::new(),
+            exact_attribute_filters: HashMap::new(),
+            case_sensitive_attributes: false,
             page: 1,
-            page_size: DEFAULT_PAGE_SIZE,
+            page_size: 0,
+            page_limits: PageLimits::default(),
+            cursor: None,
+            sort: SortSpec::default(),
+            tag_match: TagMatch::default(),
+            bbox: None,
         }
     }
 }
@@ -79,8 +481,25 @@ impl Default for SearchQuery {
 pub struct SearchResult {
     pub items: Vec<MediaFile>,
     pub total: usize,
+    /// Size of the whole library, regardless of filters.
+    pub grand_total: usize,
     pub page: usize,
     pub page_size: usize,
+    /// Number of `page_size` pages needed for `total`; 0 when nothing matches.
+    pub total_pages: usize,
+    /// Whether matches follow the returned page.
+    pub has_next: bool,
+    /// Whether matches precede the returned page (also true past the last page).
+    pub has_prev: bool,
+    /// Id of the last returned item when more results follow it.
+    pub next_cursor: Option<String>,
+}
+
+/// Tag vocabulary of the media matching a query, with per-media occurrence counts.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TagFacets {
+    pub tags: BTreeMap<String, usize>,
+    pub attributes: BTreeMap<String, BTreeMap<String, usize>>,
 }
 
 pub struct SearchService;
@@ -89,7 +508,7 @@ impl SearchService {
     #[instrument(
         skip(snapshot, query),
         fields(
-            galarie.search.tags_count = query.required_tags().len(),
+            galarie.search.tags_count = query.tag_clauses().len(),
             galarie.search.attributes_count = query.attribute_filters().len(),
             galarie.search.page = query.page(),
             galarie.search.page_size = query.page_size(),
@@ -98,29 +517,34 @@ impl SearchService {
         )
     )]
     pub fn search(snapshot: &CacheSnapshot, query: &SearchQuery) -> SearchResult {
-        let start_index = (query.page().saturating_sub(1)) * query.page_size();
-        let mut collected = Vec::with_capacity(query.page_size());
-        let mut matched_total = 0usize;
-
-        for media in &snapshot.media {
-            if !matches_required_tags(media, query.required_tags()) {
-                continue;
-            }
-            if !matches_attributes(media, query.attribute_filters()) {
-                continue;
-            }
+        let mut matches: Vec<&MediaFile> = filter_matches(snapshot, query).collect();
+        // Ids are stable across scans, so breaking ties by them keeps pages (and cursors) consistent.
+        let sort = query.sort();
+        matches.sort_by(|a, b| sort.compare(a, b));
 
-            if matched_total >= start_index && collected.len() < query.page_size() {
-                collected.push(media.clone());
-            }
-            matched_total += 1;
+        let total = matches.len();
+        let start_index = match query.cursor() {
+            Some(cursor) => cursor_start(snapshot, &matches, sort, cursor),
+            None => (query.page().saturating_sub(1)) * query.page_size(),
         }
+        .min(total);
+        let end_index = start_index.saturating_add(query.page_size()).min(total);
+        let page_items = &matches[start_index..end_index];
+        let next_cursor = match page_items.last() {
+            Some(last) if end_index < total => Some(last.id.clone()),
+            _ => None,
+        };
 
         let result = SearchResult {
-            items: collected,
-            total: matched_total,
+            items: page_items.iter().map(|media| (*media).clone()).collect(),
+            total,
+            grand_total: snapshot.media.len(),
             page: query.page(),
             page_size: query.page_size(),
+            total_pages: total.div_ceil(query.page_size()),
+            has_next: end_index < total,
+            has_prev: start_index > 0,
+            next_cursor,
         };
 
         let span = tracing::Span::current();
@@ -129,16 +553,205 @@ impl SearchService {
 
         result
     }
+
+    /// Up to `limit` other media ranked by how many normalized tags they share with
+    /// `media_id`, key/value tags weighing [`RELATED_KEY_VALUE_WEIGHT`]; ties go by id.
+    /// Media sharing no tag are left out, and the result is always a single page.
+    /// Callers bound `limit`; returns `None` when `media_id` is unknown.
+    pub fn related(snapshot: &CacheSnapshot, media_id: &str, limit: usize) -> Option<SearchResult> {
+        let source = snapshot.media.iter().find(|media| media.id == media_id)?;
+        let weights: HashMap<&str, usize> = source
+            .tags
+            .iter()
+            .map(|tag| {
+                let weight = match tag.kind {
+                    TagKind::KeyValue => RELATED_KEY_VALUE_WEIGHT,
+                    _ => 1,
+                };
+                (tag.normalized.as_str(), weight)
+            })
+            .collect();
+
+        let mut scored: Vec<(usize, &MediaFile)> = snapshot
+            .media
+            .iter()
+            .filter(|media| media.id != source.id)
+            .filter_map(|media| {
+                let shared: HashSet<&str> = media
+                    .tags
+                    .iter()
+                    .map(|tag| tag.normalized.as_str())
+                    .filter(|normalized| weights.contains_key(normalized))
+                    .collect();
+                let score: usize = shared.iter().map(|normalized| weights[normalized]).sum();
+                (score > 0).then_some((score, media))
+            })
+            .collect();
+        scored.sort_by(|(a_score, a), (b_score, b)| {
+            b_score.cmp(a_score).then_with(|| a.id.cmp(&b.id))
+        });
+
+        let page_size = limit.max(1);
+        Some(SearchResult {
+            total: scored.len(),
+            total_pages: usize::from(!scored.is_empty()),
+            items: scored
+                .into_iter()
+                .take(page_size)
+                .map(|(_, media)| media.clone())
+                .collect(),
+            grand_total: snapshot.media.len(),
+            page: 1,
+            page_size,
+            has_next: false,
+            has_prev: false,
+            next_cursor: None,
+        })
+    }
+
+    /// Every media matching the query's filters in its sort order, ignoring pagination.
+    pub fn matching<'a>(snapshot: &'a CacheSnapshot, query: &'a SearchQuery) -> Vec<&'a MediaFile> {
+        let mut matches: Vec<&MediaFile> = filter_matches(snapshot, query).collect();
+        let sort = query.sort();
+        matches.sort_by(|a, b| sort.compare(a, b));
+        matches
+    }
+
+    /// The media with the given ids in request order, as a single page. Unknown and
+    /// repeated ids are skipped; callers bound the number of ids.
+    pub fn by_ids<S: AsRef<str>>(snapshot: &CacheSnapshot, ids: &[S]) -> SearchResult {
+        let index: HashMap<&str, &MediaFile> = snapshot
+            .media
+            .iter()
+            .map(|media| (media.id.as_str(), media))
+            .collect();
+        let mut seen = HashSet::new();
+        let items: Vec<MediaFile> = ids
+            .iter()
+            .map(AsRef::as_ref)
+            .filter(|id| seen.insert(*id))
+            .filter_map(|id| index.get(id).map(|media| (*media).clone()))
+            .collect();
+        SearchResult {
+            total: items.len(),
+            total_pages: usize::from(!items.is_empty()),
+            page_size: items.len().max(1),
+            items,
+            grand_total: snapshot.media.len(),
+            page: 1,
+            has_next: false,
+            has_prev: false,
+            next_cursor: None,
+        }
+    }
+
+    /// Aggregate simple tags and key/value attributes over the media matching the
+    /// query's filters. Pagination, cursor and sort settings are ignored.
+    pub fn facets(snapshot: &CacheSnapshot, query: &SearchQuery) -> TagFacets {
+        let mut facets = TagFacets::default();
+        for media in filter_matches(snapshot, query) {
+            let mut seen = HashSet::new();
+            for tag in &media.tags {
+                if !seen.insert((tag.name.as_str(), tag.value.as_deref())) {
+                    continue;
+                }
+                match (&tag.kind, &tag.value) {
+                    (TagKind::KeyValue, Some(value)) => {
+                        *facets
+                            .attributes
+                            .entry(tag.name.clone())
+                            .or_default()
+                            .entry(value.clone())
+                            .or_default() += 1;
+                    }
+                    _ => *facets.tags.entry(tag.name.clone()).or_default() += 1,
+                }
+            }
+        }
+        facets
+    }
+}
+
+/// Index of the first match ordered after the cursor item. The cursor item itself may
+/// no longer match (or exist), so its position is derived from the sort order.
+fn cursor_start(
+    snapshot: &CacheSnapshot,
+    matches: &[&MediaFile],
+    sort: SortSpec,
+    cursor: &str,
+) -> usize {
+    if sort.field == SortField::Id {
+        return matches.partition_point(|media| {
+            sort.order.apply(media.id.as_str().cmp(cursor)) != Ordering::Greater
+        });
+    }
+    match snapshot.media.iter().find(|media| media.id == cursor) {
+        Some(anchor) => {
+            matches.partition_point(|media| sort.compare(media, anchor) != Ordering::Greater)
+        }
+        None => matches.len(),
+    }
+}
+
+fn file_name(media: &MediaFile) -> String {
+    Path::new(&media.relative_path)
+        .file_name()
+        .map(|name| name.to_string_lossy().to_lowercase())
+        .unwrap_or_default()
+}
+
+fn filter_matches<'a>(
+    snapshot: &'a CacheSnapshot,
+    query: &'a SearchQuery,
+) -> impl Iterator<Item = &'a MediaFile> {
+    snapshot
+        .media
+        .iter()
+        .filter(|media| {
+            matches_required_tags(
+                media,
+                query.tag_clauses(),
+                query.any_tags(),
+                query.tag_match(),
+            )
+        })
+        .filter(|media| {
+            if query.case_sensitive_attributes {
+                matches_exact_attributes(media, &query.exact_attribute_filters)
+            } else {
+                matches_attributes(media, query.attribute_filters())
+            }
+        })
+        .filter(|media| match query.bbox {
+            Some(bbox) => media
+                .location
+                .as_ref()
+                .is_some_and(|location| bbox.contains(location)),
+            None => true,
+        })
 }
 
-fn matches_required_tags(media: &MediaFile, required_tags: &[String]) -> bool {
-    if required_tags.is_empty() {
+/// Every clause must hold and, when given, at least one of the `any` tags must be present.
+fn matches_required_tags(
+    media: &MediaFile,
+    clauses: &[TagClause],
+    any: Option<&TagClause>,
+    mode: TagMatch,
+) -> bool {
+    if clauses.is_empty() && any.is_none() {
         return true;
     }
     let tag_set: HashSet<&str> = media.tags.iter().map(|tag| tag.name.as_str()).collect();
-    required_tags
+    let simple_tags: Vec<&str> = media
+        .tags
         .iter()
-        .all(|tag| tag_set.contains(tag.as_str()))
+        .filter(|tag| tag.kind == TagKind::Simple)
+        .map(|tag| tag.name.as_str())
+        .collect();
+    clauses
+        .iter()
+        .chain(any)
+        .all(|clause| clause.matches(&tag_set, &simple_tags, mode))
 }
 
 fn matches_attributes(media: &MediaFile, filters: &HashMap<String, HashSet<String>>) -> bool {
@@ -149,10 +762,10 @@ fn matches_attributes(media: &MediaFile, filters: &HashMap<String, HashSet<Strin
     for (key, allowed_values) in filters {
         let mut matched = false;
 
-        if let Some(value) = media.attributes.get(key) {
-            if allowed_values.contains(&value.to_lowercase()) {
-                matched = true;
-            }
+        if let Some(value) = media.attributes.get(key)
+            && attribute_value_matches(&normalize_tag_text(value), allowed_values)
+        {
+            matched = true;
         }
 
         if !matched {
@@ -161,7 +774,7 @@ fn matches_attributes(media: &MediaFile, filters: &HashMap<String, HashSet<Strin
                 .iter()
                 .filter(|tag| matches!(tag.kind, TagKind::KeyValue) && tag.name == *key)
                 .filter_map(|tag| tag.value.as_ref())
-                .any(|value| allowed_values.contains(value));
+                .any(|value| attribute_value_matches(value, allowed_values));
         }
 
         if !matched {
@@ -172,12 +785,64 @@ fn matches_attributes(media: &MediaFile, filters: &HashMap<String, HashSet<Strin
     true
 }
 
+/// Case-sensitive [`matches_attributes`]. Tag values are lowercased, so the original
+/// spelling is read back from the end of each key/value tag's raw token. Keys no tag
+/// supplies (EXIF `camera`, `lens`, `date`) keep their spelling in `media.attributes`.
+fn matches_exact_attributes(media: &MediaFile, filters: &HashMap<String, HashSet<String>>) -> bool {
+    filters.iter().all(|(key, allowed_values)| {
+        let mut tags = media
+            .tags
+            .iter()
+            .filter(|tag| tag.kind == TagKind::KeyValue && tag.name == *key)
+            .peekable();
+        if tags.peek().is_none() {
+            return media.attributes.get(key).is_some_and(|value| {
+                let value = value.nfc().collect::<String>();
+                allowed_values.iter().any(|filter| {
+                    compare_numeric(&value, filter).unwrap_or_else(|| value == *filter)
+                })
+            });
+        }
+        tags.filter_map(|tag| Some((tag.raw_token.nfc().collect::<String>(), tag.value.as_ref()?)))
+            .any(|(raw_token, value)| {
+                allowed_values.iter().any(|filter| {
+                    compare_numeric(value, filter).unwrap_or_else(|| {
+                        normalize_tag_text(filter) == *value && raw_token.ends_with(filter.as_str())
+                    })
+                })
+            })
+    })
+}
+
+fn attribute_value_matches(value: &str, allowed_values: &HashSet<String>) -> bool {
+    allowed_values.contains(value)
+        || allowed_values
+            .iter()
+            .any(|filter| compare_numeric(value, filter).unwrap_or(false))
+}
+
+/// Evaluate a `>=4` style filter; `None` when the filter has no operator or either side
+/// is not numeric, so the caller falls back to plain equality.
+fn compare_numeric(value: &str, filter: &str) -> Option<bool> {
+    let (operator, operand) = [">=", "<=", ">", "<"]
+        .into_iter()
+        .find_map(|op| filter.strip_prefix(op).map(|rest| (op, rest)))?;
+    let operand: f64 = operand.trim().parse().ok()?;
+    let value: f64 = value.trim().parse().ok()?;
+    Some(match operator {
+        ">=" => value >= operand,
+        "<=" => value <= operand,
+        ">" => value > operand,
+        _ => value < operand,
+    })
+}
+
 fn normalize_token<S: AsRef<str>>(token: S) -> Option<String> {
     let trimmed = token.as_ref().trim();
     if trimmed.is_empty() {
         None
     } else {
-        Some(trimmed.to_lowercase())
+        Some(normalize_tag_text(trimmed))
     }
 }
 
@@ -185,14 +850,6 @@ fn normalize_page(page: usize) -> usize {
     if page == 0 { 1 } else { page }
 }
 
-fn normalize_page_size(page_size: usize) -> usize {
-    if page_size == 0 {
-        DEFAULT_PAGE_SIZE
-    } else {
-        page_size.min(MAX_PAGE_SIZE)
-    }
-}
-
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -214,6 +871,152 @@ mod tests {
         assert_eq!(result.items[0].id, "sunset_A");
     }
 
+    #[test]
+    fn matches_decomposed_query_against_composed_tags() {
+        let parsed = crate::tags::parse_filename_tokens(
+            "Caf\u{e9}_city-Montr\u{e9}al",
+            &crate::tags::TagParserConfig::default(),
+        );
+        let snapshot = CacheSnapshot::new(vec![media("cafe_A", parsed.tags)]);
+
+        let mut attributes = HashMap::new();
+        attributes.insert("city".into(), vec!["MONTRE\u{301}AL".into()]);
+        let query = SearchQuery::new(vec!["Cafe\u{301}".into()], attributes, 1, 10);
+        let result = SearchService::search(&snapshot, &query);
+        assert_eq!(result.total, 1);
+    }
+
+    #[test]
+    fn random_sort_is_reproducible_per_seed() {
+        let snapshot = CacheSnapshot::new(
+            (0..20)
+                .map(|index| media(&format!("item_{index:02}"), vec![simple_tag("x")]))
+                .collect(),
+        );
+        let order = |seed: u64, page: usize| {
+            let query = SearchQuery::new(Vec::new(), HashMap::new(), page, 10)
+                .with_sort(SortSpec::new(SortField::Random { seed }, SortOrder::Asc));
+            SearchService::search(&snapshot, &query)
+                .items
+                .into_iter()
+                .map(|media| media.id)
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(order(7, 1), order(7, 1));
+        assert_ne!(order(7, 1), order(8, 1));
+        let mut sorted = order(7, 1);
+        sorted.sort();
+        assert_ne!(
+            order(7, 1),
+            sorted,
+            "seeded order should not be the id order"
+        );
+
+        // Pages of one seed partition the library.
+        let mut both_pages = [order(7, 1), order(7, 2)].concat();
+        both_pages.sort();
+        both_pages.dedup();
+        assert_eq!(both_pages.len(), 20);
+    }
+
+    #[test]
+    fn reports_grand_total_alongside_filtered_total() {
+        let snapshot = fixture_snapshot();
+        let query = SearchQuery::new(vec!["sunset".into()], HashMap::new(), 1, 1);
+        let result = SearchService::search(&snapshot, &query);
+        assert_eq!(result.items.len(), 1);
+        assert_eq!(result.total, 2);
+        assert_eq!(result.grand_total, 4);
+
+        let query = SearchQuery::new(vec!["missing".into()], HashMap::new(), 1, 10);
+        let result = SearchService::search(&snapshot, &query);
+        assert_eq!(result.total, 0);
+        assert_eq!(result.grand_total, 4);
+    }
+
+    #[test]
+    fn loose_tag_matching_modes() {
+        let snapshot = fixture_snapshot();
+        let ids = |query: &SearchQuery| {
+            SearchService::search(&snapshot, query)
+                .items
+                .into_iter()
+                .map(|media| media.id)
+                .collect::<Vec<_>>()
+        };
+
+        let exact = SearchQuery::new(vec!["sunet".into()], HashMap::new(), 1, 10);
+        assert!(ids(&exact).is_empty());
+
+        let fuzzy = exact.clone().with_tag_match(TagMatch::Fuzzy);
+        assert_eq!(ids(&fuzzy), vec!["sunset_A", "sunset_B"]);
+
+        let too_far = SearchQuery::new(vec!["sunrise".into()], HashMap::new(), 1, 10)
+            .with_tag_match(TagMatch::Fuzzy);
+        assert!(ids(&too_far).is_empty());
+
+        let substring = SearchQuery::new(vec!["uns".into()], HashMap::new(), 1, 10)
+            .with_tag_match(TagMatch::Substring);
+        assert_eq!(ids(&substring), vec!["sunset_A", "sunset_B"]);
+
+        // Attribute equality stays exact whatever the tag mode.
+        let mut attributes = HashMap::new();
+        attributes.insert("type".into(), vec!["skat".into()]);
+        let attribute =
+            SearchQuery::new(Vec::new(), attributes, 1, 10).with_tag_match(TagMatch::Substring);
+        assert!(ids(&attribute).is_empty());
+    }
+
+    #[test]
+    fn compares_numeric_attribute_ranges() {
+        let snapshot = fixture_snapshot();
+        let mut attributes = HashMap::new();
+        attributes.insert("rating".into(), vec![">=4".into()]);
+        let query = SearchQuery::new(Vec::new(), attributes, 1, 10);
+        let result = SearchService::search(&snapshot, &query);
+        let ids: Vec<&str> = result.items.iter().map(|m| m.id.as_str()).collect();
+        assert_eq!(ids, vec!["macro_B", "sunset_A", "sunset_B"]);
+
+        let mut attributes = HashMap::new();
+        attributes.insert("rating".into(), vec!["<=3".into()]);
+        let query = SearchQuery::new(Vec::new(), attributes, 1, 10);
+        let result = SearchService::search(&snapshot, &query);
+        let ids: Vec<&str> = result.items.iter().map(|m| m.id.as_str()).collect();
+        assert_eq!(ids, vec!["video_C"]);
+    }
+
+    #[test]
+    fn numeric_comparison_falls_back_to_equality_for_strings() {
+        assert_eq!(compare_numeric("5", ">=4"), Some(true));
+        assert_eq!(compare_numeric("high", ">=4"), None);
+        assert_eq!(compare_numeric("5", "high"), None);
+
+        let allowed: HashSet<String> = [">=4".to_string(), "high".to_string()].into();
+        assert!(attribute_value_matches("high", &allowed));
+        assert!(attribute_value_matches("4.5", &allowed));
+        assert!(!attribute_value_matches("low", &allowed));
+        assert!(!attribute_value_matches("3", &allowed));
+    }
+
+    #[test]
+    fn aggregates_facets_for_matching_media() {
+        let snapshot = fixture_snapshot();
+        let facets = SearchService::facets(&snapshot, &SearchQuery::default());
+        assert_eq!(facets.tags.get("sunset"), Some(&2));
+        assert_eq!(facets.tags.get("coast"), Some(&1));
+        assert_eq!(facets.tags.get("rating"), None);
+        assert_eq!(facets.attributes["rating"].get("4"), Some(&2));
+        assert_eq!(facets.attributes["rating"].get("5"), Some(&1));
+        assert_eq!(facets.attributes["subject"].get("leaf"), Some(&1));
+
+        let query = SearchQuery::new(vec!["sunset".into()], HashMap::new(), 1, 1);
+        let facets = SearchService::facets(&snapshot, &query);
+        assert_eq!(facets.tags.len(), 2);
+        assert_eq!(facets.attributes.len(), 1);
+        assert_eq!(facets.attributes["rating"].len(), 2);
+    }
+
     #[test]
     fn applies_or_semantics_within_attribute_values() {
         let snapshot = fixture_snapshot();
@@ -238,6 +1041,394 @@ mod tests {
         assert_eq!(result.items[0].id, "sunset_B");
     }
 
+    #[test]
+    fn cursor_walks_results_in_id_order() {
+        let snapshot = fixture_snapshot();
+
+        let first =
+            SearchService::search(&snapshot, &SearchQuery::new(vec![], HashMap::new(), 1, 3));
+        let ids: Vec<_> = first.items.iter().map(|m| m.id.as_str()).collect();
+        assert_eq!(ids, vec!["macro_B", "sunset_A", "sunset_B"]);
+        assert_eq!(first.next_cursor.as_deref(), Some("sunset_B"));
+
+        let query = SearchQuery::new(vec![], HashMap::new(), 1, 3).with_cursor(first.next_cursor);
+        let second = SearchService::search(&snapshot, &query);
+        let ids: Vec<_> = second.items.iter().map(|m| m.id.as_str()).collect();
+        assert_eq!(ids, vec!["video_C"]);
+        assert_eq!(second.total, 4);
+        assert_eq!(second.next_cursor, None);
+    }
+
+    #[test]
+    fn cursor_past_the_end_returns_no_items() {
+        let snapshot = fixture_snapshot();
+        let query =
+            SearchQuery::new(vec![], HashMap::new(), 1, 3).with_cursor(Some("video_C".into()));
+        let result = SearchService::search(&snapshot, &query);
+        assert!(result.items.is_empty());
+        assert_eq!(result.next_cursor, None);
+    }
+
+    #[test]
+    fn sorts_by_filesize_descending() {
+        let mut snapshot = fixture_snapshot();
+        for (media, size) in snapshot.media.iter_mut().zip([300, 100, 300, 200]) {
+            media.filesize = size;
+        }
+        let query = SearchQuery::new(Vec::new(), HashMap::new(), 1, 10)
+            .with_sort(SortSpec::new(SortField::Filesize, SortOrder::Desc));
+        let result = SearchService::search(&snapshot, &query);
+        let ids: Vec<_> = result.items.iter().map(|m| m.id.as_str()).collect();
+        assert_eq!(ids, vec!["macro_B", "sunset_A", "video_C", "sunset_B"]);
+    }
+
+    #[test]
+    fn sorts_by_duration_with_missing_values_last() {
+        let mut snapshot = fixture_snapshot();
+        snapshot.media[2].duration_ms = Some(5_000);
+        snapshot.media[3].duration_ms = Some(1_000);
+        let query = SearchQuery::new(Vec::new(), HashMap::new(), 1, 10)
+            .with_sort(SortSpec::new(SortField::Duration, SortOrder::Desc));
+        let result = SearchService::search(&snapshot, &query);
+        let ids: Vec<_> = result.items.iter().map(|m| m.id.as_str()).collect();
+        assert_eq!(ids, vec!["macro_B", "video_C", "sunset_A", "sunset_B"]);
+    }
+
+    #[test]
+    fn cursor_follows_custom_sort_order() {
+        let mut snapshot = fixture_snapshot();
+        for (media, size) in snapshot.media.iter_mut().zip([400, 300, 200, 100]) {
+            media.filesize = size;
+        }
+        let sort = SortSpec::new(SortField::Filesize, SortOrder::Asc);
+        let first = SearchService::search(
+            &snapshot,
+            &SearchQuery::new(Vec::new(), HashMap::new(), 1, 2).with_sort(sort),
+        );
+        let query = SearchQuery::new(Vec::new(), HashMap::new(), 1, 2)
+            .with_sort(sort)
+            .with_cursor(first.next_cursor);
+        let second = SearchService::search(&snapshot, &query);
+        let ids: Vec<_> = second.items.iter().map(|m| m.id.as_str()).collect();
+        assert_eq!(ids, vec!["sunset_B", "sunset_A"]);
+    }
+
+    fn search_ids(snapshot: &CacheSnapshot, clauses: Vec<TagClause>) -> Vec<String> {
+        let query = SearchQuery::new(Vec::new(), HashMap::new(), 1, 10).with_tag_clauses(clauses);
+        SearchService::search(snapshot, &query)
+            .items
+            .into_iter()
+            .map(|media| media.id)
+            .collect()
+    }
+
+    #[test]
+    fn or_clause_matches_any_alternative() {
+        let snapshot = fixture_snapshot();
+        let ids = search_ids(
+            &snapshot,
+            vec![TagClause::new(["coast", "macro"], false).unwrap()],
+        );
+        assert_eq!(ids, vec!["macro_B", "sunset_A"]);
+    }
+
+    #[test]
+    fn negated_clause_excludes_matches() {
+        let snapshot = fixture_snapshot();
+        let ids = search_ids(&snapshot, vec![TagClause::new(["sunset"], true).unwrap()]);
+        assert_eq!(ids, vec!["macro_B", "video_C"]);
+    }
+
+    #[test]
+    fn combines_and_or_and_not_clauses() {
+        let snapshot = fixture_snapshot();
+        let ids = search_ids(
+            &snapshot,
+            vec![
+                TagClause::new(["rating"], false).unwrap(),
+                TagClause::new(["sunset", "video"], false).unwrap(),
+                TagClause::new(["coast"], true).unwrap(),
+            ],
+        );
+        assert_eq!(ids, vec!["sunset_B", "video_C"]);
+    }
+
+    #[test]
+    fn pages_are_disjoint_and_independent_of_snapshot_order() {
+        let items: Vec<MediaFile> = (0..25)
+            .map(|index| media(&format!("item_{index:02}"), vec![simple_tag("x")]))
+            .collect();
+        // Unpersisted snapshots keep media in whatever order the scan produced.
+        let scrambled = |stride: usize| CacheSnapshot {
+            media: (0..items.len())
+                .map(|index| items[index * stride % items.len()].clone())
+                .collect(),
+            ..CacheSnapshot::new(Vec::new())
+        };
+        let pages = |snapshot: &CacheSnapshot| {
+            (1..=3)
+                .map(|page| {
+                    let query = SearchQuery::new(Vec::new(), HashMap::new(), page, 10);
+                    SearchService::search(snapshot, &query)
+                        .items
+                        .into_iter()
+                        .map(|media| media.id)
+                        .collect::<Vec<_>>()
+                })
+                .collect::<Vec<_>>()
+        };
+
+        let first = pages(&scrambled(7));
+        assert_eq!(first, pages(&scrambled(3)));
+        let all: Vec<String> = first.concat();
+        let unique: HashSet<&String> = all.iter().collect();
+        assert_eq!(all.len(), 25);
+        assert_eq!(unique.len(), 25);
+    }
+
+    #[test]
+    fn custom_page_limits_set_default_and_clamp() {
+        let snapshot = CacheSnapshot::new(
+            (0..30)
+                .map(|index| media(&format!("item_{index:02}"), vec![simple_tag("x")]))
+                .collect(),
+        );
+        let limits = PageLimits {
+            default_size: 8,
+            max_size: 20,
+        };
+        let page_len = |page_size: usize| {
+            let query =
+                SearchQuery::new(Vec::new(), HashMap::new(), 1, page_size).with_page_limits(limits);
+            let result = SearchService::search(&snapshot, &query);
+            (result.page_size, result.items.len())
+        };
+
+        assert_eq!(page_len(0), (8, 8));
+        assert_eq!(page_len(25), (20, 20));
+        assert_eq!(page_len(5), (5, 5));
+        // Without custom limits the built-in maximum of 200 still allows 25.
+        let query = SearchQuery::new(Vec::new(), HashMap::new(), 1, 25);
+        assert_eq!(query.page_size(), 25);
+    }
+
+    #[test]
+    fn reports_pagination_bounds() {
+        let snapshot = CacheSnapshot::new(
+            (0..5)
+                .map(|index| media(&format!("item_{index}"), vec![simple_tag("x")]))
+                .collect(),
+        );
+        let bounds = |page: usize| {
+            let query = SearchQuery::new(Vec::new(), HashMap::new(), page, 2);
+            let result = SearchService::search(&snapshot, &query);
+            (result.total_pages, result.has_prev, result.has_next)
+        };
+
+        assert_eq!(bounds(1), (3, false, true));
+        assert_eq!(bounds(2), (3, true, true));
+        assert_eq!(bounds(3), (3, true, false));
+        assert_eq!(bounds(7), (3, true, false));
+
+        let empty = CacheSnapshot::new(Vec::new());
+        for page in [1, 4] {
+            let query = SearchQuery::new(Vec::new(), HashMap::new(), page, 2);
+            let result = SearchService::search(&empty, &query);
+            assert_eq!(
+                (result.total_pages, result.has_prev, result.has_next),
+                (0, false, false)
+            );
+        }
+    }
+
+    #[test]
+    fn case_sensitive_attributes_match_original_spelling() {
+        let snapshot = CacheSnapshot::new(vec![
+            media(
+                "upper",
+                vec![kv_tag("camera", "A7iii"), kv_tag("rating", "5")],
+            ),
+            media("lower", vec![kv_tag("camera", "a7III")]),
+        ]);
+        let ids = |value: &str, case_sensitive: bool| {
+            let attributes = HashMap::from([("camera".to_string(), vec![value.to_string()])]);
+            let query = SearchQuery::new(Vec::new(), attributes, 1, 10)
+                .with_case_sensitive_attributes(case_sensitive);
+            SearchService::search(&snapshot, &query)
+                .items
+                .into_iter()
+                .map(|media| media.id)
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(ids("A7iii", false), ["lower", "upper"]);
+        assert_eq!(ids("A7iii", true), ["upper"]);
+        assert_eq!(ids("a7III", true), ["lower"]);
+        assert!(ids("a7iii", true).is_empty());
+
+        // Numeric comparisons are unaffected by the flag.
+        let attributes = HashMap::from([("rating".to_string(), vec![">=4".to_string()])]);
+        let query =
+            SearchQuery::new(Vec::new(), attributes, 1, 10).with_case_sensitive_attributes(true);
+        assert_eq!(SearchService::search(&snapshot, &query).total, 1);
+    }
+
+    #[test]
+    fn case_sensitive_attributes_match_exif_values() {
+        let mut exif = media("exif", Vec::new());
+        exif.attributes
+            .insert("camera".to_string(), "ILCE-7M3".to_string());
+        let snapshot = CacheSnapshot::new(vec![exif]);
+        let total = |value: &str| {
+            let attributes = HashMap::from([("camera".to_string(), vec![value.to_string()])]);
+            let query = SearchQuery::new(Vec::new(), attributes, 1, 10)
+                .with_case_sensitive_attributes(true);
+            SearchService::search(&snapshot, &query).total
+        };
+
+        assert_eq!(total("ILCE-7M3"), 1);
+        assert_eq!(total("ilce-7m3"), 0);
+    }
+
+    #[test]
+    fn cache_key_ignores_attribute_order_only() {
+        let query = |values: [&str; 2], page: usize| {
+            let attributes = HashMap::from([
+                ("rating".to_string(), values.map(String::from).to_vec()),
+                ("year".to_string(), vec!["2024".to_string()]),
+            ]);
+            SearchQuery::new(vec!["sunset".into()], attributes, page, 10)
+        };
+
+        assert_eq!(
+            query(["4", "5"], 1).cache_key(),
+            query(["5", "4"], 1).cache_key()
+        );
+        assert_ne!(
+            query(["4", "5"], 1).cache_key(),
+            query(["4", "5"], 2).cache_key()
+        );
+        assert_ne!(
+            query(["4", "5"], 1).cache_key(),
+            query(["4", "5"], 1)
+                .with_case_sensitive_attributes(true)
+                .cache_key()
+        );
+    }
+
+    #[test]
+    fn combines_required_tags_with_any_tags() {
+        let snapshot = CacheSnapshot::new(
+            [
+                ("a_only", vec!["a"]),
+                ("a_b", vec!["a", "b"]),
+                ("a_c", vec!["a", "c"]),
+                ("a_b_c", vec!["a", "b", "c"]),
+                ("b_c", vec!["b", "c"]),
+                ("b_only", vec!["b"]),
+                ("none", vec![]),
+            ]
+            .into_iter()
+            .map(|(id, tags)| media(id, tags.into_iter().map(simple_tag).collect()))
+            .collect(),
+        );
+        let ids = |query: SearchQuery| {
+            SearchService::search(&snapshot, &query)
+                .items
+                .into_iter()
+                .map(|media| media.id)
+                .collect::<Vec<_>>()
+        };
+
+        let query = SearchQuery::new(vec!["a".into()], HashMap::new(), 1, 10);
+        assert_eq!(
+            ids(query.clone().with_any_tags(["b", "c"])),
+            ["a_b", "a_b_c", "a_c"]
+        );
+        assert_eq!(
+            ids(SearchQuery::default().with_any_tags(["b", "c"])),
+            ["a_b", "a_b_c", "a_c", "b_c", "b_only"]
+        );
+        // Blank any-terms impose no constraint.
+        assert_eq!(ids(query.with_any_tags([" "])).len(), 4);
+    }
+
+    #[test]
+    fn related_ranks_by_weighted_shared_tags() {
+        let snapshot = fixture_snapshot();
+        let related = |id: &str, limit: usize| {
+            SearchService::related(&snapshot, id, limit)
+                .expect("known id")
+                .items
+                .into_iter()
+                .map(|media| media.id)
+                .collect::<Vec<_>>()
+        };
+
+        // macro_B shares `rating=4` (2), sunset_A shares `sunset` (1), video_C nothing.
+        assert_eq!(related("sunset_B", 10), ["macro_B", "sunset_A"]);
+        // Ties (one shared simple tag each) break by id.
+        let mut snapshot_with_tie = fixture_snapshot();
+        snapshot_with_tie.media[3].tags.push(simple_tag("sunset"));
+        let ids: Vec<String> = SearchService::related(&snapshot_with_tie, "sunset_A", 10)
+            .unwrap()
+            .items
+            .into_iter()
+            .map(|media| media.id)
+            .collect();
+        assert_eq!(ids, ["sunset_B", "video_C"]);
+
+        assert_eq!(related("sunset_B", 1), ["macro_B"]);
+        assert!(SearchService::related(&snapshot, "missing", 10).is_none());
+    }
+
+    #[test]
+    fn bbox_keeps_only_located_media_inside_the_box() {
+        let mut snapshot = fixture_snapshot();
+        let at = |latitude, longitude| {
+            Some(GeoPoint {
+                latitude,
+                longitude,
+            })
+        };
+        // Naha, Okinawa; Tokyo; Fiji (east of the antimeridian); macro_B has no location.
+        snapshot.media[0].location = at(26.21, 127.68);
+        snapshot.media[1].location = at(35.68, 139.69);
+        snapshot.media[3].location = at(-17.71, -178.07);
+        let ids = |bbox: BoundingBox| {
+            let query = SearchQuery::default().with_bbox(Some(bbox));
+            SearchService::search(&snapshot, &query)
+                .items
+                .into_iter()
+                .map(|media| media.id)
+                .collect::<Vec<_>>()
+        };
+
+        let okinawa = BoundingBox::new(127.0, 26.0, 128.5, 27.0).unwrap();
+        assert_eq!(ids(okinawa), ["sunset_A"]);
+        let japan = BoundingBox::new(122.0, 20.0, 154.0, 46.0).unwrap();
+        assert_eq!(ids(japan), ["sunset_A", "sunset_B"]);
+        let pacific = BoundingBox::new(170.0, -30.0, -170.0, 0.0).unwrap();
+        assert_eq!(ids(pacific), ["video_C"]);
+
+        let everywhere = BoundingBox::new(-180.0, -90.0, 180.0, 90.0).unwrap();
+        assert!(!ids(everywhere).contains(&"macro_B".to_string()));
+        assert!(BoundingBox::new(0.0, 10.0, 1.0, 5.0).is_none());
+        assert!(BoundingBox::new(0.0, 0.0, 181.0, 1.0).is_none());
+    }
+
+    #[test]
+    fn by_ids_preserves_request_order_and_skips_unknown() {
+        let snapshot = fixture_snapshot();
+        let result =
+            SearchService::by_ids(&snapshot, &["video_C", "missing", "sunset_A", "video_C"]);
+        let ids: Vec<&str> = result.items.iter().map(|media| media.id.as_str()).collect();
+        assert_eq!(ids, ["video_C", "sunset_A"]);
+        assert_eq!(result.total, 2);
+        assert_eq!(result.grand_total, snapshot.media.len());
+        assert!(!result.has_next);
+    }
+
     fn fixture_snapshot() -> CacheSnapshot {
         CacheSnapshot::new(vec![
             media(
@@ -276,12 +1467,12 @@ mod tests {
 
         let mut attributes = Map::new();
         for tag in &tags {
-            if matches!(tag.kind, TagKind::KeyValue) {
-                if let Some(value) = &tag.value {
-                    attributes
-                        .entry(tag.name.clone())
-                        .or_insert_with(|| value.clone());
-                }
+            if matches!(tag.kind, TagKind::KeyValue)
+                && let Some(value) = &tag.value
+            {
+                attributes
+                    .entry(tag.name.clone())
+                    .or_insert_with(|| value.clone());
             }
         }
 
@@ -294,9 +1485,13 @@ mod tests {
             filesize: 0,
             dimensions: None,
             duration_ms: None,
+            location: None,
             thumbnail_path: Some(format!("/media/{id}/thumbnail")),
+            stream_path: None,
             hash: None,
             indexed_at: Utc::now(),
+            modified_at: None,
+            invalid_tokens: Vec::new(),
         }
     }
 
@@ -0,0 +1,130 @@
+use std::{
+    collections::{HashMap, hash_map::Entry},
+    fs,
+    path::Path,
+    str::FromStr,
+};
+
+use anyhow::{Context, Result, anyhow};
+
+use super::parser::normalize_tag_text;
+
+/// Synonyms for simple tags, read from `alias=canonical` lines.
+///
+/// Each alias names exactly one canonical tag and a canonical tag is never itself an
+/// alias, so lookups need no chain following.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TagAliases {
+    canonical: HashMap<String, String>,
+    /// Canonical tag to its aliases, in file order.
+    groups: HashMap<String, Vec<String>>,
+}
+
+impl TagAliases {
+    /// Read an alias file; see the [`FromStr`] impl for the format.
+    pub fn load(path: &Path) -> Result<Self> {
+        let text = fs::read_to_string(path)
+            .with_context(|| format!("failed to read tag alias file '{}'", path.display()))?;
+        text.parse()
+            .with_context(|| format!("invalid tag alias file '{}'", path.display()))
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.canonical.is_empty()
+    }
+
+    /// Canonical form of a normalized tag; tags without an alias map to themselves.
+    pub fn canonical<'a>(&'a self, tag: &'a str) -> &'a str {
+        self.canonical.get(tag).map_or(tag, String::as_str)
+    }
+
+    /// Every spelling equivalent to a normalized tag: its canonical form first, then
+    /// the aliases of that form.
+    pub fn expand(&self, tag: &str) -> Vec<String> {
+        let canonical = self.canonical(tag);
+        std::iter::once(canonical)
+            .chain(
+                self.groups
+                    .get(canonical)
+                    .into_iter()
+                    .flatten()
+                    .map(String::as_str),
+            )
+            .map(str::to_string)
+            .collect()
+    }
+}
+
+/// One `alias=canonical` pair per line. Blank lines and lines starting with `#` are
+/// skipped; both sides are compared like tags, so case does not matter.
+impl FromStr for TagAliases {
+    type Err = anyhow::Error;
+
+    fn from_str(text: &str) -> Result<Self> {
+        let mut aliases = Self::default();
+        for (index, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let line_no = index + 1;
+            let (alias, canonical) = line.split_once('=').ok_or_else(|| {
+                anyhow!("line {line_no}: expected alias=canonical but got '{line}'")
+            })?;
+            let alias = normalize_tag_text(alias.trim());
+            let canonical = normalize_tag_text(canonical.trim());
+            if alias.is_empty() || canonical.is_empty() {
+                return Err(anyhow!("line {line_no}: empty tag in '{line}'"));
+            }
+            if alias == canonical {
+                continue;
+            }
+            if aliases.groups.contains_key(&alias) || aliases.canonical.contains_key(&canonical) {
+                return Err(anyhow!(
+                    "line {line_no}: '{line}' chains aliases; map every alias to the final tag"
+                ));
+            }
+            match aliases.canonical.entry(alias.clone()) {
+                Entry::Occupied(existing) if *existing.get() != canonical => {
+                    return Err(anyhow!(
+                        "line {line_no}: '{alias}' is already an alias of '{}'",
+                        existing.get()
+                    ));
+                }
+                Entry::Occupied(_) => continue,
+                Entry::Vacant(slot) => {
+                    slot.insert(canonical.clone());
+                }
+            }
+            aliases.groups.entry(canonical).or_default().push(alias);
+        }
+        Ok(aliases)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_alias_lines() {
+        let aliases: TagAliases = "# places\nnyc = new-york\n\nNY=New-York\nnew-york=new-york\n"
+            .parse()
+            .unwrap();
+        assert_eq!(aliases.canonical("nyc"), "new-york");
+        assert_eq!(aliases.canonical("ny"), "new-york");
+        assert_eq!(aliases.canonical("tokyo"), "tokyo");
+        assert_eq!(aliases.expand("ny"), vec!["new-york", "nyc", "ny"]);
+        assert_eq!(aliases.expand("tokyo"), vec!["tokyo"]);
+        assert!(TagAliases::default().is_empty());
+    }
+
+    #[test]
+    fn rejects_malformed_alias_lines() {
+        assert!("nyc".parse::<TagAliases>().is_err());
+        assert!("=new-york".parse::<TagAliases>().is_err());
+        assert!("nyc=new-york\nnyc=newyork".parse::<TagAliases>().is_err());
+        assert!("nyc=new-york\nbig-apple=nyc".parse::<TagAliases>().is_err());
+        assert!("new-york=usa\nnyc=new-york".parse::<TagAliases>().is_err());
+    }
+}
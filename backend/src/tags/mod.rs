@@ -1,3 +1,7 @@
+pub mod aliases;
 pub mod parser;
 
-pub use parser::{Tag, TagKind, TagParseResult, parse_filename_tokens};
+pub use aliases::TagAliases;
+pub use parser::{
+    Tag, TagKind, TagParseResult, TagParserConfig, normalize_tag_text, parse_filename_tokens,
+};
@@ -1,7 +1,9 @@
 use serde::{Deserialize, Serialize};
+use unicode_normalization::UnicodeNormalization;
+use utoipa::ToSchema;
 
 /// Normalized tag representation produced from filenames.
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, ToSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct Tag {
     pub raw_token: String,
@@ -14,7 +16,7 @@ pub struct Tag {
 }
 
 /// Distinguishes between simple tags and key/value attributes.
-#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, ToSchema)]
 #[serde(rename_all = "lowercase")]
 pub enum TagKind {
     Simple,
@@ -28,22 +30,77 @@ pub struct TagParseResult {
     pub invalid_tokens: Vec<String>,
 }
 
+/// Characters that split a filename into tokens and tokens into key/value pairs.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TagParserConfig {
+    /// Token delimiters; whitespace always separates tokens as well.
+    pub token_delimiters: Vec<char>,
+    /// Key/value separators, tried in order.
+    pub kv_separators: Vec<char>,
+    /// Lowercased attribute keys recognized as key/value pairs; empty accepts any key.
+    pub attribute_keys: Vec<String>,
+}
+
+impl Default for TagParserConfig {
+    fn default() -> Self {
+        Self {
+            token_delimiters: vec!['_', '+'],
+            kv_separators: vec![':', '-'],
+            attribute_keys: Vec::new(),
+        }
+    }
+}
+
+impl TagParserConfig {
+    pub fn new(token_delimiters: Vec<char>, kv_separators: Vec<char>) -> Self {
+        Self {
+            token_delimiters,
+            kv_separators,
+            attribute_keys: Vec::new(),
+        }
+    }
+
+    pub fn with_attribute_keys<I, S>(mut self, keys: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        self.attribute_keys = keys
+            .into_iter()
+            .map(|key| normalize_tag_text(key.as_ref().trim()))
+            .filter(|key| !key.is_empty())
+            .collect();
+        self
+    }
+
+    fn recognizes_key(&self, key: &str) -> bool {
+        self.attribute_keys.is_empty() || self.attribute_keys.contains(&normalize_tag_text(key))
+    }
+}
+
 /// Parse the tag tokens from a filename (without directories).
 ///
-/// Tokens are expected to use `_` or `+` as delimiters, with key/value pairs
-/// represented as `key-value` (or `key:value`). Returns the parsed tags plus a
-/// list of invalid tokens that were skipped.
-pub fn parse_filename_tokens(filename: &str) -> TagParseResult {
-    let stem = filename.split('.').next().unwrap_or(filename);
+/// With the default config, tokens use `_` or `+` as delimiters and key/value pairs
+/// are written `key-value` (or `key:value`). A token is a key/value pair only when a
+/// separator occurs exactly once in it, so `black-and-white` stays a simple tag; a
+/// dangling separator (`rating-`, `:missing`) marks the token invalid. Returns the
+/// parsed tags plus a list of invalid tokens that were skipped.
+pub fn parse_filename_tokens(filename: &str, config: &TagParserConfig) -> TagParseResult {
+    // Drop any extension unless `.` is itself a tag delimiter.
+    let stem = if config.token_delimiters.contains(&'.') {
+        filename
+    } else {
+        filename.split('.').next().unwrap_or(filename)
+    };
     let mut result = TagParseResult::default();
 
-    for token in stem.split(|c: char| c == '_' || c == '+' || c.is_whitespace()) {
+    for token in stem.split(|c: char| config.token_delimiters.contains(&c) || c.is_whitespace()) {
         let raw = token.trim();
         if raw.is_empty() {
             continue;
         }
 
-        match classify_token(raw) {
+        match classify_token(raw, config) {
             Some(TagParts::Simple { name }) => {
                 result.tags.push(Tag {
                     raw_token: raw.to_string(),
@@ -77,13 +134,18 @@ enum TagParts {
     KeyValue { key: String, value: String },
 }
 
-fn classify_token(token: &str) -> Option<TagParts> {
-    if token.contains(':') || token.contains('-') {
-        if let Some((key, value)) = split_kv(token, ':').or_else(|| split_kv(token, '-')) {
-            return Some(TagParts::KeyValue { key, value });
-        } else {
-            return None;
-        }
+fn classify_token(token: &str, config: &TagParserConfig) -> Option<TagParts> {
+    let separators = config.kv_separators.as_slice();
+    if token.starts_with(separators) || token.ends_with(separators) {
+        return None;
+    }
+
+    if let Some((key, value)) = separators
+        .iter()
+        .find_map(|separator| split_kv(token, *separator))
+        && config.recognizes_key(&key)
+    {
+        return Some(TagParts::KeyValue { key, value });
     }
 
     let normalized = token.trim();
@@ -96,7 +158,11 @@ fn classify_token(token: &str) -> Option<TagParts> {
     }
 }
 
+/// Split on `delimiter` when it occurs exactly once with non-empty sides.
 fn split_kv(token: &str, delimiter: char) -> Option<(String, String)> {
+    if token.matches(delimiter).count() != 1 {
+        return None;
+    }
     let idx = token.find(delimiter)?;
     let (key, rest) = token.split_at(idx);
     let value = &rest[1..];
@@ -109,7 +175,16 @@ fn split_kv(token: &str, delimiter: char) -> Option<(String, String)> {
 }
 
 fn normalize_simple(token: &str) -> String {
-    token.trim().to_lowercase()
+    normalize_tag_text(token.trim())
+}
+
+/// Canonical form used to compare tag text: Unicode NFC, then lowercase.
+///
+/// NFC makes composed and decomposed spellings (`café` typed either way) equal.
+/// Lowercasing is locale-independent, so the Turkish `İ` becomes `i̇` (`i` plus a
+/// combining dot) and does not match a plain `i`.
+pub fn normalize_tag_text(text: &str) -> String {
+    text.nfc().collect::<String>().to_lowercase()
 }
 
 #[cfg(test)]
@@ -118,7 +193,10 @@ mod tests {
 
     #[test]
     fn parses_sample_filename_into_tags() {
-        let result = parse_filename_tokens("sunset_coast+location-okinawa_rating-5");
+        let result = parse_filename_tokens(
+            "sunset_coast+location-okinawa_rating-5",
+            &TagParserConfig::default(),
+        );
         assert_eq!(result.invalid_tokens, Vec::<String>::new());
         assert_eq!(
             result
@@ -137,7 +215,10 @@ mod tests {
 
     #[test]
     fn captures_invalid_tokens() {
-        let result = parse_filename_tokens("invalid- rating-  _good+ :missing");
+        let result = parse_filename_tokens(
+            "invalid- rating-  _good+ :missing",
+            &TagParserConfig::default(),
+        );
         assert_eq!(result.tags.len(), 1);
         assert_eq!(result.tags[0].normalized, "good");
         assert_eq!(
@@ -145,4 +226,83 @@ mod tests {
             vec!["invalid-", "rating-", ":missing"]
         );
     }
+
+    #[test]
+    fn keeps_hyphenated_words_as_simple_tags() {
+        let result = parse_filename_tokens(
+            "black-and-white_rating-5_rating-",
+            &TagParserConfig::default(),
+        );
+        assert_eq!(result.tags.len(), 2);
+        assert_eq!(result.tags[0].kind, TagKind::Simple);
+        assert_eq!(result.tags[0].normalized, "black-and-white");
+        assert_eq!(result.tags[1].kind, TagKind::KeyValue);
+        assert_eq!(result.tags[1].normalized, "rating=5");
+        assert_eq!(result.invalid_tokens, vec!["rating-"]);
+    }
+
+    #[test]
+    fn only_recognized_attribute_keys_form_pairs() {
+        let config = TagParserConfig::default().with_attribute_keys(["Rating", "location"]);
+        let result = parse_filename_tokens("rating-5_sci-fi_location:tokyo", &config);
+        assert!(result.invalid_tokens.is_empty());
+        assert_eq!(
+            result
+                .tags
+                .iter()
+                .map(|tag| (tag.kind, tag.normalized.as_str()))
+                .collect::<Vec<_>>(),
+            vec![
+                (TagKind::KeyValue, "rating=5"),
+                (TagKind::Simple, "sci-fi"),
+                (TagKind::KeyValue, "location=tokyo"),
+            ]
+        );
+    }
+
+    #[test]
+    fn normalizes_composed_and_decomposed_spellings_alike() {
+        let composed =
+            parse_filename_tokens("Caf\u{e9}_place-Caf\u{e9}", &TagParserConfig::default());
+        let decomposed =
+            parse_filename_tokens("Cafe\u{301}_place-Cafe\u{301}", &TagParserConfig::default());
+        assert_eq!(composed.tags[0].normalized, "caf\u{e9}");
+        assert_eq!(
+            composed
+                .tags
+                .iter()
+                .map(|tag| &tag.normalized)
+                .collect::<Vec<_>>(),
+            decomposed
+                .tags
+                .iter()
+                .map(|tag| &tag.normalized)
+                .collect::<Vec<_>>()
+        );
+
+        // Locale-independent lowercasing keeps the dot of the Turkish capital I.
+        assert_eq!(normalize_tag_text("\u{130}stanbul"), "i\u{307}stanbul");
+        assert_ne!(normalize_tag_text("\u{130}stanbul"), "istanbul");
+    }
+
+    #[test]
+    fn honors_custom_delimiters_and_separators() {
+        let config = TagParserConfig::new(vec!['.'], vec!['=']);
+        let result = parse_filename_tokens("a.b.key=value", &config);
+        assert!(result.invalid_tokens.is_empty());
+        assert_eq!(
+            result
+                .tags
+                .iter()
+                .map(|tag| tag.normalized.as_str())
+                .collect::<Vec<_>>(),
+            vec!["a", "b", "key=value"]
+        );
+        assert_eq!(result.tags[2].kind, TagKind::KeyValue);
+
+        // Default separators lose their meaning under a custom config.
+        let result = parse_filename_tokens("black-and-white.rating:5", &config);
+        assert_eq!(result.tags[0].normalized, "black-and-white");
+        assert_eq!(result.tags[1].normalized, "rating:5");
+    }
 }
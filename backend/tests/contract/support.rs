@@ -137,10 +137,10 @@ async fn media_thumbnail(
         Err(_) => return validation_failed("invalid thumbnail parameters"),
     };
 
-    if let Some(size) = query.size.as_deref() {
-        if !matches!(size, "small" | "medium" | "large") {
-            return validation_failed("size must be one of small, medium, or large");
-        }
+    if let Some(size) = query.size.as_deref()
+        && !matches!(size, "small" | "medium" | "large")
+    {
+        return validation_failed("size must be one of small, medium, or large");
     }
 
     if id == "missing-thumb" {
@@ -151,7 +151,7 @@ async fn media_thumbnail(
         );
     }
 
-    let mut response = Response::builder()
+    Response::builder()
         .status(StatusCode::OK)
         .header(CONTENT_TYPE, HeaderValue::from_static("image/png"))
         .header(ETAG, HeaderValue::from_static("\"stub-thumb-etag\""))
@@ -160,9 +160,7 @@ async fn media_thumbnail(
             HeaderValue::from_static("public, max-age=3600"),
         )
         .body(Body::from(Bytes::from_static(b"\x89PNG\r\nstub-data")))
-        .expect("valid thumbnail response");
-
-    response
+        .expect("valid thumbnail response")
 }
 
 async fn media_stream(
@@ -180,10 +178,10 @@ async fn media_stream(
         Err(_) => return validation_failed("invalid stream parameters"),
     };
 
-    if let Some(disposition) = query.disposition.as_deref() {
-        if !matches!(disposition, "inline" | "attachment") {
-            return validation_failed("disposition must be inline or attachment");
-        }
+    if let Some(disposition) = query.disposition.as_deref()
+        && !matches!(disposition, "inline" | "attachment")
+    {
+        return validation_failed("disposition must be inline or attachment");
     }
 
     if id == "missing-stream" {
@@ -233,7 +231,7 @@ async fn media_stream(
 }
 
 async fn index_rebuild(payload: Result<Json<IndexRebuildRequest>, JsonRejection>) -> Response {
-    let Json(payload) = match payload {
+    let Json(_payload) = match payload {
         Ok(value) => value,
         Err(_) => return validation_failed("invalid JSON payload"),
     };
@@ -290,6 +288,7 @@ struct StreamQuery {
     disposition: Option<String>,
 }
 
+#[allow(dead_code)]
 #[derive(Debug, Deserialize)]
 struct IndexRebuildRequest {
     #[serde(default)]
@@ -323,7 +322,7 @@ impl SearchQuery {
                 filters
                     .entry(name.to_ascii_lowercase())
                     .or_insert_with(Vec::new)
-                    .extend(parse_csv(value).into_iter());
+                    .extend(parse_csv(value));
             }
         }
         filters
@@ -1,5 +1,20 @@
+#[path = "integration/auth.rs"]
+mod auth;
+
+#[path = "integration/cors.rs"]
+mod cors;
+
+#[path = "integration/media_archive.rs"]
+mod media_archive;
+
 #[path = "integration/media_stream.rs"]
 mod media_stream;
 
 #[path = "integration/search_cache.rs"]
 mod search_cache;
+
+#[path = "integration/shutdown.rs"]
+mod shutdown;
+
+#[path = "integration/server.rs"]
+mod server;
@@ -0,0 +1,143 @@
+use std::{net::SocketAddr, path::PathBuf, sync::Arc};
+
+use axum::{
+    Router,
+    body::Body,
+    http::{
+        Method, Request, StatusCode,
+        header::{AUTHORIZATION, WWW_AUTHENTICATE},
+    },
+};
+use galarie_backend::{
+    api::auth::ApiToken,
+    cache::{CacheSnapshot, CacheStore},
+    config::{AppConfig, LogConfig, OtelConfig},
+    indexer::IndexerConfig,
+    routes::{self, AppState},
+};
+use http_body_util::BodyExt;
+use serde_json::Value;
+use tempfile::tempdir;
+use tokio::sync::RwLock;
+use tower::ServiceExt;
+
+#[tokio::test]
+async fn missing_token_is_unauthorized() {
+    let response = router_with_token(Some("s3cret"))
+        .oneshot(request("/api/v1/media", None))
+        .await
+        .expect("router response");
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+    assert_eq!(response.headers().get(WWW_AUTHENTICATE).unwrap(), "Bearer");
+    assert_eq!(error_code(response).await, "UNAUTHORIZED");
+
+    let response = router_with_token(Some("s3cret"))
+        .oneshot(request("/api/v1/media", Some("Basic czNjcmV0")))
+        .await
+        .expect("router response");
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+
+    for blank in ["Bearer ", "Bearer    "] {
+        let response = router_with_token(Some("s3cret"))
+            .oneshot(request("/api/v1/media", Some(blank)))
+            .await
+            .expect("router response");
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED, "{blank:?}");
+        assert_eq!(response.headers().get(WWW_AUTHENTICATE).unwrap(), "Bearer");
+    }
+}
+
+#[tokio::test]
+async fn wrong_token_is_forbidden() {
+    let response = router_with_token(Some("s3cret"))
+        .oneshot(request("/api/v1/media", Some("Bearer guess")))
+        .await
+        .expect("router response");
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+    assert_eq!(error_code(response).await, "FORBIDDEN");
+}
+
+#[tokio::test]
+async fn correct_token_is_accepted() {
+    let response = router_with_token(Some("s3cret"))
+        .oneshot(request("/api/v1/media", Some("Bearer s3cret")))
+        .await
+        .expect("router response");
+    assert_eq!(response.status(), StatusCode::OK);
+}
+
+#[tokio::test]
+async fn healthz_and_unconfigured_api_stay_open() {
+    let response = router_with_token(Some("s3cret"))
+        .oneshot(request("/healthz", None))
+        .await
+        .expect("router response");
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let response = router_with_token(None)
+        .oneshot(request("/api/v1/media", None))
+        .await
+        .expect("router response");
+    assert_eq!(response.status(), StatusCode::OK);
+}
+
+async fn error_code(response: axum::response::Response) -> String {
+    let body = response.into_body().collect().await.unwrap().to_bytes();
+    let payload: Value = serde_json::from_slice(&body).expect("json body");
+    payload["error"]["code"]
+        .as_str()
+        .expect("error code")
+        .to_string()
+}
+
+fn request(uri: &str, authorization: Option<&str>) -> Request<Body> {
+    let mut builder = Request::builder().method(Method::GET).uri(uri);
+    if let Some(value) = authorization {
+        builder = builder.header(AUTHORIZATION, value);
+    }
+    builder.body(Body::empty()).expect("request")
+}
+
+fn router_with_token(token: Option<&str>) -> Router {
+    let tmp = tempdir().expect("temp dir");
+    let config = Arc::new(test_config(
+        tmp.path().to_path_buf(),
+        token.map(ApiToken::new),
+    ));
+    let cache_store = Arc::new(CacheStore::new(tmp.path()));
+    let snapshot = Arc::new(RwLock::new(CacheSnapshot::new(Vec::new())));
+    routes::router(AppState::new(config, cache_store, snapshot))
+}
+
+fn test_config(root: PathBuf, api_token: Option<ApiToken>) -> AppConfig {
+    AppConfig {
+        media_root: root.clone(),
+        cache_dir: root.clone(),
+        cache_compress: false,
+        listen_addr: SocketAddr::from(([127, 0, 0, 1], 0)),
+        environment: "test".into(),
+        otel: OtelConfig {
+            endpoint: None,
+            service_name: "test-backend".into(),
+            disable_traces: true,
+            disable_logs: true,
+            protocol: Default::default(),
+            headers: Default::default(),
+        },
+        log: LogConfig {
+            level: "info".into(),
+            format: Default::default(),
+        },
+        cors_allowed_origins: Vec::new(),
+        frontend_dist_dir: None,
+        rate_limit_per_minute: None,
+        api_token,
+        trusted_proxies: Default::default(),
+        thumbnails: Default::default(),
+        stream: Default::default(),
+        limits: Default::default(),
+        page_limits: Default::default(),
+        indexer: IndexerConfig::new(&root),
+        server: Default::default(),
+    }
+}
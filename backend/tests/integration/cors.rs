@@ -0,0 +1,145 @@
+use std::{net::SocketAddr, path::PathBuf, sync::Arc};
+
+use axum::{
+    Router,
+    body::Body,
+    http::{
+        Method, Request, StatusCode,
+        header::{ACCESS_CONTROL_ALLOW_CREDENTIALS, ACCESS_CONTROL_ALLOW_ORIGIN, ORIGIN},
+    },
+};
+use galarie_backend::{
+    cache::{CacheSnapshot, CacheStore},
+    config::{AppConfig, LogConfig, OtelConfig},
+    indexer::IndexerConfig,
+    routes::{self, AppState},
+};
+use tempfile::tempdir;
+use tokio::sync::RwLock;
+use tower::ServiceExt;
+
+#[tokio::test]
+async fn allowed_origin_receives_cors_headers_with_credentials() {
+    let app = router_with_origins(&["http://localhost:5173"]);
+
+    let response = app
+        .clone()
+        .oneshot(search_request("http://localhost:5173"))
+        .await
+        .expect("router response");
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(
+        response.headers().get(ACCESS_CONTROL_ALLOW_ORIGIN).unwrap(),
+        "http://localhost:5173"
+    );
+    assert_eq!(
+        response
+            .headers()
+            .get(ACCESS_CONTROL_ALLOW_CREDENTIALS)
+            .unwrap(),
+        "true"
+    );
+
+    let response = app
+        .oneshot(search_request("http://evil.example"))
+        .await
+        .expect("router response");
+    assert_eq!(response.status(), StatusCode::OK);
+    assert!(
+        response
+            .headers()
+            .get(ACCESS_CONTROL_ALLOW_ORIGIN)
+            .is_none(),
+        "disallowed origins must not be echoed back"
+    );
+}
+
+#[tokio::test]
+async fn wildcard_allows_any_origin_without_credentials() {
+    let app = router_with_origins(&["*"]);
+
+    let response = app
+        .oneshot(search_request("http://anywhere.example"))
+        .await
+        .expect("router response");
+    assert_eq!(
+        response.headers().get(ACCESS_CONTROL_ALLOW_ORIGIN).unwrap(),
+        "*"
+    );
+    assert!(
+        response
+            .headers()
+            .get(ACCESS_CONTROL_ALLOW_CREDENTIALS)
+            .is_none()
+    );
+}
+
+#[tokio::test]
+async fn empty_origin_list_disables_cors() {
+    let app = router_with_origins(&[]);
+
+    let response = app
+        .oneshot(search_request("http://localhost:5173"))
+        .await
+        .expect("router response");
+    assert_eq!(response.status(), StatusCode::OK);
+    assert!(
+        response
+            .headers()
+            .get(ACCESS_CONTROL_ALLOW_ORIGIN)
+            .is_none()
+    );
+}
+
+fn search_request(origin: &str) -> Request<Body> {
+    Request::builder()
+        .method(Method::GET)
+        .uri("/api/v1/media")
+        .header(ORIGIN, origin)
+        .body(Body::empty())
+        .expect("request")
+}
+
+fn router_with_origins(origins: &[&str]) -> Router {
+    let tmp = tempdir().expect("temp dir");
+    let config = Arc::new(test_config(
+        tmp.path().to_path_buf(),
+        origins.iter().map(|origin| origin.to_string()).collect(),
+    ));
+    let cache_store = Arc::new(CacheStore::new(tmp.path()));
+    let snapshot = Arc::new(RwLock::new(CacheSnapshot::new(Vec::new())));
+    routes::router(AppState::new(config, cache_store, snapshot))
+}
+
+fn test_config(root: PathBuf, cors_allowed_origins: Vec<String>) -> AppConfig {
+    AppConfig {
+        media_root: root.clone(),
+        cache_dir: root.clone(),
+        cache_compress: false,
+        listen_addr: SocketAddr::from(([127, 0, 0, 1], 0)),
+        environment: "test".into(),
+        otel: OtelConfig {
+            endpoint: None,
+            service_name: "test-backend".into(),
+            disable_traces: true,
+            disable_logs: true,
+            protocol: Default::default(),
+            headers: Default::default(),
+        },
+        log: LogConfig {
+            level: "info".into(),
+            format: Default::default(),
+        },
+        cors_allowed_origins,
+        frontend_dist_dir: None,
+        rate_limit_per_minute: None,
+        api_token: None,
+        trusted_proxies: Default::default(),
+        thumbnails: Default::default(),
+        stream: Default::default(),
+        limits: Default::default(),
+        page_limits: Default::default(),
+        indexer: IndexerConfig::new(&root),
+        server: Default::default(),
+    }
+}
@@ -0,0 +1,157 @@
+use std::{net::SocketAddr, path::PathBuf, sync::Arc};
+
+use axum::{
+    Router,
+    body::Body,
+    http::{
+        Method, Request, StatusCode,
+        header::{CONTENT_DISPOSITION, CONTENT_TYPE},
+    },
+};
+use galarie_backend::{
+    cache::CacheStore,
+    config::{AppConfig, LogConfig, OtelConfig},
+    indexer::{Indexer, IndexerConfig},
+    routes::{self, AppState},
+};
+use http_body_util::BodyExt;
+use tempfile::tempdir;
+use tokio::sync::RwLock;
+use tower::ServiceExt;
+
+#[tokio::test]
+async fn archive_contains_the_filtered_media() {
+    let router = archive_router();
+    let request = Request::builder()
+        .method(Method::GET)
+        .uri("/api/v1/media/archive?attributes[rating]=%3E%3D4")
+        .body(Body::empty())
+        .expect("request");
+    let response = router.oneshot(request).await.expect("router response");
+
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(response.headers()[CONTENT_TYPE], "application/zip");
+    assert!(
+        response.headers()[CONTENT_DISPOSITION]
+            .to_str()
+            .unwrap()
+            .starts_with("attachment")
+    );
+    let body = response
+        .into_body()
+        .collect()
+        .await
+        .expect("body bytes")
+        .to_bytes();
+
+    let mut entries = zip_entries(&body);
+    entries.sort();
+    let names: Vec<&str> = entries.iter().map(|(name, _)| name.as_str()).collect();
+    assert_eq!(
+        names,
+        [
+            "macro_leaf+subject-nature_rating-4.gif",
+            "sunset_coast+location-okinawa_rating-5.png",
+        ]
+    );
+    for (name, data) in &entries {
+        let expected = std::fs::read(sample_media_root().join(name)).expect("sample file");
+        assert_eq!(data, &expected, "{name}");
+    }
+}
+
+#[tokio::test]
+async fn archive_rejects_invalid_filters() {
+    let request = Request::builder()
+        .method(Method::GET)
+        .uri("/api/v1/media/archive?attributes[rating=4")
+        .body(Body::empty())
+        .expect("request");
+    let response = archive_router()
+        .oneshot(request)
+        .await
+        .expect("router response");
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+}
+
+/// Read `(name, data)` pairs back through the central directory, checking each CRC.
+fn zip_entries(archive: &[u8]) -> Vec<(String, Vec<u8>)> {
+    let u16_at = |at: usize| u16::from_le_bytes([archive[at], archive[at + 1]]) as usize;
+    let u32_at = |at: usize| u32::from_le_bytes(archive[at..at + 4].try_into().unwrap());
+
+    let end = archive.len() - 22;
+    assert_eq!(u32_at(end), 0x0605_4b50, "end of central directory");
+    let count = u16_at(end + 10);
+    let mut cursor = u32_at(end + 16) as usize;
+    let mut entries = Vec::with_capacity(count);
+    for _ in 0..count {
+        assert_eq!(u32_at(cursor), 0x0201_4b50, "central directory header");
+        let crc = u32_at(cursor + 16);
+        let size = u32_at(cursor + 24) as usize;
+        let name_len = u16_at(cursor + 28);
+        let offset = u32_at(cursor + 42) as usize;
+        let name = String::from_utf8(archive[cursor + 46..cursor + 46 + name_len].to_vec())
+            .expect("utf-8 name");
+
+        assert_eq!(u32_at(offset), 0x0403_4b50, "local header");
+        let data_start = offset + 30 + u16_at(offset + 26) + u16_at(offset + 28);
+        let data = archive[data_start..data_start + size].to_vec();
+        assert_eq!(crc32fast::hash(&data), crc, "{name}");
+        entries.push((name, data));
+        cursor += 46 + name_len + u16_at(cursor + 30) + u16_at(cursor + 32);
+    }
+    entries
+}
+
+fn archive_router() -> Router {
+    let media_root = sample_media_root();
+    let cache_dir = tempdir().expect("temp cache dir");
+    let cache_store = Arc::new(CacheStore::new(cache_dir.path()));
+    let scan_root = media_root.clone();
+    let snapshot = cache_store
+        .load_or_rebuild(|| Indexer::scan_once(&scan_root))
+        .expect("cache rebuild");
+    let config = Arc::new(test_config(
+        media_root.clone(),
+        cache_dir.path().to_path_buf(),
+    ));
+    let state = AppState::new(config, cache_store, Arc::new(RwLock::new(snapshot)));
+    routes::router(state)
+}
+
+fn sample_media_root() -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("../sample-media")
+}
+
+fn test_config(media_root: PathBuf, cache_dir: PathBuf) -> AppConfig {
+    AppConfig {
+        media_root: media_root.clone(),
+        cache_dir,
+        cache_compress: false,
+        listen_addr: SocketAddr::from(([127, 0, 0, 1], 0)),
+        environment: "test".into(),
+        otel: OtelConfig {
+            endpoint: None,
+            service_name: "test-backend".into(),
+            disable_traces: true,
+            disable_logs: true,
+            protocol: Default::default(),
+            headers: Default::default(),
+        },
+        log: LogConfig {
+            level: "info".into(),
+            format: Default::default(),
+        },
+        cors_allowed_origins: Vec::new(),
+        frontend_dist_dir: None,
+        rate_limit_per_minute: None,
+        api_token: None,
+        trusted_proxies: Default::default(),
+        thumbnails: Default::default(),
+        stream: Default::default(),
+        limits: Default::default(),
+        page_limits: Default::default(),
+        indexer: IndexerConfig::new(&media_root),
+        server: Default::default(),
+    }
+}
@@ -10,13 +10,16 @@ use axum::{
     body::Body,
     http::{
         Method, Request, StatusCode,
-        header::{ACCEPT_RANGES, CONTENT_TYPE, ETAG},
+        header::{
+            ACCEPT_RANGES, CACHE_CONTROL, CONTENT_LENGTH, CONTENT_RANGE, CONTENT_TYPE, ETAG,
+            IF_MODIFIED_SINCE, IF_NONE_MATCH, IF_RANGE, LAST_MODIFIED, RANGE,
+        },
     },
 };
 use galarie_backend::{
-    cache::CacheStore,
+    cache::{CacheSnapshot, CacheStore},
     config::{AppConfig, LogConfig, OtelConfig},
-    indexer::{Indexer, MediaFile, MediaType},
+    indexer::{Indexer, IndexerConfig, MediaFile, MediaRoot, MediaType},
     routes::{self, AppState},
 };
 use http_body_util::BodyExt;
@@ -69,6 +72,359 @@ async fn stream_returns_original_bytes_with_headers() {
     assert_eq!(body, expected);
 }
 
+#[tokio::test]
+async fn matching_if_none_match_returns_not_modified() {
+    let ctx = StreamTestContext::new(MediaType::Image).await;
+    let uri = format!("/api/v1/media/{}/stream", ctx.media.id);
+
+    let first = ctx
+        .router
+        .clone()
+        .oneshot(Request::get(&uri).body(Body::empty()).expect("request"))
+        .await
+        .expect("router response");
+    let etag = first.headers().get(ETAG).expect("etag header").clone();
+
+    let request = Request::get(&uri)
+        .header(IF_NONE_MATCH, etag.clone())
+        .body(Body::empty())
+        .expect("request");
+    let response = ctx
+        .router
+        .clone()
+        .oneshot(request)
+        .await
+        .expect("router response");
+
+    assert_eq!(response.status(), StatusCode::NOT_MODIFIED);
+    assert_eq!(response.headers().get(ETAG), Some(&etag));
+    let body = response
+        .into_body()
+        .collect()
+        .await
+        .expect("body")
+        .to_bytes();
+    assert!(
+        body.is_empty(),
+        "304 responses must not carry the media bytes"
+    );
+}
+
+#[tokio::test]
+async fn stream_sets_last_modified_and_honors_if_modified_since() {
+    let ctx = StreamTestContext::new(MediaType::Image).await;
+    let uri = format!("/api/v1/media/{}/stream", ctx.media.id);
+
+    let response = ctx
+        .router
+        .clone()
+        .oneshot(Request::get(&uri).body(Body::empty()).expect("request"))
+        .await
+        .expect("router response");
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(response.headers()[CACHE_CONTROL], "private, max-age=3600");
+    let last_modified = response
+        .headers()
+        .get(LAST_MODIFIED)
+        .expect("last-modified header")
+        .clone();
+
+    let request = Request::get(&uri)
+        .header(IF_MODIFIED_SINCE, last_modified.clone())
+        .body(Body::empty())
+        .expect("request");
+    let response = ctx
+        .router
+        .clone()
+        .oneshot(request)
+        .await
+        .expect("router response");
+    assert_eq!(response.status(), StatusCode::NOT_MODIFIED);
+    assert_eq!(response.headers()[LAST_MODIFIED], last_modified);
+
+    let request = Request::get(&uri)
+        .header(IF_MODIFIED_SINCE, "Thu, 01 Jan 1970 00:00:00 GMT")
+        .body(Body::empty())
+        .expect("request");
+    let response = ctx
+        .router
+        .clone()
+        .oneshot(request)
+        .await
+        .expect("router response");
+    assert_eq!(response.status(), StatusCode::OK);
+
+    // A mismatched ETag wins over a matching date.
+    let request = Request::get(&uri)
+        .header(IF_MODIFIED_SINCE, last_modified)
+        .header(IF_NONE_MATCH, "\"other\"")
+        .body(Body::empty())
+        .expect("request");
+    let response = ctx
+        .router
+        .clone()
+        .oneshot(request)
+        .await
+        .expect("router response");
+    assert_eq!(response.status(), StatusCode::OK);
+}
+
+#[tokio::test]
+async fn if_range_mismatch_serves_full_content() {
+    let ctx = StreamTestContext::new(MediaType::Image).await;
+    let uri = format!("/api/v1/media/{}/stream", ctx.media.id);
+
+    let request = Request::get(&uri)
+        .header(RANGE, "bytes=0-9")
+        .header(IF_RANGE, "\"stale-etag\"")
+        .body(Body::empty())
+        .expect("request");
+    let response = ctx
+        .router
+        .clone()
+        .oneshot(request)
+        .await
+        .expect("router response");
+    assert_eq!(response.status(), StatusCode::OK);
+    assert!(response.headers().get(CONTENT_RANGE).is_none());
+    let etag = response.headers().get(ETAG).expect("etag header").clone();
+
+    let request = Request::get(&uri)
+        .header(RANGE, "bytes=0-9")
+        .header(IF_RANGE, etag)
+        .body(Body::empty())
+        .expect("request");
+    let response = ctx
+        .router
+        .clone()
+        .oneshot(request)
+        .await
+        .expect("router response");
+    assert_eq!(response.status(), StatusCode::PARTIAL_CONTENT);
+    let body = response
+        .into_body()
+        .collect()
+        .await
+        .expect("body")
+        .to_bytes();
+    assert_eq!(body.len(), 10);
+}
+
+#[tokio::test]
+async fn same_size_rewrite_invalidates_the_etag() {
+    let root = tempdir().expect("media root");
+    let cache_dir = tempdir().expect("temp cache dir");
+    let path = root.path().join("clip.mp4");
+    fs::write(&path, b"version one").await.expect("write clip");
+
+    let config = test_config(root.path().to_path_buf(), cache_dir.path().to_path_buf());
+    let snapshot = Arc::new(RwLock::new(CacheSnapshot::new(
+        Indexer::scan(&config.indexer).expect("scan"),
+    )));
+    let id = snapshot.read().await.media[0].id.clone();
+    let router = routes::router(AppState::new(
+        Arc::new(config.clone()),
+        Arc::new(CacheStore::new(cache_dir.path())),
+        snapshot.clone(),
+    ));
+    let uri = format!("/api/v1/media/{id}/stream");
+    let response = router
+        .clone()
+        .oneshot(Request::get(&uri).body(Body::empty()).expect("request"))
+        .await
+        .expect("router response");
+    let etag = response.headers().get(ETAG).expect("etag header").clone();
+
+    // Same length, new bytes, picked up by a rescan; the id stays the same.
+    fs::write(&path, b"version two")
+        .await
+        .expect("rewrite clip");
+    std::fs::File::options()
+        .write(true)
+        .open(&path)
+        .and_then(|file| {
+            file.set_modified(std::time::SystemTime::now() + std::time::Duration::from_secs(60))
+        })
+        .expect("bump mtime");
+    *snapshot.write().await = CacheSnapshot::new(Indexer::scan(&config.indexer).expect("scan"));
+    assert_eq!(snapshot.read().await.media[0].id, id);
+
+    let request = Request::get(&uri)
+        .header(IF_NONE_MATCH, etag.clone())
+        .body(Body::empty())
+        .expect("request");
+    let response = router.clone().oneshot(request).await.expect("response");
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_ne!(response.headers().get(ETAG), Some(&etag));
+
+    let request = Request::get(&uri)
+        .header(RANGE, "bytes=8-10")
+        .header(IF_RANGE, etag)
+        .body(Body::empty())
+        .expect("request");
+    let response = router.clone().oneshot(request).await.expect("response");
+    assert_eq!(response.status(), StatusCode::OK);
+    let body = response
+        .into_body()
+        .collect()
+        .await
+        .expect("body")
+        .to_bytes();
+    assert_eq!(body.as_ref(), b"version two");
+}
+
+#[tokio::test]
+async fn multiple_ranges_return_multipart_byteranges() {
+    let ctx = StreamTestContext::new(MediaType::Image).await;
+    let expected = fs::read(ctx.media_root.join(&ctx.media.relative_path))
+        .await
+        .expect("read sample media file");
+    let total = expected.len();
+
+    let request = Request::get(format!("/api/v1/media/{}/stream", ctx.media.id))
+        .header(RANGE, "bytes=0-9,20-29")
+        .body(Body::empty())
+        .expect("request");
+    let response = ctx
+        .router
+        .clone()
+        .oneshot(request)
+        .await
+        .expect("router response");
+    assert_eq!(response.status(), StatusCode::PARTIAL_CONTENT);
+    assert!(response.headers().get(CONTENT_RANGE).is_none());
+    let content_type = response.headers()[CONTENT_TYPE]
+        .to_str()
+        .unwrap()
+        .to_string();
+    let boundary = content_type
+        .strip_prefix("multipart/byteranges; boundary=")
+        .expect("multipart content type");
+    let content_length: usize = response.headers()[CONTENT_LENGTH]
+        .to_str()
+        .unwrap()
+        .parse()
+        .unwrap();
+    let body = response
+        .into_body()
+        .collect()
+        .await
+        .expect("body")
+        .to_bytes();
+    assert_eq!(body.len(), content_length);
+
+    let delimiter = format!("\r\n--{boundary}");
+    let body = body.to_vec();
+    let mut parts = Vec::new();
+    let mut rest = body.as_slice();
+    while let Some(at) = find(rest, delimiter.as_bytes()) {
+        rest = &rest[at + delimiter.len()..];
+        if rest.starts_with(b"--") {
+            break;
+        }
+        let end = find(rest, delimiter.as_bytes()).expect("part terminated by a delimiter");
+        parts.push(rest[..end].to_vec());
+        rest = &rest[end..];
+    }
+    assert_eq!(parts.len(), 2);
+
+    for (part, (start, end)) in parts.iter().zip([(0usize, 9usize), (20, 29)]) {
+        let split = find(part, b"\r\n\r\n").expect("part headers");
+        let headers = String::from_utf8(part[..split].to_vec()).unwrap();
+        assert!(headers.contains("Content-Type: image/png"), "{headers}");
+        assert!(
+            headers.contains(&format!("Content-Range: bytes {start}-{end}/{total}")),
+            "{headers}"
+        );
+        assert_eq!(&part[split + 4..], &expected[start..=end]);
+    }
+}
+
+#[tokio::test]
+async fn unsatisfiable_range_reports_current_length() {
+    let ctx = StreamTestContext::new(MediaType::Image).await;
+    let request = Request::get(format!("/api/v1/media/{}/stream", ctx.media.id))
+        .header(RANGE, format!("bytes={}-", ctx.media.filesize + 100))
+        .body(Body::empty())
+        .expect("request");
+    let response = ctx
+        .router
+        .clone()
+        .oneshot(request)
+        .await
+        .expect("router response");
+
+    assert_eq!(response.status(), StatusCode::RANGE_NOT_SATISFIABLE);
+    assert_eq!(
+        response.headers()[CONTENT_RANGE],
+        format!("bytes */{}", ctx.media.filesize)
+    );
+    let body = response.into_body().collect().await.expect("body");
+    let json: serde_json::Value = serde_json::from_slice(&body.to_bytes()).expect("json payload");
+    assert_eq!(json["error"]["code"], "VALIDATION_FAILED");
+}
+
+#[tokio::test]
+async fn streams_large_files_byte_exact_with_custom_chunk_size() {
+    let media_root = tempdir().expect("temp media root");
+    let cache_dir = tempdir().expect("temp cache dir");
+    // Deliberately not a multiple of the chunk size.
+    let payload: Vec<u8> = (0..3_000_017u32).map(|i| (i % 251) as u8).collect();
+    fs::write(media_root.path().join("long_take.mp4"), &payload)
+        .await
+        .expect("write payload");
+
+    let mut config = test_config(
+        media_root.path().to_path_buf(),
+        cache_dir.path().to_path_buf(),
+    );
+    config.stream.chunk_bytes = 7 * 1024;
+    let cache_store = Arc::new(CacheStore::new(cache_dir.path()));
+    let scan_root = media_root.path().to_path_buf();
+    let snapshot = cache_store
+        .load_or_rebuild(|| Indexer::scan_once(&scan_root))
+        .expect("cache rebuild");
+    let media_id = snapshot.media[0].id.clone();
+    let router = routes::router(AppState::new(
+        Arc::new(config),
+        cache_store,
+        Arc::new(RwLock::new(snapshot)),
+    ));
+
+    let request = Request::get(format!("/api/v1/media/{media_id}/stream"))
+        .body(Body::empty())
+        .expect("request");
+    let response = router.clone().oneshot(request).await.expect("response");
+    assert_eq!(response.status(), StatusCode::OK);
+    let body = response
+        .into_body()
+        .collect()
+        .await
+        .expect("body")
+        .to_bytes();
+    assert_eq!(body.as_ref(), payload.as_slice());
+
+    let request = Request::get(format!("/api/v1/media/{media_id}/stream"))
+        .header(RANGE, "bytes=1000000-2500000")
+        .body(Body::empty())
+        .expect("request");
+    let response = router.oneshot(request).await.expect("response");
+    assert_eq!(response.status(), StatusCode::PARTIAL_CONTENT);
+    let body = response
+        .into_body()
+        .collect()
+        .await
+        .expect("body")
+        .to_bytes();
+    assert_eq!(body.as_ref(), &payload[1_000_000..=2_500_000]);
+}
+
+fn find(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    haystack
+        .windows(needle.len())
+        .position(|window| window == needle)
+}
+
 #[tokio::test]
 async fn missing_media_returns_not_found() {
     let ctx = StreamTestContext::new(MediaType::Image).await;
@@ -98,6 +454,125 @@ async fn missing_media_returns_not_found() {
     );
 }
 
+#[tokio::test]
+async fn deleted_media_file_returns_gone() {
+    let media_root = tempdir().expect("temp media root");
+    let cache_dir = tempdir().expect("temp cache dir");
+    let path = media_root.path().join("removed_later.png");
+    fs::write(&path, b"bytes").await.expect("write media");
+    let (router, media_id) = router_for(media_root.path(), cache_dir.path(), |_| {});
+    fs::remove_file(&path).await.expect("delete media");
+
+    let (status, json) = stream_error(&router, &media_id).await;
+    assert_eq!(status, StatusCode::GONE);
+    assert_eq!(json["error"]["code"], "GONE");
+}
+
+#[tokio::test]
+async fn media_path_outside_root_returns_forbidden() {
+    let tmp = tempdir().expect("temp dir");
+    let media_root = tmp.path().join("media");
+    std::fs::create_dir(&media_root).expect("media root");
+    std::fs::write(media_root.join("inside.png"), b"bytes").expect("write media");
+    std::fs::write(tmp.path().join("secret.png"), b"secret").expect("write outside file");
+    let cache_dir = tempdir().expect("temp cache dir");
+    // A stale or tampered snapshot entry pointing above the media root.
+    let (router, media_id) = router_for(&media_root, cache_dir.path(), |media| {
+        media.relative_path = "../secret.png".into();
+    });
+
+    let (status, json) = stream_error(&router, &media_id).await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+    assert_eq!(json["error"]["code"], "FORBIDDEN");
+}
+
+/// Router over a fresh scan of `media_root`, with `edit` applied to the first entry.
+fn router_for(
+    media_root: &Path,
+    cache_dir: &Path,
+    edit: impl FnOnce(&mut MediaFile),
+) -> (Router, String) {
+    let config = test_config(media_root.to_path_buf(), cache_dir.to_path_buf());
+    let cache_store = Arc::new(CacheStore::new(cache_dir));
+    let mut snapshot = cache_store
+        .load_or_rebuild(|| Indexer::scan_once(media_root))
+        .expect("cache rebuild");
+    edit(&mut snapshot.media[0]);
+    let media_id = snapshot.media[0].id.clone();
+    let router = routes::router(AppState::new(
+        Arc::new(config),
+        cache_store,
+        Arc::new(RwLock::new(snapshot)),
+    ));
+    (router, media_id)
+}
+
+async fn stream_error(router: &Router, media_id: &str) -> (StatusCode, serde_json::Value) {
+    let request = Request::get(format!("/api/v1/media/{media_id}/stream"))
+        .body(Body::empty())
+        .expect("request");
+    let response = router.clone().oneshot(request).await.expect("response");
+    let status = response.status();
+    let body = response.into_body().collect().await.expect("body");
+    let json = serde_json::from_slice(&body.to_bytes()).expect("json payload");
+    (status, json)
+}
+
+#[tokio::test]
+async fn streams_media_from_several_roots() {
+    let photos = tempdir().expect("photos root");
+    let videos = tempdir().expect("videos root");
+    let cache_dir = tempdir().expect("temp cache dir");
+    // The same relative path under both roots must not collide.
+    fs::write(photos.path().join("shared_name.png"), b"from photos")
+        .await
+        .expect("write photo");
+    fs::write(videos.path().join("shared_name.png"), b"from videos")
+        .await
+        .expect("write video");
+
+    let mut config = test_config(photos.path().to_path_buf(), cache_dir.path().to_path_buf());
+    config.indexer = IndexerConfig::new(photos.path()).with_roots(vec![
+        MediaRoot {
+            label: "photos".into(),
+            path: photos.path().to_path_buf(),
+        },
+        MediaRoot {
+            label: "videos".into(),
+            path: videos.path().to_path_buf(),
+        },
+    ]);
+    let snapshot = CacheSnapshot::new(Indexer::scan(&config.indexer).expect("scan"));
+    let paths: Vec<&str> = snapshot
+        .media
+        .iter()
+        .map(|media| media.relative_path.as_str())
+        .collect();
+    assert_eq!(paths, ["photos/shared_name.png", "videos/shared_name.png"]);
+    assert_ne!(snapshot.media[0].id, snapshot.media[1].id);
+
+    let media = snapshot.media.clone();
+    let router = routes::router(AppState::new(
+        Arc::new(config),
+        Arc::new(CacheStore::new(cache_dir.path())),
+        Arc::new(RwLock::new(snapshot)),
+    ));
+    for (media, expected) in media.iter().zip([&b"from photos"[..], b"from videos"]) {
+        let request = Request::get(format!("/api/v1/media/{}/stream", media.id))
+            .body(Body::empty())
+            .expect("request");
+        let response = router.clone().oneshot(request).await.expect("response");
+        assert_eq!(response.status(), StatusCode::OK, "{}", media.relative_path);
+        let body = response
+            .into_body()
+            .collect()
+            .await
+            .expect("body")
+            .to_bytes();
+        assert_eq!(body.as_ref(), expected);
+    }
+}
+
 struct StreamTestContext {
     media_root: PathBuf,
     media: MediaFile,
@@ -142,8 +617,9 @@ fn sample_media_root() -> PathBuf {
 
 fn test_config(media_root: PathBuf, cache_dir: PathBuf) -> AppConfig {
     AppConfig {
-        media_root,
+        media_root: media_root.clone(),
         cache_dir,
+        cache_compress: false,
         listen_addr: SocketAddr::from(([127, 0, 0, 1], 0)),
         environment: "test".into(),
         otel: OtelConfig {
@@ -151,11 +627,23 @@ fn test_config(media_root: PathBuf, cache_dir: PathBuf) -> AppConfig {
             service_name: "test-backend".into(),
             disable_traces: true,
             disable_logs: true,
+            protocol: Default::default(),
+            headers: Default::default(),
         },
         log: LogConfig {
             level: "info".into(),
+            format: Default::default(),
         },
         cors_allowed_origins: Vec::new(),
         frontend_dist_dir: None,
+        rate_limit_per_minute: None,
+        api_token: None,
+        trusted_proxies: Default::default(),
+        thumbnails: Default::default(),
+        stream: Default::default(),
+        limits: Default::default(),
+        page_limits: Default::default(),
+        indexer: IndexerConfig::new(&media_root),
+        server: Default::default(),
     }
 }
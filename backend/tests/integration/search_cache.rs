@@ -11,9 +11,9 @@ use axum::{
     http::{Method, Request, StatusCode},
 };
 use galarie_backend::{
-    cache::CacheStore,
+    cache::{CacheSnapshot, CacheStore},
     config::{AppConfig, LogConfig, OtelConfig},
-    indexer::Indexer,
+    indexer::{Indexer, IndexerConfig},
     routes::{self, AppState},
 };
 use http_body_util::BodyExt;
@@ -74,8 +74,9 @@ fn sample_media_root() -> PathBuf {
 
 fn test_config(media_root: PathBuf, cache_dir: PathBuf) -> AppConfig {
     AppConfig {
-        media_root,
+        media_root: media_root.clone(),
         cache_dir,
+        cache_compress: false,
         listen_addr: SocketAddr::from(([127, 0, 0, 1], 0)),
         environment: "test".into(),
         otel: OtelConfig {
@@ -83,11 +84,64 @@ fn test_config(media_root: PathBuf, cache_dir: PathBuf) -> AppConfig {
             service_name: "test-backend".into(),
             disable_traces: true,
             disable_logs: true,
+            protocol: Default::default(),
+            headers: Default::default(),
         },
         log: LogConfig {
             level: "info".into(),
+            format: Default::default(),
         },
         cors_allowed_origins: Vec::new(),
         frontend_dist_dir: None,
+        rate_limit_per_minute: None,
+        api_token: None,
+        trusted_proxies: Default::default(),
+        thumbnails: Default::default(),
+        stream: Default::default(),
+        limits: Default::default(),
+        page_limits: Default::default(),
+        indexer: IndexerConfig::new(&media_root),
+        server: Default::default(),
     }
 }
+
+#[tokio::test]
+async fn mis_tagged_media_is_listed_as_invalid() {
+    let media_root = tempdir().expect("temp media root");
+    for name in ["sunset_rating-5.png", "beach_rating-_:oops.png"] {
+        image::RgbImage::new(2, 2)
+            .save(media_root.path().join(name))
+            .expect("write fixture image");
+    }
+    let cache_dir = tempdir().expect("temp cache dir");
+    let config = Arc::new(test_config(
+        media_root.path().to_path_buf(),
+        cache_dir.path().to_path_buf(),
+    ));
+    let snapshot = CacheSnapshot::new(Indexer::scan_once(media_root.path()).expect("scan"));
+    let state = AppState::new(
+        config,
+        Arc::new(CacheStore::new(cache_dir.path())),
+        Arc::new(RwLock::new(snapshot)),
+    );
+
+    let request = Request::builder()
+        .method(Method::GET)
+        .uri("/api/v1/media/invalid")
+        .body(Body::empty())
+        .expect("request");
+    let response = routes::router(state)
+        .oneshot(request)
+        .await
+        .expect("router response");
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let body = response.into_body().collect().await.expect("body");
+    let json: Value = serde_json::from_slice(&body.to_bytes()).expect("json payload");
+    assert_eq!(json["total"], 1);
+    assert_eq!(json["items"][0]["relativePath"], "beach_rating-_:oops.png");
+    assert_eq!(
+        json["items"][0]["invalidTokens"],
+        serde_json::json!(["rating-", ":oops"])
+    );
+}
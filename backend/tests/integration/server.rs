@@ -0,0 +1,121 @@
+use std::{net::SocketAddr, path::PathBuf, sync::Arc, time::Duration};
+
+use galarie_backend::{
+    cache::{CacheSnapshot, CacheStore},
+    config::{AppConfig, LogConfig, OtelConfig, ServerConfig},
+    indexer::IndexerConfig,
+    routes::{self, AppState},
+    server,
+};
+use tempfile::tempdir;
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::{TcpListener, TcpStream},
+    sync::{RwLock, oneshot},
+};
+
+/// HTTP/2 connection preface sent by clients using prior knowledge.
+const H2_PREFACE: &[u8] = b"PRI * HTTP/2.0\r\n\r\nSM\r\n\r\n";
+const SETTINGS_FRAME: u8 = 0x4;
+const SETTINGS_MAX_CONCURRENT_STREAMS: u16 = 0x3;
+
+#[tokio::test]
+async fn serves_http1_and_http2_with_custom_tuning() {
+    let tmp = tempdir().expect("temp dir");
+    let tuning = ServerConfig {
+        http2_max_concurrent_streams: 16,
+        http2_keep_alive_interval: Some(Duration::from_secs(5)),
+        tcp_nodelay: true,
+    };
+    let config = Arc::new(test_config(tmp.path().to_path_buf(), tuning.clone()));
+    let state = AppState::new(
+        config,
+        Arc::new(CacheStore::new(tmp.path())),
+        Arc::new(RwLock::new(CacheSnapshot::new(Vec::new()))),
+    );
+
+    let listener = TcpListener::bind("127.0.0.1:0").await.expect("bind");
+    let addr = listener.local_addr().expect("local addr");
+    let (stop, stopped) = oneshot::channel::<()>();
+    let server = tokio::spawn(async move {
+        server::serve(listener, routes::router(state), &tuning, async {
+            let _ = stopped.await;
+        })
+        .await
+    });
+
+    let mut http1 = TcpStream::connect(addr).await.expect("connect");
+    http1
+        .write_all(b"GET /api/v1/media HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\r\n")
+        .await
+        .expect("write request");
+    let mut response = String::new();
+    http1
+        .read_to_string(&mut response)
+        .await
+        .expect("read response");
+    assert!(response.starts_with("HTTP/1.1 200"), "{response}");
+
+    let mut http2 = TcpStream::connect(addr).await.expect("connect");
+    http2.write_all(H2_PREFACE).await.expect("write preface");
+    // Empty client SETTINGS frame.
+    http2
+        .write_all(&[0, 0, 0, SETTINGS_FRAME, 0, 0, 0, 0, 0])
+        .await
+        .expect("write settings");
+    let mut header = [0u8; 9];
+    http2.read_exact(&mut header).await.expect("frame header");
+    assert_eq!(header[3], SETTINGS_FRAME, "server opens with SETTINGS");
+    let length = u32::from_be_bytes([0, header[0], header[1], header[2]]) as usize;
+    let mut payload = vec![0u8; length];
+    http2
+        .read_exact(&mut payload)
+        .await
+        .expect("settings payload");
+    let max_streams = payload
+        .chunks_exact(6)
+        .find(|entry| u16::from_be_bytes([entry[0], entry[1]]) == SETTINGS_MAX_CONCURRENT_STREAMS)
+        .map(|entry| u32::from_be_bytes([entry[2], entry[3], entry[4], entry[5]]));
+    assert_eq!(max_streams, Some(16));
+    drop(http2);
+
+    stop.send(()).expect("server still running");
+    tokio::time::timeout(Duration::from_secs(5), server)
+        .await
+        .expect("server stops after shutdown")
+        .expect("server task")
+        .expect("serve result");
+}
+
+fn test_config(root: PathBuf, server: ServerConfig) -> AppConfig {
+    AppConfig {
+        media_root: root.clone(),
+        cache_dir: root.clone(),
+        cache_compress: false,
+        listen_addr: SocketAddr::from(([127, 0, 0, 1], 0)),
+        environment: "test".into(),
+        otel: OtelConfig {
+            endpoint: None,
+            service_name: "test-backend".into(),
+            disable_traces: true,
+            disable_logs: true,
+            protocol: Default::default(),
+            headers: Default::default(),
+        },
+        log: LogConfig {
+            level: "info".into(),
+            format: Default::default(),
+        },
+        cors_allowed_origins: Vec::new(),
+        frontend_dist_dir: None,
+        rate_limit_per_minute: None,
+        api_token: None,
+        trusted_proxies: Default::default(),
+        thumbnails: Default::default(),
+        stream: Default::default(),
+        limits: Default::default(),
+        page_limits: Default::default(),
+        indexer: IndexerConfig::new(&root),
+        server,
+    }
+}
@@ -0,0 +1,84 @@
+use std::{net::SocketAddr, sync::Arc, time::Duration};
+
+use galarie_backend::{
+    cache::{CacheSnapshot, CacheStore},
+    config::{AppConfig, LogConfig, OtelConfig},
+    indexer::{Indexer, IndexerConfig},
+    routes::AppState,
+};
+use tempfile::tempdir;
+use tokio::sync::RwLock;
+
+#[tokio::test]
+async fn drain_during_scan_persists_snapshot_and_leaves_no_temp_files() {
+    let media_root = tempdir().expect("temp media root");
+    for index in 0..300 {
+        std::fs::write(
+            media_root.path().join(format!("shot_{index}.jpg")),
+            format!("shot-{index}"),
+        )
+        .expect("write fixture");
+    }
+    let cache_dir = tempdir().expect("temp cache dir");
+    // Left behind by a write that was interrupted before its rename.
+    let orphan = cache_dir.path().join("index.1700000000000000000.tmp");
+    std::fs::write(&orphan, b"{\"truncated").expect("plant temp file");
+
+    let indexer_config = IndexerConfig::new(media_root.path()).with_scan_concurrency(1);
+    let config = Arc::new(AppConfig {
+        media_root: media_root.path().to_path_buf(),
+        cache_dir: cache_dir.path().to_path_buf(),
+        cache_compress: false,
+        listen_addr: SocketAddr::from(([127, 0, 0, 1], 0)),
+        environment: "test".into(),
+        otel: OtelConfig {
+            endpoint: None,
+            service_name: "test-backend".into(),
+            disable_traces: true,
+            disable_logs: true,
+            protocol: Default::default(),
+            headers: Default::default(),
+        },
+        log: LogConfig {
+            level: "info".into(),
+            format: Default::default(),
+        },
+        cors_allowed_origins: Vec::new(),
+        frontend_dist_dir: None,
+        rate_limit_per_minute: None,
+        api_token: None,
+        trusted_proxies: Default::default(),
+        thumbnails: Default::default(),
+        stream: Default::default(),
+        limits: Default::default(),
+        page_limits: Default::default(),
+        indexer: indexer_config.clone(),
+        server: Default::default(),
+    });
+    let state = AppState::new(
+        config,
+        Arc::new(CacheStore::new(cache_dir.path())),
+        Arc::new(RwLock::new(CacheSnapshot::new(Vec::new()))),
+    );
+
+    let (indexer, events) = Indexer::spawn(indexer_config, Vec::new());
+    let consumer = state.spawn_index_consumer(events);
+    // Shut down right away, while the initial scan is still running.
+    state
+        .drain(indexer, consumer, Duration::from_secs(10))
+        .await;
+
+    let leftovers: Vec<_> = std::fs::read_dir(cache_dir.path())
+        .expect("read cache dir")
+        .map(|entry| entry.expect("dir entry").file_name())
+        .filter(|name| name.to_string_lossy().ends_with(".tmp"))
+        .collect();
+    assert!(leftovers.is_empty(), "orphaned temp files: {leftovers:?}");
+
+    let persisted = CacheStore::new(cache_dir.path())
+        .load()
+        .expect("read cache")
+        .expect("snapshot persisted before shutdown");
+    assert_eq!(persisted.media.len(), 300);
+    assert_eq!(state.snapshot.read().await.media.len(), 300);
+}
@@ -14,4 +14,5 @@ export type MediaSummary = {
   attributes: Record<string, string>
   filesize: number
   thumbnailPath?: string | null
+  streamPath?: string | null
 }
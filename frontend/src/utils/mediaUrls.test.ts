@@ -34,10 +34,10 @@ describe('media url helpers', () => {
       )
     })
 
-    it('prefixes relative thumbnails with API base', () => {
-      expect(resolveThumbnailUrl('/media/abc/thumbnail', 'http://localhost/api/')).toBe(
-        'http://localhost/api/media/abc/thumbnail',
-      )
+    it('resolves root-relative thumbnails against the API origin', () => {
+      expect(
+        resolveThumbnailUrl('/api/v1/media/abc/thumbnail', 'http://localhost:8080/api/v1'),
+      ).toBe('http://localhost:8080/api/v1/media/abc/thumbnail')
     })
   })
 })
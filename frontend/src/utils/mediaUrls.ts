@@ -23,6 +23,6 @@ export function resolveThumbnailUrl(path: string | null | undefined, apiBaseUrl:
   if (path.startsWith('http')) {
     return path
   }
-  const normalizedBase = apiBaseUrl.replace(/\/$/, '')
-  return `${normalizedBase}${path}`
+  // The backend links from the server root (e.g. /api/v1/media/{id}/thumbnail).
+  return new URL(path, apiBaseUrl).toString()
 }